@@ -0,0 +1,104 @@
+//! 草稿模式 - 连续听写的结果先累积在草稿中，最后一次性插入
+//!
+//! 每次听写是一段，撤销只移除最后一段
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+static SEGMENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// 草稿当前状态（同步给前端）
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DraftState {
+    pub text: String,
+    pub segments: usize,
+    /// 字数（按字符计）
+    pub chars: usize,
+}
+
+fn state_of(segments: &[String]) -> DraftState {
+    let text = join_segments(segments);
+    DraftState {
+        chars: text.chars().count(),
+        segments: segments.len(),
+        text,
+    }
+}
+
+/// 两段之间是否需要补空格：英文、数字相邻，或英文标点后接英文
+fn needs_space(prev: &str, next: &str) -> bool {
+    let (Some(p), Some(n)) = (prev.chars().last(), next.chars().next()) else {
+        return false;
+    };
+    if p.is_whitespace() || n.is_whitespace() {
+        return false;
+    }
+    p.is_ascii() && n.is_ascii() && !n.is_ascii_punctuation()
+}
+
+/// 拼接各段（中文直接相连，英文之间补空格）
+pub fn join_segments(segments: &[String]) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        if needs_space(&out, segment) {
+            out.push(' ');
+        }
+        out.push_str(segment);
+    }
+    out
+}
+
+/// 追加一段
+pub fn append(text: &str) -> DraftState {
+    let mut segments = SEGMENTS.lock().unwrap();
+    if !text.trim().is_empty() {
+        segments.push(text.to_string());
+    }
+    log::info!("[Draft] Appended segment, {} in draft", segments.len());
+    state_of(&segments)
+}
+
+/// 移除最后一段
+pub fn pop_last() -> DraftState {
+    let mut segments = SEGMENTS.lock().unwrap();
+    if segments.pop().is_some() {
+        log::info!("[Draft] Removed last segment, {} left", segments.len());
+    }
+    state_of(&segments)
+}
+
+/// 清空草稿
+pub fn clear() {
+    SEGMENTS.lock().unwrap().clear();
+    log::info!("[Draft] Cleared");
+}
+
+/// 取出全部内容并清空
+pub fn take() -> String {
+    let segments = std::mem::take(&mut *SEGMENTS.lock().unwrap());
+    join_segments(&segments)
+}
+
+pub fn state() -> DraftState {
+    state_of(&SEGMENTS.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join(parts: &[&str]) -> String {
+        join_segments(&parts.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_join_segments_spacing() {
+        assert_eq!(join(&["你好。", "今天天气不错。"]), "你好。今天天气不错。");
+        assert_eq!(join(&["Hello world.", "How are you?"]), "Hello world. How are you?");
+        assert_eq!(join(&["version 2", "is out"]), "version 2 is out");
+        // 中英混排、标点开头、已有空格时不补空格
+        assert_eq!(join(&["我用 Rust", "写代码"]), "我用 Rust写代码");
+        assert_eq!(join(&["done", ", then"]), "done, then");
+        assert_eq!(join(&["ok ", "next"]), "ok next");
+    }
+}
@@ -0,0 +1,347 @@
+//! 应用事件 - 后端发往前端的所有事件
+//!
+//! 事件名和载荷只在这里定义，前端可通过 `get_event_schema` 获取 JSON Schema 生成类型
+
+use crate::doubao_asr::AsrResult;
+use crate::draft::DraftState;
+use crate::error::ErrorDisplay;
+use crate::permissions::PermissionChange;
+use crate::update::UpdateInfo;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter};
+
+/// 事件载荷格式版本，载荷结构有不兼容变化时递增
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// 就绪状态（失败时带原因）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub reason: Option<String>,
+}
+
+impl Readiness {
+    pub fn ready() -> Self {
+        Self {
+            ready: true,
+            reason: None,
+        }
+    }
+
+    pub fn failed(reason: impl Into<String>) -> Self {
+        Self {
+            ready: false,
+            reason: Some(reason.into()),
+        }
+    }
+
+    pub fn from_result<T, E: std::fmt::Display>(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => Self::ready(),
+            Err(e) => Self::failed(e.to_string()),
+        }
+    }
+}
+
+/// 定义事件枚举：变体、事件名和载荷 schema 写在同一张表里，`name()` 和 `schema()` 都由它生成
+macro_rules! app_events {
+    ($($(#[$meta:meta])* $variant:ident $(($payload:ty))? => $name:literal, $schema:expr;)*) => {
+        /// 后端发往前端的事件（序列化结果即事件载荷，无载荷的事件为 null）
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(untagged)]
+        pub enum AppEvent {
+            $($(#[$meta])* $variant $(($payload))?,)*
+        }
+
+        impl AppEvent {
+            /// 前端 listen 使用的事件名
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(AppEvent::$variant { .. } => $name,)*
+                }
+            }
+        }
+
+        /// 每个事件的名称和载荷 schema
+        fn event_schemas() -> Vec<(&'static str, Value)> {
+            vec![$(($name, $schema),)*]
+        }
+    };
+}
+
+app_events! {
+    /// 浮层显示前清空内容
+    OverlayReset => "overlay-reset", typed("null");
+    /// 浮层状态文字
+    OverlayStatus(String) => "overlay-status", typed("string");
+    /// 浮层识别文字
+    OverlayText(String) => "overlay-text", typed("string");
+    /// 浮层显示待确认的结果
+    OverlayReview(String) => "overlay-review", typed("string");
+    /// 浮层错误提示
+    OverlayError(ErrorDisplay) => "overlay-error", error_display_schema();
+    /// 浮层未能创建为 NSPanel，已降级为普通置顶窗口（载荷为说明）
+    OverlayFallback(String) => "overlay-fallback", typed("string");
+    /// 松开触发键，录音结束
+    RecordingStopped => "recording-stopped", typed("null");
+    /// 录音或识别失败（用户可读的说明）
+    SttError(String) => "stt-error", typed("string");
+    /// 会话异常结束（panic 或超时），录音状态已强制复位（载荷为原因）
+    SessionAborted(String) => "session-aborted", typed("string");
+    /// 听写总开关变化（载荷为是否启用）
+    DictationEnabledChanged(bool) => "dictation-enabled-changed", typed("boolean");
+    /// 触发键监听一直收不到按键，可能被其他软件占用（载荷为说明）
+    HotkeyConflict(String) => "hotkey-conflict", typed("string");
+    /// 按键时间短于最短时长，结果已丢弃（载荷为按住的毫秒数）
+    SessionTooShort(u64) => "session-too-short", typed("integer");
+    /// 识别结果只有标点或语气词，已忽略（载荷为原文）
+    ContentTooShort(String) => "content-too-short", typed("string");
+    /// 录音即将到达最长时长（载荷为剩余秒数）
+    SessionCountdown(u64) => "session-countdown", typed("integer");
+    /// 录音到达最长时长，已自动结束（载荷为最长秒数）
+    SessionMaxReached(u64) => "session-max-reached", typed("integer");
+    /// 最终识别结果（含分句信息）
+    AsrFinal(AsrResult) => "asr-final", asr_result_schema();
+    /// 当前会话的实时识别文字（主窗口转写面板）
+    TranscriptPartial(String) => "transcript-partial", typed("string");
+    /// 本次录音的重采样已降级为线性
+    ResampleFallback => "resample-fallback", typed("null");
+    /// Cookie 中没有有效的设备标识，ASR URL 使用了内置值（载荷为标识名）
+    DeviceIdFallback(String) => "device-id-fallback", typed("string");
+    /// 草稿变化
+    DraftChanged(DraftState) => "draft-changed", draft_schema();
+    /// 便签内容变化（载荷为全文）
+    ScratchpadChanged(String) => "scratchpad-changed", typed("string");
+    /// ASR URL 参数捕获完成（失败时使用默认参数）
+    AsrParamsReady(Readiness) => "asr-params-ready", readiness_schema();
+    /// 手动管理模式下需要用户自行启动豆包
+    DoubaoRequirement(String) => "doubao-requirement", typed("string");
+    /// 豆包调试模式是否就绪
+    DoubaoReady(Readiness) => "doubao-ready", readiness_schema();
+    /// 检查到新版本
+    UpdateAvailable(UpdateInfo) => "update-available", update_info_schema();
+    /// 权限状态变化（变化前后的状态）
+    PermissionsChanged(PermissionChange) => "permissions-changed", permission_change_schema();
+}
+
+/// 发送事件到所有窗口
+pub fn emit(app: &AppHandle, event: AppEvent) {
+    if let Err(e) = app.emit(event.name(), &event) {
+        log::warn!("[Events] Failed to emit {}: {}", event.name(), e);
+    }
+}
+
+// ============ Schema ============
+
+fn typed(name: &str) -> Value {
+    json!({ "type": name })
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "type": "null" }] })
+}
+
+fn readiness_schema() -> Value {
+    object(
+        json!({ "ready": { "type": "boolean" }, "reason": nullable(json!({ "type": "string" })) }),
+        &["ready", "reason"],
+    )
+}
+
+fn asr_result_schema() -> Value {
+    let ms = nullable(json!({ "type": "integer" }));
+    let word = object(
+        json!({ "text": { "type": "string" }, "start_ms": ms, "end_ms": ms }),
+        &["text", "start_ms", "end_ms"],
+    );
+    let utterance = object(
+        json!({
+            "text": { "type": "string" },
+            "start_ms": ms,
+            "end_ms": ms,
+            "definite": { "type": "boolean" },
+            "words": { "type": "array", "items": word },
+        }),
+        &["text", "start_ms", "end_ms", "definite", "words"],
+    );
+    object(
+        json!({
+            "text": { "type": "string" },
+            "utterances": { "type": "array", "items": utterance },
+            "language": nullable(json!({ "type": "string" })),
+        }),
+        &["text", "utterances", "language"],
+    )
+}
+
+fn error_display_schema() -> Value {
+    object(
+        json!({ "message": { "type": "string" }, "detail": nullable(json!({ "type": "string" })) }),
+        &["message", "detail"],
+    )
+}
+
+fn update_info_schema() -> Value {
+    object(
+        json!({
+            "available": { "type": "boolean" },
+            "version": { "type": "string" },
+            "notes": { "type": "string" },
+            "url": { "type": "string" },
+        }),
+        &["available", "version", "notes", "url"],
+    )
+}
+
+fn permission_change_schema() -> Value {
+    let boolean = json!({ "type": "boolean" });
+    // 按功能的字段，加上兼容旧版的 input_monitoring / accessibility / microphone
+    let fields = [
+        "can_monitor_keys",
+        "can_paste",
+        "can_type_direct",
+        "can_record_audio",
+        "input_monitoring",
+        "accessibility",
+        "microphone",
+    ];
+    let properties: serde_json::Map<String, Value> = fields
+        .iter()
+        .map(|field| (field.to_string(), boolean.clone()))
+        .collect();
+    let status = object(Value::Object(properties), &fields);
+    object(
+        json!({ "before": status, "after": status }),
+        &["before", "after"],
+    )
+}
+
+fn draft_schema() -> Value {
+    object(
+        json!({
+            "text": { "type": "string" },
+            "segments": { "type": "integer" },
+            "chars": { "type": "integer" },
+        }),
+        &["text", "segments", "chars"],
+    )
+}
+
+/// 所有事件的名称和载荷 JSON Schema
+pub fn schema() -> Value {
+    let events: serde_json::Map<String, Value> = event_schemas()
+        .into_iter()
+        .map(|(name, payload)| (name.to_string(), payload))
+        .collect();
+    json!({ "version": EVENT_SCHEMA_VERSION, "events": events })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::PermissionStatus;
+
+    fn all_events() -> Vec<AppEvent> {
+        vec![
+            AppEvent::OverlayReset,
+            AppEvent::OverlayStatus("聆听中...".to_string()),
+            AppEvent::OverlayText("你好".to_string()),
+            AppEvent::OverlayReview("你好".to_string()),
+            AppEvent::OverlayError(ErrorDisplay {
+                message: "请先启动豆包桌面端".to_string(),
+                detail: None,
+            }),
+            AppEvent::OverlayFallback("浮层已改用普通窗口".to_string()),
+            AppEvent::RecordingStopped,
+            AppEvent::SttError("麦克风不可用".to_string()),
+            AppEvent::SessionAborted("会话超时".to_string()),
+            AppEvent::DictationEnabledChanged(false),
+            AppEvent::HotkeyConflict("热键可能被其他软件占用".to_string()),
+            AppEvent::SessionTooShort(120),
+            AppEvent::ContentTooShort("嗯".to_string()),
+            AppEvent::SessionCountdown(3),
+            AppEvent::SessionMaxReached(600),
+            AppEvent::AsrFinal(AsrResult::default()),
+            AppEvent::TranscriptPartial("你好".to_string()),
+            AppEvent::ResampleFallback,
+            AppEvent::DeviceIdFallback("device_id/web_id".to_string()),
+            AppEvent::DraftChanged(DraftState::default()),
+            AppEvent::ScratchpadChanged("你好".to_string()),
+            AppEvent::AsrParamsReady(Readiness::ready()),
+            AppEvent::DoubaoRequirement("请启动豆包".to_string()),
+            AppEvent::DoubaoReady(Readiness::failed("未安装")),
+            AppEvent::UpdateAvailable(UpdateInfo {
+                available: true,
+                version: "0.2.0".to_string(),
+                notes: "修复豆包选择器".to_string(),
+                url: "https://github.com/ChaosRealmsAI/TypeFree/releases/tag/v0.2.0".to_string(),
+            }),
+            AppEvent::PermissionsChanged(PermissionChange {
+                before: PermissionStatus {
+                    can_monitor_keys: true,
+                    can_paste: false,
+                    can_type_direct: true,
+                    can_record_audio: true,
+                },
+                after: PermissionStatus {
+                    can_monitor_keys: true,
+                    can_paste: true,
+                    can_type_direct: true,
+                    can_record_audio: true,
+                },
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_schema_covers_all_events() {
+        let schema = schema();
+        let events = schema["events"].as_object().unwrap();
+        let all = all_events();
+        // 名称不重复，且每个变体都有示例
+        let names: std::collections::HashSet<&str> = all.iter().map(AppEvent::name).collect();
+        assert_eq!(events.len(), event_schemas().len());
+        assert_eq!(names.len(), all.len());
+        assert_eq!(events.len(), all.len());
+        for event in &all {
+            let payload_schema = &events[event.name()];
+            let payload = serde_json::to_value(event).unwrap();
+            // 载荷的顶层类型与 schema 一致
+            let expected = payload_schema["type"].as_str().unwrap();
+            let actual = match payload {
+                Value::Null => "null",
+                Value::Bool(_) => "boolean",
+                Value::String(_) => "string",
+                Value::Number(n) if n.is_f64() => "number",
+                Value::Number(_) => "integer",
+                Value::Object(_) => "object",
+                _ => "other",
+            };
+            assert_eq!(actual, expected, "{}", event.name());
+        }
+    }
+
+    #[test]
+    fn test_payloads() {
+        let value = |event: AppEvent| serde_json::to_value(event).unwrap();
+        assert_eq!(value(AppEvent::RecordingStopped), Value::Null);
+        assert_eq!(
+            value(AppEvent::OverlayText("你好".to_string())),
+            json!("你好")
+        );
+        assert_eq!(
+            value(AppEvent::DoubaoReady(Readiness::failed("未安装"))),
+            json!({ "ready": false, "reason": "未安装" })
+        );
+        assert_eq!(Readiness::from_result::<(), String>(&Ok(())), Readiness::ready());
+        assert_eq!(
+            Readiness::from_result::<(), String>(&Err("超时".to_string())),
+            Readiness::failed("超时")
+        );
+    }
+}
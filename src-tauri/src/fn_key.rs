@@ -1,4 +1,357 @@
-//! macOS Fn key monitoring using IOKit HID
+//! 全局触发键监听
+//!
+//! macOS 使用 IOKit HID（没有输入监控权限但有辅助功能权限时改用 CGEventTap），
+//! Windows 使用低级键盘钩子（长按触发，由计时线程判定，不依赖按键重复）。
+//! 可同时绑定多个触发键，任一按下即开始录音，全部松开后结束。
+//! 另有快捷短语键，按下即粘贴预设文本。
+//! 按下事件附带当时按住的修饰键，用于单次会话的临时切换。
+//! 监听打开后一直收不到按键时，提示可能被其他软件占用。
+//! Windows 的钩子被安全软件摘除时自动重装；也可在设置中改用兼容模式（RegisterHotKey + 按键轮询）。
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+// ============ 触发键定义 ============
+
+pub use typefree_core::hotkey::{Modifiers, Trigger};
+
+/// 最多同时监听的触发键数量
+const MAX_TRIGGERS: usize = 8;
+
+/// 当前监听的触发键
+static TRIGGERS: RwLock<Vec<Trigger>> = RwLock::new(Vec::new());
+
+/// 各触发键的按下状态（按位），合并成单一的按下/松开信号
+static TRIGGER_STATE: TriggerState = TriggerState(AtomicU64::new(0));
+
+struct TriggerState(AtomicU64);
+
+impl TriggerState {
+    /// 记录第 index 个触发键的变化，合并状态改变时返回新状态
+    fn set(&self, index: usize, pressed: bool) -> Option<bool> {
+        let bit = 1u64 << index;
+        if pressed {
+            let prev = self.0.fetch_or(bit, Ordering::SeqCst);
+            (prev == 0).then_some(true)
+        } else {
+            let prev = self.0.fetch_and(!bit, Ordering::SeqCst);
+            (prev == bit).then_some(false)
+        }
+    }
+
+    /// 清空状态，返回之前是否处于按下
+    fn reset(&self) -> bool {
+        self.0.swap(0, Ordering::SeqCst) != 0
+    }
+}
+
+/// 查找匹配的触发键序号
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn trigger_index(matches: impl Fn(&Trigger) -> bool) -> Option<usize> {
+    TRIGGERS.read().ok()?.iter().position(matches)
+}
+
+/// 替换监听的触发键（可在运行中调用）
+pub fn set_triggers(triggers: Vec<Trigger>) {
+    let mut triggers = triggers;
+    if triggers.len() > MAX_TRIGGERS {
+        log::warn!(
+            "[FnKey] Too many triggers ({}), only the first {} are used",
+            triggers.len(),
+            MAX_TRIGGERS
+        );
+        triggers.truncate(MAX_TRIGGERS);
+    }
+    if triggers.is_empty() {
+        log::warn!("[FnKey] No triggers configured, dictation hotkey disabled");
+    }
+    log::info!("[FnKey] Triggers: {:?}", triggers);
+
+    if let Ok(mut t) = TRIGGERS.write() {
+        *t = triggers;
+    }
+
+    // 序号已变化，丢弃旧的按下状态；若正在按住则补发松开
+    #[cfg(target_os = "windows")]
+    windows::reset_key_states();
+    if TRIGGER_STATE.reset() {
+        emit(false);
+    }
+}
+
+// ============ 快捷短语键 ============
+
+/// 最多绑定的快捷短语键数量
+const MAX_SNIPPET_KEYS: usize = 16;
+
+/// 快捷短语键，序号与设置中的短语列表一致（与触发键重复时触发键优先）
+static SNIPPET_KEYS: RwLock<Vec<Trigger>> = RwLock::new(Vec::new());
+
+/// 快捷短语键的按下状态（按位），用于忽略按键重复
+static SNIPPET_HELD: AtomicU64 = AtomicU64::new(0);
+
+static SNIPPET_CALLBACK: OnceLock<Arc<dyn Fn(usize) + Send + Sync>> = OnceLock::new();
+
+/// 设置快捷短语回调（参数为短语序号）
+pub fn set_snippet_handler<F>(handler: F)
+where
+    F: Fn(usize) + Send + Sync + 'static,
+{
+    let _ = SNIPPET_CALLBACK.set(Arc::new(handler));
+}
+
+/// 替换快捷短语键（可在运行中调用）
+pub fn set_snippet_keys(keys: Vec<Trigger>) {
+    let mut keys = keys;
+    if keys.len() > MAX_SNIPPET_KEYS {
+        log::warn!(
+            "[FnKey] Too many snippet keys ({}), only the first {} are used",
+            keys.len(),
+            MAX_SNIPPET_KEYS
+        );
+        keys.truncate(MAX_SNIPPET_KEYS);
+    }
+    log::info!("[FnKey] Snippet keys: {:?}", keys);
+
+    if let Ok(mut k) = SNIPPET_KEYS.write() {
+        *k = keys;
+    }
+    SNIPPET_HELD.store(0, Ordering::SeqCst);
+    #[cfg(target_os = "windows")]
+    windows::snippet_keys_changed();
+}
+
+/// 查找匹配的快捷短语键序号
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn snippet_index(matches: impl Fn(&Trigger) -> bool) -> Option<usize> {
+    SNIPPET_KEYS.read().ok()?.iter().position(matches)
+}
+
+/// 记录快捷短语键的变化，返回是否为首次按下（按键重复不算）
+fn snippet_first_press(held: &AtomicU64, index: usize, pressed: bool) -> bool {
+    let bit = 1u64 << index;
+    if pressed {
+        held.fetch_or(bit, Ordering::SeqCst) & bit == 0
+    } else {
+        held.fetch_and(!bit, Ordering::SeqCst);
+        false
+    }
+}
+
+/// 处理快捷短语键，首次按下时在新线程中回调（回调会模拟粘贴，不能阻塞监听线程）
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn handle_snippet_key(index: usize, pressed: bool) {
+    if !snippet_first_press(&SNIPPET_HELD, index, pressed) {
+        return;
+    }
+    log::info!("[FnKey] Snippet key #{} PRESSED", index);
+    if let Some(cb) = SNIPPET_CALLBACK.get() {
+        let cb = cb.clone();
+        std::thread::spawn(move || cb(index));
+    }
+}
+
+// ============ 占用检测 ============
+
+/// 监听打开后至少等这么久才下结论
+const CONFLICT_GRACE: Duration = Duration::from_secs(20);
+/// 检查间隔
+const CONFLICT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 一直没有键盘输入就无法判断，最多观察这么久
+const CONFLICT_MAX_WAIT: Duration = Duration::from_secs(600);
+
+/// 监听成功打开的时间
+static OPENED_AT: OnceLock<Instant> = OnceLock::new();
+
+/// 监听收到的按键事件数（任意键，不只是触发键）
+static RAW_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn record_raw_event() {
+    RAW_EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 监听是否正常工作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookHealth {
+    /// 还不能判断
+    Unknown,
+    /// 收到过按键
+    Working,
+    /// 有键盘输入但一次都没收到，多半被其他软件占用
+    Blocked,
+}
+
+/// 判断监听状态
+///
+/// - `since_open`: 监听打开后经过的时间
+/// - `events_seen`: 监听收到过按键事件
+/// - `key_activity`: 打开后系统有键盘输入（Windows 为已发出自检按键）
+/// - `permitted`: 已授予输入监控权限（没有权限时收不到是预期的）
+pub fn judge_hook(
+    since_open: Duration,
+    events_seen: bool,
+    key_activity: bool,
+    permitted: bool,
+) -> HookHealth {
+    if events_seen {
+        HookHealth::Working
+    } else if permitted && key_activity && since_open >= CONFLICT_GRACE {
+        HookHealth::Blocked
+    } else {
+        HookHealth::Unknown
+    }
+}
+
+/// 系统显示触发键按着、钩子却没收到按下，连续这么多次检查都如此才发自检按键
+/// （避开钩子恰好还在处理这次按键的竞争）
+const HOOK_MISS_CHECKS: u32 = 2;
+
+/// 键盘钩子自动重装的次数
+static HOOK_REINSTALLS: AtomicU64 = AtomicU64::new(0);
+
+/// 更新钩子漏报的连续次数：`missed_now`（系统显示触发键按着、钩子却没有记录按下）时加一，
+/// 否则清零；返回新的次数和钩子是否疑似失效
+pub fn count_missed_press(missed: u32, missed_now: bool) -> (u32, bool) {
+    let missed = if missed_now { missed + 1 } else { 0 };
+    (missed, missed >= HOOK_MISS_CHECKS)
+}
+
+/// 按键监听的诊断信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerStatus {
+    /// 监听方式
+    pub backend: &'static str,
+    /// 键盘钩子失效后自动重装的次数
+    pub reinstalls: u64,
+}
+
+/// 当前的监听方式和钩子重装次数
+pub fn listener_status() -> ListenerStatus {
+    ListenerStatus {
+        backend: backend_name(),
+        reinstalls: HOOK_REINSTALLS.load(Ordering::SeqCst),
+    }
+}
+
+#[cfg(target_os = "macos")]
+use macos::backend_name;
+#[cfg(target_os = "windows")]
+use windows::backend_name;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn backend_name() -> &'static str {
+    "不支持"
+}
+
+/// 后台检查监听是否收得到按键，怀疑被占用时调用 `on_blocked`（最多一次）
+pub fn spawn_conflict_check<F>(on_blocked: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let started = Instant::now();
+        while started.elapsed() < CONFLICT_MAX_WAIT {
+            std::thread::sleep(CONFLICT_CHECK_INTERVAL);
+            // 监听打开失败时已有日志，这里不再判断
+            let Some(opened) = OPENED_AT.get() else {
+                continue;
+            };
+            let health = judge_hook(
+                opened.elapsed(),
+                RAW_EVENTS.load(Ordering::Relaxed) > 0,
+                probe_key_activity(*opened),
+                crate::permissions::check_input_monitoring(),
+            );
+            match health {
+                HookHealth::Working => {
+                    log::info!("[FnKey] Key listener receives events");
+                    return;
+                }
+                HookHealth::Blocked => {
+                    log::warn!(
+                        "[FnKey] No key events {}s after opening the listener, another app may hold it",
+                        opened.elapsed().as_secs()
+                    );
+                    on_blocked();
+                    return;
+                }
+                HookHealth::Unknown => {}
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+use macos::probe_key_activity;
+#[cfg(target_os = "windows")]
+use windows::probe_key_activity;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn probe_key_activity(_opened: Instant) -> bool {
+    false
+}
+
+#[cfg(target_os = "macos")]
+use macos::emit;
+#[cfg(target_os = "windows")]
+use windows::emit;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn emit(_pressed: bool) {}
+
+// ============ macOS 备用监听的按键映射 ============
+
+/// 触发键对应的 macOS 虚拟键码（CGEventTap 只拿得到键码），不支持的按键为 None
+#[cfg(any(target_os = "macos", test))]
+fn tap_keycode(trigger: &Trigger) -> Option<u16> {
+    match *trigger {
+        Trigger::Fn => Some(63),
+        Trigger::HidUsage { usage } => match usage {
+            // 左 Control / Shift / Option / Command，右 Control / Shift / Option / Command
+            0xE0 => Some(59),
+            0xE1 => Some(56),
+            0xE2 => Some(58),
+            0xE3 => Some(55),
+            0xE4 => Some(62),
+            0xE5 => Some(60),
+            0xE6 => Some(61),
+            0xE7 => Some(54),
+            // F13 ~ F19
+            0x68 => Some(105),
+            0x69 => Some(107),
+            0x6A => Some(113),
+            0x6B => Some(106),
+            0x6C => Some(64),
+            0x6D => Some(79),
+            0x6E => Some(80),
+            _ => None,
+        },
+        Trigger::VirtualKey { .. } => None,
+    }
+}
+
+/// 修饰键键码在事件标志中对应的位（区分左右），不是修饰键时为 None
+#[cfg(any(target_os = "macos", test))]
+fn modifier_flag(keycode: u16) -> Option<u64> {
+    match keycode {
+        59 => Some(0x0000_0001),
+        56 => Some(0x0000_0002),
+        60 => Some(0x0000_0004),
+        55 => Some(0x0000_0008),
+        54 => Some(0x0000_0010),
+        58 => Some(0x0000_0020),
+        61 => Some(0x0000_0040),
+        62 => Some(0x0000_2000),
+        // Fn（kCGEventFlagMaskSecondaryFn）
+        63 => Some(0x0080_0000),
+        _ => None,
+    }
+}
+
+// ============ macOS: IOKit HID ============
 
 #[cfg(target_os = "macos")]
 mod macos {
@@ -7,14 +360,20 @@ mod macos {
     use core_foundation::number::*;
     use core_foundation::runloop::*;
     use core_foundation::string::*;
+    use core_foundation_sys::mach_port::{CFMachPortCreateRunLoopSource, CFMachPortRef};
     use std::ffi::c_void;
+    use std::sync::atomic::{AtomicPtr, Ordering};
     use std::sync::mpsc::{self, Sender};
     use std::sync::OnceLock;
+    use std::time::Instant;
+
+    use super::{Modifiers, Trigger, OPENED_AT, TRIGGER_STATE};
 
     const K_IO_HID_DEVICE_USAGE_PAGE_KEY: &str = "DeviceUsagePage";
     const K_IO_HID_DEVICE_USAGE_KEY: &str = "DeviceUsage";
     const K_HID_PAGE_GENERIC_DESKTOP: i32 = 0x01;
     const K_HID_USAGE_KEYBOARD: i32 = 0x06;
+    const K_HID_PAGE_KEYBOARD: u32 = 0x07;
 
     #[repr(C)]
     struct __IOHIDManager {
@@ -50,6 +409,11 @@ mod macos {
             run_loop: CFRunLoopRef,
             run_loop_mode: CFStringRef,
         );
+        fn IOHIDManagerUnscheduleFromRunLoop(
+            manager: IOHIDManagerRef,
+            run_loop: CFRunLoopRef,
+            run_loop_mode: CFStringRef,
+        );
         fn IOHIDManagerOpen(manager: IOHIDManagerRef, options: u32) -> i32;
         fn IOHIDValueGetElement(value: IOHIDValueRef) -> IOHIDElementRef;
         fn IOHIDValueGetIntegerValue(value: IOHIDValueRef) -> i64;
@@ -57,8 +421,124 @@ mod macos {
         fn IOHIDElementGetUsage(element: IOHIDElementRef) -> u32;
     }
 
+    const K_CG_EVENT_SOURCE_STATE_HID_SYSTEM_STATE: i32 = 1;
+    const K_CG_EVENT_KEY_DOWN: u32 = 10;
+
+    const K_CG_EVENT_KEY_UP: u32 = 11;
+    const K_CG_EVENT_FLAGS_CHANGED: u32 = 12;
+    const K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+    const K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+    const K_CG_SESSION_EVENT_TAP: u32 = 1;
+    const K_CG_HEAD_INSERT_EVENT_TAP: u32 = 0;
+    /// 可修改事件的 tap（只监听的 tap 在 10.15+ 需要输入监控权限，辅助功能不够）
+    const K_CG_EVENT_TAP_OPTION_DEFAULT: u32 = 0;
+    const K_CG_KEYBOARD_EVENT_KEYCODE: u32 = 9;
+
+    type CGEventRef = *mut c_void;
+    type CGEventTapCallBack = extern "C" fn(*mut c_void, u32, CGEventRef, *mut c_void) -> CGEventRef;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state_id: i32, event_type: u32) -> f64;
+        fn CGEventTapCreate(
+            tap: u32,
+            place: u32,
+            options: u32,
+            events_of_interest: u64,
+            callback: CGEventTapCallBack,
+            user_info: *mut c_void,
+        ) -> CFMachPortRef;
+        fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+        fn CGEventGetFlags(event: CGEventRef) -> u64;
+        fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+    }
+
+    /// 实际在用的监听方式（同一时间只有一种）
+    static BACKEND: OnceLock<&'static str> = OnceLock::new();
+
+    /// 备用监听的 CGEventTap（被系统停用时重新启用）
+    static EVENT_TAP: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+    /// 当前使用的监听方式
+    pub(super) fn backend_name() -> &'static str {
+        BACKEND.get().copied().unwrap_or("未启动")
+    }
+
+    /// 监听打开后系统是否收到过按键（不需要 HID 回调，其他软件占用时也能读到）
+    pub(super) fn probe_key_activity(opened: Instant) -> bool {
+        let idle = unsafe {
+            CGEventSourceSecondsSinceLastEventType(
+                K_CG_EVENT_SOURCE_STATE_HID_SYSTEM_STATE,
+                K_CG_EVENT_KEY_DOWN,
+            )
+        };
+        idle < opened.elapsed().as_secs_f64()
+    }
+
     // 使用 OnceLock + Sender 替代 static mut，避免数据竞争
-    static FN_EVENT_SENDER: OnceLock<Sender<bool>> = OnceLock::new();
+    static FN_EVENT_SENDER: OnceLock<Sender<(bool, Modifiers)>> = OnceLock::new();
+
+    // NSEventModifierFlags
+    const NS_SHIFT_KEY_MASK: u64 = 1 << 17;
+    const NS_CONTROL_KEY_MASK: u64 = 1 << 18;
+    const NS_ALTERNATE_KEY_MASK: u64 = 1 << 19;
+    const NS_COMMAND_KEY_MASK: u64 = 1 << 20;
+
+    /// 当前按住的修饰键（[NSEvent modifierFlags]，可在任意线程调用）
+    fn current_modifiers() -> Modifiers {
+        use objc::{class, msg_send, sel, sel_impl};
+
+        let flags: u64 = unsafe { msg_send![class!(NSEvent), modifierFlags] };
+        Modifiers {
+            shift: flags & NS_SHIFT_KEY_MASK != 0,
+            control: flags & NS_CONTROL_KEY_MASK != 0,
+            alt: flags & NS_ALTERNATE_KEY_MASK != 0,
+            command: flags & NS_COMMAND_KEY_MASK != 0,
+        }
+    }
+
+    /// 发送合并后的按下/松开事件，按下时附带修饰键
+    pub(super) fn emit(pressed: bool) {
+        let modifiers = if pressed { current_modifiers() } else { Modifiers::default() };
+        // 通过 channel 发送事件，不直接调用回调（避免在 IOKit 线程执行 GUI 操作）
+        if let Some(sender) = FN_EVENT_SENDER.get() {
+            if let Err(e) = sender.send((pressed, modifiers)) {
+                log::error!("[FnKey] Failed to send event: {}", e);
+            }
+        }
+    }
+
+    fn matches(trigger: &Trigger, usage_page: u32, usage: u32) -> bool {
+        match *trigger {
+            // Fn key: Apple vendor page 0xFF or 0xFF00, usage 0x03
+            Trigger::Fn => (usage_page == 0xFF || usage_page == 0xFF00) && usage == 0x03,
+            Trigger::HidUsage { usage: u } => usage_page == K_HID_PAGE_KEYBOARD && usage == u,
+            Trigger::VirtualKey { .. } => false,
+        }
+    }
+
+    /// 两种监听方式共用：更新匹配的触发键或快捷短语键
+    fn handle_key(matches: impl Fn(&Trigger) -> bool, pressed: bool) {
+        super::record_raw_event();
+
+        let Some(index) = super::trigger_index(&matches) else {
+            if let Some(index) = super::snippet_index(&matches) {
+                super::handle_snippet_key(index, pressed);
+            }
+            return;
+        };
+
+        log::info!(
+            "[FnKey] Trigger #{} {} ({})",
+            index,
+            if pressed { "PRESSED" } else { "RELEASED" },
+            backend_name()
+        );
+
+        if let Some(combined) = TRIGGER_STATE.set(index, pressed) {
+            emit(combined);
+        }
+    }
 
     extern "C" fn hid_callback(
         _ctx: *mut c_void,
@@ -70,32 +550,101 @@ mod macos {
             let element = IOHIDValueGetElement(value);
             let usage_page = IOHIDElementGetUsagePage(element);
             let usage = IOHIDElementGetUsage(element);
-            let int_value = IOHIDValueGetIntegerValue(value);
+            let pressed = IOHIDValueGetIntegerValue(value) != 0;
+            handle_key(|t| matches(t, usage_page, usage), pressed);
+        }
+    }
 
-            // Fn key: Apple vendor page 0xFF or 0xFF00, usage 0x03
-            if (usage_page == 0xFF || usage_page == 0xFF00) && usage == 0x03 {
-                let pressed = int_value != 0;
-                log::info!(
-                    "[FnKey] Fn key {} (IOKit callback thread)",
-                    if pressed { "PRESSED" } else { "RELEASED" }
-                );
+    // ============ 备用：CGEventTap ============
 
-                // 通过 channel 发送事件，不直接调用回调（避免在 IOKit 线程执行 GUI 操作）
-                if let Some(sender) = FN_EVENT_SENDER.get() {
-                    if let Err(e) = sender.send(pressed) {
-                        log::error!("[FnKey] Failed to send event: {}", e);
-                    }
+    extern "C" fn tap_callback(
+        _proxy: *mut c_void,
+        event_type: u32,
+        event: CGEventRef,
+        _user_info: *mut c_void,
+    ) -> CGEventRef {
+        unsafe {
+            if matches!(
+                event_type,
+                K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT | K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT
+            ) {
+                log::warn!("[FnKey] Event tap disabled by the system, re-enabling");
+                let tap = EVENT_TAP.load(Ordering::SeqCst);
+                if !tap.is_null() {
+                    CGEventTapEnable(tap as CFMachPortRef, true);
                 }
+                return event;
             }
+
+            let keycode = CGEventGetIntegerValueField(event, K_CG_KEYBOARD_EVENT_KEYCODE) as u16;
+            let pressed = match event_type {
+                // 修饰键（含 Fn）只有 flagsChanged，按标志位判断按下还是松开
+                K_CG_EVENT_FLAGS_CHANGED => match super::modifier_flag(keycode) {
+                    Some(flag) => CGEventGetFlags(event) & flag != 0,
+                    None => return event,
+                },
+                K_CG_EVENT_KEY_DOWN => true,
+                _ => false,
+            };
+            handle_key(|t| super::tap_keycode(t) == Some(keycode), pressed);
+            event
         }
     }
 
-    pub fn start_fn_key_monitor<F>(callback: F) -> std::thread::JoinHandle<()>
+    /// 等待辅助功能权限的检查间隔
+    const ACCESSIBILITY_POLL: std::time::Duration = std::time::Duration::from_secs(3);
+
+    /// 在当前线程创建 CGEventTap 并运行 run loop（需要辅助功能权限），失败时返回
+    ///
+    /// 回调原样返回事件，不拦截按键
+    unsafe fn run_event_tap() {
+        let unsupported: Vec<_> = super::TRIGGERS
+            .read()
+            .map(|t| t.iter().filter(|t| super::tap_keycode(t).is_none()).copied().collect())
+            .unwrap_or_default();
+        if !unsupported.is_empty() {
+            log::warn!("[FnKey] Triggers not supported by the event tap: {:?}", unsupported);
+        }
+
+        let mask = (1u64 << K_CG_EVENT_KEY_DOWN)
+            | (1u64 << K_CG_EVENT_KEY_UP)
+            | (1u64 << K_CG_EVENT_FLAGS_CHANGED);
+        let tap = CGEventTapCreate(
+            K_CG_SESSION_EVENT_TAP,
+            K_CG_HEAD_INSERT_EVENT_TAP,
+            K_CG_EVENT_TAP_OPTION_DEFAULT,
+            mask,
+            tap_callback,
+            std::ptr::null_mut(),
+        );
+        if tap.is_null() {
+            log::error!("[FnKey] Failed to create event tap");
+            return;
+        }
+        EVENT_TAP.store(tap as *mut c_void, Ordering::SeqCst);
+
+        let source = CFMachPortCreateRunLoopSource(kCFAllocatorDefault, tap, 0);
+        let run_loop = CFRunLoop::get_current();
+        CFRunLoopAddSource(run_loop.as_concrete_TypeRef(), source, kCFRunLoopDefaultMode);
+        CGEventTapEnable(tap, true);
+
+        let _ = BACKEND.set("CGEventTap（辅助功能）");
+        let _ = OPENED_AT.set(Instant::now());
+        log::info!("[FnKey] Event tap started, entering run loop");
+        CFRunLoop::run_current();
+    }
+
+    pub fn start_fn_key_monitor<F>(
+        triggers: Vec<Trigger>,
+        callback: F,
+    ) -> std::thread::JoinHandle<()>
     where
-        F: Fn(bool) + Send + Sync + 'static,
+        F: Fn(bool, Modifiers) + Send + Sync + 'static,
     {
+        super::set_triggers(triggers);
+
         // 创建 channel 用于 IOKit 线程和事件处理线程之间通信
-        let (tx, rx) = mpsc::channel::<bool>();
+        let (tx, rx) = mpsc::channel::<(bool, Modifiers)>();
         let _ = FN_EVENT_SENDER.set(tx);
 
         // 启动事件处理线程，接收 IOKit 发来的事件并调用回调
@@ -104,9 +653,13 @@ mod macos {
 
         std::thread::spawn(move || {
             log::info!("[FnKey] Event processor thread started");
-            while let Ok(pressed) = rx.recv() {
-                log::info!("[FnKey] Processing event: pressed={}", pressed);
-                callback_clone(pressed);
+            while let Ok((pressed, modifiers)) = rx.recv() {
+                log::info!(
+                    "[FnKey] Processing event: pressed={}, modifiers={:?}",
+                    pressed,
+                    modifiers
+                );
+                callback_clone(pressed, modifiers);
             }
             log::info!("[FnKey] Event processor thread ended");
         });
@@ -147,9 +700,27 @@ mod macos {
                     "[FnKey] Failed to open HID manager (error: {}). Grant Input Monitoring permission.",
                     result
                 );
+                // 同一时间只保留一种监听，先撤下 HID，再看能否改用 CGEventTap
+                IOHIDManagerUnscheduleFromRunLoop(
+                    manager,
+                    run_loop.as_concrete_TypeRef(),
+                    kCFRunLoopDefaultMode,
+                );
+                CFRelease(manager as CFTypeRef);
+                // 辅助功能可能之后才授权，授权后再启用备用监听
+                if !crate::permissions::check_accessibility() {
+                    log::info!("[FnKey] Waiting for Accessibility to fall back to CGEventTap");
+                    while !crate::permissions::check_accessibility() {
+                        std::thread::sleep(ACCESSIBILITY_POLL);
+                    }
+                }
+                log::info!("[FnKey] Accessibility granted, falling back to CGEventTap");
+                run_event_tap();
                 return;
             }
 
+            let _ = BACKEND.set("IOKit HID");
+            let _ = OPENED_AT.set(Instant::now());
             log::info!("[FnKey] HID monitor started, entering run loop");
             CFRunLoop::run_current();
         })
@@ -159,35 +730,95 @@ mod macos {
 #[cfg(target_os = "macos")]
 pub use macos::start_fn_key_monitor;
 
-// ============ Windows: 右 Alt 长按 ============
+// ============ Windows: 长按触发 ============
 #[cfg(target_os = "windows")]
 mod windows {
-    use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicI64, AtomicPtr, AtomicU32, Ordering};
     use std::sync::OnceLock;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
     use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
-    use winapi::shared::windef::HHOOK;
+    use winapi::shared::windef::HHOOK__;
+    use winapi::um::processthreadsapi::GetCurrentThreadId;
     use winapi::um::winuser::{
-        CallNextHookEx, DispatchMessageW, GetMessageW, SetWindowsHookExW, TranslateMessage,
-        UnhookWindowsHookEx, KBDLLHOOKSTRUCT, WH_KEYBOARD_LL, WM_KEYDOWN, WM_KEYUP,
-        WM_SYSKEYDOWN, WM_SYSKEYUP,
+        CallNextHookEx, DispatchMessageW, GetAsyncKeyState, GetMessageW, PostThreadMessageW,
+        RegisterHotKey, SendInput, SetWindowsHookExW, TranslateMessage, UnhookWindowsHookEx,
+        UnregisterHotKey, INPUT, INPUT_KEYBOARD, KBDLLHOOKSTRUCT, KEYEVENTF_KEYUP, MOD_NOREPEAT,
+        MSG, WH_KEYBOARD_LL, WM_APP, WM_HOTKEY, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
     };
 
-    const VK_RMENU: u32 = 0xA5; // 右 Alt
+    use super::{Modifiers, Trigger, HOOK_REINSTALLS, MAX_TRIGGERS, OPENED_AT, RAW_EVENTS, TRIGGER_STATE};
+
+    const VK_RETURN: u32 = 0x0D;
+    const VK_ESCAPE: u32 = 0x1B;
+    const VK_SHIFT: i32 = 0x10;
+    const VK_CONTROL: i32 = 0x11;
+    const VK_MENU: i32 = 0x12;
+    const VK_LWIN: i32 = 0x5B;
+    const VK_RWIN: i32 = 0x5C;
     const LONG_PRESS_THRESHOLD_MS: u64 = 200;
+    /// 自检按键（F24，几乎没有软件使用），由钩子拦截，不会传给其他应用
+    const VK_SELF_TEST: u16 = 0x87;
+    /// 自检按键的 dwExtraInfo 标记
+    const SELF_TEST_MARK: usize = 0x5459_5046;
+
+    /// 发给监听线程的消息：重装钩子
+    const WM_REINSTALL_HOOK: u32 = WM_APP + 1;
+    /// 发给监听线程的消息：重新注册快捷短语热键（兼容模式）
+    const WM_REGISTER_SNIPPETS: u32 = WM_APP + 2;
+    /// 发给监听线程的消息：注册/注销确认粘贴的 Enter/Esc（兼容模式，wParam 为 1 表示注册）
+    const WM_REVIEW_KEYS: u32 = WM_APP + 3;
+
+    /// 钩子看门狗检查触发键状态的间隔
+    const HOOK_WATCH_INTERVAL: Duration = Duration::from_millis(200);
+    /// 发出自检按键后等待钩子收到的时间
+    const HOOK_PROBE_WAIT: Duration = Duration::from_millis(500);
+    /// 重装后至少隔这么久再检查，避免重装无效时反复重装
+    const HOOK_REINSTALL_COOLDOWN: Duration = Duration::from_secs(30);
 
-    // HHOOK 是裸指针，不实现 Sync，需要包装
-    struct HookHandle(HHOOK);
-    unsafe impl Send for HookHandle {}
-    unsafe impl Sync for HookHandle {}
+    /// 兼容模式下轮询触发键的间隔
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+    /// 兼容模式热键 id：快捷短语从 1 开始，确认/取消使用固定 id
+    const SNIPPET_HOTKEY_BASE: i32 = 1;
+    const REVIEW_CONFIRM_HOTKEY: i32 = 0x100;
+    const REVIEW_CANCEL_HOTKEY: i32 = 0x101;
 
-    static CALLBACK: OnceLock<Box<dyn Fn(bool) + Send + Sync>> = OnceLock::new();
-    static HOOK: OnceLock<HookHandle> = OnceLock::new();
-    static IS_PRESSED: AtomicBool = AtomicBool::new(false);
-    static LONG_PRESS_TRIGGERED: AtomicBool = AtomicBool::new(false);
-    // 使用 AtomicI64 存储按下时间戳（毫秒），避免 static mut 的不安全性
-    // 0 表示未按下
-    static PRESS_TIME_MS: AtomicI64 = AtomicI64::new(0);
+    /// 单个触发键的长按状态
+    struct KeyPress {
+        is_pressed: AtomicBool,
+        long_press_triggered: AtomicBool,
+        // 使用 AtomicI64 存储按下时间戳（毫秒），避免 static mut 的不安全性
+        // 0 表示未按下
+        press_time_ms: AtomicI64,
+    }
+
+    impl KeyPress {
+        const fn new() -> Self {
+            Self {
+                is_pressed: AtomicBool::new(false),
+                long_press_triggered: AtomicBool::new(false),
+                press_time_ms: AtomicI64::new(0),
+            }
+        }
+
+        fn reset(&self) {
+            self.is_pressed.store(false, Ordering::SeqCst);
+            self.long_press_triggered.store(false, Ordering::SeqCst);
+            self.press_time_ms.store(0, Ordering::SeqCst);
+        }
+    }
+
+    static CALLBACK: OnceLock<Box<dyn Fn(bool, Modifiers) + Send + Sync>> = OnceLock::new();
+    // 当前安装的钩子（重装时替换）
+    static HOOK: AtomicPtr<HHOOK__> = AtomicPtr::new(std::ptr::null_mut());
+    // 监听线程 id，用于向它的消息循环发送消息（0 表示未启动）
+    static LISTENER_THREAD: AtomicU32 = AtomicU32::new(0);
+    // 使用兼容模式（RegisterHotKey + 按键轮询）
+    static COMPAT_MODE: AtomicBool = AtomicBool::new(false);
+    // 确认粘贴模式：拦截 Enter/Esc 并回调 (true=确认, false=取消)
+    static REVIEW_CALLBACK: OnceLock<std::sync::Arc<dyn Fn(bool) + Send + Sync>> = OnceLock::new();
+    static REVIEW_KEYS_ACTIVE: AtomicBool = AtomicBool::new(false);
+    // 按触发键序号记录长按状态
+    static KEY_PRESSES: [KeyPress; MAX_TRIGGERS] = [const { KeyPress::new() }; MAX_TRIGGERS];
 
     fn current_time_ms() -> i64 {
         SystemTime::now()
@@ -196,6 +827,132 @@ mod windows {
             .unwrap_or(0)
     }
 
+    /// 当前使用的监听方式
+    pub(super) fn backend_name() -> &'static str {
+        if COMPAT_MODE.load(Ordering::SeqCst) {
+            "兼容模式（RegisterHotKey + 按键轮询）"
+        } else {
+            "低级键盘钩子"
+        }
+    }
+
+    /// 发出一次自检按键（钩子正常时一定能收到，被其他软件的钩子吞掉时收不到），返回是否已发出
+    pub(super) fn probe_key_activity(_opened: Instant) -> bool {
+        unsafe {
+            let mut inputs: [INPUT; 2] = std::mem::zeroed();
+            for (input, up) in inputs.iter_mut().zip([false, true]) {
+                input.type_ = INPUT_KEYBOARD;
+                let ki = input.u.ki_mut();
+                ki.wVk = VK_SELF_TEST;
+                ki.dwFlags = if up { KEYEVENTF_KEYUP } else { 0 };
+                ki.dwExtraInfo = SELF_TEST_MARK;
+            }
+            let sent = SendInput(2, inputs.as_mut_ptr(), std::mem::size_of::<INPUT>() as i32);
+            sent == 2
+        }
+    }
+
+    /// 当前按住的修饰键
+    fn current_modifiers() -> Modifiers {
+        Modifiers {
+            shift: is_key_down(VK_SHIFT),
+            control: is_key_down(VK_CONTROL),
+            alt: is_key_down(VK_MENU),
+            command: is_key_down(VK_LWIN) || is_key_down(VK_RWIN),
+        }
+    }
+
+    fn is_key_down(vk: i32) -> bool {
+        unsafe { GetAsyncKeyState(vk) as u16 & 0x8000 != 0 }
+    }
+
+    /// 发送合并后的按下/松开事件，按下时附带修饰键
+    pub(super) fn emit(pressed: bool) {
+        let modifiers = if pressed { current_modifiers() } else { Modifiers::default() };
+        if let Some(cb) = CALLBACK.get() {
+            cb(pressed, modifiers);
+        }
+    }
+
+    /// 清空所有触发键的长按状态
+    pub(super) fn reset_key_states() {
+        for key in &KEY_PRESSES {
+            key.reset();
+        }
+    }
+
+    /// 长按计时：阈值到达时若仍是同一次按下则触发（不依赖系统按键重复）
+    fn start_long_press_timer(index: usize, vk: u32, press_time: i64) {
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(LONG_PRESS_THRESHOLD_MS));
+            let key = &KEY_PRESSES[index];
+            // 松开（或重新按下）后时间戳已变化，视为计时取消
+            if !key.is_pressed.load(Ordering::SeqCst)
+                || key.press_time_ms.load(Ordering::SeqCst) != press_time
+            {
+                return;
+            }
+            if key
+                .long_press_triggered
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                log::info!("[FnKey] VK 0x{:02X} LONG PRESS", vk);
+                if let Some(combined) = TRIGGER_STATE.set(index, true) {
+                    emit(combined);
+                }
+            }
+        });
+    }
+
+    /// 处理第 index 个触发键的按键事件
+    fn handle_trigger_key(index: usize, vk: u32, w_param: u32) {
+        let key = &KEY_PRESSES[index];
+        match w_param {
+            WM_KEYDOWN | WM_SYSKEYDOWN => {
+                // 按键重复直接忽略，长按由计时线程判定
+                if !key.is_pressed.swap(true, Ordering::SeqCst) {
+                    key.long_press_triggered.store(false, Ordering::SeqCst);
+                    let press_time = current_time_ms();
+                    key.press_time_ms.store(press_time, Ordering::SeqCst);
+                    log::info!("[FnKey] VK 0x{:02X} PRESSED", vk);
+                    start_long_press_timer(index, vk, press_time);
+                }
+            }
+            WM_KEYUP | WM_SYSKEYUP => {
+                if key.is_pressed.swap(false, Ordering::SeqCst) {
+                    // 取消计时：计时线程会发现时间戳已清零
+                    key.press_time_ms.store(0, Ordering::SeqCst);
+
+                    // 与计时线程竞争：先把标记置为已处理，计时线程就不会再触发
+                    let was_long_press = key.long_press_triggered.swap(true, Ordering::SeqCst);
+                    log::info!(
+                        "[FnKey] VK 0x{:02X} RELEASED (was_long_press={})",
+                        vk,
+                        was_long_press
+                    );
+
+                    if was_long_press {
+                        // 长按结束，所有触发键都松开后停止录音
+                        if let Some(combined) = TRIGGER_STATE.set(index, false) {
+                            emit(combined);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 确认/取消已按下，在新线程中回调（回调中会模拟粘贴，不能在监听线程里执行）
+    fn handle_review_key(confirmed: bool) {
+        log::info!("[FnKey] Review key: {}", if confirmed { "Enter" } else { "Esc" });
+        if let Some(cb) = REVIEW_CALLBACK.get() {
+            let cb = cb.clone();
+            std::thread::spawn(move || cb(confirmed));
+        }
+    }
+
     unsafe extern "system" fn keyboard_hook(
         code: i32,
         w_param: WPARAM,
@@ -203,114 +960,389 @@ mod windows {
     ) -> LRESULT {
         if code >= 0 {
             let kb = *(l_param as *const KBDLLHOOKSTRUCT);
+            super::record_raw_event();
 
-            if kb.vkCode == VK_RMENU {
+            // 自检按键只用来确认钩子收得到事件
+            if kb.dwExtraInfo == SELF_TEST_MARK {
+                return 1;
+            }
+
+            // 等待确认时拦截 Enter/Esc，不传给目标应用
+            if REVIEW_KEYS_ACTIVE.load(Ordering::SeqCst)
+                && (kb.vkCode == VK_RETURN || kb.vkCode == VK_ESCAPE)
+            {
+                if matches!(w_param as u32, WM_KEYDOWN | WM_SYSKEYDOWN) {
+                    handle_review_key(kb.vkCode == VK_RETURN);
+                }
+                return 1;
+            }
+
+            let vk = kb.vkCode;
+            if let Some(index) = super::trigger_index(|t| *t == Trigger::VirtualKey { vk }) {
+                handle_trigger_key(index, vk, w_param as u32);
+            } else if let Some(index) = super::snippet_index(|t| *t == Trigger::VirtualKey { vk }) {
                 match w_param as u32 {
-                    WM_KEYDOWN | WM_SYSKEYDOWN => {
-                        if !IS_PRESSED.load(Ordering::SeqCst) {
-                            IS_PRESSED.store(true, Ordering::SeqCst);
-                            LONG_PRESS_TRIGGERED.store(false, Ordering::SeqCst);
-                            PRESS_TIME_MS.store(current_time_ms(), Ordering::SeqCst);
-                            log::info!("[FnKey] Right Alt PRESSED");
-                        } else {
-                            // 按键重复时检查是否达到长按阈值
-                            if !LONG_PRESS_TRIGGERED.load(Ordering::SeqCst) {
-                                let press_time = PRESS_TIME_MS.load(Ordering::SeqCst);
-                                if press_time > 0 {
-                                    let elapsed = current_time_ms() - press_time;
-                                    if elapsed > LONG_PRESS_THRESHOLD_MS as i64 {
-                                        LONG_PRESS_TRIGGERED.store(true, Ordering::SeqCst);
-                                        log::info!("[FnKey] Right Alt LONG PRESS - Start recording");
-                                        if let Some(cb) = CALLBACK.get() {
-                                            cb(true);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    WM_KEYUP | WM_SYSKEYUP => {
-                        if IS_PRESSED.load(Ordering::SeqCst) {
-                            IS_PRESSED.store(false, Ordering::SeqCst);
-
-                            let was_long_press = LONG_PRESS_TRIGGERED.load(Ordering::SeqCst);
-                            log::info!(
-                                "[FnKey] Right Alt RELEASED (was_long_press={})",
-                                was_long_press
-                            );
-
-                            if was_long_press {
-                                // 长按结束，停止录音
-                                if let Some(cb) = CALLBACK.get() {
-                                    cb(false);
-                                }
-                            }
-                            PRESS_TIME_MS.store(0, Ordering::SeqCst);
-                        }
-                    }
+                    WM_KEYDOWN | WM_SYSKEYDOWN => super::handle_snippet_key(index, true),
+                    WM_KEYUP | WM_SYSKEYUP => super::handle_snippet_key(index, false),
                     _ => {}
                 }
             }
         }
 
-        let hook = HOOK.get().map(|h| h.0).unwrap_or(std::ptr::null_mut());
-        CallNextHookEx(hook, code, w_param, l_param)
+        CallNextHookEx(HOOK.load(Ordering::SeqCst), code, w_param, l_param)
     }
 
-    pub fn start_fn_key_monitor<F>(callback: F) -> std::thread::JoinHandle<()>
+    /// 设置确认粘贴模式的按键回调
+    pub fn set_review_key_handler<F>(handler: F)
     where
         F: Fn(bool) + Send + Sync + 'static,
     {
-        let _ = CALLBACK.set(Box::new(callback));
+        let _ = REVIEW_CALLBACK.set(std::sync::Arc::new(handler));
+    }
 
-        std::thread::spawn(|| unsafe {
-            log::info!("[FnKey] Starting Windows keyboard hook...");
+    /// 开始/停止拦截 Enter/Esc
+    pub fn set_review_keys_active(active: bool) {
+        REVIEW_KEYS_ACTIVE.store(active, Ordering::SeqCst);
+        // 兼容模式没有钩子，改为临时注册热键
+        if COMPAT_MODE.load(Ordering::SeqCst) {
+            post_to_listener(WM_REVIEW_KEYS, active as usize);
+        }
+    }
 
-            let hook =
-                SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), std::ptr::null_mut(), 0);
+    /// 快捷短语键已变化（兼容模式需要重新注册热键）
+    pub(super) fn snippet_keys_changed() {
+        if COMPAT_MODE.load(Ordering::SeqCst) {
+            post_to_listener(WM_REGISTER_SNIPPETS, 0);
+        }
+    }
 
-            if hook.is_null() {
-                let error = std::io::Error::last_os_error();
-                log::error!(
-                    "[FnKey] Failed to set keyboard hook: {} (error code: {})",
-                    error,
-                    error.raw_os_error().unwrap_or(-1)
+    /// 向监听线程的消息循环发送消息
+    fn post_to_listener(message: u32, w_param: usize) {
+        let thread = LISTENER_THREAD.load(Ordering::SeqCst);
+        if thread == 0 {
+            return;
+        }
+        let posted = unsafe { PostThreadMessageW(thread, message, w_param, 0) };
+        if posted == 0 {
+            log::warn!(
+                "[FnKey] Failed to post message 0x{:X} to the key listener: {}",
+                message,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    // ============ 键盘钩子 ============
+
+    /// 安装低级键盘钩子（必须在有消息循环的监听线程中调用），返回是否成功
+    unsafe fn install_hook() -> bool {
+        let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), std::ptr::null_mut(), 0);
+        if hook.is_null() {
+            let error = std::io::Error::last_os_error();
+            log::error!(
+                "[FnKey] Failed to set keyboard hook: {} (error code: {})",
+                error,
+                error.raw_os_error().unwrap_or(-1)
+            );
+            return false;
+        }
+        HOOK.store(hook, Ordering::SeqCst);
+        true
+    }
+
+    /// 卸载当前钩子
+    unsafe fn uninstall_hook() {
+        let hook = HOOK.swap(std::ptr::null_mut(), Ordering::SeqCst);
+        if !hook.is_null() {
+            UnhookWindowsHookEx(hook);
+        }
+    }
+
+    /// 钩子被摘除后重装（在监听线程中执行）
+    unsafe fn reinstall_hook() {
+        uninstall_hook();
+        if install_hook() {
+            let count = HOOK_REINSTALLS.fetch_add(1, Ordering::SeqCst) + 1;
+            log::warn!("[FnKey] Keyboard hook reinstalled (#{})", count);
+        }
+    }
+
+    /// 系统显示按着、钩子却没记录按下的触发键是否存在
+    fn trigger_missed_by_hook() -> bool {
+        let triggers = super::TRIGGERS.read().map(|t| t.clone()).unwrap_or_default();
+        triggers.iter().zip(&KEY_PRESSES).any(|(trigger, key)| match *trigger {
+            Trigger::VirtualKey { vk } => {
+                is_key_down(vk as i32) && !key.is_pressed.load(Ordering::SeqCst)
+            }
+            _ => false,
+        })
+    }
+
+    /// 钩子看门狗：触发键按下而钩子没收到时发自检按键确认，确认失效后让监听线程重装钩子
+    ///
+    /// 只看触发键，不看鼠标等其他输入，平时不会发出自检按键
+    fn spawn_hook_watchdog() {
+        std::thread::spawn(|| {
+            let mut missed = 0;
+            loop {
+                std::thread::sleep(HOOK_WATCH_INTERVAL);
+                let (count, stalled) = super::count_missed_press(missed, trigger_missed_by_hook());
+                missed = count;
+                if !stalled {
+                    continue;
+                }
+                missed = 0;
+
+                let before = RAW_EVENTS.load(Ordering::Relaxed);
+                if !probe_key_activity(Instant::now()) {
+                    continue;
+                }
+                std::thread::sleep(HOOK_PROBE_WAIT);
+                if RAW_EVENTS.load(Ordering::Relaxed) != before {
+                    continue;
+                }
+
+                log::warn!("[FnKey] Keyboard hook missed a trigger key, reinstalling...");
+                post_to_listener(WM_REINSTALL_HOOK, 0);
+                std::thread::sleep(HOOK_REINSTALL_COOLDOWN);
+            }
+        });
+    }
+
+    // ============ 兼容模式 ============
+
+    /// 按当前快捷短语键重新注册热键（必须在监听线程中调用）
+    unsafe fn register_snippet_hotkeys(registered: &mut Vec<i32>) {
+        for id in registered.drain(..) {
+            UnregisterHotKey(std::ptr::null_mut(), id);
+        }
+        let keys = super::SNIPPET_KEYS.read().map(|k| k.clone()).unwrap_or_default();
+        for (index, key) in keys.iter().enumerate() {
+            let Trigger::VirtualKey { vk } = *key else {
+                continue;
+            };
+            let id = SNIPPET_HOTKEY_BASE + index as i32;
+            if RegisterHotKey(std::ptr::null_mut(), id, MOD_NOREPEAT as u32, vk) != 0 {
+                registered.push(id);
+            } else {
+                log::warn!(
+                    "[FnKey] Failed to register snippet hotkey VK 0x{:02X}: {}",
+                    vk,
+                    std::io::Error::last_os_error()
                 );
-                log::error!("[FnKey] This may be due to security software blocking the hook.");
-                log::error!("[FnKey] Try running the application as Administrator.");
-                return;
             }
+        }
+    }
 
-            let _ = HOOK.set(HookHandle(hook));
-            log::info!("[FnKey] Right Alt key monitor started (long press to activate)");
+    /// 注册/注销确认粘贴的 Enter/Esc（必须在监听线程中调用）
+    unsafe fn register_review_hotkeys(active: bool) {
+        if active {
+            RegisterHotKey(std::ptr::null_mut(), REVIEW_CONFIRM_HOTKEY, MOD_NOREPEAT as u32, VK_RETURN);
+            RegisterHotKey(std::ptr::null_mut(), REVIEW_CANCEL_HOTKEY, MOD_NOREPEAT as u32, VK_ESCAPE);
+        } else {
+            UnregisterHotKey(std::ptr::null_mut(), REVIEW_CONFIRM_HOTKEY);
+            UnregisterHotKey(std::ptr::null_mut(), REVIEW_CANCEL_HOTKEY);
+        }
+    }
 
-            // 标准 Windows 消息循环
-            let mut msg = std::mem::zeroed();
-            while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
-                TranslateMessage(&msg);
-                DispatchMessageW(&msg);
+    /// 处理 WM_HOTKEY
+    fn handle_hotkey(id: i32) {
+        match id {
+            REVIEW_CONFIRM_HOTKEY => handle_review_key(true),
+            REVIEW_CANCEL_HOTKEY => handle_review_key(false),
+            id => {
+                // 热键只有按下事件，随即补一次松开
+                let index = (id - SNIPPET_HOTKEY_BASE) as usize;
+                super::handle_snippet_key(index, true);
+                super::handle_snippet_key(index, false);
+            }
+        }
+    }
+
+    /// 轮询触发键的按下状态（RegisterHotKey 收不到松开，按住说话改用轮询）
+    fn spawn_trigger_poller() {
+        std::thread::spawn(|| {
+            let mut held = [false; MAX_TRIGGERS];
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let triggers = super::TRIGGERS.read().map(|t| t.clone()).unwrap_or_default();
+                for (index, trigger) in triggers.iter().enumerate() {
+                    let Trigger::VirtualKey { vk } = *trigger else {
+                        continue;
+                    };
+                    let down = is_key_down(vk as i32);
+                    if down != held[index] {
+                        held[index] = down;
+                        let w_param = if down { WM_KEYDOWN } else { WM_KEYUP };
+                        handle_trigger_key(index, vk, w_param);
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn start_fn_key_monitor<F>(
+        triggers: Vec<Trigger>,
+        callback: F,
+    ) -> std::thread::JoinHandle<()>
+    where
+        F: Fn(bool, Modifiers) + Send + Sync + 'static,
+    {
+        let _ = CALLBACK.set(Box::new(callback));
+        super::set_triggers(triggers);
+        let compat = crate::settings::get().hotkey_compat_mode;
+        COMPAT_MODE.store(compat, Ordering::SeqCst);
+
+        std::thread::spawn(move || unsafe {
+            LISTENER_THREAD.store(GetCurrentThreadId(), Ordering::SeqCst);
+            let mut snippet_hotkeys = Vec::new();
+
+            if compat {
+                log::info!("[FnKey] Starting Windows key listener in compatibility mode...");
+                register_snippet_hotkeys(&mut snippet_hotkeys);
+                spawn_trigger_poller();
+                log::info!("[FnKey] Compatibility mode started (polling triggers, hotkeys for snippets)");
+            } else {
+                log::info!("[FnKey] Starting Windows keyboard hook...");
+                if !install_hook() {
+                    log::error!("[FnKey] This may be due to security software blocking the hook.");
+                    log::error!("[FnKey] Try running the application as Administrator, or enable compatibility mode.");
+                    return;
+                }
+                let _ = OPENED_AT.set(Instant::now());
+                spawn_hook_watchdog();
+                log::info!("[FnKey] Keyboard hook started (long press to activate)");
             }
 
-            // 清理钩子
-            if let Some(hook_handle) = HOOK.get() {
-                if !hook_handle.0.is_null() {
-                    UnhookWindowsHookEx(hook_handle.0);
-                    log::info!("[FnKey] Keyboard hook uninstalled");
+            // 标准 Windows 消息循环（同时处理发给本线程的消息）
+            let mut msg: MSG = std::mem::zeroed();
+            while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+                match msg.message {
+                    WM_REINSTALL_HOOK => reinstall_hook(),
+                    WM_REGISTER_SNIPPETS => register_snippet_hotkeys(&mut snippet_hotkeys),
+                    WM_REVIEW_KEYS => register_review_hotkeys(msg.wParam != 0),
+                    WM_HOTKEY => handle_hotkey(msg.wParam as i32),
+                    _ => {
+                        TranslateMessage(&msg);
+                        DispatchMessageW(&msg);
+                    }
                 }
             }
 
+            // 清理钩子和热键
+            uninstall_hook();
+            for id in snippet_hotkeys {
+                UnregisterHotKey(std::ptr::null_mut(), id);
+            }
+            register_review_hotkeys(false);
+            LISTENER_THREAD.store(0, Ordering::SeqCst);
+
             log::info!("[FnKey] Message loop ended");
         })
     }
 }
 
 #[cfg(target_os = "windows")]
-pub use windows::start_fn_key_monitor;
+pub use windows::{set_review_key_handler, set_review_keys_active, start_fn_key_monitor};
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn start_fn_key_monitor<F>(_callback: F) -> std::thread::JoinHandle<()>
+// 其他平台由浮层面板获取键盘焦点接收 Enter/Esc，这里无需处理
+#[cfg(not(target_os = "windows"))]
+pub fn set_review_key_handler<F>(_handler: F)
 where
     F: Fn(bool) + Send + Sync + 'static,
+{
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn set_review_keys_active(_active: bool) {}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn start_fn_key_monitor<F>(_triggers: Vec<Trigger>, _callback: F) -> std::thread::JoinHandle<()>
+where
+    F: Fn(bool, Modifiers) + Send + Sync + 'static,
 {
     std::thread::spawn(|| log::warn!("[FnKey] Key monitoring not supported on this platform"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_trigger_starts_and_last_release_stops() {
+        let state = TriggerState(AtomicU64::new(0));
+        assert_eq!(state.set(0, true), Some(true));
+        // 第二个键按下不重复触发
+        assert_eq!(state.set(1, true), None);
+        assert_eq!(state.set(0, false), None);
+        assert_eq!(state.set(1, false), Some(false));
+        // 未按下的键松开不触发
+        assert_eq!(state.set(2, false), None);
+    }
+
+    #[test]
+    fn snippet_key_fires_once_per_press() {
+        let held = AtomicU64::new(0);
+        assert!(snippet_first_press(&held, 3, true));
+        // 按键重复不再触发
+        assert!(!snippet_first_press(&held, 3, true));
+        assert!(snippet_first_press(&held, 0, true));
+        assert!(!snippet_first_press(&held, 3, false));
+        assert!(snippet_first_press(&held, 3, true));
+    }
+
+    #[test]
+    fn hook_conflict_needs_activity_and_permission() {
+        let late = CONFLICT_GRACE + Duration::from_secs(1);
+        assert_eq!(judge_hook(late, false, true, true), HookHealth::Blocked);
+        // 收到过任何按键就是正常的
+        assert_eq!(judge_hook(late, true, true, true), HookHealth::Working);
+        assert_eq!(
+            judge_hook(Duration::from_secs(1), true, false, false),
+            HookHealth::Working
+        );
+        // 宽限期内、没有键盘输入或没有权限时不下结论
+        assert_eq!(
+            judge_hook(Duration::from_secs(5), false, true, true),
+            HookHealth::Unknown
+        );
+        assert_eq!(judge_hook(late, false, false, true), HookHealth::Unknown);
+        assert_eq!(judge_hook(late, false, true, false), HookHealth::Unknown);
+    }
+
+    #[test]
+    fn missed_press_needs_consecutive_checks() {
+        let (missed, stalled) = count_missed_press(0, true);
+        assert!(!stalled);
+        assert_eq!(count_missed_press(missed, true), (2, true));
+        // 钩子收到了按键（或触发键已松开）时清零
+        assert_eq!(count_missed_press(missed, false), (0, false));
+    }
+
+    #[test]
+    fn event_tap_keycodes() {
+        assert_eq!(tap_keycode(&Trigger::Fn), Some(63));
+        assert_eq!(tap_keycode(&Trigger::HidUsage { usage: 0xE6 }), Some(61));
+        assert_eq!(tap_keycode(&Trigger::HidUsage { usage: 0x04 }), None);
+        assert_eq!(tap_keycode(&Trigger::VirtualKey { vk: 0xA5 }), None);
+        // 修饰键按标志位判断，右 Option 与左 Option 区分开
+        assert_eq!(modifier_flag(63), Some(0x0080_0000));
+        assert_ne!(modifier_flag(61), modifier_flag(58));
+        assert_eq!(modifier_flag(105), None);
+    }
+
+    #[test]
+    fn trigger_serde_format() {
+        let triggers = vec![
+            Trigger::Fn,
+            Trigger::HidUsage { usage: 0xE6 },
+            Trigger::VirtualKey { vk: 0xA5 },
+        ];
+        let json = serde_json::to_string(&triggers).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"kind":"fn"},{"kind":"hid_usage","usage":230},{"kind":"virtual_key","vk":165}]"#
+        );
+        assert_eq!(serde_json::from_str::<Vec<Trigger>>(&json).unwrap(), triggers);
+    }
+}
@@ -0,0 +1,435 @@
+//! 历史导出 - 把转写记录导出为 Markdown、CSV 或 JSON Lines
+//!
+//! 记录来自本次运行的实时转写，逐条写入文件，不先拼成完整的字符串。
+//! 可按时间范围筛选；脱敏导出只保留时间、时长和字数，正文换成带本机密钥的哈希
+
+use crate::transcript::Entry;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// 脱敏哈希密钥的文件名（每次安装随机生成，同一台机器上相同内容的哈希一致）
+const REDACTION_KEY_FILE: &str = "history_redaction_key";
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// 每条一个标题（时间）
+    Markdown,
+    /// timestamp, duration_ms, chars, text
+    Csv,
+    /// 每行一个 JSON 对象
+    Jsonl,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// 时间范围（Unix 毫秒，含起点不含终点），None 表示不限
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct TimeRange {
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+}
+
+impl TimeRange {
+    fn contains(&self, ms: u64) -> bool {
+        self.from_ms.is_none_or(|from| ms >= from) && self.to_ms.is_none_or(|to| ms < to)
+    }
+
+    /// 本地时间的今天
+    pub fn today() -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default();
+        Self::day_of(now, local_offset_secs())
+    }
+
+    /// `ms` 所在的本地日（`offset_secs` 为本地时间相对 UTC 的偏移）
+    fn day_of(ms: i64, offset_secs: i64) -> Self {
+        let offset_ms = offset_secs * 1000;
+        let start = (ms + offset_ms).div_euclid(DAY_MS) * DAY_MS - offset_ms;
+        Self {
+            from_ms: Some(start.max(0) as u64),
+            to_ms: Some((start + DAY_MS).max(0) as u64),
+        }
+    }
+}
+
+/// 导出符合范围的记录到 `path`（目录时在其中新建文件），返回写入的文件路径
+pub fn export(
+    path: &Path,
+    format: ExportFormat,
+    range: TimeRange,
+    redact: bool,
+) -> Result<PathBuf, String> {
+    let key = if redact { Some(redaction_key()?) } else { None };
+    crate::transcript::with_entries(|entries| {
+        let matching = || {
+            entries
+                .iter()
+                .filter(|entry| range.contains(entry.timestamp_ms))
+        };
+        let count = matching().count();
+        if count == 0 {
+            return Err("所选时间范围内没有记录".to_string());
+        }
+        let path = write_file(path, format, |writer| {
+            write_entries(
+                writer,
+                matching(),
+                format,
+                key.as_deref(),
+                local_offset_secs(),
+            )
+        })?;
+
+        log::info!(
+            "[History] Exported {} entries as {:?}{} to {}",
+            count,
+            format,
+            if redact { " (redacted)" } else { "" },
+            path.display()
+        );
+        Ok(path)
+    })
+}
+
+/// 创建导出文件（`path` 为目录时在其中新建），用 `write` 写入内容
+fn write_file(
+    path: &Path,
+    format: ExportFormat,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> Result<PathBuf, String> {
+    let path = if path.is_dir() {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        path.join(format!("typefree-history-{}.{}", secs, format.extension()))
+    } else {
+        path.to_path_buf()
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let file =
+        File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// 逐条写入记录，`key` 为 Some 时正文换成哈希
+fn write_entries<'a>(
+    writer: &mut impl Write,
+    entries: impl Iterator<Item = &'a Entry>,
+    format: ExportFormat,
+    key: Option<&[u8]>,
+    offset_secs: i64,
+) -> std::io::Result<()> {
+    match format {
+        ExportFormat::Markdown => writeln!(writer, "# TypeFree 转写记录\n")?,
+        ExportFormat::Csv => writeln!(writer, "timestamp,duration_ms,chars,text")?,
+        ExportFormat::Jsonl => {}
+    }
+    for entry in entries {
+        write_entry(writer, entry, format, key, offset_secs)?;
+    }
+    Ok(())
+}
+
+fn write_entry(
+    writer: &mut impl Write,
+    entry: &Entry,
+    format: ExportFormat,
+    key: Option<&[u8]>,
+    offset_secs: i64,
+) -> std::io::Result<()> {
+    let time = format_local(entry.timestamp_ms, offset_secs);
+    let chars = entry.text.chars().count();
+    let redact = key.is_some();
+    let text = match key {
+        Some(key) => text_hash(key, &entry.text),
+        None => entry.text.clone(),
+    };
+
+    match format {
+        ExportFormat::Markdown => {
+            let mark = if entry.discarded {
+                "（已忽略）"
+            } else if entry.undone {
+                "（已撤销）"
+            } else if entry.repasted {
+                "（已重新粘贴）"
+            } else {
+                ""
+            };
+            writeln!(writer, "## {}{}\n", time, mark)?;
+            if redact {
+                writeln!(
+                    writer,
+                    "`{}`（{} 字，{}ms）\n",
+                    text, chars, entry.duration_ms
+                )
+            } else {
+                writeln!(writer, "{}\n", text)
+            }
+        }
+        ExportFormat::Csv => writeln!(
+            writer,
+            "{},{},{},{}",
+            time,
+            entry.duration_ms,
+            chars,
+            csv_field(&text)
+        ),
+        ExportFormat::Jsonl => {
+            let text_key = if redact { "text_hash" } else { "text" };
+            let line = serde_json::json!({
+                "timestamp": time,
+                "timestamp_ms": entry.timestamp_ms,
+                "duration_ms": entry.duration_ms,
+                "chars": chars,
+                text_key: text,
+                "discarded": entry.discarded,
+                "repasted": entry.repasted,
+                "undone": entry.undone,
+                "language": entry.language,
+            });
+            writeln!(writer, "{}", line)
+        }
+    }
+}
+
+/// CSV 字段：含逗号、引号或换行时加引号；以 = + - @ 开头时加单引号前缀，
+/// 避免表格软件当作公式执行
+fn csv_field(text: &str) -> String {
+    let text = if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", text)
+    } else {
+        text.to_string()
+    };
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// 正文的哈希（HMAC-SHA256，密钥每次安装随机生成，短文本无法逐一枚举还原）
+fn text_hash(key: &[u8], text: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(text.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// 本机的脱敏哈希密钥，第一次使用时随机生成并保存在配置目录
+fn redaction_key() -> Result<Vec<u8>, String> {
+    let dir = crate::settings::config_dir().ok_or("配置目录不可用")?;
+    let path = dir.join(REDACTION_KEY_FILE);
+    if let Ok(key) = std::fs::read(&path) {
+        if key.len() == 32 {
+            return Ok(key);
+        }
+        log::warn!("[History] Invalid redaction key, generating a new one");
+    }
+
+    let mut key = vec![0u8; 32];
+    getrandom::getrandom(&mut key).map_err(|e| format!("Failed to generate key: {}", e))?;
+    std::fs::write(&path, &key)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    log::info!("[History] Generated redaction key");
+    Ok(key)
+}
+
+/// 本地时间 `YYYY-MM-DD HH:MM:SS`
+fn format_local(ms: u64, offset_secs: i64) -> String {
+    let secs = (ms / 1000) as i64 + offset_secs;
+    let (days, rest) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// 1970-01-01 起的天数 → (年, 月, 日)（Howard Hinnant 的算法）
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// 本地时间相对 UTC 的偏移（秒）
+#[cfg(target_os = "macos")]
+fn local_offset_secs() -> i64 {
+    use core_foundation_sys::base::CFRelease;
+    use core_foundation_sys::date::CFAbsoluteTimeGetCurrent;
+    use core_foundation_sys::timezone::{CFTimeZoneCopySystem, CFTimeZoneGetSecondsFromGMT};
+
+    unsafe {
+        let zone = CFTimeZoneCopySystem();
+        if zone.is_null() {
+            return 0;
+        }
+        let offset = CFTimeZoneGetSecondsFromGMT(zone, CFAbsoluteTimeGetCurrent());
+        CFRelease(zone as _);
+        offset as i64
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn local_offset_secs() -> i64 {
+    use winapi::um::timezoneapi::{GetTimeZoneInformation, TIME_ZONE_INFORMATION};
+
+    const TIME_ZONE_ID_DAYLIGHT: u32 = 2;
+    unsafe {
+        let mut info: TIME_ZONE_INFORMATION = std::mem::zeroed();
+        let bias = match GetTimeZoneInformation(&mut info) {
+            TIME_ZONE_ID_DAYLIGHT => info.Bias + info.DaylightBias,
+            _ => info.Bias + info.StandardBias,
+        };
+        // Bias 为 UTC 减本地时间（分钟）
+        -(bias as i64) * 60
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn local_offset_secs() -> i64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, timestamp_ms: u64) -> Entry {
+        Entry {
+            text: text.to_string(),
+            discarded: false,
+            language: Some("zh".to_string()),
+            timestamp_ms,
+            duration_ms: 2500,
+            repasted: false,
+            undone: false,
+        }
+    }
+
+    fn render(entries: &[Entry], format: ExportFormat, redact: bool) -> String {
+        let key = redact.then_some(KEY);
+        let mut out = Vec::new();
+        write_entries(&mut out, entries.iter(), format, key, 8 * 3600).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    const KEY: &[u8] = b"test-redaction-key";
+
+    // 2026-10-16 06:00:00 UTC（北京时间 14:00）
+    const AFTERNOON_MS: u64 = 1_792_130_400_000;
+
+    #[test]
+    fn test_format_local() {
+        assert_eq!(format_local(0, 0), "1970-01-01 00:00:00");
+        assert_eq!(format_local(AFTERNOON_MS, 8 * 3600), "2026-10-16 14:00:00");
+        // 跨日和闰年
+        assert_eq!(format_local(AFTERNOON_MS, 18 * 3600), "2026-10-17 00:00:00");
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn test_today_range() {
+        let day = TimeRange::day_of(AFTERNOON_MS as i64, 8 * 3600);
+        // 北京时间 2026-10-16 00:00 起的 24 小时
+        assert_eq!(day.from_ms, Some(AFTERNOON_MS - 14 * 3600 * 1000));
+        assert!(day.contains(AFTERNOON_MS));
+        assert!(!day.contains(AFTERNOON_MS + 10 * 3600 * 1000));
+        assert!(TimeRange::default().contains(0));
+    }
+
+    #[test]
+    fn test_formats() {
+        let entries = [
+            entry("你好，世界", AFTERNOON_MS),
+            entry("说\"好\"", AFTERNOON_MS + 1000),
+        ];
+        assert_eq!(
+            render(&entries, ExportFormat::Csv, false),
+            "timestamp,duration_ms,chars,text\n\
+             2026-10-16 14:00:00,2500,5,你好，世界\n\
+             2026-10-16 14:00:01,2500,4,\"说\"\"好\"\"\"\n"
+        );
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        // 公式注入
+        assert_eq!(csv_field("=1+1"), "'=1+1");
+        assert_eq!(csv_field("@SUM(A1),x"), "\"'@SUM(A1),x\"");
+        assert_eq!(csv_field("-"), "'-");
+        assert_eq!(
+            render(&entries[..1], ExportFormat::Markdown, false),
+            "# TypeFree 转写记录\n\n## 2026-10-16 14:00:00\n\n你好，世界\n\n"
+        );
+
+        let line = render(&entries[..1], ExportFormat::Jsonl, false);
+        let value: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(value["text"], "你好，世界");
+        assert_eq!(value["chars"], 5);
+        assert_eq!(value["timestamp_ms"], AFTERNOON_MS);
+    }
+
+    #[test]
+    fn test_redact() {
+        let entries = [entry("私密内容", AFTERNOON_MS)];
+        for format in [
+            ExportFormat::Markdown,
+            ExportFormat::Csv,
+            ExportFormat::Jsonl,
+        ] {
+            let out = render(&entries, format, true);
+            assert!(!out.contains("私密内容"), "{:?}", format);
+            assert!(out.contains(&text_hash(KEY, "私密内容")), "{:?}", format);
+        }
+        let line = render(&entries, ExportFormat::Jsonl, true);
+        let value: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert!(value.get("text").is_none());
+        assert_eq!(value["chars"], 4);
+        // 同一密钥哈希稳定，不同内容或不同密钥不同
+        assert_eq!(text_hash(KEY, "a"), text_hash(KEY, "a"));
+        assert_eq!(text_hash(KEY, "a").len(), 64);
+        assert_ne!(text_hash(KEY, "a"), text_hash(KEY, "b"));
+        assert_ne!(text_hash(KEY, "a"), text_hash(b"other-key", "a"));
+    }
+}
@@ -0,0 +1,114 @@
+//! 自动识别语言 - 先用当前语言识别，开头没有结果或结果文字不符时换另一种语言重来
+//!
+//! 只根据识别结果判断，不分析音频；每次会话最多切换一次，
+//! 换语言后用已录的音频从头重新识别
+
+use crate::script;
+use std::time::Duration;
+
+/// 检测到语音后最多观察这么久，仍没有识别结果就换语言
+pub const DETECT_WINDOW: Duration = Duration::from_millis(1000);
+
+/// 判断结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// 还不能判断，继续观察
+    Undecided,
+    /// 语言正确，不再观察
+    Keep,
+    /// 换语言
+    Switch,
+}
+
+/// 根据目前的识别结果判断是否换语言
+///
+/// - `partials`: 本次会话按顺序收到的识别结果
+/// - `speech_for`: 检测到语音后经过的时间，还没有语音时为 None
+/// - `threshold`: 其他文字超过该比例视为乱码（同识别语言检查）
+pub fn judge(
+    partials: &[String],
+    language: &str,
+    speech_for: Option<Duration>,
+    threshold: f64,
+) -> Verdict {
+    let latest = partials
+        .iter()
+        .rev()
+        .map(|partial| partial.trim())
+        .find(|partial| !partial.is_empty());
+
+    // 结果的文字与语言不符（如中文识别出拼音），不用等满观察时间
+    if latest.is_some_and(|text| script::mismatch_hint(text, language, threshold).is_some()) {
+        return Verdict::Switch;
+    }
+    match speech_for {
+        Some(elapsed) if elapsed >= DETECT_WINDOW => {
+            if latest.is_some() {
+                Verdict::Keep
+            } else {
+                Verdict::Switch
+            }
+        }
+        _ => Verdict::Undecided,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按时间顺序回放识别结果：(距语音开始的毫秒数, 结果)，返回第一个确定的判断
+    fn replay(language: &str, steps: &[(u64, &str)]) -> Verdict {
+        let mut partials = Vec::new();
+        for &(ms, partial) in steps {
+            if !partial.is_empty() {
+                partials.push(partial.to_string());
+            }
+            let verdict = judge(&partials, language, Some(Duration::from_millis(ms)), 0.8);
+            if verdict != Verdict::Undecided {
+                return verdict;
+            }
+        }
+        Verdict::Undecided
+    }
+
+    #[test]
+    fn test_keeps_matching_language() {
+        let steps = [
+            (200, ""),
+            (500, "今天"),
+            (800, "今天天气"),
+            (1100, "今天天气不错"),
+        ];
+        assert_eq!(replay("zh", &steps), Verdict::Keep);
+        // 夹杂英文单词不算乱码
+        assert_eq!(
+            replay("zh", &[(600, "打开 GitHub 看看"), (1000, "")]),
+            Verdict::Keep
+        );
+    }
+
+    #[test]
+    fn test_switches_on_garbled_partials() {
+        // 中文识别出了拼音，不等观察时间结束
+        let steps = [(300, "ni"), (600, "ni hao shi"), (900, "ni hao shi jie")];
+        assert_eq!(replay("zh", &steps), Verdict::Switch);
+        assert_eq!(replay("en", &[(500, "今天天气不错")]), Verdict::Switch);
+    }
+
+    #[test]
+    fn test_switches_when_no_partials() {
+        let steps = [(300, ""), (700, ""), (1000, "")];
+        assert_eq!(replay("zh", &steps), Verdict::Switch);
+        // 观察时间内还不能判断
+        assert_eq!(replay("zh", &[(300, ""), (900, "")]), Verdict::Undecided);
+    }
+
+    #[test]
+    fn test_waits_for_speech() {
+        // 没有检测到语音时不计时
+        assert_eq!(judge(&[], "zh", None, 0.8), Verdict::Undecided);
+        // 太短的结果无法判断文字
+        assert_eq!(replay("zh", &[(400, "ok")]), Verdict::Undecided);
+    }
+}
@@ -2,20 +2,32 @@
 //!
 //! 仅使用 CDP 方案：通过豆包桌面端的 Chrome DevTools Protocol 进行语音识别
 
-mod audio;
-mod doubao_asr;
-mod doubao_cdp;
-mod doubao_launcher;
+mod draft;
+mod events;
 mod fn_key;
-mod keyboard;
+mod history;
+mod language_detect;
 mod overlay;
-mod permissions;
-mod resample;
+mod recording_dot;
+mod scratchpad;
+mod script;
+mod selftest;
+mod transcript;
 mod tray;
+mod update;
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tauri::{AppHandle, Emitter, WebviewUrl, WebviewWindowBuilder};
+// 录音、识别、粘贴等流程在 typefree-core 中，这里只负责把它们接到浮层、托盘和触发键上
+use typefree_core::{
+    audio, audio_dump, cue, doubao_asr, doubao_cdp, doubao_launcher, error, focus, focus_state,
+    keyboard, latency, permissions, postprocess, settings, silence,
+};
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use error::TypeFreeError;
+use events::{AppEvent, Readiness};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
 
 // 全局 AppHandle
 static APP_HANDLE: std::sync::OnceLock<AppHandle> = std::sync::OnceLock::new();
@@ -24,8 +36,64 @@ static APP_HANDLE: std::sync::OnceLock<AppHandle> = std::sync::OnceLock::new();
 
 static IS_RECORDING: AtomicBool = AtomicBool::new(false);
 
-static STOP_FLAG: std::sync::LazyLock<Arc<AtomicBool>> =
-    std::sync::LazyLock::new(|| Arc::new(AtomicBool::new(false)));
+// 确认粘贴模式下等待用户确认的文本
+static PENDING_REVIEW: Mutex<Option<String>> = Mutex::new(None);
+
+/// 锁住待确认的文本（持锁线程 panic 过也照常使用，避免确认流程和会话收尾跟着 panic）
+fn pending_review() -> std::sync::MutexGuard<'static, Option<String>> {
+    PENDING_REVIEW.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 一次录音会话
+struct Session {
+    generation: u64,
+    /// 松开按键时设置，结束录音
+    stop_flag: Arc<AtomicBool>,
+    /// 被新会话取代时设置，立即交付已有结果并退出
+    superseded: Arc<AtomicBool>,
+    task: tokio::task::JoinHandle<()>,
+    started: Instant,
+    /// 按住触发键的时长，松开时记录
+    held: Option<std::time::Duration>,
+    /// 松开后等待续录的时刻，续录窗口结束前再次按下则继续本次会话
+    released_at: Option<Instant>,
+    /// 续录窗口内置位，期间录到的音频先扣住
+    audio_held: Arc<AtomicBool>,
+    timeline: latency::SessionTimeline,
+}
+
+/// 会话的结果去向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionMode {
+    /// 按键听写：松开触发键结束，结果粘贴到目标窗口
+    Dictation,
+    /// 字幕模式：说完一句后按静音自动结束，结果只显示在浮层
+    Caption,
+}
+
+/// 一次会话的模式、识别选项和粘贴目标（开始录音时确定）
+struct SessionSetup {
+    mode: SessionMode,
+    options: doubao_asr::SessionOptions,
+    /// 按下触发键时的焦点，粘贴前确认仍在这里
+    paste_target: Option<focus::FocusTarget>,
+    /// 记录的粘贴目标，优先于焦点
+    pinned: Option<focus::PinnedTarget>,
+    /// 续录窗口内置位，见 `hold_released_audio`
+    audio_held: Arc<AtomicBool>,
+}
+
+// 最近一次会话（任务结束后保留，下次按下时检查）
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+// 会话代数，只有当前代的会话可以操作浮层
+static SESSION_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 等待上一次会话清理的最长时间
+const SESSION_TEARDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 会话超过最长时间后再等这么久，仍未结束则看门狗强制复位
+const SESSION_WATCHDOG_GRACE: std::time::Duration = std::time::Duration::from_secs(15);
 
 static RUNTIME: std::sync::LazyLock<tokio::runtime::Runtime> =
     std::sync::LazyLock::new(|| {
@@ -38,12 +106,14 @@ static RUNTIME: std::sync::LazyLock<tokio::runtime::Runtime> =
 
 // ============ Overlay 控制 ============
 
-fn show_overlay(app: &AppHandle) {
+fn show_overlay(app: &AppHandle, timeline: &latency::SessionTimeline) {
     let app_for_thread = app.clone();
+    let timeline = timeline.clone();
     // UI 操作必须在主线程执行
     let _ = app.run_on_main_thread(move || {
         overlay::update_status(&app_for_thread, "聆听中...");
         overlay::show(&app_for_thread);
+        timeline.mark(latency::Stage::OverlayShown);
     });
 }
 
@@ -54,196 +124,2487 @@ fn hide_overlay(app: &AppHandle) {
     });
 }
 
+/// 延迟隐藏浮层（取代之前安排的隐藏），期间有新会话开始则不隐藏
+fn hide_overlay_after(app: &AppHandle, generation: u64, delay: std::time::Duration) {
+    if is_current_session(generation) {
+        overlay::hide_after(app, delay);
+    }
+}
+
+// ============ 会话生命周期 ============
+
+/// 更新录音状态，变化时切换录音指示（托盘图标、角落红点），返回之前的状态
+fn set_recording(recording: bool) -> bool {
+    let was = IS_RECORDING.swap(recording, Ordering::SeqCst);
+    if was != recording {
+        if let Some(app) = APP_HANDLE.get() {
+            tray::set_recording_icon(app, recording);
+            recording_dot::set_visible(app, recording && settings::get().recording_dot);
+        }
+    }
+    was
+}
+
+fn is_current_session(generation: u64) -> bool {
+    SESSION_GENERATION.load(Ordering::SeqCst) == generation
+}
+
+/// 取代仍在收尾的上一次会话：只设置停止标志，返回它交给新会话的任务等待清理
+///
+/// 在触发键回调中调用，不能阻塞（Windows 会摘掉卡住的低级键盘钩子）
+fn supersede_previous_session() -> Option<Session> {
+    let prev = SESSION.lock().unwrap().take()?;
+    if prev.task.is_finished() {
+        return None;
+    }
+
+    log::warn!(
+        "[TypeFree] Session #{} still tearing down, superseding",
+        prev.generation
+    );
+    prev.stop_flag.store(true, Ordering::SeqCst);
+    prev.superseded.store(true, Ordering::SeqCst);
+    Some(prev)
+}
+
+/// 等待被取代的会话清理完成，超时则丢弃
+async fn wait_for_teardown(mut prev: Session) {
+    let start = Instant::now();
+    let finished = tokio::time::timeout(SESSION_TEARDOWN_TIMEOUT, &mut prev.task)
+        .await
+        .is_ok();
+    if finished {
+        log::info!(
+            "[TypeFree] Session #{} torn down in {}ms",
+            prev.generation,
+            start.elapsed().as_millis()
+        );
+    } else {
+        log::warn!(
+            "[TypeFree] Session #{} did not tear down in time, dropped",
+            prev.generation
+        );
+        prev.task.abort();
+    }
+}
+
+/// 松开触发键：续录窗口内保持录音，窗口结束仍未再次按下时结束录音
+///
+/// 返回是否推迟了停止（推迟时录音状态和指示保持到真正停止）
+fn release_current_session(window: std::time::Duration) -> bool {
+    let mut guard = SESSION.lock().unwrap();
+    let Some(session) = guard.as_mut() else {
+        return false;
+    };
+    session.held = Some(session.started.elapsed());
+    if window.is_zero() {
+        session.timeline.mark(latency::Stage::StopRequested);
+        session.stop_flag.store(true, Ordering::SeqCst);
+        return false;
+    }
+
+    let released_at = Instant::now();
+    session.released_at = Some(released_at);
+    session.audio_held.store(true, Ordering::SeqCst);
+    let generation = session.generation;
+    RUNTIME.spawn(async move {
+        tokio::time::sleep(window).await;
+        {
+            let mut guard = SESSION.lock().unwrap();
+            // 已续录（released_at 被取走）或已换成新会话时不处理
+            let Some(session) = guard
+                .as_mut()
+                .filter(|s| s.generation == generation && s.released_at == Some(released_at))
+            else {
+                return;
+            };
+            session.released_at = None;
+            session.timeline.mark(latency::Stage::StopRequested);
+            session.stop_flag.store(true, Ordering::SeqCst);
+        }
+        if let Some(app) = APP_HANDLE.get() {
+            mark_recording_stopped(app);
+        }
+    });
+    true
+}
+
+/// 录音真正停止：复位录音状态和指示
+fn mark_recording_stopped(app: &AppHandle) {
+    if !set_recording(false) {
+        return;
+    }
+    tray::set_recording_indicator(app, false);
+    events::emit(app, AppEvent::RecordingStopped);
+}
+
+/// 续录窗口内再次按下：取消待执行的停止，继续上一次会话
+fn resume_released_session() -> Option<u64> {
+    let mut guard = SESSION.lock().unwrap();
+    let session = guard.as_mut()?;
+    if session.stop_flag.load(Ordering::SeqCst) {
+        return None;
+    }
+    let released_at = session.released_at.take()?;
+    session.held = None;
+    session.audio_held.store(false, Ordering::SeqCst);
+    log::info!(
+        "[TypeFree] Resuming session #{} ({}ms after release)",
+        session.generation,
+        released_at.elapsed().as_millis()
+    );
+    Some(session.generation)
+}
+
+/// 会话按住触发键的时长（尚未松开或由上限结束时为 None）
+fn held_duration(generation: u64) -> Option<std::time::Duration> {
+    SESSION
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|session| session.generation == generation)
+        .and_then(|session| session.held)
+}
+
+/// 在任务边界运行会话：无论正常返回、panic 还是超时，都复位录音状态
+async fn run_session_guarded(
+    app: &AppHandle,
+    generation: u64,
+    stop_flag: Arc<AtomicBool>,
+    superseded: Arc<AtomicBool>,
+    session: impl std::future::Future<Output = ()>,
+) {
+    use futures_util::FutureExt;
+
+    let limit = std::time::Duration::from_secs(settings::get().max_session_secs.max(1))
+        + SESSION_WATCHDOG_GRACE;
+    let outcome =
+        tokio::time::timeout(limit, std::panic::AssertUnwindSafe(session).catch_unwind()).await;
+
+    if is_current_session(generation) {
+        tray::set_recording_indicator(app, false);
+    }
+    let reason = match outcome {
+        Ok(Ok(())) => {
+            // 提前返回（如麦克风打开失败）时按键可能还没松开
+            if is_current_session(generation) {
+                set_recording(false);
+            }
+            return;
+        }
+        Ok(Err(panic)) => format!("会话异常结束：{}", panic_message(panic.as_ref())),
+        Err(_) => format!("会话超过 {} 秒未结束，已强制复位", limit.as_secs()),
+    };
+    abort_session(app, generation, &stop_flag, &superseded, &reason);
+}
+
+/// 强制复位异常结束的会话
+fn abort_session(
+    app: &AppHandle,
+    generation: u64,
+    stop_flag: &AtomicBool,
+    superseded: &AtomicBool,
+    reason: &str,
+) {
+    log::error!("[TypeFree] Session #{} aborted: {}", generation, reason);
+    // 让录音线程和识别连接随之退出
+    stop_flag.store(true, Ordering::SeqCst);
+    superseded.store(true, Ordering::SeqCst);
+
+    if !is_current_session(generation) {
+        return;
+    }
+    set_recording(false);
+    if pending_review().take().is_some() {
+        fn_key::set_review_keys_active(false);
+    }
+    hide_overlay(app);
+    tray::clear_title(app);
+
+    let mut health = tray::health();
+    health.last_error = Some(reason.to_string());
+    tray::update_health(app, health);
+    events::emit(app, AppEvent::SessionAborted(reason.to_string()));
+}
+
+/// panic 载荷中的说明文字
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("未知错误")
+}
+
+// ============ 确认粘贴 ============
+
+/// 进入确认状态：显示识别结果，等待 Enter/Esc
+fn begin_review(app: &AppHandle, text: &str) {
+    log::info!("[TypeFree] Waiting for review confirmation");
+    *pending_review() = Some(text.to_string());
+    fn_key::set_review_keys_active(true);
+
+    let app_for_thread = app.clone();
+    let text = text.to_string();
+    let _ = app.run_on_main_thread(move || {
+        overlay::show_review(&app_for_thread, &text);
+    });
+}
+
+/// 结束确认状态，confirmed 为 true 时粘贴
+fn finish_review(app: &AppHandle, confirmed: bool) {
+    fn_key::set_review_keys_active(false);
+
+    let Some(text) = pending_review().take() else {
+        return;
+    };
+
+    // 先隐藏浮层，让焦点回到目标应用
+    hide_overlay(app);
+
+    if confirmed {
+        log::info!("[TypeFree] Review confirmed, pasting");
+        settle_focus_before_paste(app, None);
+        let settings = settings::get();
+        let target_app = focus::frontmost_app();
+        let suffix = postprocess::paste_suffix_for(&settings, target_app.as_ref());
+        keyboard::paste_final(&text, suffix);
+        cue::play(cue::Cue::Stop);
+    } else {
+        log::info!("[TypeFree] Review cancelled");
+    }
+}
 
 // ============ Fn 键处理 ============
 
-fn on_fn_pressed(app: &AppHandle) {
+/// 本次会话的选项：按住设置的修饰键时临时使用备用语言
+fn session_options(
+    settings: &settings::Settings,
+    modifiers: fn_key::Modifiers,
+) -> doubao_asr::SessionOptions {
+    let mut options = doubao_asr::SessionOptions {
+        audio_format: settings.audio_format,
+        advanced: settings.asr_overrides.clone(),
+        ..Default::default()
+    };
+    let language = settings.alternate_language.trim();
+    if let Some(modifier) = settings.alternate_language_modifier {
+        if modifiers.contains(modifier) && !language.is_empty() {
+            log::info!(
+                "[TypeFree] {:?} held, using language '{}' for this session",
+                modifier,
+                language
+            );
+            options.url_overrides.push(("language".to_string(), language.to_string()));
+        }
+    }
+    options
+}
+
+/// 本次会话的识别语言（高级覆盖 > 会话参数 > 捕获的模板，都没有时为中文）
+fn session_language(options: &doubao_asr::SessionOptions) -> String {
+    options
+        .advanced
+        .url_params
+        .get("language")
+        .or_else(|| {
+            options
+                .url_overrides
+                .iter()
+                .rev()
+                .find(|(key, _)| key == "language")
+                .map(|(_, value)| value)
+        })
+        .cloned()
+        .or_else(|| doubao_cdp::get_cached_url_params().and_then(|p| p.get("language").cloned()))
+        .unwrap_or_else(|| "zh".to_string())
+}
+
+/// 文字不符时重试用的另一种语言：用了备用语言就换回主语言，反之亦然
+///
+/// 高级设置覆盖了 language 时无法切换，返回 None
+fn other_language(
+    settings: &settings::Settings,
+    options: &doubao_asr::SessionOptions,
+) -> Option<String> {
+    if options.advanced.url_params.contains_key("language") {
+        return None;
+    }
+    let current = session_language(options);
+    let primary = doubao_cdp::get_cached_url_params()
+        .and_then(|p| p.get("language").cloned())
+        .unwrap_or_else(|| "zh".to_string());
+    let alternate = settings.alternate_language.trim();
+    let other = if current == alternate { primary } else { alternate.to_string() };
+    (!other.is_empty() && other != current).then_some(other)
+}
+
+/// 录音副本：保留全部帧（重新识别时使用），后续的帧可以改发到新的接收端
+#[derive(Clone)]
+struct AudioTee {
+    state: Arc<Mutex<TeeState>>,
+}
+
+struct TeeState {
+    frames: Vec<Vec<u8>>,
+    /// 当前转发的接收端
+    tx: Option<std::sync::mpsc::Sender<Vec<u8>>>,
+    /// 录音已结束，不会再有新的帧
+    finished: bool,
+}
+
+impl AudioTee {
+    /// 已录的全部帧
+    fn frames(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().frames.clone()
+    }
+
+    /// 停止转发，当前的接收端随即结束（帧仍然保留）
+    fn detach(&self) {
+        self.state.lock().unwrap().tx = None;
+    }
+
+    /// 从头重放已录的帧，之后的帧改发到返回的接收端（录音已结束时重放完即结束）
+    fn restart(&self) -> std::sync::mpsc::Receiver<Vec<u8>> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut state = self.state.lock().unwrap();
+        for frame in &state.frames {
+            let _ = tx.send(frame.clone());
+        }
+        if !state.finished {
+            state.tx = Some(tx);
+        }
+        rx
+    }
+}
+
+/// 松开后扣住录到的音频：续录时连同扣住的帧一起转发，续录窗口结束时丢弃
+fn hold_released_audio(
+    audio_rx: std::sync::mpsc::Receiver<Vec<u8>>,
+    held: Arc<AtomicBool>,
+) -> std::sync::mpsc::Receiver<Vec<u8>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut pending = Vec::new();
+        for frame in audio_rx {
+            if held.load(Ordering::SeqCst) {
+                pending.push(frame);
+                continue;
+            }
+            for frame in pending.drain(..).chain(std::iter::once(frame)) {
+                if tx.send(frame).is_err() {
+                    return;
+                }
+            }
+        }
+        if held.load(Ordering::SeqCst) {
+            if !pending.is_empty() {
+                log::info!(
+                    "[TypeFree] Dropped {} audio frames recorded after release",
+                    pending.len()
+                );
+            }
+            return;
+        }
+        for frame in pending {
+            let _ = tx.send(frame);
+        }
+    });
+    rx
+}
+
+/// 复制一份录音，返回转发后的接收端
+fn tee_audio(
+    audio_rx: std::sync::mpsc::Receiver<Vec<u8>>,
+) -> (std::sync::mpsc::Receiver<Vec<u8>>, AudioTee) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tee = AudioTee {
+        state: Arc::new(Mutex::new(TeeState {
+            frames: Vec::new(),
+            tx: Some(tx),
+            finished: false,
+        })),
+    };
+    let tee_for_thread = tee.clone();
+    std::thread::spawn(move || {
+        for frame in audio_rx {
+            let mut state = tee_for_thread.state.lock().unwrap();
+            state.frames.push(frame.clone());
+            if let Some(tx) = &state.tx {
+                let _ = tx.send(frame);
+            }
+        }
+        let mut state = tee_for_thread.state.lock().unwrap();
+        state.tx = None;
+        state.finished = true;
+    });
+    (rx, tee)
+}
+
+/// 改用指定的识别语言
+fn with_language(
+    mut options: doubao_asr::SessionOptions,
+    language: String,
+) -> doubao_asr::SessionOptions {
+    options.url_overrides.retain(|(key, _)| key != "language");
+    options.url_overrides.push(("language".to_string(), language));
+    options
+}
+
+/// 用录好的音频和另一种语言重新识别，结果交给同一个 on_final
+async fn retry_with_language(
+    tee: &AudioTee,
+    options: doubao_asr::SessionOptions,
+    language: String,
+    superseded: Arc<AtomicBool>,
+    on_final: impl Fn(&doubao_asr::AsrResult) + Send + 'static,
+) -> Result<doubao_asr::SessionStats, doubao_asr::AsrError> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    for frame in tee.frames() {
+        let _ = tx.send(frame);
+    }
+    drop(tx);
+    let options = with_language(options, language);
+
+    // 音频一次发完，服务端迟迟不结束时按停止处理
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let stop_for_timer = stop_flag.clone();
+    RUNTIME.spawn(async move {
+        tokio::time::sleep(SCRIPT_RETRY_TIMEOUT).await;
+        stop_for_timer.store(true, Ordering::SeqCst);
+    });
+
+    doubao_asr::run_asr_session(rx, options, None, stop_flag, superseded, |_: &str| {}, on_final)
+        .await
+}
+
+/// 自动识别语言：观察开头的识别结果，需要换语言时结束当前这次识别
+///
+/// 同时把会话的 superseded 转给这次识别（它使用单独的标志，换语言时不影响会话）。
+/// `settled` 由交付结果和换语言共用，先置位的一方生效，结果不会交付两次；
+/// 任务结果表示是否换了语言
+fn spawn_language_detector(
+    language: String,
+    partials: Arc<Mutex<Vec<String>>>,
+    activity: Arc<audio::AudioActivity>,
+    superseded: Arc<AtomicBool>,
+    attempt_superseded: Arc<AtomicBool>,
+    settled: Arc<AtomicBool>,
+    tee: AudioTee,
+) -> tokio::task::JoinHandle<bool> {
+    let threshold = settings::get().script_mismatch_ratio;
+    RUNTIME.spawn(async move {
+        let mut decided = false;
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            if superseded.load(Ordering::SeqCst) {
+                attempt_superseded.store(true, Ordering::SeqCst);
+                return false;
+            }
+            if decided {
+                continue;
+            }
+            let speech_for = activity.first_speech().map(|t| t.elapsed());
+            let partials = partials.lock().unwrap().clone();
+            match language_detect::judge(&partials, &language, speech_for, threshold) {
+                language_detect::Verdict::Undecided => {}
+                language_detect::Verdict::Keep => decided = true,
+                language_detect::Verdict::Switch => {
+                    // 这次识别的结果已经交付，不再换语言
+                    if settled.swap(true, Ordering::SeqCst) {
+                        return false;
+                    }
+                    log::info!(
+                        "[TypeFree] No usable result for '{}' ({} partials), switching language",
+                        language,
+                        partials.len()
+                    );
+                    attempt_superseded.store(true, Ordering::SeqCst);
+                    // 让这次识别的音频转发结束，帧留给换语言后的识别
+                    tee.detach();
+                    return true;
+                }
+            }
+        }
+    })
+}
+
+fn on_fn_pressed(app: &AppHandle, modifiers: fn_key::Modifiers) {
     log::info!("[TypeFree] === Fn PRESSED ===");
 
+    // 听写已暂停：忽略触发键
+    if !settings::get().dictation_enabled {
+        log::info!("[TypeFree] Dictation paused, ignoring trigger");
+        return;
+    }
+    // 字幕模式自己管理录音，触发键不起作用
+    if is_caption_mode() {
+        log::info!("[TypeFree] Caption mode active, ignoring trigger");
+        return;
+    }
+
+    // 刚松开又按下：接着录，不开始新会话（录音状态和指示一直保持着）
+    if resume_released_session().is_some() {
+        return;
+    }
+    let timeline = latency::SessionTimeline::new();
+
+    // 连续失败后暂停，不再反复取 Cookie、捕获参数
+    if let Err(e) = doubao_asr::check_cooldown() {
+        log::warn!("[TypeFree] ASR cooling down, ignoring trigger");
+        overlay::show_error(app, &e);
+        return;
+    }
+
     // 检查豆包是否在运行（需要保持运行以获取实时 Cookie）
     let doubao_running = RUNTIME.block_on(async { doubao_cdp::is_doubao_debug_available().await });
 
     if !doubao_running {
         log::warn!("[TypeFree] Doubao not running in debug mode");
-        show_overlay(app);
-        let app_for_error = app.clone();
-        let _ = app.run_on_main_thread(move || {
-            overlay::update_text(&app_for_error, "请先启动豆包桌面端");
-        });
-        // 2秒后隐藏
-        let app_for_hide = app.clone();
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            hide_overlay(&app_for_hide);
-        });
+        overlay::show_error(app, &TypeFreeError::DoubaoNotRunning);
         return;
     }
 
-    if IS_RECORDING.swap(true, Ordering::SeqCst) {
+    if set_recording(true) {
         log::warn!("[TypeFree] Already recording");
         return;
     }
 
-    STOP_FLAG.store(false, Ordering::SeqCst);
-    show_overlay(app);
+    // 按键听写优先于进行中的一键诊断
+    selftest::cancel_pipeline();
 
-    let app_clone = app.clone();
-    let stop_flag = STOP_FLAG.clone();
+    // 上一次会话可能还在等最终结果，先让它结束（在新会话的任务中等待）
+    let previous = supersede_previous_session();
 
-    RUNTIME.spawn(async move {
-        run_stt(&app_clone, stop_flag).await;
+    // 新的录音开始时丢弃未确认的结果
+    if pending_review().take().is_some() {
+        log::info!("[TypeFree] Discarding unconfirmed review");
+        fn_key::set_review_keys_active(false);
+    }
+
+    let generation = SESSION_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    log::info!("[TypeFree] Starting session #{}", generation);
+    // 记录了粘贴目标时插入到该目标，否则记录按下时的前台窗口，粘贴前确认没有切走
+    let pinned = focus::pinned_target();
+    let paste_target = match &pinned {
+        Some(pinned) => Some(pinned.target.clone()),
+        None => focus::capture_target(),
+    };
+    // 指定了插入应用时现在就在后台启动它，识别结束时不用再等
+    let configured_app = settings::get().target_app.filter(|id| !id.trim().is_empty());
+    if let (None, Some(bundle_id)) = (&pinned, configured_app) {
+        RUNTIME.spawn_blocking(move || focus::ensure_app_running(bundle_id.trim()));
+    }
+    // 勿扰和演示状态只在会话开始时检测；浮层被隐藏时改用托盘提示录音中
+    focus_state::refresh();
+    if focus_state::overlay_suppressed() {
+        tray::set_recording_indicator(app, true);
+    }
+    show_overlay(app, &timeline);
+
+    let options = doubao_asr::SessionOptions {
+        timeline: timeline.clone(),
+        ..session_options(&settings::get(), modifiers)
+    };
+    if !options.url_overrides.is_empty() {
+        // 排在 show_overlay 之后执行，覆盖默认状态文字
+        let app_for_thread = app.clone();
+        let _ = app.run_on_main_thread(move || {
+            overlay::update_status(&app_for_thread, "聆听中（备用语言）...");
+        });
+    }
+
+    let app_clone = app.clone();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let superseded = Arc::new(AtomicBool::new(false));
+    let stop_for_task = stop_flag.clone();
+    let superseded_for_task = superseded.clone();
+    let audio_held = Arc::new(AtomicBool::new(false));
+    let audio_held_for_task = audio_held.clone();
+
+    // 持有锁直到会话登记完成，避免松开事件找不到会话
+    let mut session = SESSION.lock().unwrap();
+    let task = RUNTIME.spawn(async move {
+        if let Some(previous) = previous {
+            wait_for_teardown(previous).await;
+        }
+        let setup = SessionSetup {
+            mode: SessionMode::Dictation,
+            options,
+            paste_target,
+            pinned,
+            audio_held: audio_held_for_task,
+        };
+        let stt = run_stt(
+            &app_clone,
+            generation,
+            setup,
+            stop_for_task.clone(),
+            superseded_for_task.clone(),
+        );
+        run_session_guarded(
+            &app_clone,
+            generation,
+            stop_for_task,
+            superseded_for_task,
+            stt,
+        )
+        .await;
+    });
+    *session = Some(Session {
+        generation,
+        stop_flag,
+        superseded,
+        task,
+        started: Instant::now(),
+        held: None,
+        released_at: None,
+        audio_held,
+        timeline,
     });
 }
 
 fn on_fn_released(app: &AppHandle) {
     log::info!("[TypeFree] === Fn RELEASED ===");
 
-    if !IS_RECORDING.swap(false, Ordering::SeqCst) {
+    if !IS_RECORDING.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let window = std::time::Duration::from_millis(settings::get().resume_window_ms);
+    if release_current_session(window) {
         return;
     }
+    mark_recording_stopped(app);
+}
+
+// ============ 字幕模式 ============
+
+/// 带此参数启动时等同于开启字幕模式
+const CAPTION_ARG: &str = "--caption";
+
+/// 字幕模式下一句结束后开始下一句前的间隔
+const CAPTION_RESTART_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// 豆包不可用或听写暂停时，字幕模式重新检查的间隔
+const CAPTION_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// 字幕模式连续失败后重新开始的最长间隔
+const CAPTION_MAX_BACKOFF: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 字幕模式一直没人说话时提前结束这一句，不等录音上限
+const CAPTION_IDLE_MS: u64 = 15_000;
+
+/// 字幕模式开始下一句前的间隔：连续失败时按 1s、2s、4s… 退避
+fn caption_restart_delay(failures: u32) -> std::time::Duration {
+    if failures == 0 {
+        return CAPTION_RESTART_DELAY;
+    }
+    std::time::Duration::from_secs(1u64 << (failures - 1).min(5)).min(CAPTION_MAX_BACKOFF)
+}
+
+/// 本次是否以字幕模式运行（启动时确定，修改设置后重启生效）
+fn is_caption_mode() -> bool {
+    static CAPTION: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *CAPTION.get_or_init(|| {
+        std::env::args().any(|arg| arg == CAPTION_ARG) || settings::get().caption_mode
+    })
+}
+
+/// 字幕模式：启动后不等触发键，一句接一句地录音识别，结果显示在浮层上
+///
+/// 每句仍走 run_stt，由 spawn_caption_endpoint 在说完后的静音处结束；
+/// 与按键听写互不干扰（该模式下忽略触发键），听写暂停时也跟着暂停
+async fn run_caption_loop(app: AppHandle) {
+    log::info!("[Caption] Caption mode on, recording continuously");
+    let mut failures = 0u32;
+    loop {
+        // 连续失败后的冷却期间不再尝试，也不探测豆包，等冷却结束或被解除
+        if doubao_asr::check_cooldown().is_err() {
+            tokio::time::sleep(CAPTION_RETRY_DELAY).await;
+            continue;
+        }
+        if !settings::get().dictation_enabled || !doubao_cdp::is_doubao_debug_available().await {
+            tokio::time::sleep(CAPTION_RETRY_DELAY).await;
+            continue;
+        }
+        if set_recording(true) {
+            tokio::time::sleep(CAPTION_RETRY_DELAY).await;
+            continue;
+        }
+
+        let generation = SESSION_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+        log::info!("[Caption] Starting session #{}", generation);
+        let timeline = latency::SessionTimeline::new();
+        // 浮层一直保留上一句，直到被隐藏（如显示错误后）才重新弹出
+        if !overlay::is_visible() {
+            show_overlay(&app, &timeline);
+        }
+
+        let options = doubao_asr::SessionOptions {
+            timeline,
+            ..session_options(&settings::get(), fn_key::Modifiers::default())
+        };
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let superseded = Arc::new(AtomicBool::new(false));
+        let setup = SessionSetup {
+            mode: SessionMode::Caption,
+            options,
+            paste_target: None,
+            pinned: None,
+            audio_held: Arc::new(AtomicBool::new(false)),
+        };
+        let stt = run_stt(
+            &app,
+            generation,
+            setup,
+            stop_flag.clone(),
+            superseded.clone(),
+        );
+        run_session_guarded(&app, generation, stop_flag, superseded, stt).await;
+        set_recording(false);
+
+        // 麦克风打不开或识别失败时退避，避免每 200ms 重新取 Cookie、连接豆包
+        let failed = audio::last_error().is_some() || doubao_asr::recent_failures() > 0;
+        failures = if failed { failures.saturating_add(1) } else { 0 };
+        let delay = caption_restart_delay(failures);
+        if failures > 0 {
+            log::warn!(
+                "[Caption] Session #{} failed ({} in a row), restarting in {}ms",
+                generation,
+                failures,
+                delay.as_millis()
+            );
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
 
-    STOP_FLAG.store(true, Ordering::SeqCst);
-    let _ = app.emit("recording-stopped", ());
+/// 字幕模式的自动结束：说话后静音达到设置的时长时结束这一句
+fn spawn_caption_endpoint(
+    generation: u64,
+    stop_flag: Arc<AtomicBool>,
+    activity: Arc<audio::AudioActivity>,
+) -> tokio::task::JoinHandle<()> {
+    let silence_ms = settings::get().caption_silence_ms;
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            if stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+            if activity.first_speech().is_none() && activity.captured_ms() >= CAPTION_IDLE_MS {
+                log::info!("[Caption] Session #{} ended, no speech", generation);
+                stop_flag.store(true, Ordering::SeqCst);
+                break;
+            }
+            let ended = silence::utterance_ended(
+                activity.speech_end_ms(),
+                activity.captured_ms(),
+                silence_ms,
+            );
+            if ended {
+                log::info!(
+                    "[Caption] Session #{} ended after {}ms of silence",
+                    generation,
+                    silence_ms
+                );
+                stop_flag.store(true, Ordering::SeqCst);
+                break;
+            }
+        }
+    })
 }
 
 // ============ STT 流程 ============
 
+/// 换语言重新识别的最长等待（超时后再等 1 秒最终结果）
+const SCRIPT_RETRY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// 切回原窗口后等待焦点稳定再粘贴
+const REACTIVATE_SETTLE: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// 粘贴前等待焦点回到目标窗口的最长时间
+const FOCUS_SETTLE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+const FOCUS_SETTLE_POLL: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// 粘贴前确认浮层没有拿着键盘焦点、`target` 已在前台（显示或隐藏浮层可能短暂抢走焦点，
+/// 此时粘贴键会发到浮层或丢失）
+///
+/// 浮层拿着焦点时让出并切回目标窗口，短暂轮询直到焦点稳定；超时后照常继续，
+/// 目标窗口确实变了的情况由 confirm_paste_target 按设置处理
+fn settle_focus_before_paste(app: &AppHandle, target: Option<&focus::FocusTarget>) {
+    let deadline = Instant::now() + FOCUS_SETTLE_TIMEOUT;
+    let mut reactivated = false;
+    loop {
+        let overlay_focused = overlay::release_focus(app);
+        let target_in_front = target.is_none_or(|target| {
+            focus::capture_target().is_some_and(|current| current.same_as(target))
+        });
+        if !overlay_focused && target_in_front {
+            return;
+        }
+
+        if overlay_focused && !reactivated {
+            if let Some(target) = target {
+                log::info!("[TypeFree] Overlay held focus, reactivating {}", target.app.id);
+                reactivated = focus::activate(target);
+            }
+        }
+        if Instant::now() >= deadline {
+            log::warn!(
+                "[TypeFree] Focus not settled before paste (overlay focused: {}, target in front: {})",
+                overlay_focused,
+                target_in_front
+            );
+            return;
+        }
+        std::thread::sleep(FOCUS_SETTLE_POLL);
+    }
+}
+
+/// 粘贴前确认前台窗口仍是按下触发键时的窗口，返回是否继续粘贴
+///
+/// 窗口变了时按设置切回原窗口，或只复制到剪贴板并在浮层提示
+fn confirm_paste_target(
+    app: &AppHandle,
+    generation: u64,
+    target: Option<&focus::FocusTarget>,
+    text: &str,
+) -> bool {
+    let Some(target) = target else {
+        return true;
+    };
+    let current = focus::capture_target();
+    if current.as_ref().is_some_and(|current| current.same_as(target)) {
+        return true;
+    }
+
+    log::info!(
+        "[TypeFree] Focus moved from {} to {} during recognition",
+        target.app.id,
+        current.as_ref().map(|c| c.app.id.as_str()).unwrap_or("unknown")
+    );
+    match settings::get().focus_change {
+        settings::FocusChangeAction::PasteAnyway => return true,
+        settings::FocusChangeAction::Reactivate => {
+            if focus::activate(target) {
+                std::thread::sleep(REACTIVATE_SETTLE);
+                return true;
+            }
+            log::warn!("[TypeFree] Failed to reactivate {}, copying instead", target.app.id);
+        }
+        settings::FocusChangeAction::CopyOnly => {}
+    }
+
+    keyboard::copy_text(text);
+    if is_current_session(generation) {
+        overlay::update_text(app, "目标窗口已变化，结果已复制");
+    }
+    false
+}
+
+/// 会话的粘贴线程：等待焦点和粘贴会阻塞几百毫秒，按顺序在这里执行，不占用 RUNTIME 的工作线程
+#[derive(Clone)]
+struct PasteWorker {
+    tx: std::sync::mpsc::Sender<Box<dyn FnOnce() + Send>>,
+}
+
+impl PasteWorker {
+    /// 启动粘贴线程，所有 PasteWorker 释放后线程退出
+    fn spawn() -> Self {
+        let (tx, rx) = std::sync::mpsc::channel::<Box<dyn FnOnce() + Send>>();
+        std::thread::spawn(move || {
+            for job in rx {
+                job();
+            }
+        });
+        Self { tx }
+    }
+
+    /// 排队执行一次粘贴
+    fn run(&self, job: impl FnOnce() + Send + 'static) {
+        let _ = self.tx.send(Box::new(job));
+    }
+
+    /// 等待已排队的粘贴全部完成
+    async fn flush(&self) {
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        self.run(move || {
+            let _ = done_tx.send(());
+        });
+        let _ = done_rx.await;
+    }
+}
+
+/// 插入到记录的粘贴目标，返回是否已插入
+///
+/// 能直接写入记录的输入框时不切换窗口，否则切回目标窗口后粘贴；目标已关闭时只复制
+fn paste_to_pinned(
+    app: &AppHandle,
+    generation: u64,
+    pinned: &focus::PinnedTarget,
+    text: &str,
+    suffix: &str,
+) -> bool {
+    if pinned.insert_text(&postprocess::append_suffix(text, suffix)) {
+        log::info!("[TypeFree] Inserted into pinned field of {}", pinned.target.app.id);
+        return true;
+    }
+
+    let in_front = focus::capture_target().is_some_and(|current| current.same_as(&pinned.target));
+    if !in_front {
+        if !focus::activate(&pinned.target) {
+            log::warn!(
+                "[TypeFree] Pinned target {} is gone, copying instead",
+                pinned.target.app.id
+            );
+            keyboard::copy_text(text);
+            if is_current_session(generation) {
+                overlay::update_text(app, "粘贴目标已关闭，结果已复制");
+            }
+            return false;
+        }
+        std::thread::sleep(REACTIVATE_SETTLE);
+    }
+    keyboard::paste_final(text, suffix);
+    true
+}
+
+/// 插入到设置中指定的应用（不管当前前台是哪个），返回是否已插入
+///
+/// 应用在按下触发键时已开始启动；此时仍没有运行则只复制并在浮层提示
+fn paste_to_app(
+    app: &AppHandle,
+    generation: u64,
+    bundle_id: &str,
+    text: &str,
+    suffix: &str,
+) -> bool {
+    match focus::app_target(bundle_id) {
+        Some(target) => paste_to_pinned(app, generation, &target, text, suffix),
+        None => {
+            log::warn!("[TypeFree] Target app {} is unavailable, copying instead", bundle_id);
+            keyboard::copy_text(text);
+            if is_current_session(generation) {
+                overlay::update_text(app, "无法打开指定的应用，结果已复制");
+            }
+            false
+        }
+    }
+}
+
 /// 运行 STT 流程（CDP 方案）
-async fn run_stt(app: &AppHandle, stop_flag: Arc<AtomicBool>) {
+async fn run_stt(
+    app: &AppHandle,
+    generation: u64,
+    setup: SessionSetup,
+    stop_flag: Arc<AtomicBool>,
+    superseded: Arc<AtomicBool>,
+) {
+    let SessionSetup {
+        mode,
+        options,
+        paste_target,
+        pinned,
+        audio_held,
+    } = setup;
     log::info!("[TypeFree] Starting STT (realtime Cookie mode)...");
+    let timeline = options.timeline.clone();
 
     // 启动录音
     let (audio_tx, audio_rx) = std::sync::mpsc::channel::<Vec<u8>>();
     let audio_stop = stop_flag.clone();
-
-    let audio_handle = match audio::start_recording(audio_tx, audio_stop) {
+    let activity = Arc::new(audio::AudioActivity::with_timeline(timeline.clone()));
+
+    let recording_options = audio::RecordingOptions::from_settings(&settings::get());
+    let audio_handle = match audio::start_recording(
+        audio_tx,
+        audio_stop,
+        activity.clone(),
+        recording_options,
+    ) {
         Ok(h) => {
             log::info!("[TypeFree] Recording started");
             h
         }
         Err(e) => {
             log::error!("[TypeFree] Recording failed: {}", e);
-            hide_overlay(app);
+            let message = e.user_message();
+            events::emit(app, AppEvent::SttError(message.clone()));
+
+            let mut health = tray::health();
+            health.last_error = Some(message.clone());
+            tray::update_health(app, health);
+
+            if is_current_session(generation) {
+                overlay::show_error(app, &TypeFreeError::Audio(e));
+            }
             return;
         }
     };
 
+    // 无识别结果检测：记录最近一次中间结果时累计的语音时长
+    let speech_at_partial = Arc::new(AtomicU64::new(0));
+    let watchdog = spawn_no_result_watchdog(
+        app,
+        stop_flag.clone(),
+        activity.clone(),
+        speech_at_partial.clone(),
+    );
+    let activity_for_partial = activity.clone();
+    // 菜单栏显示录音秒数（字幕模式一直在录，不显示）
+    let show_title = mode == SessionMode::Dictation && settings::get().menu_bar_status;
+    let timer = spawn_session_timer(app, generation, mode, stop_flag.clone(), show_title);
+    let endpoint = (mode == SessionMode::Caption)
+        .then(|| spawn_caption_endpoint(generation, stop_flag.clone(), activity.clone()));
+
+    // 识别语言检查：重试模式下留一份录音，文字不符时换另一种语言再识别一次
+    let language = session_language(&options);
+    let retry_language = match settings::get().script_mismatch {
+        settings::ScriptMismatchAction::Retry => other_language(&settings::get(), &options),
+        _ => None,
+    };
+    // 自动识别语言：开头没有结果或文字不符时换另一种语言，用留下的录音从头识别
+    let switch_language = if settings::get().auto_language {
+        other_language(&settings::get(), &options)
+    } else {
+        None
+    };
+    let audio_rx = hold_released_audio(audio_rx, audio_held);
+    let (audio_rx, tee) = if retry_language.is_some() || switch_language.is_some() {
+        let (rx, tee) = tee_audio(audio_rx);
+        (rx, Some(tee))
+    } else {
+        (audio_rx, None)
+    };
+    // 自动识别语言时记录开头的识别结果供判断
+    let partials: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    let collect_partials = switch_language.is_some();
+    let detect_language = language.clone();
+    let retry_options = options.clone();
+    let final_language = Arc::new(Mutex::new(language));
+    let retrying = Arc::new(AtomicBool::new(retry_language.is_none()));
+    let retry_pending: Arc<Mutex<Option<doubao_asr::AsrResult>>> = Arc::new(Mutex::new(None));
+    let finals = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let delivered_chars = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
     // 回调函数
     let app_for_partial = app.clone();
     let app_for_final = app.clone();
+    let final_delivered = Arc::new(AtomicBool::new(false));
+    let final_delivered_clone = final_delivered.clone();
+    let language_for_final = final_language.clone();
+    let retrying_for_final = retrying.clone();
+    let retry_pending_for_final = retry_pending.clone();
+    let finals_for_final = finals.clone();
+    let chars_for_final = delivered_chars.clone();
+    let timeline_for_final = timeline.clone();
+    let activity_for_final = activity.clone();
+    let partials_for_partial = partials.clone();
+    let paste_worker = PasteWorker::spawn();
+    let paste_for_final = paste_worker.clone();
+    // 中间结果节流，最终结果到达时丢弃还没显示的中间结果
+    let throttle = Arc::new(Mutex::new(overlay::PartialThrottle::new(
+        std::time::Duration::from_millis(settings::get().partial_throttle_ms),
+    )));
+    let throttle_for_final = throttle.clone();
 
     let on_partial = move |text: &str| {
-        overlay::update_text(&app_for_partial, text);
-    };
+        speech_at_partial.store(activity_for_partial.speech_ms(), Ordering::SeqCst);
+        if collect_partials {
+            partials_for_partial.lock().unwrap().push(text.to_string());
+        }
+        let throttled = throttle.lock().unwrap().offer(text, Instant::now());
+        match throttled {
+            overlay::Throttled::Show(text) => show_partial(&app_for_partial, generation, &text),
+            overlay::Throttled::Hold { flush_at: Some(at) } => {
+                // 间隔到了补上最新的文字
+                let app = app_for_partial.clone();
+                let throttle = throttle.clone();
+                RUNTIME.spawn(async move {
+                    tokio::time::sleep_until(tokio::time::Instant::from_std(at)).await;
+                    let pending = throttle.lock().unwrap().take_pending(Instant::now());
+                    if let Some(text) = pending {
+                        show_partial(&app, generation, &text);
+                    }
+                });
+            }
+            overlay::Throttled::Hold { flush_at: None } => {}
+        }
+    };
+
+    let on_final = move |result: &doubao_asr::AsrResult| {
+        throttle_for_final.lock().unwrap().clear();
+        final_delivered_clone.store(true, Ordering::SeqCst);
+        finals_for_final.fetch_add(1, Ordering::SeqCst);
+        let text = result.text.as_str();
 
-    let on_final = move |text: &str| {
         log::info!("[TypeFree] ========== 最终结果 ==========");
         log::info!("[TypeFree] {}", text);
         log::info!("[TypeFree] ================================");
 
-        // 粘贴到光标
-        keyboard::paste_final(text);
+        // 误触：录音太短或没有语音能量，结果多半是噪声
+        let settings = settings::get();
+        let language = language_for_final.lock().unwrap().clone();
+        let (captured_ms, peak_rms) = (
+            activity_for_final.captured_ms(),
+            activity_for_final.peak_rms(),
+        );
+        if silence::is_accidental_tap(
+            captured_ms,
+            peak_rms,
+            settings.min_audio_ms,
+            settings.min_audio_rms,
+        ) {
+            log::info!(
+                "[TypeFree] Discarding result: {}ms captured, peak RMS {:.0}",
+                captured_ms,
+                peak_rms
+            );
+            if is_current_session(generation) {
+                overlay::update_status(&app_for_final, "未检测到语音");
+            }
+            return;
+        }
+
+        // 按键时间太短：多半是误按
+        let min_session = std::time::Duration::from_millis(settings.min_session_ms);
+        if let Some(held) = held_duration(generation).filter(|held| *held < min_session) {
+            log::info!("[TypeFree] Discarding result: key held for {}ms", held.as_millis());
+            events::emit(&app_for_final, AppEvent::SessionTooShort(held.as_millis() as u64));
+            if is_current_session(generation) {
+                overlay::update_status(&app_for_final, "按键时间太短，已忽略");
+            }
+            return;
+        }
+
+        // 只有标点或语气词：不粘贴，在转写记录中标记为已忽略
+        if postprocess::is_too_short(text, &settings) {
+            log::info!("[TypeFree] Discarding result with too little content: {}", text);
+            transcript::push_discarded(text, &language, captured_ms);
+            events::emit(&app_for_final, AppEvent::ContentTooShort(text.to_string()));
+            if is_current_session(generation) {
+                overlay::update_status(&app_for_final, "内容太短，已忽略");
+            }
+            return;
+        }
+
+        // 文字与识别语言不符：重试模式下先换语言再识别一次，否则只提示
+        if settings.script_mismatch != settings::ScriptMismatchAction::Off {
+            if let Some(hint) =
+                script::mismatch_hint(text, &language, settings.script_mismatch_ratio)
+            {
+                if !retrying_for_final.swap(true, Ordering::SeqCst) {
+                    log::info!("[TypeFree] Script mismatch for '{}', retrying: {}", language, text);
+                    *retry_pending_for_final.lock().unwrap() = Some(result.clone());
+                    return;
+                }
+                log::info!("[TypeFree] Script mismatch for '{}': {}", language, text);
+                if is_current_session(generation) {
+                    overlay::update_status(&app_for_final, &hint);
+                }
+            }
+        }
+
+        chars_for_final.store(text.chars().count(), Ordering::SeqCst);
+        if !result.utterances.is_empty() {
+            log::info!(
+                "[TypeFree] {} utterances ({} definite)",
+                result.utterances.len(),
+                result.definite_utterances().count()
+            );
+        }
+        transcript::push(text, &language, captured_ms);
+        let tagged = doubao_asr::AsrResult {
+            language: Some(language),
+            ..result.clone()
+        };
+        events::emit(&app_for_final, AppEvent::AsrFinal(tagged));
+
+        // 字幕模式：只显示，不粘贴
+        if mode == SessionMode::Caption {
+            if is_current_session(generation) {
+                overlay::update_text(&app_for_final, text);
+            }
+            return;
+        }
+
+        // 按目标应用（设置中指定的应用，或按下触发键时的前台应用）的规则后处理
+        let configured_app = settings
+            .target_app
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty() && pinned.is_none());
+        let target_app = configured_app
+            .map(|id| focus::AppInfo {
+                name: id.to_string(),
+                id: id.to_string(),
+            })
+            .or_else(|| paste_target.as_ref().map(|target| target.app.clone()))
+            .or_else(focus::frontmost_app);
+        let processed = postprocess::process(text, &settings, target_app.as_ref());
+        if processed != text {
+            log::info!(
+                "[TypeFree] Post-processed for {}: {}",
+                target_app.as_ref().map(|a| a.id.as_str()).unwrap_or("unknown app"),
+                processed
+            );
+        }
+        let text = processed.as_str();
+
+        // 听写到便签：写入便签窗口，不粘贴
+        if scratchpad::is_active() {
+            write_to_scratchpad(&app_for_final, generation, text);
+            return;
+        }
+
+        // 草稿模式：加入草稿，不立即粘贴
+        if settings.draft_mode {
+            let state = draft::append(text);
+            emit_draft_changed(&app_for_final, &state);
+            if is_current_session(generation) {
+                overlay::update_text(
+                    &app_for_final,
+                    &format!("已加入草稿 (共 {} 字)", state.chars),
+                );
+            }
+            return;
+        }
+
+        // 确认粘贴模式：等待用户按 Enter/Esc
+        if settings.review_before_paste {
+            begin_review(&app_for_final, text);
+            return;
+        }
+
+        let suffix = postprocess::paste_suffix_for(&settings, target_app.as_ref()).to_string();
+        let text = text.to_string();
+        let configured_app = configured_app.map(str::to_string);
+        let pinned = pinned.clone();
+        let paste_target = paste_target.clone();
+        let app = app_for_final.clone();
+        let timeline = timeline_for_final.clone();
+        // 等待焦点和粘贴会阻塞，放到粘贴线程执行
+        paste_for_final.run(move || {
+            // 浮层可能短暂拿走焦点：先让出焦点，粘贴到原窗口时等它回到前台
+            let expected_front = paste_target
+                .as_ref()
+                .filter(|_| pinned.is_none() && configured_app.is_none());
+            settle_focus_before_paste(&app, expected_front);
+            if let Some(pinned) = &pinned {
+                if !paste_to_pinned(&app, generation, pinned, &text, &suffix) {
+                    return;
+                }
+            } else if let Some(bundle_id) = &configured_app {
+                if !paste_to_app(&app, generation, bundle_id, &text, &suffix) {
+                    return;
+                }
+            } else {
+                // 识别期间切换了窗口时按设置处理
+                if !confirm_paste_target(&app, generation, paste_target.as_ref(), &text) {
+                    return;
+                }
+                // 粘贴到光标
+                keyboard::paste_final(&text, &suffix);
+            }
+            timeline.mark(latency::Stage::Pasted);
+            cue::play(cue::Cue::Stop);
+
+            // 显示最终结果，会话结束后隐藏
+            if is_current_session(generation) {
+                overlay::update_text(&app, &text);
+            }
+        });
+    };
+
+    // 开机自动启动时推迟的豆包初始化在首次使用时完成（录音已在缓冲）
+    run_deferred_doubao_init(app).await;
+
+    // 运行 ASR 会话
+    let trimmer = || {
+        let settings = settings::get();
+        settings.trim_silence.then(|| {
+            silence::SilenceTrimmer::new(settings.silence_rms_threshold, settings.pre_speech_chunks)
+        })
+    };
+    let on_final = Arc::new(on_final);
+    let on_final_for_session = on_final.clone();
+    let on_partial = Arc::new(on_partial);
+    let on_partial_for_session = on_partial.clone();
+    let session_result = match (switch_language, &tee) {
+        (Some(switch_language), Some(tee)) => {
+            // 这次识别使用单独的取代标志，换语言时结束它而不影响整个会话
+            let attempt_superseded = Arc::new(AtomicBool::new(false));
+            let settled = Arc::new(AtomicBool::new(false));
+            let detector = spawn_language_detector(
+                detect_language,
+                partials,
+                activity.clone(),
+                superseded.clone(),
+                attempt_superseded.clone(),
+                settled.clone(),
+                tee.clone(),
+            );
+            let first = doubao_asr::run_asr_session(
+                audio_rx,
+                options.clone(),
+                trimmer(),
+                stop_flag.clone(),
+                attempt_superseded,
+                move |text: &str| on_partial_for_session(text),
+                move |result: &doubao_asr::AsrResult| {
+                    // 已决定换语言时丢弃这次识别的结果
+                    if !settled.swap(true, Ordering::SeqCst) {
+                        on_final_for_session(result)
+                    }
+                },
+            )
+            .await;
+            // 已结束的检测任务不受 abort 影响，仍能取到是否换了语言
+            detector.abort();
+            let switched = detector.await.unwrap_or(false);
+
+            if switched && !superseded.load(Ordering::SeqCst) {
+                log::info!(
+                    "[TypeFree] Restarting session #{} with language '{}'",
+                    generation,
+                    switch_language
+                );
+                if is_current_session(generation) {
+                    overlay::update_status(
+                        app,
+                        &format!("改用{}识别...", script::language_name(&switch_language)),
+                    );
+                }
+                // 换过语言后不再按文字不符重试
+                retrying.store(true, Ordering::SeqCst);
+                *final_language.lock().unwrap() = switch_language.clone();
+                let on_final_for_switch = on_final.clone();
+                doubao_asr::run_asr_session(
+                    tee.restart(),
+                    with_language(options, switch_language),
+                    trimmer(),
+                    stop_flag,
+                    superseded.clone(),
+                    move |text: &str| on_partial(text),
+                    move |result: &doubao_asr::AsrResult| on_final_for_switch(result),
+                )
+                .await
+            } else {
+                first
+            }
+        }
+        _ => {
+            doubao_asr::run_asr_session(
+                audio_rx,
+                options,
+                trimmer(),
+                stop_flag,
+                superseded.clone(),
+                move |text: &str| on_partial_for_session(text),
+                move |result: &doubao_asr::AsrResult| on_final_for_session(result),
+            )
+            .await
+        }
+    };
+
+    if let Some(handle) = watchdog {
+        handle.abort();
+    }
+    timer.abort();
+    if let Some(handle) = endpoint {
+        handle.abort();
+    }
+
+    let _ = audio_handle.join();
+
+    // 文字不符，换语言重新识别；没有得到结果时交付原来的结果
+    let pending = retry_pending.lock().unwrap().take();
+    let mut session_result = match (pending, retry_language, tee) {
+        (Some(original), Some(retry_language), Some(tee))
+            if !superseded.load(Ordering::SeqCst) =>
+        {
+            log::info!(
+                "[TypeFree] Retrying session #{} with language '{}'",
+                generation,
+                retry_language
+            );
+            if is_current_session(generation) {
+                overlay::update_status(
+                    app,
+                    &format!("正在用{}重新识别...", script::language_name(&retry_language)),
+                );
+            }
+            let original_language = final_language.lock().unwrap().clone();
+            *final_language.lock().unwrap() = retry_language.clone();
+            let before = finals.load(Ordering::SeqCst);
+            let on_final_for_retry = on_final.clone();
+            let retry_result = retry_with_language(
+                &tee,
+                retry_options,
+                retry_language,
+                superseded.clone(),
+                move |result: &doubao_asr::AsrResult| on_final_for_retry(result),
+            )
+            .await;
+            if let Err(e) = &retry_result {
+                log::warn!("[TypeFree] Retry failed: {}", e);
+            }
+            if finals.load(Ordering::SeqCst) == before {
+                // 重试没有结果：按原来的语言交付原结果（带提示）
+                log::info!("[TypeFree] Retry produced no result, delivering original");
+                *final_language.lock().unwrap() = original_language;
+                on_final(&original);
+            }
+            session_result
+        }
+        (Some(original), ..) => {
+            // 被取代等情况下不再重试，直接交付
+            on_final(&original);
+            session_result
+        }
+        _ => session_result,
+    };
+    // 等粘贴完成再收尾（之后会统计延迟、隐藏浮层）
+    paste_worker.flush().await;
+    // 录音线程已结束，丢弃次数不会再变
+    if let Ok(stats) | Err(doubao_asr::AsrError::Server { stats, .. }) = &mut session_result {
+        stats.dropped_callbacks = activity.dropped_callbacks();
+    }
+
+    log::info!(
+        "[TypeFree] STT session #{} ended (audio {}ms, {} callbacks dropped)",
+        generation,
+        activity.captured_ms(),
+        activity.dropped_callbacks()
+    );
+    latency::finish(generation, &timeline);
+
+    if activity.resample_fell_back() {
+        events::emit(app, AppEvent::ResampleFallback);
+    }
+
+    if show_title && is_current_session(generation) {
+        show_result_title(app, generation, delivered_chars.load(Ordering::SeqCst));
+    }
+
+    if superseded.load(Ordering::SeqCst) {
+        log::info!("[TypeFree] Session #{} superseded by a new recording", generation);
+        return;
+    }
+
+    report_session_health(app, &session_result);
+
+    let delivered = final_delivered.load(Ordering::SeqCst);
+    let error = match &session_result {
+        Ok(_) => {
+            if !delivered {
+                // 没有任何识别结果，稍后隐藏（保留"未检测到语音结果"等提示）
+                log::info!("[TypeFree] No final result, hiding overlay");
+            }
+            None
+        }
+        Err(e @ doubao_asr::AsrError::Server { error, .. }) if delivered => {
+            // 报错前的结果已粘贴，额外提示一下
+            log::warn!("[TypeFree] ASR session degraded: {}", e);
+            Some(TypeFreeError::interrupted(error))
+        }
+        Err(e) => {
+            log::error!("[TypeFree] ASR session error: {}", e);
+            Some(TypeFreeError::from(e))
+        }
+    };
+
+    // 确认粘贴模式下浮层由确认流程关闭
+    if pending_review().is_some() {
+        return;
+    }
+    let settings = settings::get();
+    match error {
+        // 错误提示自行定时隐藏
+        Some(error) if is_current_session(generation) => overlay::show_error(app, &error),
+        Some(_) => {}
+        // 字幕模式下浮层一直显示
+        None if mode == SessionMode::Caption => overlay::cancel_hide(),
+        // 保留最终结果直到下次录音或点击关闭
+        None if delivered && settings.keep_final_result => overlay::cancel_hide(),
+        None => {
+            let delay = std::time::Duration::from_millis(settings.final_display_ms);
+            hide_overlay_after(app, generation, delay);
+        }
+    }
+}
+
+/// 菜单栏显示结果字数的时长
+const RESULT_TITLE_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// 会话结束后在菜单栏短暂显示结果字数，没有结果时直接清除
+fn show_result_title(app: &AppHandle, generation: u64, chars: usize) {
+    if chars == 0 {
+        tray::clear_title(app);
+        return;
+    }
+    tray::set_title(app, &tray::result_title(chars));
+    let app = app.clone();
+    RUNTIME.spawn(async move {
+        tokio::time::sleep(RESULT_TITLE_DURATION).await;
+        // 期间开始了新会话时标题归新会话
+        if is_current_session(generation) {
+            tray::clear_title(&app);
+        }
+    });
+}
+
+/// 在浮层显示中间结果并通知前端
+fn show_partial(app: &AppHandle, generation: u64, text: &str) {
+    if is_current_session(generation) {
+        let shown = overlay::partial_text(text, settings::get().overlay_text);
+        overlay::update_text(app, &shown);
+        events::emit(app, AppEvent::TranscriptPartial(text.to_string()));
+    }
+}
+
+/// 会话结束后更新托盘的健康状态
+fn report_session_health(
+    app: &AppHandle,
+    result: &Result<doubao_asr::SessionStats, doubao_asr::AsrError>,
+) {
+    let mut health = tray::health();
+    health.mic_device = audio::last_device_name();
+    health.logged_in = doubao_cdp::get_cached_login_status().or(health.logged_in);
+    match result {
+        Ok(stats) => {
+            health.doubao_connected = Some(true);
+            health.latency_ms = stats.first_partial_ms.or(health.latency_ms);
+        }
+        Err(doubao_asr::AsrError::Server { error, stats }) => {
+            health.doubao_connected = Some(true);
+            health.latency_ms = stats.first_partial_ms.or(health.latency_ms);
+            health.last_error = Some(error.user_message().to_string());
+        }
+        Err(doubao_asr::AsrError::Connect(e)) => {
+            health.doubao_connected = Some(false);
+            health.last_error = Some(e.to_string());
+        }
+    }
+    // 本次失败触发了冷却：托盘提示暂停状态，后台留意重新登录
+    if let Err(e) = doubao_asr::check_cooldown() {
+        health.last_error = Some(e.to_string());
+        spawn_cooldown_watch(app);
+    }
+    tray::update_health(app, health);
+}
+
+/// 冷却期间检查 Cookie 的间隔
+const COOLDOWN_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+static COOLDOWN_WATCHING: AtomicBool = AtomicBool::new(false);
+
+/// 冷却期间定时从豆包读取 Cookie（只访问本机调试端口，不连接识别服务）：
+/// 用户按提示重新登录后 Cookie 变化，冷却随之解除
+fn spawn_cooldown_watch(app: &AppHandle) {
+    if COOLDOWN_WATCHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app = app.clone();
+    RUNTIME.spawn(async move {
+        loop {
+            tokio::time::sleep(COOLDOWN_CHECK_INTERVAL).await;
+            if doubao_asr::check_cooldown().is_ok() {
+                break;
+            }
+            if let Err(e) = doubao_cdp::fetch_cookies().await {
+                log::debug!("[TypeFree] Cooldown cookie check failed: {}", e);
+            }
+        }
+        COOLDOWN_WATCHING.store(false, Ordering::SeqCst);
+        log::info!("[TypeFree] ASR cooldown over");
+        let mut health = tray::health();
+        health.last_error = None;
+        tray::update_health(&app, health);
+    });
+}
+
+/// 无识别结果检测
+///
+/// 统计上一次识别结果之后采集到的语音时长（不计停顿），超过设置的时间仍没有新结果时提示，
+/// 可选直接结束会话。每次识别结果都会重新计时。
+fn spawn_no_result_watchdog(
+    app: &AppHandle,
+    stop_flag: Arc<AtomicBool>,
+    activity: Arc<audio::AudioActivity>,
+    speech_at_partial: Arc<AtomicU64>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let s = settings::get();
+    if s.no_result_timeout_ms == 0 {
+        return None;
+    }
+
+    let timeout_ms = s.no_result_timeout_ms;
+    let end_session = s.end_session_on_no_result;
+    let app = app.clone();
+
+    Some(tokio::spawn(async move {
+        let mut warned_for: Option<u64> = None;
+
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+            if stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let since = speech_at_partial.load(Ordering::SeqCst);
+            let unanswered = activity.speech_ms().saturating_sub(since);
+            if warned_for == Some(since) || unanswered < timeout_ms {
+                continue;
+            }
+
+            warned_for = Some(since);
+            log::warn!("[TypeFree] No ASR result within {}ms of speech", timeout_ms);
+            overlay::update_status(&app, "未检测到语音结果");
+
+            if end_session {
+                log::info!("[TypeFree] Ending session due to missing results");
+                stop_flag.store(true, Ordering::SeqCst);
+                break;
+            }
+        }
+    }))
+}
+
+/// 到达录音上限前倒计时的秒数
+const SESSION_COUNTDOWN_SECS: u64 = 5;
+
+/// 距离录音上限的剩余秒数（向上取整），只在最后几秒返回
+fn countdown_secs(elapsed: std::time::Duration, max: std::time::Duration) -> Option<u64> {
+    let left = max.checked_sub(elapsed)?;
+    let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+    (secs <= SESSION_COUNTDOWN_SECS).then_some(secs)
+}
+
+/// 录音时长上限
+///
+/// 最后几秒发出倒计时事件，到达上限时像松开按键一样结束录音，识别结果照常交付；
+/// 字幕模式一句接一句地录，不提示倒计时和到达上限
+fn spawn_session_timer(
+    app: &AppHandle,
+    generation: u64,
+    mode: SessionMode,
+    stop_flag: Arc<AtomicBool>,
+    show_title: bool,
+) -> tokio::task::JoinHandle<()> {
+    let max = std::time::Duration::from_secs(settings::get().max_session_secs.max(1));
+    let started = Instant::now();
+    let app = app.clone();
+    let notify = mode == SessionMode::Dictation;
+
+    tokio::spawn(async move {
+        let mut shown = None;
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+            if stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let elapsed = started.elapsed();
+            if show_title && is_current_session(generation) {
+                tray::set_title(&app, &tray::recording_title(elapsed));
+            }
+            if elapsed >= max {
+                log::info!(
+                    "[TypeFree] Session #{} reached the {}s limit, finishing",
+                    generation,
+                    max.as_secs()
+                );
+                stop_flag.store(true, Ordering::SeqCst);
+                if notify && is_current_session(generation) {
+                    events::emit(&app, AppEvent::SessionMaxReached(max.as_secs()));
+                }
+                break;
+            }
+
+            let left = countdown_secs(elapsed, max);
+            if notify && left != shown && is_current_session(generation) {
+                if let Some(secs) = left {
+                    events::emit(&app, AppEvent::SessionCountdown(secs));
+                }
+            }
+            shown = left;
+        }
+    })
+}
+
+// ============ Tauri Commands ============
+
+#[tauri::command]
+fn get_permission_status() -> permissions::PermissionStatus {
+    let status = permissions::PermissionStatus::check();
+    log::info!(
+        "[TypeFree] Permission status: monitor_keys={}, paste={}, type_direct={}, record_audio={}",
+        status.can_monitor_keys,
+        status.can_paste,
+        status.can_type_direct,
+        status.can_record_audio
+    );
+    mark_onboarding_if_ready(&status);
+    status
+}
+
+/// 麦克风诊断：最近一次打开麦克风失败的原因
+#[tauri::command]
+fn get_audio_diagnostic() -> Option<String> {
+    audio::last_error().map(|e| e.user_message())
+}
+
+/// 预览下一次会话的 ASR 请求，reveal 为 true 时显示完整值
+#[tauri::command]
+fn get_asr_request_preview(reveal: bool) -> Result<doubao_asr::RequestPreview, TypeFreeError> {
+    doubao_asr::request_preview(reveal)
+}
+
+/// 按键监听诊断：监听方式和钩子自动重装次数
+#[tauri::command]
+fn get_hotkey_diagnostic() -> fn_key::ListenerStatus {
+    fn_key::listener_status()
+}
+
+/// 浮层降级为普通窗口的说明（正常时为 None）
+#[tauri::command]
+fn get_overlay_fallback() -> Option<String> {
+    overlay::fallback_reason()
+}
+
+/// 最近一次听写的各阶段耗时
+#[tauri::command]
+fn get_last_latency() -> Option<latency::LatencyBreakdown> {
+    latency::last()
+}
+
+/// 输入设备列表（含通道数）
+#[tauri::command]
+fn list_input_devices() -> Result<Vec<audio::InputDevice>, String> {
+    audio::list_input_devices().map_err(|e| e.user_message())
+}
+
+/// 切换输入设备（None 为系统默认），下次录音生效
+pub(crate) fn set_input_device(app: &AppHandle, name: Option<String>) -> Result<(), String> {
+    settings::update(|s| s.input_device = name.clone())?;
+    log::info!(
+        "[TypeFree] Input device: {}",
+        name.as_deref().unwrap_or("system default")
+    );
+    tray::refresh_input_devices(app);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_audio_device(app: AppHandle, name: Option<String>) -> Result<(), String> {
+    set_input_device(&app, name)
+}
+
+#[tauri::command]
+fn open_input_monitoring_settings() {
+    #[cfg(target_os = "macos")]
+    {
+        let _ = std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_ListenEvent")
+            .spawn();
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Windows 没有专门的 Input Monitoring 设置
+        // 打开隐私设置主页面
+        let _ = std::process::Command::new("cmd")
+            .args(["/C", "start", "ms-settings:privacy"])
+            .spawn();
+    }
+}
+
+#[tauri::command]
+fn open_accessibility_settings() {
+    #[cfg(target_os = "macos")]
+    {
+        let _ = std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility")
+            .spawn();
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Windows 辅助功能设置
+        let _ = std::process::Command::new("cmd")
+            .args(["/C", "start", "ms-settings:easeofaccess"])
+            .spawn();
+    }
+}
+
+#[tauri::command]
+fn open_automation_settings() {
+    #[cfg(target_os = "macos")]
+    {
+        let _ = std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Automation")
+            .spawn();
+    }
+}
+
+/// 请求粘贴需要的自动化权限：没询问过时弹出系统提示，已拒绝时打开系统设置，返回是否已授权
+#[tauri::command]
+async fn request_paste_permission() -> bool {
+    let granted = tauri::async_runtime::spawn_blocking(permissions::request_automation)
+        .await
+        .unwrap_or(false);
+    if !granted {
+        open_automation_settings();
+    }
+    permissions::request_recheck();
+    granted
+}
+
+#[tauri::command]
+fn open_microphone_settings() {
+    #[cfg(target_os = "macos")]
+    {
+        let _ = std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone")
+            .spawn();
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Windows 麦克风隐私设置
+        let _ = std::process::Command::new("cmd")
+            .args(["/C", "start", "ms-settings:privacy-microphone"])
+            .spawn();
+    }
+}
+
+/// 在文件管理器中打开配置目录（不存在时先创建）
+fn reveal_data_dir() -> Result<(), String> {
+    let dir = settings::config_dir().ok_or("Settings not initialized")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+
+    log::info!("[TypeFree] Opening data dir: {}", dir.display());
+    open_with_system(&dir)
+}
+
+/// 在文件管理器中打开调试录音目录（不存在时先创建）
+#[tauri::command]
+fn reveal_audio_dumps() -> Result<(), String> {
+    let dir = audio_dump::dir().ok_or("Log dir not resolved")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create audio dump dir: {}", e))?;
+    open_with_system(&dir)
+}
+
+/// 用系统默认程序打开设置文件（不存在时先写入当前设置）
+fn reveal_settings_file() -> Result<(), String> {
+    let path = settings::file_path().ok_or("Settings not initialized")?;
+    if !path.exists() {
+        settings::set(settings::get())?;
+    }
+    log::info!("[TypeFree] Opening settings file: {}", path.display());
+    open_with_system(&path)
+}
+
+/// 用系统默认程序打开文件或目录
+fn open_with_system(path: &std::path::Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let opener = "open";
+    #[cfg(target_os = "windows")]
+    let opener = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let opener = "xdg-open";
+
+    std::process::Command::new(opener)
+        .arg(path)
+        .spawn()
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+#[tauri::command]
+fn open_data_dir() -> Result<(), String> {
+    reveal_data_dir()
+}
+
+// ============ 设置 ============
+
+#[tauri::command]
+fn get_settings() -> settings::Settings {
+    settings::get()
+}
+
+#[tauri::command]
+fn update_settings(
+    app: AppHandle,
+    new_settings: settings::Settings,
+) -> Result<settings::Settings, String> {
+    doubao_cdp::AsrEndpoint {
+        host: new_settings.asr_host.clone(),
+        region: new_settings.asr_region.clone(),
+    }
+    .validate()?;
+    new_settings.asr_overrides.validate()?;
+    let old_settings = settings::get();
+    let hotkeys_changed = new_settings.hotkeys != old_settings.hotkeys;
+    let new_shortcut_keys = shortcut_keys(&new_settings);
+    let shortcuts_changed = new_shortcut_keys != shortcut_keys(&old_settings);
+    let hotkeys = new_settings.hotkeys.clone();
+    let doubao_page_changed = new_settings.doubao_page != old_settings.doubao_page;
+    let dictation_changed = new_settings.dictation_enabled != old_settings.dictation_enabled;
+    let input_device_changed = new_settings.input_device != old_settings.input_device;
+    settings::set(new_settings)?;
+    if hotkeys_changed {
+        fn_key::set_triggers(hotkeys);
+    }
+    if doubao_page_changed {
+        // 换了账号，旧账号的 Cookie 和参数不能再用
+        doubao_cdp::clear_account_cache();
+        doubao_asr::reset_cooldown();
+    }
+    if shortcuts_changed {
+        fn_key::set_snippet_keys(new_shortcut_keys);
+    }
+    if input_device_changed {
+        tray::refresh_input_devices(&app);
+    }
+    if dictation_changed {
+        on_dictation_enabled_changed(&app);
+    }
+    Ok(settings::get())
+}
+
+/// 清空高级设置中的 ASR 参数覆盖
+#[tauri::command]
+fn reset_asr_overrides() -> Result<settings::Settings, String> {
+    log::info!("[TypeFree] Resetting ASR overrides");
+    settings::update(|s| s.asr_overrides = Default::default())
+}
+
+/// 重新粘贴上一次的识别结果
+#[tauri::command]
+fn repaste_last(app: AppHandle) {
+    std::thread::spawn(move || repaste_last_result(&app));
+}
+
+/// 点击浮层关闭（确认粘贴时除外）
+#[tauri::command]
+fn dismiss_overlay(app: AppHandle) {
+    if pending_review().is_some() {
+        return;
+    }
+    overlay::cancel_hide();
+    hide_overlay(&app);
+}
+
+#[tauri::command]
+fn confirm_review(app: AppHandle) {
+    std::thread::spawn(move || finish_review(&app, true));
+}
+
+#[tauri::command]
+fn cancel_review(app: AppHandle) {
+    std::thread::spawn(move || finish_review(&app, false));
+}
+
+// ============ 快捷短语与单击动作键 ============
+
+/// 单击动作键对应的操作
+enum Shortcut {
+    /// 粘贴快捷短语（序号）
+    Snippet(usize),
+    UndoPaste,
+    Redictate,
+    PinTarget,
+    ToggleDictation,
+    CommitDraft,
+}
+
+/// 所有单击动作键：先是各快捷短语，再是撤销粘贴、重新听写、记录粘贴目标、暂停听写、插入全部草稿（按序号对应）
+fn shortcuts(settings: &settings::Settings) -> Vec<(fn_key::Trigger, Shortcut)> {
+    let mut shortcuts: Vec<_> = settings
+        .snippets
+        .iter()
+        .enumerate()
+        .map(|(index, snippet)| (snippet.key, Shortcut::Snippet(index)))
+        .collect();
+    if let Some(key) = settings.undo_paste_key {
+        shortcuts.push((key, Shortcut::UndoPaste));
+    }
+    if let Some(key) = settings.redictate_key {
+        shortcuts.push((key, Shortcut::Redictate));
+    }
+    if let Some(key) = settings.pin_target_key {
+        shortcuts.push((key, Shortcut::PinTarget));
+    }
+    if let Some(key) = settings.toggle_dictation_key {
+        shortcuts.push((key, Shortcut::ToggleDictation));
+    }
+    if let Some(key) = settings.commit_draft_key {
+        shortcuts.push((key, Shortcut::CommitDraft));
+    }
+    shortcuts
+}
+
+fn shortcut_keys(settings: &settings::Settings) -> Vec<fn_key::Trigger> {
+    shortcuts(settings).into_iter().map(|(key, _)| key).collect()
+}
+
+/// 执行第 index 个单击动作键的操作
+fn run_shortcut(app: &AppHandle, index: usize) {
+    let settings = settings::get();
+    match shortcuts(&settings).into_iter().nth(index).map(|(_, shortcut)| shortcut) {
+        Some(Shortcut::Snippet(snippet)) => {
+            log::info!("[TypeFree] Pasting snippet #{}", snippet);
+            keyboard::paste_final(&settings.snippets[snippet].text, "");
+        }
+        Some(Shortcut::UndoPaste) => undo_last_paste(app),
+        Some(Shortcut::Redictate) => redictate(app),
+        Some(Shortcut::PinTarget) => pin_paste_target(app),
+        Some(Shortcut::ToggleDictation) => toggle_dictation(app),
+        Some(Shortcut::CommitDraft) => commit_draft_now(app),
+        None => log::warn!("[TypeFree] Shortcut #{} not found", index),
+    }
+}
+
+// ============ 草稿 ============
+
+fn emit_draft_changed(app: &AppHandle, state: &draft::DraftState) {
+    events::emit(app, AppEvent::DraftChanged(state.clone()));
+}
+
+/// 插入全部草稿并清空
+///
+/// 先隐藏主窗口，让焦点回到目标应用后再粘贴
+pub(crate) fn commit_draft_now(app: &AppHandle) {
+    let text = draft::take();
+    emit_draft_changed(app, &draft::state());
+    if text.is_empty() {
+        log::info!("[TypeFree] Draft is empty, nothing to commit");
+        return;
+    }
+
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+        if window.is_focused().unwrap_or(false) {
+            let _ = window.hide();
+            #[cfg(target_os = "macos")]
+            let _ = app.hide();
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+    }
+
+    let settings = settings::get();
+    let target_app = focus::frontmost_app();
+    let suffix = postprocess::paste_suffix_for(&settings, target_app.as_ref());
+    log::info!("[TypeFree] Committing draft ({} chars)", text.chars().count());
+    keyboard::paste_final(&text, suffix);
+}
+
+#[tauri::command]
+fn get_draft() -> draft::DraftState {
+    draft::state()
+}
+
+#[tauri::command]
+fn clear_draft(app: AppHandle) {
+    draft::clear();
+    emit_draft_changed(&app, &draft::state());
+}
+
+/// 撤销：只移除最后加入的一段
+#[tauri::command]
+fn undo_draft(app: AppHandle) -> draft::DraftState {
+    let state = draft::pop_last();
+    emit_draft_changed(&app, &state);
+    state
+}
+
+#[tauri::command]
+fn commit_draft(app: AppHandle) {
+    std::thread::spawn(move || commit_draft_now(&app));
+}
+
+// ============ 便签 ============
+
+const SCRATCHPAD_WINDOW_LABEL: &str = "scratchpad";
+
+/// 识别结果写入便签，并在浮层提示
+fn write_to_scratchpad(app: &AppHandle, generation: u64, text: &str) {
+    let message = match scratchpad::append(text) {
+        Ok(content) => {
+            events::emit(app, AppEvent::ScratchpadChanged(content));
+            "已写入便签"
+        }
+        Err(e) => {
+            log::error!("[TypeFree] Failed to write scratchpad: {}", e);
+            "写入便签失败"
+        }
+    };
+    if is_current_session(generation) {
+        overlay::update_text(app, message);
+    }
+}
+
+/// 显示便签窗口，尚未创建时先创建（关闭时改为隐藏）
+pub(crate) fn show_scratchpad_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(SCRATCHPAD_WINDOW_LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+
+    let window = match WebviewWindowBuilder::new(
+        app,
+        SCRATCHPAD_WINDOW_LABEL,
+        WebviewUrl::App("scratchpad.html".into()),
+    )
+    .title("便签")
+    .inner_size(360.0, 420.0)
+    .build()
+    {
+        Ok(window) => window,
+        Err(e) => {
+            log::error!("[TypeFree] Failed to create scratchpad window: {}", e);
+            return;
+        }
+    };
+    let window_for_event = window.clone();
+    window.on_window_event(move |event| {
+        if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            api.prevent_close();
+            let _ = window_for_event.hide();
+        }
+    });
+}
+
+/// 暂停或恢复听写（托盘菜单、单击动作键）
+pub(crate) fn toggle_dictation(app: &AppHandle) {
+    match settings::update(|s| s.dictation_enabled = !s.dictation_enabled) {
+        Ok(s) => {
+            log::info!("[TypeFree] Dictation enabled: {}", s.dictation_enabled);
+            on_dictation_enabled_changed(app);
+        }
+        Err(e) => log::error!("[TypeFree] Failed to toggle dictation: {}", e),
+    }
+}
+
+/// 触发键可能被其他软件占用：记到托盘状态并通知主窗口
+fn on_hotkey_conflict(app: &AppHandle) {
+    let message = "热键可能被其他软件占用：已授予权限，但一直收不到按键。\
+                   请退出其他全局快捷键或键盘映射软件后重启 TypeFree"
+        .to_string();
+    let mut health = tray::health();
+    health.last_error = Some("热键可能被其他软件占用".to_string());
+    tray::update_health(app, health);
+    events::emit(app, AppEvent::HotkeyConflict(message));
+}
+
+/// 听写开关变化：刷新托盘并通知主窗口
+fn on_dictation_enabled_changed(app: &AppHandle) {
+    let enabled = settings::get().dictation_enabled;
+    tray::update_dictation_enabled(app);
+    events::emit(app, AppEvent::DictationEnabledChanged(enabled));
+}
+
+/// 切换「听写到便签」模式，开启时打开便签窗口
+pub(crate) fn toggle_scratchpad_mode(app: &AppHandle) {
+    let active = !scratchpad::is_active();
+    scratchpad::set_active(active);
+    tray::update_scratchpad_mode(app);
+    if active {
+        show_scratchpad_window(app);
+    }
+}
+
+#[tauri::command]
+fn append_scratchpad(app: AppHandle, text: String) -> Result<String, String> {
+    let content = scratchpad::append(&text)?;
+    events::emit(&app, AppEvent::ScratchpadChanged(content.clone()));
+    Ok(content)
+}
+
+#[tauri::command]
+fn get_scratchpad() -> String {
+    scratchpad::get()
+}
+
+#[tauri::command]
+fn clear_scratchpad(app: AppHandle) -> Result<(), String> {
+    scratchpad::clear()?;
+    events::emit(&app, AppEvent::ScratchpadChanged(String::new()));
+    Ok(())
+}
+
+// ============ 实时转写 ============
+
+#[tauri::command]
+fn get_transcript() -> Vec<transcript::Entry> {
+    transcript::entries()
+}
+
+#[tauri::command]
+fn clear_transcript() {
+    transcript::clear();
+}
 
-        // 显示最终结果，1秒后隐藏
-        overlay::update_text(&app_for_final, text);
-        let app_clone = app_for_final.clone();
-        std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::from_secs(1));
-            hide_overlay(&app_clone);
-        });
-    };
+/// 导出到配置目录下的 transcripts 目录并打开，返回文件路径
+#[tauri::command]
+fn export_transcript() -> Result<String, String> {
+    let dir = settings::config_dir().ok_or("Settings not initialized")?;
+    let path = transcript::export(&dir.join("transcripts"))?;
+    open_with_system(&path)?;
+    Ok(path.display().to_string())
+}
 
-    // 运行 ASR 会话
-    let session_result = doubao_asr::run_asr_session(audio_rx, stop_flag, on_partial, on_final).await;
+/// 导出转写记录（`path` 为文件或目录），返回写入的文件路径
+#[tauri::command]
+fn export_history(
+    format: history::ExportFormat,
+    path: String,
+    range: Option<history::TimeRange>,
+    redact: Option<bool>,
+) -> Result<String, String> {
+    let path = history::export(
+        std::path::Path::new(&path),
+        format,
+        range.unwrap_or_default(),
+        redact.unwrap_or(false),
+    )?;
+    Ok(path.display().to_string())
+}
 
-    if let Err(e) = &session_result {
-        log::error!("[TypeFree] ASR session error: {}", e);
-        // 显示错误信息
-        overlay::update_text(app, &format!("错误: {}", e));
+/// 把今天的记录导出到桌面（托盘一键操作）
+pub(crate) fn export_today_to_desktop(app: &AppHandle) {
+    let result = app
+        .path()
+        .desktop_dir()
+        .map_err(|e| format!("Failed to locate desktop: {}", e))
+        .and_then(|dir| {
+            history::export(
+                &dir,
+                history::ExportFormat::Markdown,
+                history::TimeRange::today(),
+                false,
+            )
+        });
+    match result {
+        Ok(_) => flash_status(app, "今天的记录已导出到桌面"),
+        Err(e) => {
+            log::warn!("[TypeFree] Failed to export today's history: {}", e);
+            flash_status(app, &format!("导出失败：{}", e));
+        }
     }
+}
 
-    let _ = audio_handle.join();
-    log::info!("[TypeFree] STT session ended");
+// ============ 撤销与重新听写 ============
 
-    // 如果 ASR 出错，2秒后隐藏 overlay
-    if session_result.is_err() {
-        let app_clone = app.clone();
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        hide_overlay(&app_clone);
-    }
+/// 在浮层上短暂显示提示
+fn flash_status(app: &AppHandle, text: &str) {
+    let app_for_thread = app.clone();
+    let text = text.to_string();
+    let _ = app.run_on_main_thread(move || {
+        overlay::update_status(&app_for_thread, &text);
+        overlay::show(&app_for_thread);
+    });
+    let generation = SESSION_GENERATION.load(Ordering::SeqCst);
+    let delay = std::time::Duration::from_millis(settings::get().status_display_ms);
+    hide_overlay_after(app, generation, delay);
 }
 
-// ============ Tauri Commands ============
+// ============ 更新检查 ============
 
+/// 检查更新，有新版本时同时刷新托盘并通知前端
 #[tauri::command]
-fn get_permission_status() -> permissions::PermissionStatus {
-    let status = permissions::PermissionStatus::check();
-    log::info!(
-        "[TypeFree] Permission status: input_monitoring={}, accessibility={}, microphone={}",
-        status.input_monitoring,
-        status.accessibility,
-        status.microphone
-    );
-    status
+async fn check_for_updates(app: AppHandle) -> Result<update::UpdateInfo, TypeFreeError> {
+    let info = update::check().await.map_err(TypeFreeError::Other)?;
+    if info.available {
+        on_update_available(&app, info.clone());
+    }
+    Ok(info)
 }
 
+/// 用浏览器打开检查到的新版本的下载页
 #[tauri::command]
-fn open_input_monitoring_settings() {
-    #[cfg(target_os = "macos")]
-    {
-        let _ = std::process::Command::new("open")
-            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_ListenEvent")
-            .spawn();
+fn open_update_page() -> Result<(), String> {
+    let info = update::latest().ok_or("No update available")?;
+    if !info.url.starts_with("https://") {
+        return Err(format!("Unexpected download URL: {}", info.url));
     }
+    log::info!("[TypeFree] Opening download page for {}", info.version);
+    open_with_system(std::path::Path::new(&info.url))
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        // Windows 没有专门的 Input Monitoring 设置
-        // 打开隐私设置主页面
-        let _ = std::process::Command::new("cmd")
-            .args(["/C", "start", "ms-settings:privacy"])
-            .spawn();
+fn on_update_available(app: &AppHandle, info: update::UpdateInfo) {
+    tray::update_available(app, &info.version);
+    events::emit(app, AppEvent::UpdateAvailable(info));
+}
+
+/// 托盘「检查更新」：已有新版本时打开下载页，否则立即检查并在浮层提示结果
+pub(crate) fn check_updates_from_tray(app: &AppHandle) {
+    if update::latest().is_some() {
+        if let Err(e) = open_update_page() {
+            log::warn!("[TypeFree] {}", e);
+        }
+        return;
     }
+    let app = app.clone();
+    RUNTIME.spawn(async move {
+        match update::check().await {
+            Ok(info) if info.available => {
+                flash_status(&app, &format!("发现新版本 {}", info.version));
+                on_update_available(&app, info);
+            }
+            Ok(_) => flash_status(&app, "已是最新版本"),
+            Err(e) => {
+                log::warn!("[TypeFree] Update check failed: {}", e);
+                flash_status(&app, "检查更新失败，请稍后再试");
+            }
+        }
+    });
 }
 
-#[tauri::command]
-fn open_accessibility_settings() {
-    #[cfg(target_os = "macos")]
-    {
-        let _ = std::process::Command::new("open")
-            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility")
-            .spawn();
+/// 草稿模式下撤销草稿的最后一段，返回是否已处理
+fn undo_draft_segment(app: &AppHandle) -> bool {
+    if !settings::get().draft_mode || draft::state().segments == 0 {
+        return false;
     }
+    let state = draft::pop_last();
+    emit_draft_changed(app, &state);
+    flash_status(app, &format!("已撤销草稿最后一段 (共 {} 字)", state.chars));
+    true
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        // Windows 辅助功能设置
-        let _ = std::process::Command::new("cmd")
-            .args(["/C", "start", "ms-settings:easeofaccess"])
-            .spawn();
+/// 撤销上一次粘贴（删除与粘贴字数相同的字符，粘贴后移动过光标时会删错）；
+/// 草稿模式下只移除草稿的最后一段
+pub(crate) fn undo_last_paste(app: &AppHandle) {
+    if IS_RECORDING.load(Ordering::SeqCst) {
+        log::info!("[TypeFree] Recording in progress, skip undo");
+        return;
+    }
+    if undo_draft_segment(app) {
+        return;
+    }
+    match keyboard::undo_last_paste() {
+        Ok(Some(chars)) => log::info!("[TypeFree] Undid last paste ({} chars)", chars),
+        Ok(None) => flash_status(app, "没有可撤销的粘贴"),
+        Err(message) => flash_status(app, &message),
     }
 }
 
-#[tauri::command]
-fn open_microphone_settings() {
-    #[cfg(target_os = "macos")]
-    {
-        let _ = std::process::Command::new("open")
-            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone")
-            .spawn();
+/// 重新听写：撤销上一次粘贴（草稿模式下移除草稿最后一段），丢弃其结果并开始新的录音；
+/// 录音中再次调用则结束录音
+pub(crate) fn redictate(app: &AppHandle) {
+    if IS_RECORDING.load(Ordering::SeqCst) {
+        on_fn_released(app);
+        return;
+    }
+    if undo_draft_segment(app) {
+        transcript::mark_undone();
+        log::info!("[TypeFree] Re-dictating last draft segment");
+        on_fn_pressed(app, fn_key::Modifiers::default());
+        return;
     }
+    if let Err(message) = keyboard::undo_last_paste() {
+        flash_status(app, &message);
+        return;
+    }
+    transcript::mark_undone();
+    log::info!("[TypeFree] Re-dictating");
+    on_fn_pressed(app, fn_key::Modifiers::default());
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        // Windows 麦克风隐私设置
-        let _ = std::process::Command::new("cmd")
-            .args(["/C", "start", "ms-settings:privacy-microphone"])
-            .spawn();
+// ============ 粘贴目标 ============
+
+/// 记录当前窗口和输入框为粘贴目标，之后的听写都插入到这里
+pub(crate) fn pin_paste_target(app: &AppHandle) {
+    match focus::pin_target() {
+        Some(target) => flash_status(app, &format!("已记录粘贴目标：{}", target.name)),
+        None => flash_status(app, "无法获取当前窗口"),
+    }
+}
+
+/// 清除记录的粘贴目标，恢复粘贴到光标
+pub(crate) fn clear_paste_target(app: &AppHandle) {
+    if focus::clear_pinned_target() {
+        log::info!("[TypeFree] Cleared pinned paste target");
+        flash_status(app, "已清除粘贴目标");
+    }
+}
+
+// ============ 重新粘贴 ============
+
+/// 把最近一次识别结果按当前前台应用的规则再粘贴一次
+pub(crate) fn repaste_last_result(app: &AppHandle) {
+    if IS_RECORDING.load(Ordering::SeqCst) {
+        log::info!("[TypeFree] Recording in progress, skip repaste");
+        return;
+    }
+
+    // 密码框等开启了安全输入时，系统会拦截模拟的粘贴键
+    if keyboard::secure_input_enabled() {
+        log::info!("[TypeFree] Secure input enabled, skip repaste");
+        flash_status(app, "当前输入框不允许粘贴");
+        return;
     }
+
+    let Some(text) = transcript::mark_repasted() else {
+        log::info!("[TypeFree] No previous result to repaste");
+        flash_status(app, "没有可重新粘贴的内容");
+        return;
+    };
+
+    let settings = settings::get();
+    let target_app = focus::frontmost_app();
+    let processed = postprocess::process(&text, &settings, target_app.as_ref());
+    let suffix = postprocess::paste_suffix_for(&settings, target_app.as_ref());
+    log::info!(
+        "[TypeFree] Repasting last result into {}",
+        target_app.as_ref().map(|a| a.id.as_str()).unwrap_or("unknown app")
+    );
+    keyboard::paste_final(&processed, suffix);
 }
 
 // ============ 豆包桌面端管理 ============
 
+/// 被动捕获 ASR URL 的最长等待
+const PASSIVE_CAPTURE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(120);
+
+/// 启动时捕获 ASR URL 参数
+///
+/// 被听写取消后等录音结束再重试；若听写过程中已捕获到参数则不再重复
+async fn capture_startup_url_params() -> Result<(), TypeFreeError> {
+    if settings::get().passive_url_capture {
+        let url = doubao_cdp::capture_asr_url_passively(PASSIVE_CAPTURE_TIMEOUT).await?;
+        log::info!("[TypeFree] Captured ASR URL: {}", url);
+        doubao_cdp::set_cached_url_params(doubao_cdp::parse_asr_url_params(&url));
+        return Ok(());
+    }
+    loop {
+        match doubao_cdp::capture_asr_url_by_click().await {
+            Ok(url) => {
+                log::info!("[TypeFree] Captured ASR URL: {}", url);
+                let params = doubao_cdp::parse_asr_url_params(&url);
+                log::info!("[TypeFree] Parsed {} params, caching...", params.len());
+                doubao_cdp::set_cached_url_params(params);
+                return Ok(());
+            }
+            Err(TypeFreeError::CaptureCancelled) => {
+                while IS_RECORDING.load(Ordering::SeqCst) {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                }
+                if doubao_cdp::get_cached_url_params().is_some() {
+                    log::info!("[TypeFree] ASR URL params captured during dictation");
+                    return Ok(());
+                }
+                log::info!("[TypeFree] Rescheduling ASR URL capture...");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 #[derive(serde::Serialize)]
 struct DoubaoStatus {
     installed: bool,
@@ -251,16 +2612,36 @@ struct DoubaoStatus {
     debug_mode: bool,
     logged_in: bool,
     ws_available: bool,
+    /// 使用捕获到的真实 URL 参数；false 表示使用内置默认参数，识别可能不稳定
+    using_real_params: bool,
+    /// 调试端口已打开但不是 TypeFree 启动的豆包（其他本地程序也能访问该端口）
+    external_debug_port: bool,
+    /// 调试端口被其他浏览器占用时，占用者的 Browser 标识
+    port_owner: Option<String>,
+    /// 调试端口可用但页面列表不可用（可能僵死，可触发恢复）
+    unresponsive: bool,
 }
 
 #[tauri::command]
-async fn get_doubao_status() -> DoubaoStatus {
+async fn get_doubao_status(app: AppHandle) -> DoubaoStatus {
     let installed = doubao_launcher::is_doubao_installed();
     let running = doubao_launcher::is_doubao_running();
-    let debug_mode = doubao_cdp::is_doubao_debug_available().await;
+
+    // 通过 /json/version 确认端口属于豆包
+    let (debug_mode, port_owner) = match doubao_cdp::fetch_cdp_version().await {
+        Ok(version) if version.is_doubao() => (true, None),
+        Ok(version) => {
+            log::warn!("[TypeFree] Debug port owned by another browser: {}", version.browser);
+            (false, Some(version.browser))
+        }
+        Err(_) => (false, None),
+    };
+
+    // 缓存的登录状态、Cookie 和参数取自同一快照
+    let state = doubao_cdp::snapshot();
 
     // 优先使用缓存的登录状态，如果没有缓存且 CDP 可用则实时检测
-    let logged_in = match doubao_cdp::get_cached_login_status() {
+    let logged_in = match state.login_status {
         Some(status) => status,
         None if debug_mode => {
             doubao_cdp::check_login_status().await.unwrap_or(false)
@@ -269,39 +2650,344 @@ async fn get_doubao_status() -> DoubaoStatus {
     };
 
     // 判断服务是否可用（有缓存的 Cookie 和 URL 参数即可）
+    let using_real_params = state.url_params.is_some();
     let ws_available = logged_in &&
-        doubao_cdp::get_cached_cookies().is_some() &&
-        doubao_cdp::get_cached_url_params().is_some();
+        state.cookies.is_some() &&
+        using_real_params;
+
+    // 单次探测页面列表，持续不可用的判定交给 recover_doubao
+    let unresponsive = debug_mode && doubao_cdp::probe_pages().await.is_err();
+    if unresponsive {
+        log::warn!("[TypeFree] Doubao debug port open but page list unavailable");
+    }
+
+    let external_debug_port = debug_mode && !doubao_launcher::is_owned();
+    if external_debug_port {
+        log::warn!("[TypeFree] Doubao debug port is open but was not started by TypeFree");
+    }
 
     log::info!(
         "[TypeFree] Doubao status: installed={}, running={}, debug_mode={}, logged_in={}, ws_available={}",
         installed, running, debug_mode, logged_in, ws_available
     );
 
+    let mut health = tray::health();
+    health.logged_in = Some(logged_in);
+    tray::update_health(&app, health);
+
     DoubaoStatus {
         installed,
         running,
         debug_mode,
         logged_in,
         ws_available,
+        using_real_params,
+        external_debug_port,
+        port_owner,
+        unresponsive,
+    }
+}
+
+/// 重新捕获 ASR URL 参数（当前使用默认参数时由用户触发）
+#[tauri::command]
+async fn recapture_asr_params(app: AppHandle) -> Result<(), TypeFreeError> {
+    doubao_asr::reset_cooldown();
+    let result = capture_startup_url_params().await;
+    events::emit(&app, AppEvent::AsrParamsReady(Readiness::from_result(&result)));
+    result
+}
+
+/// 豆包页面列表（选择使用哪个账号）
+#[tauri::command]
+async fn list_doubao_pages() -> Result<Vec<doubao_cdp::DoubaoPage>, TypeFreeError> {
+    doubao_cdp::list_doubao_pages().await
+}
+
+/// 一键诊断：用测试音频走一遍完整识别链路（不粘贴、不显示浮层）
+#[tauri::command]
+async fn run_self_test() -> Result<selftest::PipelineReport, TypeFreeError> {
+    if IS_RECORDING.load(Ordering::SeqCst) {
+        return Err(TypeFreeError::Busy("正在听写，请稍后再试".to_string()));
     }
+    selftest::run_pipeline().await
 }
 
 #[tauri::command]
-async fn test_doubao_connection() -> Result<(), String> {
-    doubao_asr::test_connection().await
+async fn test_doubao_connection(app: AppHandle) -> Vec<doubao_asr::StageResult> {
+    let results = doubao_asr::test_connection().await;
+    let failure = doubao_asr::first_failure(&results);
+
+    let mut health = tray::health();
+    health.doubao_connected = Some(failure.is_none());
+    health.logged_in = doubao_cdp::get_cached_login_status().or(health.logged_in);
+    if let Some(stage) = failure {
+        health.last_error = Some(format!("{}: {}", stage.label, stage.detail));
+    }
+    tray::update_health(&app, health);
+
+    results
 }
 
 #[tauri::command]
-async fn launch_doubao_debug() -> Result<(), String> {
+async fn launch_doubao_debug() -> Result<(), TypeFreeError> {
     doubao_launcher::ensure_doubao_debug_mode().await.map(|_| ())
 }
 
 #[tauri::command]
-async fn restart_doubao_debug() -> Result<(), String> {
+async fn restart_doubao_debug() -> Result<(), TypeFreeError> {
     doubao_launcher::restart_doubao_debug_mode().await
 }
 
+/// 本次是否由开机自动启动（前端据此调整首屏）
+#[tauri::command]
+fn was_autostarted() -> bool {
+    is_autostarted_launch()
+}
+
+/// 所有事件的名称和载荷 JSON Schema（供前端生成类型）
+#[tauri::command]
+fn get_event_schema() -> serde_json::Value {
+    events::schema()
+}
+
+/// 检测豆包是否僵死，僵死则强制重启
+#[tauri::command]
+async fn recover_doubao() -> Result<doubao_launcher::Liveness, TypeFreeError> {
+    doubao_asr::reset_cooldown();
+    doubao_launcher::recover_if_zombie().await
+}
+
+/// 开机自动启动时推迟的豆包初始化（豆包以普通模式运行时，不在登录时重启它）
+static DOUBAO_INIT_DEFERRED: AtomicBool = AtomicBool::new(false);
+
+/// 确保豆包处于调试模式，然后捕获 ASR URL 参数
+async fn init_doubao(app: &AppHandle) {
+    log::info!("[TypeFree] Ensuring Doubao debug mode...");
+    if !ensure_doubao_ready(app).await {
+        if !doubao_launcher::is_managed_externally() {
+            return;
+        }
+        if !wait_for_external_doubao(app).await {
+            return;
+        }
+    }
+    prepare_doubao_session(app).await;
+}
+
+/// 应用正在退出（后台等待任务据此停止）
+static EXITING: AtomicBool = AtomicBool::new(false);
+
+/// 手动管理模式：提示用户自行启动豆包，等调试端口可用后继续，返回是否已可用
+///
+/// 用户关闭手动管理或应用退出时停止等待
+async fn wait_for_external_doubao(app: &AppHandle) -> bool {
+    let requirement = doubao_launcher::MANUAL_MODE_REQUIREMENT.to_string();
+    events::emit(app, AppEvent::DoubaoRequirement(requirement));
+    notify_without_window(app, TypeFreeError::DoubaoNotRunning);
+    log::info!("[TypeFree] Waiting for externally managed Doubao...");
+    while !doubao_cdp::is_doubao_debug_available().await {
+        if EXITING.load(Ordering::SeqCst) || !doubao_launcher::is_managed_externally() {
+            log::info!("[TypeFree] Stopped waiting for externally managed Doubao");
+            return false;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    }
+    log::info!("[TypeFree] Externally managed Doubao is now available");
+    events::emit(app, AppEvent::DoubaoReady(Readiness::ready()));
+    true
+}
+
+/// 确保豆包处于调试模式，并通知前端
+async fn ensure_doubao_ready(app: &AppHandle) -> bool {
+    match doubao_launcher::ensure_doubao_debug_mode().await {
+        Ok(_) => {
+            log::info!("[TypeFree] Doubao debug mode ready");
+            events::emit(app, AppEvent::DoubaoReady(Readiness::ready()));
+            true
+        }
+        Err(e) => {
+            log::warn!("[TypeFree] Doubao debug mode not available: {}", e);
+            events::emit(app, AppEvent::DoubaoReady(Readiness::failed(e.to_string())));
+            if !doubao_launcher::is_managed_externally() {
+                notify_without_window(app, TypeFreeError::DoubaoNotRunning);
+            }
+            false
+        }
+    }
+}
+
+/// 豆包就绪后捕获 ASR URL 参数并检测登录状态
+async fn prepare_doubao_session(app: &AppHandle) {
+    // 等待豆包页面完全加载
+    log::info!("[TypeFree] Waiting for Doubao page to load...");
+    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+
+    // 自动捕获 ASR URL 参数
+    log::info!("[TypeFree] Capturing ASR URL params...");
+    match capture_startup_url_params().await {
+        Ok(()) => {
+            // 检测登录状态
+            log::info!("[TypeFree] Checking login status...");
+            match doubao_cdp::check_login_status().await {
+                Ok(logged_in) => {
+                    log::info!("[TypeFree] Login status: {}", logged_in);
+                    doubao_cdp::set_cached_login_status(logged_in);
+                    // 豆包重新启动并已登录：之前的连续失败不再算数
+                    if logged_in {
+                        doubao_asr::reset_cooldown();
+                    }
+                }
+                Err(e) => {
+                    log::warn!("[TypeFree] Failed to check login: {}", e);
+                }
+            }
+
+            // 保持豆包在后台运行，不关闭
+            log::info!("[TypeFree] Doubao will keep running in background for real-time Cookie fetching");
+
+            events::emit(app, AppEvent::AsrParamsReady(Readiness::ready()));
+        }
+        Err(e) => {
+            log::warn!("[TypeFree] Failed to capture ASR URL: {}", e);
+            log::warn!("[TypeFree] Will use fallback params when needed");
+            events::emit(app, AppEvent::AsrParamsReady(Readiness::failed(e.to_string())));
+        }
+    }
+}
+
+/// 首次使用时完成推迟的豆包初始化：听写只等调试模式就绪，参数捕获在后台进行
+async fn run_deferred_doubao_init(app: &AppHandle) {
+    if !DOUBAO_INIT_DEFERRED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    log::info!("[TypeFree] Running deferred Doubao init");
+    overlay::update_status(app, "正在初始化豆包…");
+    if ensure_doubao_ready(app).await {
+        let app = app.clone();
+        RUNTIME.spawn(async move { prepare_doubao_session(&app).await });
+    }
+}
+
+// ============ 主窗口 ============
+
+const MAIN_WINDOW_LABEL: &str = "main";
+
+/// 带此参数启动时可不创建主窗口
+const HIDDEN_ARG: &str = "--hidden";
+
+/// 开机自动启动时传入的参数（同时意味着 `--hidden`）
+const AUTOSTARTED_ARG: &str = "--autostarted";
+
+/// 带此参数启动时等同于开启仅菜单栏模式
+const MENU_BAR_ONLY_ARG: &str = "--menu-bar-only";
+
+/// 本次是否由开机自动启动
+fn is_autostarted_launch() -> bool {
+    static AUTOSTARTED: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AUTOSTARTED.get_or_init(|| std::env::args().any(|arg| arg == AUTOSTARTED_ARG))
+}
+
+/// 本次是否以仅菜单栏模式运行（启动时确定，修改设置后重启生效）
+pub(crate) fn is_menu_bar_only() -> bool {
+    static MENU_BAR_ONLY: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *MENU_BAR_ONLY.get_or_init(|| {
+        std::env::args().any(|arg| arg == MENU_BAR_ONLY_ARG) || settings::get().menu_bar_only
+    })
+}
+
+/// 仅菜单栏模式下没有主窗口接收事件，改为在浮层和托盘状态中提示
+fn notify_without_window(app: &AppHandle, error: TypeFreeError) {
+    if !is_menu_bar_only() {
+        return;
+    }
+    let mut health = tray::health();
+    health.last_error = Some(error.describe().0);
+    tray::update_health(app, health);
+    overlay::show_error(app, &error);
+}
+
+/// 创建主窗口（关闭时改为隐藏）
+fn create_main_window(app: &AppHandle) -> tauri::Result<tauri::WebviewWindow> {
+    log::info!("[TypeFree] Creating main window...");
+    let main_window = WebviewWindowBuilder::new(
+        app,
+        MAIN_WINDOW_LABEL,
+        WebviewUrl::App("index.html".into()),
+    )
+    .title("TypeFree")
+    .inner_size(440.0, 850.0)
+    .resizable(false)
+    .center()
+    .build()?;
+
+    // 拦截关闭事件，改为隐藏窗口而不是销毁
+    let window_for_event = main_window.clone();
+    main_window.on_window_event(move |event| match event {
+        tauri::WindowEvent::CloseRequested { api, .. } => {
+            api.prevent_close();
+            let _ = window_for_event.hide();
+            log::info!("[TypeFree] Window hidden instead of closed");
+        }
+        // 从系统设置切回来时立即检查权限
+        tauri::WindowEvent::Focused(true) => permissions::request_recheck(),
+        _ => {}
+    });
+
+    Ok(main_window)
+}
+
+/// 显示主窗口，尚未创建时先创建
+pub(crate) fn show_main_window(app: &AppHandle) {
+    if is_menu_bar_only() {
+        log::info!("[TypeFree] Menu-bar-only mode, not creating main window");
+        return;
+    }
+    let window = match app.get_webview_window(MAIN_WINDOW_LABEL) {
+        Some(window) => window,
+        None => match create_main_window(app) {
+            Ok(window) => window,
+            Err(e) => {
+                log::error!("[TypeFree] Failed to create main window: {}", e);
+                return;
+            }
+        },
+    };
+    let _ = window.show();
+    let _ = window.set_focus();
+}
+
+/// 权限变化：记录引导进度并通知前端
+fn on_permissions_changed(app: &AppHandle, change: permissions::PermissionChange) {
+    mark_onboarding_if_ready(&change.after);
+    events::emit(app, AppEvent::PermissionsChanged(change));
+}
+
+/// 权限全部授予后记录引导已完成
+fn mark_onboarding_if_ready(status: &permissions::PermissionStatus) {
+    if status.all_granted() && !settings::get().onboarding_completed {
+        log::info!("[TypeFree] Onboarding completed");
+        if let Err(e) = settings::update(|s| s.onboarding_completed = true) {
+            log::warn!("[TypeFree] Failed to save onboarding state: {}", e);
+        }
+    }
+}
+
+/// 启动时是否不创建主窗口：自动启动、已完成引导、开启了隐藏且权限正常
+fn should_start_hidden(permissions: &permissions::PermissionStatus) -> bool {
+    if !is_autostarted_launch() && !std::env::args().any(|arg| arg == HIDDEN_ARG) {
+        return false;
+    }
+    let settings = settings::get();
+    if !settings.start_hidden || !settings.onboarding_completed {
+        return false;
+    }
+    if !permissions.all_granted() {
+        log::info!("[TypeFree] Permission missing, showing main window despite {}", HIDDEN_ARG);
+        return false;
+    }
+    true
+}
+
 // ============ 入口 ============
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -311,7 +2997,7 @@ pub fn run() {
     let mut builder = tauri::Builder::default()
         .plugin(tauri_plugin_autostart::init(
             tauri_plugin_autostart::MacosLauncher::LaunchAgent,
-            Some(vec![]),
+            Some(vec![AUTOSTARTED_ARG]),
         ));
 
     // macOS: 添加 nspanel 插件用于置顶 overlay
@@ -323,13 +3009,50 @@ pub fn run() {
     builder
         .invoke_handler(tauri::generate_handler![
             get_permission_status,
+            get_audio_diagnostic,
+            get_hotkey_diagnostic,
+            get_asr_request_preview,
+            get_overlay_fallback,
+            get_last_latency,
+            reveal_audio_dumps,
+            list_input_devices,
+            set_audio_device,
             open_input_monitoring_settings,
             open_accessibility_settings,
+            open_automation_settings,
+            request_paste_permission,
             open_microphone_settings,
+            open_data_dir,
             get_doubao_status,
             test_doubao_connection,
+            run_self_test,
+            list_doubao_pages,
+            recapture_asr_params,
             launch_doubao_debug,
             restart_doubao_debug,
+            recover_doubao,
+            was_autostarted,
+            get_settings,
+            update_settings,
+            reset_asr_overrides,
+            dismiss_overlay,
+            confirm_review,
+            cancel_review,
+            repaste_last,
+            get_draft,
+            clear_draft,
+            undo_draft,
+            commit_draft,
+            append_scratchpad,
+            get_scratchpad,
+            clear_scratchpad,
+            get_transcript,
+            clear_transcript,
+            export_transcript,
+            export_history,
+            check_for_updates,
+            open_update_page,
+            get_event_schema,
         ])
         .setup(|app| {
             let app_handle = app.handle().clone();
@@ -337,6 +3060,39 @@ pub fn run() {
             // 保存全局 AppHandle
             let _ = APP_HANDLE.set(app_handle.clone());
 
+            // 加载用户设置
+            match app.path().app_config_dir() {
+                Ok(dir) => {
+                    settings::init(dir.clone());
+                    scratchpad::init(dir);
+                }
+                Err(e) => log::error!("[TypeFree] Failed to resolve config dir: {}", e),
+            }
+            match app.path().app_log_dir() {
+                Ok(dir) => audio_dump::init(dir),
+                Err(e) => log::error!("[TypeFree] Failed to resolve log dir: {}", e),
+            }
+
+            // 自检模式：输出报告后退出，不创建窗口、托盘和按键监听
+            if selftest::requested() {
+                std::thread::spawn(move || {
+                    let code = selftest::run();
+                    app_handle.exit(code);
+                });
+                return Ok(());
+            }
+
+            // 旧版本注册的开机启动项不带参数，重新注册以带上 --autostarted
+            {
+                use tauri_plugin_autostart::ManagerExt;
+                let autolaunch = app.autolaunch();
+                if autolaunch.is_enabled().unwrap_or(false) {
+                    if let Err(e) = autolaunch.enable() {
+                        log::warn!("[TypeFree] Failed to refresh autostart entry: {}", e);
+                    }
+                }
+            }
+
             // 初始化系统托盘
             log::info!("[TypeFree] Initializing tray...");
             if let Err(e) = tray::init(&app_handle) {
@@ -344,99 +3100,105 @@ pub fn run() {
             }
 
             // 预热麦克风 - 只在没有权限时触发系统权限弹窗
-            if !permissions::check_microphone() {
+            let permission_status = permissions::PermissionStatus::check();
+            if !permission_status.can_record_audio {
                 log::info!("[TypeFree] Microphone not authorized, warming up to trigger permission prompt...");
                 audio::warmup_microphone();
             } else {
                 log::info!("[TypeFree] Microphone already authorized");
             }
 
-            // 创建主窗口
-            log::info!("[TypeFree] Creating main window...");
-            let main_window = WebviewWindowBuilder::new(
-                &app_handle,
-                "main",
-                WebviewUrl::App("index.html".into()),
-            )
-            .title("TypeFree")
-            .inner_size(440.0, 850.0)
-            .resizable(false)
-            .center()
-            .build()
-            .expect("Failed to create main window");
-
-            // 拦截关闭事件，改为隐藏窗口而不是销毁
-            let window_for_event = main_window.clone();
-            main_window.on_window_event(move |event| {
-                if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                    api.prevent_close();
-                    let _ = window_for_event.hide();
-                    log::info!("[TypeFree] Window hidden instead of closed");
-                }
-            });
+            // 创建主窗口（自动启动且无需引导时推迟到托盘「打开 TypeFree」）
+            mark_onboarding_if_ready(&permission_status);
+            if is_menu_bar_only() {
+                log::info!("[TypeFree] Menu-bar-only mode, main window disabled");
+            } else if should_start_hidden(&permission_status) {
+                log::info!("[TypeFree] Started hidden, main window deferred");
+            } else {
+                create_main_window(&app_handle).expect("Failed to create main window");
+            }
 
             // 创建 Overlay Panel（使用 NSPanel 置顶显示，定位到焦点窗口所在屏幕底部）
             log::info!("[TypeFree] Creating overlay panel...");
             overlay::preload(&app_handle);
 
-            // 自动启动豆包调试模式 + 捕获 ASR URL 参数
-            log::info!("[TypeFree] Ensuring Doubao debug mode...");
+            // 定时检查权限，变化时通知前端
+            let app_for_permissions = app_handle.clone();
+            permissions::spawn_watcher(permission_status.clone(), move |change| {
+                on_permissions_changed(&app_for_permissions, change)
+            });
+
+            // 仅菜单栏模式没有引导页，缺少的权限在浮层提示
+            let missing = permission_status.missing();
+            if !missing.is_empty() {
+                notify_without_window(&app_handle, TypeFreeError::MissingPermissions(missing));
+            }
+
+            // 启动豆包调试模式 + 捕获 ASR URL 参数（开机自动启动时延后，且不重启普通模式的豆包）
             let app_for_doubao = app.handle().clone();
             RUNTIME.spawn(async move {
-                match doubao_launcher::ensure_doubao_debug_mode().await {
-                    Ok(_) => {
-                        log::info!("[TypeFree] Doubao debug mode ready");
-                        let _ = app_for_doubao.emit("doubao-ready", true);
-
-                        // 等待豆包页面完全加载
-                        log::info!("[TypeFree] Waiting for Doubao page to load...");
-                        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-
-                        // 自动捕获 ASR URL 参数
-                        log::info!("[TypeFree] Capturing ASR URL params...");
-                        match doubao_cdp::capture_asr_url_by_click().await {
-                            Ok(url) => {
-                                log::info!("[TypeFree] Captured ASR URL: {}", url);
-                                let params = doubao_cdp::parse_asr_url_params(&url);
-                                log::info!("[TypeFree] Parsed {} params, caching...", params.len());
-                                doubao_cdp::set_cached_url_params(params);
-
-                                // 检测登录状态
-                                log::info!("[TypeFree] Checking login status...");
-                                match doubao_cdp::check_login_status().await {
-                                    Ok(logged_in) => {
-                                        log::info!("[TypeFree] Login status: {}", logged_in);
-                                        doubao_cdp::set_cached_login_status(logged_in);
-                                    }
-                                    Err(e) => {
-                                        log::warn!("[TypeFree] Failed to check login: {}", e);
-                                    }
-                                }
-
-                                // 保持豆包在后台运行，不关闭
-                                log::info!("[TypeFree] Doubao will keep running in background for real-time Cookie fetching");
-
-                                let _ = app_for_doubao.emit("asr-params-ready", true);
-                            }
-                            Err(e) => {
-                                log::warn!("[TypeFree] Failed to capture ASR URL: {}", e);
-                                log::warn!("[TypeFree] Will use fallback params when needed");
-                                let _ = app_for_doubao.emit("asr-params-ready", false);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!("[TypeFree] Doubao debug mode not available: {}", e);
-                        let _ = app_for_doubao.emit("doubao-ready", false);
+                if is_autostarted_launch() {
+                    if !doubao_launcher::is_managed_externally()
+                        && doubao_launcher::is_doubao_running()
+                        && !doubao_cdp::is_doubao_debug_available().await
+                    {
+                        log::info!("[TypeFree] Doubao running normally, deferring restart to first use");
+                        DOUBAO_INIT_DEFERRED.store(true, Ordering::SeqCst);
+                        return;
                     }
+
+                    let grace = settings::get().autostart_grace_secs;
+                    log::info!("[TypeFree] Autostarted, delaying Doubao init by {}s", grace);
+                    tokio::time::sleep(std::time::Duration::from_secs(grace)).await;
                 }
+                init_doubao(&app_for_doubao).await;
             });
 
-            // 启动 Fn 键监听
+            // 听写等待豆包初始化（ASR URL 捕获即将完成）时提示
+            let app_for_gate = app_handle.clone();
+            doubao_cdp::set_gate_wait_notifier(move || {
+                if IS_RECORDING.load(Ordering::SeqCst) {
+                    overlay::update_status(&app_for_gate, "正在初始化豆包…");
+                }
+            });
+
+            // Cookie 中没有有效的设备标识时提醒前端（识别多半会认证失败）
+            let app_for_fallback_id = app_handle.clone();
+            doubao_cdp::set_fallback_id_notifier(move |ids| {
+                events::emit(&app_for_fallback_id, AppEvent::DeviceIdFallback(ids.to_string()));
+            });
+
+            // 确认粘贴模式的 Enter/Esc（Windows 通过键盘钩子拦截）
+            let app_for_review = app_handle.clone();
+            fn_key::set_review_key_handler(move |confirmed| {
+                finish_review(&app_for_review, confirmed);
+            });
+
+            // 快捷短语键
+            let app_for_shortcut = app_handle.clone();
+            fn_key::set_snippet_handler(move |index| run_shortcut(&app_for_shortcut, index));
+            fn_key::set_snippet_keys(shortcut_keys(&settings::get()));
+
+            // 触发键监听一直收不到按键时提示（其他软件占用了键盘钩子或 Fn 键）
+            let app_for_conflict = app_handle.clone();
+            fn_key::spawn_conflict_check(move || on_hotkey_conflict(&app_for_conflict));
+
+            // 每天检查一次新版本（设置中可关闭）
+            let app_for_update = app_handle.clone();
+            RUNTIME.spawn(update::run_daily_check(move |info| {
+                on_update_available(&app_for_update, info)
+            }));
+
+            // 字幕模式：启动后直接开始循环录音
+            if is_caption_mode() {
+                RUNTIME.spawn(run_caption_loop(app_handle.clone()));
+            }
+
+            // 启动触发键监听
             log::info!("[TypeFree] Starting Fn key monitor...");
-            fn_key::start_fn_key_monitor(move |pressed| {
+            fn_key::start_fn_key_monitor(settings::get().hotkeys, move |pressed, modifiers| {
                 if pressed {
-                    on_fn_pressed(&app_handle);
+                    on_fn_pressed(&app_handle, modifiers);
                 } else {
                     on_fn_released(&app_handle);
                 }
@@ -445,6 +3207,17 @@ pub fn run() {
             log::info!("[TypeFree] Ready!");
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                EXITING.store(true, Ordering::SeqCst);
+            }
+            if let tauri::RunEvent::Exit = event {
+                // 关闭由 TypeFree 启动的豆包，避免调试端口残留
+                if settings::get().quit_doubao_on_exit {
+                    doubao_launcher::shutdown_owned_doubao();
+                }
+            }
+        });
 }
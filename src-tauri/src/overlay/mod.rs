@@ -3,5 +3,10 @@
 //! 纯 UI 浮层，显示识别状态和结果
 
 pub mod panel;
+pub mod text;
 
-pub use panel::{hide, preload, show, update_status, update_text};
+pub use panel::{
+    cancel_hide, fallback_reason, hide, hide_after, is_visible, preload, release_focus, show,
+    show_error, show_review, update_status, update_text,
+};
+pub use text::{partial_text, PartialThrottle, Throttled};
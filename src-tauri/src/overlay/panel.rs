@@ -3,7 +3,12 @@
 //! 纯 HTML/CSS 浮层窗口，显示识别状态和结果。
 //! 使用 NSPanel 实现置顶显示，不加载任何网页。
 
-use tauri::{AppHandle, Emitter, Manager};
+use crate::error::TypeFreeError;
+use crate::events::{self, AppEvent};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
 
 // macOS 窗口层级常量（高于全屏应用）
 #[cfg(target_os = "macos")]
@@ -11,6 +16,32 @@ const NS_SCREEN_SAVER_WINDOW_LEVEL: i32 = 1000;
 
 const OVERLAY_WINDOW_LABEL: &str = "overlay";
 
+/// 浮层尺寸与距屏幕底部的距离（逻辑像素）
+const OVERLAY_WIDTH: f64 = 500.0;
+const OVERLAY_HEIGHT: f64 = 120.0;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+const BOTTOM_MARGIN: f64 = 80.0;
+
+/// 浮层是否正在显示（显示器配置变化时需要立即重新定位）
+static OVERLAY_VISIBLE: AtomicBool = AtomicBool::new(false);
+
+/// 浮层每次显示或重新安排隐藏时递增，到时的隐藏计时器据此判断是否已被取消
+static SHOW_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 浮层未能转换为 NSPanel、退回普通置顶窗口时的说明
+static FALLBACK_REASON: Mutex<Option<String>> = Mutex::new(None);
+
+/// 浮层降级为普通窗口的说明（正常时为 None）
+pub fn fallback_reason() -> Option<String> {
+    FALLBACK_REASON.lock().unwrap().clone()
+}
+
+/// 把一维位置限制在 [start, start + extent - len] 内，放不下时贴住起点
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn clamp_axis(pos: f64, len: f64, start: f64, extent: f64) -> f64 {
+    pos.min(start + extent - len).max(start)
+}
+
 // macOS 屏幕检测模块
 #[cfg(target_os = "macos")]
 #[allow(deprecated)]
@@ -20,14 +51,14 @@ mod screen {
     use cocoa::foundation::{NSPoint, NSRect};
     use objc::{class, msg_send, sel, sel_impl};
 
-    const OVERLAY_WIDTH: f64 = 500.0;
-    const BOTTOM_MARGIN: f64 = 80.0;
+    use std::ffi::CStr;
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
 
-    /// 获取鼠标所在屏幕（比 CGWindowListCopyWindowInfo 快很多）
-    pub unsafe fn get_screen_at_focused_window() -> id {
-        // 使用鼠标位置代替焦点窗口位置，避免 CGWindowListCopyWindowInfo 的性能问题
-        let mouse_location: cocoa::foundation::NSPoint = msg_send![class!(NSEvent), mouseLocation];
+    use super::{clamp_axis, BOTTOM_MARGIN, OVERLAY_HEIGHT, OVERLAY_WIDTH};
 
+    /// 包含某点（Cocoa 坐标）的屏幕，没有时返回 nil
+    unsafe fn screen_containing(point: NSPoint) -> id {
         let screens: id = NSScreen::screens(nil);
         let count: usize = msg_send![screens, count];
 
@@ -35,35 +66,497 @@ mod screen {
             let screen: id = msg_send![screens, objectAtIndex: i];
             let frame: NSRect = NSScreen::frame(screen);
 
-            if mouse_location.x >= frame.origin.x
-                && mouse_location.x < frame.origin.x + frame.size.width
-                && mouse_location.y >= frame.origin.y
-                && mouse_location.y < frame.origin.y + frame.size.height
+            if point.x >= frame.origin.x
+                && point.x < frame.origin.x + frame.size.width
+                && point.y >= frame.origin.y
+                && point.y < frame.origin.y + frame.size.height
             {
                 return screen;
             }
         }
 
-        NSScreen::mainScreen(nil)
+        nil
+    }
+
+    /// 获取鼠标所在屏幕（比 CGWindowListCopyWindowInfo 快很多）
+    pub unsafe fn get_screen_at_mouse() -> id {
+        let mouse_location: NSPoint = msg_send![class!(NSEvent), mouseLocation];
+        let screen = screen_containing(mouse_location);
+        if screen == nil {
+            NSScreen::mainScreen(nil)
+        } else {
+            screen
+        }
+    }
+
+    /// 获取前台窗口所在屏幕，取不到窗口位置时退回鼠标所在屏幕
+    pub unsafe fn get_screen_at_focused_window() -> id {
+        let screen = match cached_focused_window_center() {
+            Some(center) => screen_containing(center),
+            None => nil,
+        };
+        if screen == nil {
+            get_screen_at_mouse()
+        } else {
+            screen
+        }
     }
 
+    // ============ 前台窗口位置 ============
+
+    /// 前台窗口位置缓存：(进程 pid, 记录时间, 窗口中心点)
+    ///
+    /// CGWindowListCopyWindowInfo 较慢，同一应用短时间内连续听写时复用结果
+    static FOCUSED_WINDOW_CACHE: Mutex<Option<(i32, Instant, NSPoint)>> = Mutex::new(None);
+    const FOCUSED_WINDOW_CACHE_TTL: Duration = Duration::from_secs(2);
+
+    /// kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements
+    const WINDOW_LIST_OPTIONS: u32 = (1 << 0) | (1 << 4);
+
+    unsafe fn cached_focused_window_center() -> Option<NSPoint> {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        if app == nil {
+            return None;
+        }
+        let pid: i32 = msg_send![app, processIdentifier];
+
+        let mut cache = FOCUSED_WINDOW_CACHE.lock().unwrap();
+        if let Some((cached_pid, at, center)) = *cache {
+            if cached_pid == pid && at.elapsed() < FOCUSED_WINDOW_CACHE_TTL {
+                return Some(center);
+            }
+        }
+
+        let center = focused_window_center(pid)?;
+        *cache = Some((pid, Instant::now(), center));
+        Some(center)
+    }
+
+    /// 字典中的值（CFDictionary 与 NSDictionary 可直接互转），没有时返回 nil
+    unsafe fn value_for_key(dict: id, key: &CStr) -> id {
+        let key: id = msg_send![class!(NSString), stringWithUTF8String: key.as_ptr()];
+        msg_send![dict, objectForKey: key]
+    }
+
+    unsafe fn number_for_key(dict: id, key: &CStr) -> Option<f64> {
+        let value = value_for_key(dict, key);
+        if value == nil {
+            return None;
+        }
+        let number: f64 = msg_send![value, doubleValue];
+        Some(number)
+    }
+
+    /// 窗口边界字典 → (x, y, width, height)
+    unsafe fn window_bounds(info: id) -> Option<(f64, f64, f64, f64)> {
+        let rect = value_for_key(info, c"kCGWindowBounds");
+        if rect == nil {
+            return None;
+        }
+        Some((
+            number_for_key(rect, c"X")?,
+            number_for_key(rect, c"Y")?,
+            number_for_key(rect, c"Width")?,
+            number_for_key(rect, c"Height")?,
+        ))
+    }
+
+    /// 进程最上层普通窗口的中心点（Cocoa 坐标）
+    unsafe fn focused_window_center(pid: i32) -> Option<NSPoint> {
+        let windows = CGWindowListCopyWindowInfo(WINDOW_LIST_OPTIONS, 0);
+        if windows == nil {
+            return None;
+        }
+
+        // 窗口列表按前后顺序排列，第一个匹配的就是最上层窗口
+        let mut bounds = None;
+        let count: usize = msg_send![windows, count];
+        for i in 0..count {
+            let info: id = msg_send![windows, objectAtIndex: i];
+            let owner = number_for_key(info, c"kCGWindowOwnerPID").unwrap_or(-1.0) as i32;
+            let layer = number_for_key(info, c"kCGWindowLayer").unwrap_or(-1.0) as i32;
+            if owner != pid || layer != 0 {
+                continue;
+            }
+            bounds = window_bounds(info);
+            if bounds.is_some() {
+                break;
+            }
+        }
+        let _: () = msg_send![windows, release];
+
+        // CG 坐标原点在主屏左上角，Cocoa 坐标原点在主屏左下角
+        let (x, y, width, height) = bounds?;
+        let screens: id = NSScreen::screens(nil);
+        let primary: id = msg_send![screens, objectAtIndex: 0usize];
+        let primary_height = NSScreen::frame(primary).size.height;
+        Some(NSPoint {
+            x: x + width / 2.0,
+            y: primary_height - (y + height / 2.0),
+        })
+    }
+
+    /// 屏幕可见区域底部居中的位置，保证面板完整落在可见区域内
     pub unsafe fn get_bottom_center(screen: id) -> NSPoint {
         let frame: NSRect = NSScreen::visibleFrame(screen);
+        let x = frame.origin.x + (frame.size.width - OVERLAY_WIDTH) / 2.0;
+        let y = frame.origin.y + BOTTOM_MARGIN;
         NSPoint {
-            x: frame.origin.x + (frame.size.width - OVERLAY_WIDTH) / 2.0,
-            y: frame.origin.y + BOTTOM_MARGIN,
+            x: clamp_axis(x, OVERLAY_WIDTH, frame.origin.x, frame.size.width),
+            y: clamp_axis(y, OVERLAY_HEIGHT, frame.origin.y, frame.size.height),
+        }
+    }
+
+    /// 显示器配置变化回调（CoreGraphics）
+    type DisplayReconfigurationCallback = extern "C" fn(u32, u32, *mut std::ffi::c_void);
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        /// 返回 CFArrayRef（调用方负责释放）
+        fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> id;
+        fn CGDisplayRegisterReconfigurationCallback(
+            callback: DisplayReconfigurationCallback,
+            user_info: *mut std::ffi::c_void,
+        ) -> i32;
+    }
+
+    /// kCGDisplayBeginConfigurationFlag：变化开始前的通知，忽略
+    const K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG: u32 = 1;
+
+    extern "C" fn on_reconfiguration(
+        _display: u32,
+        flags: u32,
+        _user_info: *mut std::ffi::c_void,
+    ) {
+        if flags & K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG == 0 {
+            super::on_display_changed();
+        }
+    }
+
+    /// 监听显示器插拔、分辨率变化
+    pub fn watch_display_changes() {
+        let result = unsafe {
+            CGDisplayRegisterReconfigurationCallback(on_reconfiguration, std::ptr::null_mut())
+        };
+        if result != 0 {
+            log::warn!("[Overlay] Failed to watch display changes (error: {})", result);
         }
     }
 }
 
+/// 把面板移到目标屏幕（鼠标或前台窗口所在屏幕）的底部（必须在主线程调用）
+#[cfg(target_os = "macos")]
+fn position_panel<P: objc::Message>(panel: &P) {
+    use crate::settings::OverlayScreen;
+    use objc::{msg_send, sel, sel_impl};
+
+    unsafe {
+        let target_screen = match crate::settings::get().overlay_screen {
+            OverlayScreen::Mouse => screen::get_screen_at_mouse(),
+            OverlayScreen::FocusedWindow => screen::get_screen_at_focused_window(),
+        };
+        if target_screen == cocoa::base::nil {
+            log::warn!("[Overlay] No screen available, keeping current position");
+            return;
+        }
+        let position = screen::get_bottom_center(target_screen);
+        log::info!("[Overlay] Setting position to ({}, {})", position.x, position.y);
+
+        // 使用 Cocoa API 设置位置（坐标系原点在左下角）
+        let _: () = msg_send![panel, setFrameOrigin: position];
+    }
+}
+
+/// 把降级后的普通窗口移到目标屏幕底部（必须在主线程调用）
+#[cfg(target_os = "macos")]
+fn position_fallback_window(window: &tauri::WebviewWindow) {
+    if let Ok(ns_window) = window.ns_window() {
+        position_panel(unsafe { &*(ns_window as cocoa::base::id) });
+    }
+}
+
+/// NSPanel 转换失败时退回普通置顶窗口，尽量设置与面板相同的层级和跨空间显示
+///
+/// 普通窗口在部分系统版本上不能浮在全屏应用之上
+#[cfg(target_os = "macos")]
+#[allow(deprecated)]
+fn configure_fallback_window(window: &tauri::WebviewWindow) {
+    use cocoa::appkit::{NSWindow, NSWindowCollectionBehavior};
+
+    if let Err(e) = window.set_always_on_top(true) {
+        log::warn!("[Overlay] Fallback: failed to set always on top: {}", e);
+    }
+    if let Err(e) = window.set_visible_on_all_workspaces(true) {
+        log::warn!("[Overlay] Fallback: failed to join all spaces: {}", e);
+    }
+    match window.ns_window() {
+        Ok(ns_window) => unsafe {
+            let ns_window = ns_window as cocoa::base::id;
+            ns_window.setLevel_(NS_SCREEN_SAVER_WINDOW_LEVEL as i64);
+            ns_window.setCollectionBehavior_(
+                NSWindowCollectionBehavior::NSWindowCollectionBehaviorCanJoinAllSpaces
+                    | NSWindowCollectionBehavior::NSWindowCollectionBehaviorFullScreenAuxiliary,
+            );
+        },
+        Err(e) => log::warn!("[Overlay] Fallback: no native window: {}", e),
+    }
+}
+
+// Windows 置顶模块：与 macOS 的 NSPanel 对应，浮在全屏应用之上且不抢焦点
+#[cfg(target_os = "windows")]
+pub(crate) mod topmost {
+    use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+    use std::time::Duration;
+    use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::{
+        CallWindowProcW, GetForegroundWindow, GetWindowLongPtrW, SetForegroundWindow,
+        SetWindowLongPtrW, SetWindowPos, ShowWindow, GWLP_WNDPROC, GWL_EXSTYLE, HWND_TOPMOST,
+        SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SW_SHOWNOACTIVATE,
+        WM_DISPLAYCHANGE, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    };
+
+    /// 显示期间重新置顶的间隔（全屏游戏、视频会把自己提到最前）
+    const REASSERT_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// 重新置顶的线程是否在运行
+    static GUARD_RUNNING: AtomicBool = AtomicBool::new(false);
+
+    fn native_handle(window: &tauri::WebviewWindow) -> Option<HWND> {
+        match window.hwnd() {
+            Ok(hwnd) => Some(hwnd.0 as HWND),
+            Err(e) => {
+                log::warn!("[Overlay] No native window handle: {}", e);
+                None
+            }
+        }
+    }
+
+    fn raise(hwnd: HWND, flags: u32) {
+        let flags = SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE | flags;
+        let ok = unsafe { SetWindowPos(hwnd, HWND_TOPMOST, 0, 0, 0, 0, flags) };
+        if ok == 0 {
+            log::warn!("[Overlay] SetWindowPos(HWND_TOPMOST) failed");
+        }
+    }
+
+    /// 设为不激活的工具窗口（不出现在 Alt+Tab，点击不抢焦点）并置顶
+    pub fn configure(window: &tauri::WebviewWindow) {
+        let Some(hwnd) = native_handle(window) else {
+            return;
+        };
+        unsafe {
+            let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+            let extra = (WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE | WS_EX_TOPMOST) as isize;
+            SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | extra);
+        }
+        raise(hwnd, SWP_FRAMECHANGED);
+        log::info!("[Overlay] Configured as topmost tool window");
+    }
+
+    /// 浮层原来的窗口过程（替换后转发其余消息）
+    static ORIGINAL_WNDPROC: AtomicIsize = AtomicIsize::new(0);
+
+    unsafe extern "system" fn wndproc(
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == WM_DISPLAYCHANGE {
+            super::on_display_changed();
+        }
+        let original = ORIGINAL_WNDPROC.load(Ordering::SeqCst);
+        // 保存的是 SetWindowLongPtrW(GWLP_WNDPROC) 返回的原窗口过程
+        let original: winapi::um::winuser::WNDPROC = std::mem::transmute(original);
+        CallWindowProcW(original, hwnd, msg, wparam, lparam)
+    }
+
+    /// 监听显示器配置变化（WM_DISPLAYCHANGE 只发给顶层窗口，替换浮层的窗口过程接收）
+    pub fn watch_display_changes(window: &tauri::WebviewWindow) {
+        let Some(hwnd) = native_handle(window) else {
+            return;
+        };
+        unsafe {
+            let original = SetWindowLongPtrW(hwnd, GWLP_WNDPROC, wndproc as usize as isize);
+            ORIGINAL_WNDPROC.store(original, Ordering::SeqCst);
+        }
+    }
+
+    /// 不激活地显示并置顶；前台窗口被抢走时还给原窗口，保证之后粘贴到原应用
+    pub fn show(window: &tauri::WebviewWindow) {
+        let Some(hwnd) = native_handle(window) else {
+            let _ = window.show();
+            return;
+        };
+        unsafe {
+            let previous = GetForegroundWindow();
+            ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+            raise(hwnd, 0);
+            if !previous.is_null() && previous != hwnd && GetForegroundWindow() == hwnd {
+                log::warn!("[Overlay] Overlay took focus, restoring previous window");
+                SetForegroundWindow(previous);
+            }
+        }
+    }
+
+    /// 浮层显示期间定时重新置顶（已有线程在运行时不重复启动）
+    pub fn guard(app: &tauri::AppHandle) {
+        if GUARD_RUNNING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let app = app.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(REASSERT_INTERVAL);
+            if !super::OVERLAY_VISIBLE.load(Ordering::SeqCst) {
+                GUARD_RUNNING.store(false, Ordering::SeqCst);
+                // 退出前浮层又显示了，且没有新线程接手时继续
+                if !super::OVERLAY_VISIBLE.load(Ordering::SeqCst)
+                    || GUARD_RUNNING.swap(true, Ordering::SeqCst)
+                {
+                    return;
+                }
+            }
+            let app_for_main = app.clone();
+            let _ = app.run_on_main_thread(move || {
+                use tauri::Manager;
+                let window = app_for_main.get_webview_window(super::OVERLAY_WINDOW_LABEL);
+                if let Some(hwnd) = window.as_ref().and_then(native_handle) {
+                    raise(hwnd, 0);
+                }
+            });
+        });
+    }
+}
+
+/// 前台窗口的中心点（物理像素）
+#[cfg(target_os = "windows")]
+fn foreground_window_center() -> Option<(f64, f64)> {
+    use winapi::shared::windef::RECT;
+    use winapi::um::winuser::{GetForegroundWindow, GetWindowRect};
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+        let mut rect: RECT = std::mem::zeroed();
+        if GetWindowRect(hwnd, &mut rect) == 0 {
+            return None;
+        }
+        Some((
+            (rect.left + rect.right) as f64 / 2.0,
+            (rect.top + rect.bottom) as f64 / 2.0,
+        ))
+    }
+}
+
+/// 把窗口移到目标显示器（鼠标或前台窗口所在，找不到时用主显示器）的底部，
+/// 并限制在显示器范围内
+#[cfg(target_os = "windows")]
+fn position_window(window: &tauri::WebviewWindow) {
+    use crate::settings::OverlayScreen;
+
+    let point = match crate::settings::get().overlay_screen {
+        OverlayScreen::FocusedWindow => foreground_window_center(),
+        OverlayScreen::Mouse => None,
+    }
+    .or_else(|| window.cursor_position().ok().map(|p| (p.x, p.y)));
+    let monitor = point
+        .and_then(|(x, y)| window.monitor_from_point(x, y).ok().flatten())
+        .or_else(|| window.primary_monitor().ok().flatten());
+    let Some(monitor) = monitor else {
+        log::warn!("[Overlay] No monitor available, keeping current position");
+        return;
+    };
+
+    // 全部按物理像素计算
+    let scale = monitor.scale_factor();
+    let (mx, my) = (monitor.position().x as f64, monitor.position().y as f64);
+    let (mw, mh) = (monitor.size().width as f64, monitor.size().height as f64);
+    let (w, h) = (OVERLAY_WIDTH * scale, OVERLAY_HEIGHT * scale);
+
+    let x = clamp_axis(mx + (mw - w) / 2.0, w, mx, mw);
+    let y = clamp_axis(my + mh - h - BOTTOM_MARGIN * scale, h, my, mh);
+
+    let _ = window.set_position(tauri::Position::Physical(tauri::PhysicalPosition::new(
+        x as i32, y as i32,
+    )));
+    log::info!("[Overlay] Window positioned at ({}, {})", x, y);
+}
+
+/// 全局 AppHandle（显示器变化回调中使用）
+static APP_HANDLE: std::sync::OnceLock<AppHandle> = std::sync::OnceLock::new();
+
+/// 显示器配置变化：浮层正在显示时立即重新定位，否则等下次 show() 时计算
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn on_display_changed() {
+    log::info!("[Overlay] Display configuration changed");
+    if !OVERLAY_VISIBLE.load(Ordering::SeqCst) {
+        return;
+    }
+    let Some(app) = APP_HANDLE.get() else {
+        return;
+    };
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || reposition(&app_for_thread));
+}
+
+/// 按当前显示器重新定位浮层（必须在主线程调用）
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn reposition(app: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    {
+        use tauri_nspanel::ManagerExt;
+
+        if let Ok(panel) = app.get_webview_panel(OVERLAY_WINDOW_LABEL) {
+            position_panel(&*panel);
+            return;
+        }
+    }
+
+    if let Some(window) = app.get_webview_window(OVERLAY_WINDOW_LABEL) {
+        #[cfg(target_os = "macos")]
+        position_fallback_window(&window);
+        #[cfg(target_os = "windows")]
+        position_window(&window);
+    }
+}
+
+/// 把窗口转换为不激活的置顶 NSPanel（显示时不抢走前台应用的焦点，浮在全屏应用之上）
+#[cfg(target_os = "macos")]
+pub(crate) fn convert_to_panel(window: &tauri::WebviewWindow) -> Result<(), String> {
+    #[allow(deprecated)]
+    use cocoa::appkit::NSWindowCollectionBehavior;
+    use tauri_nspanel::WebviewWindowExt;
+
+    let panel = window.to_panel().map_err(|e| format!("{:?}", e))?;
+    panel.set_released_when_closed(false);
+    panel.set_becomes_key_only_if_needed(true);
+    panel.set_floating_panel(true);
+    panel.set_level(NS_SCREEN_SAVER_WINDOW_LEVEL);
+
+    const NS_WINDOW_STYLE_MASK_NON_ACTIVATING_PANEL: i32 = 1 << 7;
+    panel.set_style_mask(NS_WINDOW_STYLE_MASK_NON_ACTIVATING_PANEL);
+
+    #[allow(deprecated)]
+    panel.set_collection_behaviour(
+        NSWindowCollectionBehavior::NSWindowCollectionBehaviorCanJoinAllSpaces
+            | NSWindowCollectionBehavior::NSWindowCollectionBehaviorFullScreenAuxiliary,
+    );
+    Ok(())
+}
+
 /// 预加载 UI Overlay（启动时调用，创建但不显示）
 pub fn preload(app: &AppHandle) {
+    if APP_HANDLE.set(app.clone()).is_ok() {
+        #[cfg(target_os = "macos")]
+        screen::watch_display_changes();
+    }
+
     #[cfg(target_os = "macos")]
     {
-        #[allow(deprecated)]
-        use cocoa::appkit::NSWindowCollectionBehavior;
-        use tauri_nspanel::WebviewWindowExt;
-
         log::info!("[Overlay] Creating UI panel...");
 
         // 使用本地 HTML 文件，不加载网页
@@ -73,7 +566,7 @@ pub fn preload(app: &AppHandle) {
             tauri::WebviewUrl::App("overlay.html".into()),
         )
         .title("")
-        .inner_size(500.0, 120.0)
+        .inner_size(OVERLAY_WIDTH, OVERLAY_HEIGHT)
         .decorations(false)
         .transparent(true)
         .always_on_top(true)
@@ -85,26 +578,20 @@ pub fn preload(app: &AppHandle) {
             Ok(win) => {
                 log::info!("[Overlay] Window created, converting to panel");
 
-                match win.to_panel() {
-                    Ok(panel) => {
-                        panel.set_released_when_closed(false);
-                        panel.set_becomes_key_only_if_needed(true);
-                        panel.set_floating_panel(true);
-                        panel.set_level(NS_SCREEN_SAVER_WINDOW_LEVEL);
-
-                        const NS_WINDOW_STYLE_MASK_NON_ACTIVATING_PANEL: i32 = 1 << 7;
-                        panel.set_style_mask(NS_WINDOW_STYLE_MASK_NON_ACTIVATING_PANEL);
-
-                        #[allow(deprecated)]
-                        panel.set_collection_behaviour(
-                            NSWindowCollectionBehavior::NSWindowCollectionBehaviorCanJoinAllSpaces
-                                | NSWindowCollectionBehavior::NSWindowCollectionBehaviorFullScreenAuxiliary,
-                        );
-
-                        log::info!("[Overlay] Panel ready (hidden)");
-                    }
+                match convert_to_panel(&win) {
+                    Ok(()) => log::info!("[Overlay] Panel ready (hidden)"),
                     Err(e) => {
-                        log::error!("[Overlay] Failed to convert to panel: {:?}", e);
+                        log::error!(
+                            "[Overlay] Failed to convert to panel: {}, falling back to a regular window",
+                            e
+                        );
+                        configure_fallback_window(&win);
+                        let reason =
+                            "浮层未能创建为系统面板，已改用普通置顶窗口，在全屏应用中可能不显示"
+                                .to_string();
+                        *FALLBACK_REASON.lock().unwrap() = Some(reason.clone());
+                        events::emit(app, AppEvent::OverlayFallback(reason));
+                        log::info!("[Overlay] Fallback window ready (hidden)");
                     }
                 }
             }
@@ -123,7 +610,7 @@ pub fn preload(app: &AppHandle) {
             tauri::WebviewUrl::App("overlay.html".into()),
         )
         .title("")
-        .inner_size(500.0, 120.0)
+        .inner_size(OVERLAY_WIDTH, OVERLAY_HEIGHT)
         .decorations(false)
         .transparent(true)
         .always_on_top(true)
@@ -133,30 +620,19 @@ pub fn preload(app: &AppHandle) {
 
         match window {
             Ok(win) => {
-                // 尝试将窗口定位到屏幕底部中央
-                if let Ok(monitor) = win.current_monitor() {
-                    if let Some(monitor) = monitor {
-                        let size = monitor.size();
-                        let position = monitor.position();
-                        let scale = monitor.scale_factor();
-
-                        // 计算底部中央位置
-                        let window_width = 500.0;
-                        let window_height = 120.0;
-                        let bottom_margin = 80.0;
-
-                        let x = position.x as f64 + (size.width as f64 / scale - window_width) / 2.0;
-                        let y = position.y as f64 + size.height as f64 / scale - window_height - bottom_margin;
-
-                        let _ = win.set_position(tauri::Position::Physical(
-                            tauri::PhysicalPosition::new(
-                                (x * scale) as i32,
-                                (y * scale) as i32,
-                            ),
-                        ));
-                        log::info!("[Overlay] Window positioned at ({}, {})", x, y);
+                // 定位到屏幕底部中央
+                position_window(&win);
+                // 浮在全屏应用之上、不抢焦点
+                topmost::configure(&win);
+
+                // 显示器配置或缩放比例变化（插拔显示器、切换主显示器）时重新定位；
+                // 不响应 Moved，重新定位本身就会移动窗口
+                topmost::watch_display_changes(&win);
+                win.on_window_event(|event| {
+                    if matches!(event, tauri::WindowEvent::ScaleFactorChanged { .. }) {
+                        on_display_changed();
                     }
-                }
+                });
                 log::info!("[Overlay] Window ready (hidden)");
             }
             Err(e) => log::error!("[Overlay] Failed to create window: {}", e),
@@ -173,7 +649,7 @@ pub fn preload(app: &AppHandle) {
             tauri::WebviewUrl::App("overlay.html".into()),
         )
         .title("")
-        .inner_size(500.0, 120.0)
+        .inner_size(OVERLAY_WIDTH, OVERLAY_HEIGHT)
         .decorations(false)
         .transparent(true)
         .always_on_top(true)
@@ -192,32 +668,27 @@ pub fn preload(app: &AppHandle) {
 /// 显示 Overlay（必须在主线程调用）
 pub fn show(app: &AppHandle) {
     log::info!("[Overlay] show called");
+    SHOW_SEQ.fetch_add(1, Ordering::SeqCst);
 
     // 发送重置事件
-    let _ = app.emit("overlay-reset", ());
+    events::emit(app, AppEvent::OverlayReset);
+
+    // 演示或勿扰时不弹出浮层
+    if crate::focus_state::overlay_suppressed() {
+        log::info!("[Overlay] Suppressed while presenting");
+        return;
+    }
 
     #[cfg(target_os = "macos")]
     {
-        use objc::{msg_send, sel, sel_impl};
         use tauri_nspanel::ManagerExt;
 
         if let Ok(panel) = app.get_webview_panel(OVERLAY_WINDOW_LABEL) {
             log::info!("[Overlay] Positioning to current screen bottom");
-
-            unsafe {
-                let target_screen = screen::get_screen_at_focused_window();
-                let position = screen::get_bottom_center(target_screen);
-                log::info!(
-                    "[Overlay] Setting position to ({}, {})",
-                    position.x,
-                    position.y
-                );
-
-                // 使用 Cocoa API 设置位置（坐标系原点在左下角）
-                let _: () = msg_send![&*panel, setFrameOrigin: position];
-            }
+            position_panel(&*panel);
 
             panel.order_front_regardless();
+            OVERLAY_VISIBLE.store(true, Ordering::SeqCst);
             log::info!("[Overlay] Panel shown");
             return;
         }
@@ -226,32 +697,22 @@ pub fn show(app: &AppHandle) {
     if let Some(window) = app.get_webview_window(OVERLAY_WINDOW_LABEL) {
         log::info!("[Overlay] Window exists, showing it");
 
-        // Windows: 重新定位窗口到当前屏幕底部中央
+        // macOS: 面板转换失败时的降级窗口
+        #[cfg(target_os = "macos")]
+        position_fallback_window(&window);
+
+        // Windows: 重新定位窗口到当前屏幕底部中央，不激活地显示
         #[cfg(target_os = "windows")]
         {
-            if let Ok(Some(monitor)) = window.current_monitor() {
-                let size = monitor.size();
-                let position = monitor.position();
-                let scale = monitor.scale_factor();
-
-                let window_width = 500.0;
-                let window_height = 120.0;
-                let bottom_margin = 80.0;
-
-                let x = position.x as f64 + (size.width as f64 / scale - window_width) / 2.0;
-                let y = position.y as f64 + size.height as f64 / scale - window_height - bottom_margin;
-
-                let _ = window.set_position(tauri::Position::Physical(
-                    tauri::PhysicalPosition::new(
-                        (x * scale) as i32,
-                        (y * scale) as i32,
-                    ),
-                ));
-                log::info!("[Overlay] Window repositioned to ({}, {})", x, y);
-            }
+            position_window(&window);
+            topmost::show(&window);
         }
-
+        #[cfg(not(target_os = "windows"))]
         let _ = window.show();
+
+        OVERLAY_VISIBLE.store(true, Ordering::SeqCst);
+        #[cfg(target_os = "windows")]
+        topmost::guard(app);
         return;
     }
 
@@ -262,6 +723,7 @@ pub fn show(app: &AppHandle) {
 /// 隐藏 Overlay
 pub fn hide(app: &AppHandle) {
     log::info!("[Overlay] hide called");
+    OVERLAY_VISIBLE.store(false, Ordering::SeqCst);
 
     #[cfg(target_os = "macos")]
     {
@@ -280,12 +742,127 @@ pub fn hide(app: &AppHandle) {
     }
 }
 
+/// 等待主线程查询浮层焦点的最长时间
+const FOCUS_QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// 浮层是否拿着键盘焦点，拿着时让出，返回让出前是否拿着
+///
+/// 确认粘贴模式下面板会成为 key window，显示或隐藏时也可能短暂抢走焦点；
+/// 粘贴前调用，避免粘贴键发到浮层。在主线程上查询，可以从任意线程调用
+pub fn release_focus(app: &AppHandle) -> bool {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let app_for_main = app.clone();
+    let dispatched = app.run_on_main_thread(move || {
+        let _ = tx.send(release_focus_on_main(&app_for_main));
+    });
+    if dispatched.is_err() {
+        return false;
+    }
+    rx.recv_timeout(FOCUS_QUERY_TIMEOUT).unwrap_or(false)
+}
+
+fn release_focus_on_main(app: &AppHandle) -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        use tauri_nspanel::ManagerExt;
+
+        if let Ok(panel) = app.get_webview_panel(OVERLAY_WINDOW_LABEL) {
+            return order_out_if_key(&*panel);
+        }
+    }
+
+    let Some(window) = app.get_webview_window(OVERLAY_WINDOW_LABEL) else {
+        return false;
+    };
+    let focused = window.is_focused().unwrap_or(false);
+    if focused {
+        log::warn!("[Overlay] Overlay window has focus before paste");
+    }
+    focused
+}
+
+/// 面板是 key window 时收起面板让出焦点
+///
+/// 不能直接调用 resignKeyWindow（只应由系统调用）；收起后由调用方切回粘贴目标
+#[cfg(target_os = "macos")]
+fn order_out_if_key<P: objc::Message>(panel: &P) -> bool {
+    use cocoa::base::{nil, BOOL, NO};
+    use objc::{msg_send, sel, sel_impl};
+
+    unsafe {
+        let is_key: BOOL = msg_send![panel, isKeyWindow];
+        if is_key == NO {
+            return false;
+        }
+        let _: () = msg_send![panel, orderOut: nil];
+    }
+    log::info!("[Overlay] Panel was key window, ordered out before paste");
+    true
+}
+
+/// 显示待确认的识别结果（确认粘贴模式，必须在主线程调用）
+///
+/// macOS 上让面板成为 key window 以接收 Enter/Esc（非激活面板，不会抢走目标应用的激活状态）
+pub fn show_review(app: &AppHandle, text: &str) {
+    events::emit(app, AppEvent::OverlayReview(text.to_string()));
+
+    #[cfg(target_os = "macos")]
+    {
+        use tauri_nspanel::ManagerExt;
+
+        if let Ok(panel) = app.get_webview_panel(OVERLAY_WINDOW_LABEL) {
+            panel.make_key_window();
+            log::info!("[Overlay] Panel made key for review");
+        }
+    }
+}
+
+/// 显示错误提示（重复的错误合并计数），一段时间后自动隐藏
+pub fn show_error(app: &AppHandle, error: &TypeFreeError) {
+    let display = crate::error::present(error);
+    log::info!("[Overlay] Showing error: {}", display.message);
+
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        if !OVERLAY_VISIBLE.load(Ordering::SeqCst) {
+            show(&app_for_thread);
+        }
+        events::emit(&app_for_thread, AppEvent::OverlayError(display));
+
+        let delay = Duration::from_millis(crate::settings::get().error_display_ms);
+        hide_after(&app_for_thread, delay);
+    });
+}
+
+/// 安排 delay 后隐藏浮层，取代之前安排的隐藏；期间浮层重新显示则取消
+pub fn hide_after(app: &AppHandle, delay: Duration) {
+    let seq = SHOW_SEQ.fetch_add(1, Ordering::SeqCst) + 1;
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        if SHOW_SEQ.load(Ordering::SeqCst) == seq {
+            let app_for_hide = app.clone();
+            let _ = app.run_on_main_thread(move || hide(&app_for_hide));
+        }
+    });
+}
+
+/// 浮层当前是否显示
+pub fn is_visible() -> bool {
+    OVERLAY_VISIBLE.load(Ordering::SeqCst)
+}
+
+/// 取消已安排的隐藏
+pub fn cancel_hide() {
+    SHOW_SEQ.fetch_add(1, Ordering::SeqCst);
+}
+
 /// 更新状态文字（如 "聆听中..."、"识别中..."）
 pub fn update_status(app: &AppHandle, status: &str) {
-    let _ = app.emit("overlay-status", status);
+    events::emit(app, AppEvent::OverlayStatus(status.to_string()));
 }
 
 /// 更新识别结果文字
 pub fn update_text(app: &AppHandle, text: &str) {
-    let _ = app.emit("overlay-text", text);
+    events::emit(app, AppEvent::OverlayText(text.to_string()));
 }
@@ -0,0 +1,162 @@
+//! 浮层文字 - 识别结果在浮层上的显示方式
+//!
+//! 服务端返回的中间结果是整次会话的文字；只显示当前这句时按句末标点截取最后一句。
+//! 浮层只有几行高，太长的文字只保留末尾。
+//! 中间结果到得很快时按设置的间隔节流，间隔内只保留最新的文字，到时间后补上
+
+use crate::settings::OverlayText;
+use std::time::{Duration, Instant};
+
+/// 浮层最多显示的字数，超出时开头用省略号代替
+const MAX_CHARS: usize = 120;
+
+/// 句末标点（其后的文字属于新的一句）
+const SENTENCE_ENDS: &[char] = &['。', '！', '？', '!', '?', '.', '；', ';', '…'];
+
+/// 中间结果在浮层上显示的文字
+pub fn partial_text(text: &str, mode: OverlayText) -> String {
+    let text = text.trim();
+    let shown = match mode {
+        OverlayText::Session => text,
+        OverlayText::Utterance => current_utterance(text),
+    };
+    truncate_front(shown, MAX_CHARS)
+}
+
+/// 最后一句（最后一个句末标点之后的文字，该句还没说出内容时取上一句）
+fn current_utterance(text: &str) -> &str {
+    let body = text.trim_end_matches(SENTENCE_ENDS);
+    let mut start = 0;
+    let mut chars = body.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        // 英文标点后要有空格才算断句，避免拆开 3.5 之类
+        let next = chars.peek().map(|&(_, next)| next);
+        if SENTENCE_ENDS.contains(&c) && (!c.is_ascii() || next.is_none_or(char::is_whitespace)) {
+            start = index + c.len_utf8();
+        }
+    }
+    text[start..].trim_start()
+}
+
+/// 超过 max 个字时只保留末尾
+fn truncate_front(text: &str, max: usize) -> String {
+    let count = text.chars().count();
+    if count <= max {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - (max - 1)).collect();
+    format!("…{}", tail)
+}
+
+/// 中间结果的节流：两次显示至少间隔 interval（为 0 时不节流）
+#[derive(Debug)]
+pub struct PartialThrottle {
+    interval: Duration,
+    last_shown: Option<Instant>,
+    /// 间隔内到达、还没显示的最新文字
+    pending: Option<String>,
+}
+
+/// 收到中间结果后的处理
+#[derive(Debug, PartialEq)]
+pub enum Throttled {
+    /// 立即显示
+    Show(String),
+    /// 暂存；`flush_at` 为 Some 时调用方需要在该时间调用 `take_pending`（已安排过时为 None）
+    Hold { flush_at: Option<Instant> },
+}
+
+impl PartialThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_shown: None,
+            pending: None,
+        }
+    }
+
+    /// 收到新的中间结果
+    pub fn offer(&mut self, text: &str, now: Instant) -> Throttled {
+        match self.last_shown {
+            Some(last) if now < last + self.interval => {
+                let scheduled = self.pending.replace(text.to_string()).is_some();
+                Throttled::Hold {
+                    flush_at: (!scheduled).then_some(last + self.interval),
+                }
+            }
+            _ => {
+                self.last_shown = Some(now);
+                self.pending = None;
+                Throttled::Show(text.to_string())
+            }
+        }
+    }
+
+    /// 到时间后取出暂存的最新文字（最终结果已到或没有暂存时为 None）
+    pub fn take_pending(&mut self, now: Instant) -> Option<String> {
+        let text = self.pending.take()?;
+        self.last_shown = Some(now);
+        Some(text)
+    }
+
+    /// 最终结果到达：丢弃暂存的中间结果，避免盖住最终结果
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_utterance() {
+        assert_eq!(current_utterance("今天天气不错。我们出去"), "我们出去");
+        // 这句刚结束，还没有下一句
+        assert_eq!(current_utterance("今天天气不错。我们出去走走。"), "我们出去走走。");
+        assert_eq!(current_utterance("Hello there. How are"), "How are");
+        assert_eq!(current_utterance("还没有标点"), "还没有标点");
+        assert_eq!(current_utterance("价格是3.5元"), "价格是3.5元");
+        assert_eq!(current_utterance(""), "");
+    }
+
+    #[test]
+    fn test_partial_text() {
+        let text = "第一句。第二句";
+        assert_eq!(partial_text(text, OverlayText::Session), text);
+        assert_eq!(partial_text(text, OverlayText::Utterance), "第二句");
+
+        // 太长时只保留末尾
+        let long = "字".repeat(MAX_CHARS + 10);
+        let shown = partial_text(&long, OverlayText::Session);
+        assert_eq!(shown.chars().count(), MAX_CHARS);
+        assert!(shown.starts_with('…'));
+        assert_eq!(truncate_front("短", 5), "短");
+    }
+
+    #[test]
+    fn test_partial_throttle() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let mut throttle = PartialThrottle::new(Duration::from_millis(80));
+
+        assert_eq!(throttle.offer("a", ms(0)), Throttled::Show("a".into()));
+        // 间隔内只保留最新的，且只安排一次补发
+        assert_eq!(throttle.offer("ab", ms(20)), Throttled::Hold { flush_at: Some(ms(80)) });
+        assert_eq!(throttle.offer("abc", ms(50)), Throttled::Hold { flush_at: None });
+        assert_eq!(throttle.take_pending(ms(80)).as_deref(), Some("abc"));
+        assert_eq!(throttle.take_pending(ms(81)), None);
+
+        // 补发后重新计时
+        assert_eq!(throttle.offer("abcd", ms(100)), Throttled::Hold { flush_at: Some(ms(160)) });
+        // 最终结果到达后不再补发
+        throttle.clear();
+        assert_eq!(throttle.take_pending(ms(160)), None);
+        assert_eq!(throttle.offer("x", ms(200)), Throttled::Show("x".into()));
+
+        // 间隔为 0 时不节流
+        let mut off = PartialThrottle::new(Duration::ZERO);
+        assert_eq!(off.offer("a", ms(0)), Throttled::Show("a".into()));
+        assert_eq!(off.offer("ab", ms(0)), Throttled::Show("ab".into()));
+    }
+}
@@ -0,0 +1,109 @@
+//! 屏幕角落的录音指示点（可选）
+//!
+//! 浮层被隐藏或关闭时也能看到麦克风正在录音；窗口不接收鼠标、不抢焦点
+
+use tauri::{AppHandle, Manager};
+
+const DOT_WINDOW_LABEL: &str = "recording-dot";
+
+/// 窗口边长（逻辑像素）
+const DOT_SIZE: f64 = 14.0;
+
+/// 距屏幕右边缘的距离
+const RIGHT_MARGIN: f64 = 8.0;
+
+/// 距屏幕上边缘的距离（macOS 避开菜单栏）
+#[cfg(target_os = "macos")]
+const TOP_MARGIN: f64 = 40.0;
+#[cfg(not(target_os = "macos"))]
+const TOP_MARGIN: f64 = 8.0;
+
+/// 显示或隐藏指示点，第一次显示时创建窗口
+pub fn set_visible(app: &AppHandle, visible: bool) {
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        let window = match app_for_thread.get_webview_window(DOT_WINDOW_LABEL) {
+            Some(window) => window,
+            None if visible => match create(&app_for_thread) {
+                Ok(window) => window,
+                Err(e) => {
+                    log::error!("[RecordingDot] Failed to create window: {}", e);
+                    return;
+                }
+            },
+            None => return,
+        };
+        if visible {
+            position(&window);
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            use tauri_nspanel::ManagerExt;
+
+            if let Ok(panel) = app_for_thread.get_webview_panel(DOT_WINDOW_LABEL) {
+                if visible {
+                    panel.order_front_regardless();
+                } else {
+                    panel.order_out(None);
+                }
+                return;
+            }
+        }
+
+        if visible {
+            // 不激活地显示，录音开始时不抢走听写目标的焦点
+            #[cfg(target_os = "windows")]
+            crate::overlay::panel::topmost::show(&window);
+            #[cfg(not(target_os = "windows"))]
+            let _ = window.show();
+        } else {
+            let _ = window.hide();
+        }
+    });
+}
+
+fn create(app: &AppHandle) -> tauri::Result<tauri::WebviewWindow> {
+    let window = tauri::WebviewWindowBuilder::new(
+        app,
+        DOT_WINDOW_LABEL,
+        tauri::WebviewUrl::App("recording-dot.html".into()),
+    )
+    .title("")
+    .inner_size(DOT_SIZE, DOT_SIZE)
+    .decorations(false)
+    .transparent(true)
+    .shadow(false)
+    .resizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .focused(false)
+    .visible(false)
+    .build()?;
+    let _ = window.set_ignore_cursor_events(true);
+
+    // 与浮层一样设为不激活的窗口
+    #[cfg(target_os = "macos")]
+    if let Err(e) = crate::overlay::panel::convert_to_panel(&window) {
+        log::warn!("[RecordingDot] Failed to convert to panel: {}", e);
+    }
+    #[cfg(target_os = "windows")]
+    crate::overlay::panel::topmost::configure(&window);
+
+    log::info!("[RecordingDot] Window created");
+    Ok(window)
+}
+
+/// 放到主显示器右上角（按物理像素计算）
+fn position(window: &tauri::WebviewWindow) {
+    let Some(monitor) = window.primary_monitor().ok().flatten() else {
+        return;
+    };
+    let scale = monitor.scale_factor();
+    let x = monitor.position().x as f64 + monitor.size().width as f64
+        - (DOT_SIZE + RIGHT_MARGIN) * scale;
+    let y = monitor.position().y as f64 + TOP_MARGIN * scale;
+    let _ = window.set_position(tauri::Position::Physical(tauri::PhysicalPosition::new(
+        x as i32, y as i32,
+    )));
+}
@@ -0,0 +1,96 @@
+//! 便签 - 没有合适的目标应用时，听写结果先写入内置的便签窗口
+//!
+//! 便签内容保存在配置目录，重启后保留；「听写到便签」模式只在本次运行中有效
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+const SCRATCHPAD_FILE: &str = "scratchpad.txt";
+
+static TEXT: Mutex<String> = Mutex::new(String::new());
+
+static FILE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// 「听写到便签」模式是否开启
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// 加载便签内容（启动时调用一次）
+pub fn init(config_dir: PathBuf) {
+    let path = config_dir.join(SCRATCHPAD_FILE);
+    if let Ok(content) = std::fs::read_to_string(&path) {
+        log::info!("[Scratchpad] Loaded {} chars", content.chars().count());
+        *TEXT.lock().unwrap() = content;
+    }
+    let _ = FILE_PATH.set(path);
+}
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::SeqCst)
+}
+
+pub fn set_active(active: bool) {
+    ACTIVE.store(active, Ordering::SeqCst);
+    log::info!(
+        "[Scratchpad] Dictate-to-scratchpad {}",
+        if active { "on" } else { "off" }
+    );
+}
+
+/// 每次听写另起一行
+fn join_entry(buffer: &str, text: &str) -> String {
+    let text = text.trim();
+    if text.is_empty() {
+        return buffer.to_string();
+    }
+    if buffer.is_empty() || buffer.ends_with('\n') {
+        format!("{}{}", buffer, text)
+    } else {
+        format!("{}\n{}", buffer, text)
+    }
+}
+
+/// 追加一段并保存，返回便签全文
+pub fn append(text: &str) -> Result<String, String> {
+    let mut buffer = TEXT.lock().unwrap();
+    let updated = join_entry(&buffer, text);
+    save(&updated)?;
+    *buffer = updated;
+    Ok(buffer.clone())
+}
+
+pub fn get() -> String {
+    TEXT.lock().unwrap().clone()
+}
+
+/// 清空便签
+pub fn clear() -> Result<(), String> {
+    let mut buffer = TEXT.lock().unwrap();
+    save("")?;
+    buffer.clear();
+    log::info!("[Scratchpad] Cleared");
+    Ok(())
+}
+
+fn save(content: &str) -> Result<(), String> {
+    let path = FILE_PATH.get().ok_or("Scratchpad not initialized")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write scratchpad: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_entry() {
+        assert_eq!(join_entry("", "你好"), "你好");
+        assert_eq!(join_entry("第一条", "第二条"), "第一条\n第二条");
+        // 用户在窗口中手动换过行时不再补换行
+        assert_eq!(join_entry("第一条\n", "第二条"), "第一条\n第二条");
+        assert_eq!(join_entry("第一条", "  "), "第一条");
+        assert_eq!(join_entry("", " hello "), "hello");
+    }
+}
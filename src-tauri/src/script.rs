@@ -0,0 +1,106 @@
+//! 文字检测 - 识别结果的文字与本次识别语言不符时给出提示
+//!
+//! 例如语言为中文却说了英文，豆包有时会返回拼音或乱码
+
+/// 至少有这么多个字母/汉字才判断，避免短结果误报
+const MIN_LETTERS: usize = 4;
+
+/// 语言使用的文字
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Han,
+    Latin,
+}
+
+impl Script {
+    /// 语言代码对应的文字，未知语言返回 None
+    fn of_language(language: &str) -> Option<Self> {
+        let primary = language
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" | "yue" => Some(Script::Han),
+            "en" | "fr" | "de" | "es" | "it" | "pt" | "nl" | "id" | "ms" | "vi" => {
+                Some(Script::Latin)
+            }
+            _ => None,
+        }
+    }
+
+    fn of_char(c: char) -> Option<Self> {
+        match c {
+            '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => Some(Script::Han),
+            c if c.is_alphabetic() && (c.is_ascii() || ('\u{00C0}'..='\u{024F}').contains(&c)) => {
+                Some(Script::Latin)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// 给用户看的语言名称
+pub fn language_name(language: &str) -> String {
+    match language.split(['-', '_']).next().unwrap_or_default() {
+        "zh" => "中文".to_string(),
+        "yue" => "粤语".to_string(),
+        "en" => "英文".to_string(),
+        "ja" => "日文".to_string(),
+        _ => language.to_string(),
+    }
+}
+
+/// 不属于期望文字的字母比例（字母太少时返回 None）
+fn foreign_ratio(text: &str, expected: Script) -> Option<f64> {
+    let (mut total, mut foreign) = (0usize, 0usize);
+    for script in text.chars().filter_map(Script::of_char) {
+        total += 1;
+        if script != expected {
+            foreign += 1;
+        }
+    }
+    (total >= MIN_LETTERS).then(|| foreign as f64 / total as f64)
+}
+
+/// 识别结果与语言不符（其他文字的比例超过 threshold）时返回浮层提示
+pub fn mismatch_hint(text: &str, language: &str, threshold: f64) -> Option<String> {
+    let expected = Script::of_language(language)?;
+    let ratio = foreign_ratio(text, expected)?;
+    (ratio > threshold).then(|| format!("识别语言可能不匹配，当前为{}", language_name(language)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mismatch_hint() {
+        // 中文识别出了拼音
+        assert_eq!(
+            mismatch_hint("ni hao shi jie", "zh", 0.8).as_deref(),
+            Some("识别语言可能不匹配，当前为中文")
+        );
+        assert_eq!(
+            mismatch_hint("你好世界", "en", 0.8).as_deref(),
+            Some("识别语言可能不匹配，当前为英文")
+        );
+
+        // 夹杂少量英文单词不算
+        assert_eq!(mismatch_hint("帮我打开 GitHub 仓库看看", "zh", 0.8), None);
+        assert_eq!(mismatch_hint("Hello world", "en-US", 0.8), None);
+        // 太短或未知语言不判断
+        assert_eq!(mismatch_hint("OK", "zh", 0.8), None);
+        assert_eq!(mismatch_hint("ni hao shi jie", "ko", 0.8), None);
+    }
+
+    #[test]
+    fn test_foreign_ratio() {
+        assert_eq!(
+            foreign_ratio("今天 weather 不错", Script::Han),
+            Some(7.0 / 11.0)
+        );
+        assert_eq!(foreign_ratio("Café au lait", Script::Latin), Some(0.0));
+        assert_eq!(foreign_ratio("123，。", Script::Han), None);
+    }
+}
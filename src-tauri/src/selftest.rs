@@ -0,0 +1,636 @@
+//! 自检 - 用测试音频走一遍完整识别链路
+//!
+//! - `--selftest` 参数启动时依次检查权限、麦克风、豆包和识别链路，
+//!   不创建窗口和托盘，输出可直接贴进问题反馈的报告后退出
+//! - 主窗口的「一键诊断」只检查识别链路，不操作剪贴板和浮层
+
+use crate::doubao_asr::AsrError;
+use crate::error::TypeFreeError;
+use crate::{audio, doubao_asr, doubao_cdp, permissions, settings, RUNTIME};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// 带此参数启动时运行自检
+pub const SELFTEST_ARG: &str = "--selftest";
+
+/// 报告文件名（保存在配置目录）
+const REPORT_FILE: &str = "selftest.txt";
+
+/// 配置目录中可选的测试录音（16kHz 16-bit 单声道 PCM），存在时代替合成语音
+const SAMPLE_FILE: &str = "selftest.pcm";
+
+/// 测试录音的原文（与 SAMPLE_FILE 放在一起，没有时只检查有识别文本）
+const SAMPLE_TEXT_FILE: &str = "selftest.expected.txt";
+
+/// 用系统语音合成朗读的测试语句
+const UTTERANCE_TEXT: &str = "今天天气很好";
+
+/// 每帧时长
+const FRAME_MS: usize = 100;
+
+/// 识别链路的检查项（按执行顺序）
+const PIPELINE_CHECKS: [&str; 4] = ["Cookie", "URL 参数", "WebSocket 连接", "识别结果"];
+
+/// 识别链路诊断进行中
+static PIPELINE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// 进行中的诊断的中断标志（按键听写开始时设置）
+static PIPELINE_CANCEL: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+/// 单项检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    name: &'static str,
+    /// None 表示因前置检查失败而跳过
+    ok: Option<bool>,
+    detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, result: Result<String, String>) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (Some(true), detail),
+            Err(detail) => (Some(false), detail),
+        };
+        Self { name, ok, detail }
+    }
+
+    fn skipped(name: &'static str) -> Self {
+        Self {
+            name,
+            ok: None,
+            detail: "前置检查失败，未执行".to_string(),
+        }
+    }
+}
+
+/// 本次是否以自检模式启动
+pub fn requested() -> bool {
+    std::env::args().any(|arg| arg == SELFTEST_ARG)
+}
+
+/// 运行自检，显示并保存报告，返回进程退出码（全部通过为 0）
+pub fn run() -> i32 {
+    log::info!("[SelfTest] Running self-test...");
+    let checks = RUNTIME.block_on(run_checks());
+    let report = format_report(&checks);
+    let passed = checks.iter().all(|c| c.ok == Some(true));
+
+    let mut output = report.clone();
+    if let Some(dir) = settings::config_dir() {
+        let path = dir.join(REPORT_FILE);
+        match std::fs::write(&path, &report) {
+            Ok(()) => output.push_str(&format!("\n报告已保存到 {}", path.display())),
+            Err(e) => log::warn!("[SelfTest] Failed to save report: {}", e),
+        }
+    }
+    show_report(&output, passed);
+
+    if passed {
+        0
+    } else {
+        1
+    }
+}
+
+/// 输出报告；Windows 发布版没有控制台，连到启动它的命令行窗口，
+/// 从资源管理器启动（没有命令行窗口）时改用对话框显示
+fn show_report(output: &str, passed: bool) {
+    #[cfg(target_os = "windows")]
+    if !console::attach() {
+        console::message_box(output, passed);
+        return;
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = passed;
+    println!("{}", output);
+}
+
+#[cfg(target_os = "windows")]
+mod console {
+    use winapi::um::wincon::{AttachConsole, GetConsoleWindow, ATTACH_PARENT_PROCESS};
+    use winapi::um::winuser::{MessageBoxW, MB_ICONINFORMATION, MB_ICONWARNING, MB_OK};
+
+    /// 已有控制台，或成功连到父进程的控制台
+    pub fn attach() -> bool {
+        unsafe { !GetConsoleWindow().is_null() || AttachConsole(ATTACH_PARENT_PROCESS) != 0 }
+    }
+
+    pub fn message_box(text: &str, passed: bool) {
+        let wide = |s: &str| s.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+        let icon = if passed {
+            MB_ICONINFORMATION
+        } else {
+            MB_ICONWARNING
+        };
+        unsafe {
+            MessageBoxW(
+                std::ptr::null_mut(),
+                wide(text).as_ptr(),
+                wide("TypeFree 自检").as_ptr(),
+                MB_OK | icon,
+            );
+        }
+    }
+}
+
+async fn run_checks() -> Vec<Check> {
+    let mut checks = vec![check_permissions(), check_devices()];
+
+    let doubao = Check::new(
+        "豆包调试端口",
+        doubao_cdp::verify_cdp_endpoint()
+            .await
+            .map(|_| "可访问".to_string())
+            .map_err(|e| e.to_string()),
+    );
+    let doubao_ok = doubao.ok == Some(true);
+    checks.push(doubao);
+    if !doubao_ok {
+        checks.extend(PIPELINE_CHECKS.map(Check::skipped));
+        return checks;
+    }
+
+    match run_pipeline().await {
+        Ok(report) => checks.extend(report.checks),
+        Err(e) => checks.push(Check::new("识别链路", Err(e.to_string()))),
+    }
+    checks
+}
+
+fn check_permissions() -> Check {
+    let status = permissions::PermissionStatus::check();
+    let mark = |ok: bool| if ok { "✓" } else { "✗" };
+    let detail = format!(
+        "监听按键 {}  粘贴 {}  直接写入 {}  录音 {}",
+        mark(status.can_monitor_keys),
+        mark(status.can_paste),
+        mark(status.can_type_direct),
+        mark(status.can_record_audio)
+    );
+    let ok = status.all_granted();
+    Check::new("权限", if ok { Ok(detail) } else { Err(detail) })
+}
+
+fn check_devices() -> Check {
+    let result = match audio::list_input_devices() {
+        Ok(devices) => match devices.iter().find(|d| d.is_default) {
+            Some(device) => Ok(format!(
+                "{} 个输入设备，默认 {}（{} 通道）",
+                devices.len(),
+                device.name,
+                device.channels
+            )),
+            None if devices.is_empty() => Err("未找到麦克风设备".to_string()),
+            None => Err(format!("{} 个输入设备，但没有默认设备", devices.len())),
+        },
+        Err(e) => Err(e.user_message()),
+    };
+    // 附上最近一次打开麦克风失败的原因
+    let result = match (result, audio::last_error()) {
+        (Err(e), Some(last)) => Err(format!("{}；最近一次错误：{}", e, last.user_message())),
+        (result, _) => result,
+    };
+    Check::new("输入设备", result)
+}
+
+// ============ 测试音频 ============
+
+/// 按实时速度回放的音频缓冲，代替麦克风作为识别会话的输入
+struct BufferSource {
+    frames: Vec<Vec<u8>>,
+    /// 音频的原文，识别结果要与之一致
+    expected: Option<String>,
+}
+
+impl BufferSource {
+    /// 16kHz 16-bit 单声道 PCM 按帧切分
+    fn from_pcm(pcm: &[u8], expected: Option<String>) -> Self {
+        Self {
+            frames: pcm.chunks(16 * FRAME_MS * 2).map(<[u8]>::to_vec).collect(),
+            expected,
+        }
+    }
+
+    /// 配置目录中有测试录音时使用录音，否则用系统语音合成朗读测试语句
+    fn load() -> Result<Self, String> {
+        let dir = settings::config_dir();
+        let sample = dir
+            .as_ref()
+            .and_then(|dir| std::fs::read(dir.join(SAMPLE_FILE)).ok())
+            .filter(|pcm| !pcm.is_empty());
+        if let Some(pcm) = sample {
+            log::info!("[SelfTest] Using {} ({} bytes)", SAMPLE_FILE, pcm.len());
+            let expected = dir
+                .and_then(|dir| std::fs::read_to_string(dir.join(SAMPLE_TEXT_FILE)).ok())
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty());
+            return Ok(Self::from_pcm(&pcm, expected));
+        }
+
+        let wav = synthesize(UTTERANCE_TEXT)?;
+        let pcm = wav_pcm_data(&wav)?;
+        log::info!("[SelfTest] Synthesized utterance ({} bytes)", pcm.len());
+        Ok(Self::from_pcm(pcm, Some(UTTERANCE_TEXT.to_string())))
+    }
+
+    /// 在后台线程按帧时长发送，发完或被中断时设置 stop_flag
+    fn play(
+        self,
+        stop_flag: Arc<AtomicBool>,
+        cancel: Arc<AtomicBool>,
+    ) -> (Receiver<Vec<u8>>, JoinHandle<()>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            for frame in self.frames {
+                if cancel.load(Ordering::SeqCst) || tx.send(frame).is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(FRAME_MS as u64));
+            }
+            stop_flag.store(true, Ordering::SeqCst);
+        });
+        (rx, handle)
+    }
+}
+
+/// 用系统语音合成把 `text` 朗读成 16kHz 16-bit 单声道 WAV
+#[cfg(target_os = "macos")]
+fn synthesize(text: &str) -> Result<Vec<u8>, String> {
+    let path = std::env::temp_dir().join("typefree-selftest.wav");
+    let status = std::process::Command::new("say")
+        .args(["-v", "Tingting", "--file-format=WAVE"])
+        .args(["--data-format=LEI16@16000", "-o"])
+        .arg(&path)
+        .arg(text)
+        .status()
+        .map_err(|e| format!("无法运行 say：{}", e))?;
+    read_synthesized(&path, status.success())
+}
+
+/// 用系统语音合成把 `text` 朗读成 16kHz 16-bit 单声道 WAV（优先中文语音）
+#[cfg(target_os = "windows")]
+fn synthesize(text: &str) -> Result<Vec<u8>, String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let path = std::env::temp_dir().join("typefree-selftest.wav");
+    let script = format!(
+        "Add-Type -AssemblyName System.Speech; \
+         $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+         $zh = $s.GetInstalledVoices() | Where-Object {{ $_.VoiceInfo.Culture.Name -like 'zh*' }} | Select-Object -First 1; \
+         if ($zh) {{ $s.SelectVoice($zh.VoiceInfo.Name) }}; \
+         $f = New-Object System.Speech.AudioFormat.SpeechAudioFormatInfo(16000, \
+           [System.Speech.AudioFormat.AudioBitsPerSample]::Sixteen, [System.Speech.AudioFormat.AudioChannel]::Mono); \
+         $s.SetOutputToWaveFile('{}', $f); $s.Speak('{}'); $s.Dispose()",
+        path.display(),
+        text
+    );
+    let status = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .map_err(|e| format!("无法运行 PowerShell：{}", e))?;
+    read_synthesized(&path, status.success())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn synthesize(_text: &str) -> Result<Vec<u8>, String> {
+    Err("当前系统不支持语音合成，请在配置目录放置 selftest.pcm".to_string())
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn read_synthesized(path: &std::path::Path, success: bool) -> Result<Vec<u8>, String> {
+    let wav = std::fs::read(path);
+    let _ = std::fs::remove_file(path);
+    match wav {
+        Ok(wav) if success => Ok(wav),
+        _ => Err("语音合成失败（可能未安装中文语音）".to_string()),
+    }
+}
+
+/// 取出 WAV 中的 PCM 数据，要求 16kHz 16-bit 单声道
+fn wav_pcm_data(wav: &[u8]) -> Result<&[u8], String> {
+    if wav.len() < 12 || &wav[0..4] != b"RIFF" || &wav[8..12] != b"WAVE" {
+        return Err("不是 WAV 文件".to_string());
+    }
+    let mut format_ok = false;
+    let mut pos = 12;
+    while pos + 8 <= wav.len() {
+        let id = &wav[pos..pos + 4];
+        let size = u32::from_le_bytes([wav[pos + 4], wav[pos + 5], wav[pos + 6], wav[pos + 7]]);
+        let body = pos + 8;
+        let end = (body + size as usize).min(wav.len());
+        match id {
+            b"fmt " if end - body >= 16 => {
+                let channels = u16::from_le_bytes([wav[body + 2], wav[body + 3]]);
+                let rate = u32::from_le_bytes([
+                    wav[body + 4],
+                    wav[body + 5],
+                    wav[body + 6],
+                    wav[body + 7],
+                ]);
+                let bits = u16::from_le_bytes([wav[body + 14], wav[body + 15]]);
+                format_ok = channels == 1 && rate == 16000 && bits == 16;
+            }
+            b"data" if format_ok => return Ok(&wav[body..end]),
+            b"data" => return Err("WAV 格式不是 16kHz 16-bit 单声道".to_string()),
+            _ => {}
+        }
+        // 块按偶数字节对齐
+        pos = body + size as usize + (size as usize & 1);
+    }
+    Err("WAV 中没有音频数据".to_string())
+}
+
+/// 比较识别文本时忽略标点和空白
+fn normalize_transcript(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+// ============ 识别链路 ============
+
+/// 识别链路诊断的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineReport {
+    /// Cookie、URL 参数、WebSocket 连接、识别结果四项
+    pub checks: Vec<Check>,
+    /// 识别出的文本
+    pub text: String,
+    /// 首个识别结果的延迟
+    pub latency_ms: Option<u64>,
+}
+
+/// 用测试音频走一遍完整识别链路，不操作剪贴板和浮层
+pub async fn run_pipeline() -> Result<PipelineReport, TypeFreeError> {
+    if PIPELINE_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(TypeFreeError::Busy("诊断正在进行中".to_string()));
+    }
+    log::info!("[SelfTest] Running pipeline check...");
+    let cancel = Arc::new(AtomicBool::new(false));
+    *PIPELINE_CANCEL.lock().unwrap() = Some(cancel.clone());
+
+    let report = check_pipeline(cancel).await;
+
+    *PIPELINE_CANCEL.lock().unwrap() = None;
+    PIPELINE_RUNNING.store(false, Ordering::SeqCst);
+    Ok(report)
+}
+
+/// 中断进行中的诊断（按键听写优先），返回是否有诊断在进行
+pub fn cancel_pipeline() -> bool {
+    match PIPELINE_CANCEL.lock().unwrap().as_ref() {
+        Some(cancel) => {
+            log::info!("[SelfTest] Pipeline check interrupted by dictation");
+            cancel.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+async fn check_pipeline(cancel: Arc<AtomicBool>) -> PipelineReport {
+    let mut checks = Vec::new();
+    // 前置检查失败时补齐跳过的项
+    let stop_at = |mut checks: Vec<Check>| {
+        let done = checks.len();
+        checks.extend(PIPELINE_CHECKS[done..].iter().copied().map(Check::skipped));
+        PipelineReport {
+            checks,
+            text: String::new(),
+            latency_ms: None,
+        }
+    };
+
+    let cookies = doubao_cdp::fetch_cookies()
+        .await
+        .map(|cookie| format!("已获取 Cookie（{} 字节）", cookie.len()))
+        .map_err(|e| e.to_string());
+    let cookies_ok = cookies.is_ok();
+    checks.push(Check::new(PIPELINE_CHECKS[0], cookies));
+    if !cookies_ok {
+        return stop_at(checks);
+    }
+
+    let params = doubao_cdp::fetch_asr_info_auto().await.map(|_| {
+        if doubao_cdp::get_cached_url_params().is_some() {
+            "使用捕获的参数模板".to_string()
+        } else {
+            "未能捕获真实参数，使用内置参数".to_string()
+        }
+    })
+    .map_err(|e| e.to_string());
+    let params_ok = params.is_ok();
+    checks.push(Check::new(PIPELINE_CHECKS[1], params));
+    if !params_ok {
+        return stop_at(checks);
+    }
+
+    let source = match BufferSource::load() {
+        Ok(source) => source,
+        Err(e) => {
+            checks.push(Check::skipped(PIPELINE_CHECKS[2]));
+            checks.push(Check::new(
+                PIPELINE_CHECKS[3],
+                Err(format!("无法生成测试语音：{}", e)),
+            ));
+            return stop_at(checks);
+        }
+    };
+    let expected = source.expected.clone();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let (audio_rx, sender) = source.play(stop_flag.clone(), cancel.clone());
+
+    let settings = settings::get();
+    let options = doubao_asr::SessionOptions {
+        audio_format: settings.audio_format,
+        advanced: settings.asr_overrides.clone(),
+        ..Default::default()
+    };
+    let final_text = Arc::new(Mutex::new(None));
+    let final_for_callback = final_text.clone();
+    let result = doubao_asr::run_asr_session(
+        audio_rx,
+        options,
+        None,
+        stop_flag,
+        cancel.clone(),
+        |_: &str| {},
+        move |r: &doubao_asr::AsrResult| *final_for_callback.lock().unwrap() = Some(r.text.clone()),
+    )
+    .await;
+    let _ = sender.join();
+    let text = final_text.lock().unwrap().clone().unwrap_or_default();
+
+    let (connected, results, latency_ms) = match result {
+        Err(AsrError::Connect(e)) => {
+            checks.push(Check::new(PIPELINE_CHECKS[2], Err(e.to_string())));
+            return stop_at(checks);
+        }
+        Err(AsrError::Server { error, stats }) => (
+            format!("连接 {}ms", stats.connect_ms),
+            Err(format!("{}（错误码 {}）", error.user_message(), error.code)),
+            stats.first_partial_ms,
+        ),
+        Ok(_) if cancel.load(Ordering::SeqCst) => (
+            "已连接".to_string(),
+            Err("被按键听写中断".to_string()),
+            None,
+        ),
+        Ok(stats) => (
+            format!(
+                "连接 {}ms，发送 {} 条消息",
+                stats.connect_ms, stats.messages_sent
+            ),
+            results_detail(
+                stats.partials,
+                stats.first_partial_ms,
+                &text,
+                expected.as_deref(),
+            ),
+            stats.first_partial_ms,
+        ),
+    };
+    checks.push(Check::new(PIPELINE_CHECKS[2], Ok(connected)));
+    checks.push(Check::new(PIPELINE_CHECKS[3], results));
+    PipelineReport {
+        checks,
+        text,
+        latency_ms,
+    }
+}
+
+/// 识别结果一项的结论：要有识别文本，知道原文时还要与原文一致（忽略标点）
+fn results_detail(
+    partials: usize,
+    latency_ms: Option<u64>,
+    text: &str,
+    expected: Option<&str>,
+) -> Result<String, String> {
+    let Some(ms) = latency_ms.filter(|_| partials > 0 && !text.trim().is_empty()) else {
+        return Err("没有收到识别结果".to_string());
+    };
+    if let Some(expected) = expected {
+        if normalize_transcript(text) != normalize_transcript(expected) {
+            return Err(format!(
+                "识别文本「{}」与测试语音「{}」不一致",
+                text, expected
+            ));
+        }
+    }
+    Ok(format!(
+        "{} 个结果，首个结果 {}ms，文本「{}」",
+        partials, ms, text
+    ))
+}
+
+/// 格式化报告
+fn format_report(checks: &[Check]) -> String {
+    let mut lines = vec![format!(
+        "TypeFree 自检报告 (v{}, {} {})",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )];
+    for check in checks {
+        let status = match check.ok {
+            Some(true) => "PASS",
+            Some(false) => "FAIL",
+            None => "SKIP",
+        };
+        lines.push(format!("[{}] {}：{}", status, check.name, check.detail));
+    }
+    let passed = checks.iter().filter(|c| c.ok == Some(true)).count();
+    lines.push(format!("结果：{}/{} 通过", passed, checks.len()));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let checks = vec![
+            Check::new("权限", Ok("输入监控 ✓".to_string())),
+            Check::new("豆包调试端口", Err("CDP request timed out".to_string())),
+            Check::skipped("ASR 参数捕获"),
+        ];
+        let report = format_report(&checks);
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("TypeFree 自检报告 (v"));
+        assert_eq!(lines[1], "[PASS] 权限：输入监控 ✓");
+        assert_eq!(lines[2], "[FAIL] 豆包调试端口：CDP request timed out");
+        assert_eq!(lines[3], "[SKIP] ASR 参数捕获：前置检查失败，未执行");
+        assert_eq!(lines[4], "结果：1/3 通过");
+    }
+
+    #[test]
+    fn test_buffer_source() {
+        // 录音按帧切分，最后一帧可以不满
+        let source = BufferSource::from_pcm(&vec![0u8; 16 * FRAME_MS * 2 * 3 + 10], None);
+        assert_eq!(source.frames.len(), 4);
+        assert_eq!(source.frames[0].len(), 16 * FRAME_MS * 2);
+        assert_eq!(source.frames[3].len(), 10);
+
+        // 发送完毕后设置停止标志
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (rx, handle) = BufferSource::from_pcm(&[1, 2, 3, 4], None)
+            .play(stop_flag.clone(), Arc::new(AtomicBool::new(false)));
+        handle.join().unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![vec![1, 2, 3, 4]]);
+        assert!(stop_flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_results_detail() {
+        assert_eq!(
+            results_detail(3, Some(420), "你好", None),
+            Ok("3 个结果，首个结果 420ms，文本「你好」".to_string())
+        );
+        // 没有文本一律失败，知道原文时忽略标点比较
+        assert_eq!(
+            results_detail(0, None, "", Some(UTTERANCE_TEXT)),
+            Err("没有收到识别结果".to_string())
+        );
+        assert!(results_detail(2, Some(300), "今天天气很好。", Some(UTTERANCE_TEXT)).is_ok());
+        assert_eq!(
+            results_detail(2, Some(300), "今天天气", Some(UTTERANCE_TEXT)),
+            Err("识别文本「今天天气」与测试语音「今天天气很好」不一致".to_string())
+        );
+    }
+
+    #[test]
+    fn test_wav_pcm_data() {
+        let wav = |rate: u32, channels: u16, data: &[u8]| {
+            let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+            wav.extend_from_slice(b"fmt ");
+            wav.extend_from_slice(&16u32.to_le_bytes());
+            wav.extend_from_slice(&1u16.to_le_bytes());
+            wav.extend_from_slice(&channels.to_le_bytes());
+            wav.extend_from_slice(&rate.to_le_bytes());
+            wav.extend_from_slice(&(rate * 2 * channels as u32).to_le_bytes());
+            wav.extend_from_slice(&(2 * channels).to_le_bytes());
+            wav.extend_from_slice(&16u16.to_le_bytes());
+            // say 会在 data 前写入其他块
+            wav.extend_from_slice(b"FLLR");
+            wav.extend_from_slice(&3u32.to_le_bytes());
+            wav.extend_from_slice(&[0, 0, 0, 0]);
+            wav.extend_from_slice(b"data");
+            wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            wav.extend_from_slice(data);
+            wav
+        };
+        assert_eq!(
+            wav_pcm_data(&wav(16000, 1, &[1, 2, 3, 4])),
+            Ok(&[1u8, 2, 3, 4][..])
+        );
+        assert!(wav_pcm_data(&wav(22050, 1, &[1, 2])).is_err());
+        assert!(wav_pcm_data(&wav(16000, 2, &[1, 2])).is_err());
+        assert!(wav_pcm_data(b"not a wav").is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! 实时转写 - 本次运行的最终识别结果，供主窗口的转写面板显示和导出
+//!
+//! 主窗口可能还没创建或处于隐藏状态，结果先保存在这里，面板打开时整体拉取。
+//! 内容太短被忽略的结果也会记录，并标记为未粘贴；最近一条已粘贴的结果可以重新粘贴
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 最多保留的结果条数，超出时丢弃最早的
+const MAX_ENTRIES: usize = 500;
+
+/// 一条结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    pub text: String,
+    /// 内容太短，已忽略（没有粘贴）
+    pub discarded: bool,
+    /// 实际使用的识别语言
+    pub language: Option<String>,
+    /// 识别完成的时间（Unix 毫秒）
+    pub timestamp_ms: u64,
+    /// 录音时长（毫秒）
+    pub duration_ms: u64,
+    /// 之后又重新粘贴过
+    pub repasted: bool,
+    /// 粘贴后被重新听写撤销
+    pub undone: bool,
+}
+
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+fn push_capped(entries: &mut Vec<Entry>, entry: Entry, max: usize) {
+    if entry.text.is_empty() {
+        return;
+    }
+    entries.push(entry);
+    if entries.len() > max {
+        let excess = entries.len() - max;
+        entries.drain(..excess);
+    }
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn entry(text: &str, discarded: bool, language: &str, duration_ms: u64) -> Entry {
+    Entry {
+        text: text.trim().to_string(),
+        discarded,
+        language: Some(language.to_string()),
+        timestamp_ms: now_ms(),
+        duration_ms,
+        repasted: false,
+        undone: false,
+    }
+}
+
+/// 记录一条最终结果（`duration_ms` 为录音时长）
+pub fn push(text: &str, language: &str, duration_ms: u64) {
+    let entry = entry(text, false, language, duration_ms);
+    push_capped(&mut ENTRIES.lock().unwrap(), entry, MAX_ENTRIES);
+}
+
+/// 记录一条被忽略的结果
+pub fn push_discarded(text: &str, language: &str, duration_ms: u64) {
+    let entry = entry(text, true, language, duration_ms);
+    push_capped(&mut ENTRIES.lock().unwrap(), entry, MAX_ENTRIES);
+}
+
+/// 最近一条已粘贴的结果（被忽略或已撤销时没有）
+fn last_pasted(entries: &mut [Entry]) -> Option<&mut Entry> {
+    entries
+        .last_mut()
+        .filter(|entry| !entry.discarded && !entry.undone)
+}
+
+/// 标记最近一条结果被重新粘贴，返回其文本（没有可重新粘贴的结果时为 None）
+pub fn mark_repasted() -> Option<String> {
+    let mut entries = ENTRIES.lock().unwrap();
+    let entry = last_pasted(&mut entries)?;
+    entry.repasted = true;
+    Some(entry.text.clone())
+}
+
+/// 标记最近一条结果已被撤销（不再可重新粘贴）
+pub fn mark_undone() {
+    if let Some(entry) = last_pasted(&mut ENTRIES.lock().unwrap()) {
+        entry.undone = true;
+    }
+}
+
+pub fn entries() -> Vec<Entry> {
+    ENTRIES.lock().unwrap().clone()
+}
+
+/// 不复制地读取全部结果（导出时逐条写入）
+pub fn with_entries<R>(f: impl FnOnce(&[Entry]) -> R) -> R {
+    f(&ENTRIES.lock().unwrap())
+}
+
+pub fn clear() {
+    ENTRIES.lock().unwrap().clear();
+    log::info!("[Transcript] Cleared");
+}
+
+/// 导出文本：每条结果一行，被忽略的结果加上标记
+fn export_text(entries: &[Entry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            if entry.discarded {
+                format!("[已忽略] {}\n", entry.text)
+            } else if entry.undone {
+                format!("[已撤销] {}\n", entry.text)
+            } else if entry.repasted {
+                format!("[已重新粘贴] {}\n", entry.text)
+            } else {
+                format!("{}\n", entry.text)
+            }
+        })
+        .collect()
+}
+
+/// 导出到 dir 下的文本文件，返回文件路径
+pub fn export(dir: &Path) -> Result<PathBuf, String> {
+    let entries = entries();
+    if entries.is_empty() {
+        return Err("没有可导出的内容".to_string());
+    }
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("transcript-{}.txt", secs));
+    std::fs::write(&path, export_text(&entries))
+        .map_err(|e| format!("Failed to write transcript: {}", e))?;
+    log::info!(
+        "[Transcript] Exported {} entries to {}",
+        entries.len(),
+        path.display()
+    );
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_capped() {
+        let mut entries = Vec::new();
+        for text in ["一", " ", "二", "三 "] {
+            push_capped(&mut entries, entry(text, false, "zh", 1000), 2);
+        }
+        // 空结果不记录，超出上限丢弃最早的
+        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["二", "三"]);
+        assert_eq!(export_text(&entries), "二\n三\n");
+        assert_eq!(export_text(&[]), "");
+
+        push_capped(&mut entries, entry("嗯", true, "zh", 300), 3);
+        assert_eq!(export_text(&entries), "二\n三\n[已忽略] 嗯\n");
+    }
+
+    #[test]
+    fn test_last_pasted() {
+        let mut entries = vec![entry("一", false, "zh", 1000)];
+        last_pasted(&mut entries).unwrap().repasted = true;
+        assert_eq!(export_text(&entries), "[已重新粘贴] 一\n");
+
+        // 最近一条被忽略或撤销时没有可重新粘贴的结果
+        entries.push(entry("嗯", true, "zh", 300));
+        assert!(last_pasted(&mut entries).is_none());
+        entries.push(entry("二", false, "zh", 1000));
+        last_pasted(&mut entries).unwrap().undone = true;
+        assert!(last_pasted(&mut entries).is_none());
+        assert!(export_text(&entries).ends_with("[已撤销] 二\n"));
+    }
+}
@@ -1,16 +1,471 @@
 //! 系统托盘 (Menu Bar) 功能
 
+use crate::settings::Settings;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 use tauri::{
     image::Image,
     include_image,
-    menu::{Menu, MenuItem, PredefinedMenuItem},
-    tray::TrayIconBuilder,
-    AppHandle, Manager,
+    menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{TrayIconBuilder, TrayIconEvent},
+    AppHandle, Wry,
 };
 use tauri_plugin_autostart::ManagerExt;
 
 const TRAY_ICON: Image<'static> = include_image!("icons/tray-icon@2x.png");
 
+const TRAY_ID: &str = "main";
+
+// ============ 健康状态 ============
+
+/// ASR 链路的健康快照，显示在托盘提示和「状态」子菜单
+#[derive(Debug, Clone, Default)]
+pub struct HealthSnapshot {
+    /// 最近一次会话首个识别结果的延迟
+    pub latency_ms: Option<u64>,
+    /// 豆包 ASR 是否连得上，None 表示尚未检测
+    pub doubao_connected: Option<bool>,
+    /// 豆包是否已登录，None 表示尚未检测
+    pub logged_in: Option<bool>,
+    /// 最近使用的麦克风
+    pub mic_device: Option<String>,
+    /// 最近一次错误
+    pub last_error: Option<String>,
+}
+
+impl HealthSnapshot {
+    fn tooltip(&self) -> String {
+        let mut parts = vec!["TypeFree".to_string()];
+        if let Some(ms) = self.latency_ms {
+            parts.push(format!("延迟 {}ms", ms));
+        }
+        match self.doubao_connected {
+            Some(true) => parts.push("豆包已连接".to_string()),
+            Some(false) => parts.push("豆包未连接".to_string()),
+            None => {}
+        }
+        if !crate::settings::get().dictation_enabled {
+            parts.push("听写已暂停".to_string());
+        }
+        if crate::scratchpad::is_active() {
+            parts.push("听写到便签".to_string());
+        }
+        if let Some(update) = crate::update::latest() {
+            parts.push(format!("有新版本 {}", update.version));
+        }
+        parts.join(" · ")
+    }
+
+    fn doubao_line(&self) -> String {
+        let state = match self.doubao_connected {
+            Some(true) => "已连接",
+            Some(false) => "未连接",
+            None => "未检测",
+        };
+        format!("豆包：{}", state)
+    }
+
+    fn login_line(&self) -> String {
+        let state = match self.logged_in {
+            Some(true) => "已登录",
+            Some(false) => "未登录",
+            None => "未检测",
+        };
+        format!("登录：{}", state)
+    }
+
+    fn mic_line(&self) -> String {
+        format!("麦克风：{}", self.mic_device.as_deref().unwrap_or("未使用"))
+    }
+
+    fn error_line(&self) -> String {
+        format!("最近错误：{}", self.last_error.as_deref().unwrap_or("无"))
+    }
+}
+
+/// 最新的健康快照
+static HEALTH: Mutex<Option<HealthSnapshot>> = Mutex::new(None);
+
+/// 「状态」子菜单中的只读条目
+struct StatusItems {
+    doubao: MenuItem<Wry>,
+    login: MenuItem<Wry>,
+    mic: MenuItem<Wry>,
+    error: MenuItem<Wry>,
+}
+
+static STATUS_ITEMS: OnceLock<StatusItems> = OnceLock::new();
+
+/// 「暂停听写」开关
+static DICTATION_ITEM: OnceLock<MenuItem<Wry>> = OnceLock::new();
+
+/// 「听写到便签」开关
+static SCRATCHPAD_ITEM: OnceLock<MenuItem<Wry>> = OnceLock::new();
+
+/// 「检查更新」，有新版本时改为「下载新版本」
+static UPDATE_ITEM: OnceLock<MenuItem<Wry>> = OnceLock::new();
+
+/// 当前健康快照
+pub fn health() -> HealthSnapshot {
+    HEALTH.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// 更新健康快照，并在主线程刷新托盘提示和状态菜单
+pub fn update_health(app: &AppHandle, snapshot: HealthSnapshot) {
+    *HEALTH.lock().unwrap() = Some(snapshot.clone());
+
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        if let Some(tray) = app_for_thread.tray_by_id(TRAY_ID) {
+            let _ = tray.set_tooltip(Some(snapshot.tooltip()));
+        }
+        if let Some(items) = STATUS_ITEMS.get() {
+            let _ = items.doubao.set_text(snapshot.doubao_line());
+            let _ = items.login.set_text(snapshot.login_line());
+            let _ = items.mic.set_text(snapshot.mic_line());
+            let _ = items.error.set_text(snapshot.error_line());
+        }
+    });
+}
+
+// ============ 托盘标题 ============
+
+/// 浮层被隐藏（演示、勿扰）时在图标旁显示录音中
+static RECORDING_INDICATOR: AtomicBool = AtomicBool::new(false);
+
+/// 会话的实时状态（录音秒数、完成后的字数），有时代替「录音中」显示
+static LIVE_TITLE: Mutex<Option<String>> = Mutex::new(None);
+
+/// 录音中的标题：红点和已录秒数
+pub fn recording_title(elapsed: Duration) -> String {
+    format!("🔴 {}s", elapsed.as_secs())
+}
+
+/// 识别完成后的标题：结果字数
+pub fn result_title(chars: usize) -> String {
+    format!("✓ {}字", chars)
+}
+
+/// 图标旁的标题：听写暂停、浮层隐藏时的录音状态、「听写到便签」模式
+fn title() -> Option<String> {
+    let mut parts = Vec::new();
+    if !crate::settings::get().dictation_enabled {
+        parts.push("已暂停");
+    }
+    let live = LIVE_TITLE.lock().unwrap().clone();
+    if let Some(live) = &live {
+        parts.push(live.as_str());
+    } else if RECORDING_INDICATOR.load(Ordering::SeqCst) {
+        parts.push("● 录音中");
+    }
+    if crate::scratchpad::is_active() {
+        parts.push("便签");
+    }
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// 在主线程刷新托盘提示和标题
+fn refresh_title(app: &AppHandle) {
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        if let Some(tray) = app_for_thread.tray_by_id(TRAY_ID) {
+            let _ = tray.set_tooltip(Some(health().tooltip()));
+            let _ = tray.set_title(title());
+        }
+    });
+}
+
+/// 浮层被隐藏时用托盘标题提示正在录音
+pub fn set_recording_indicator(app: &AppHandle, recording: bool) {
+    if RECORDING_INDICATOR.swap(recording, Ordering::SeqCst) != recording {
+        refresh_title(app);
+    }
+}
+
+// ============ 录音指示 ============
+
+/// 录音指示的红色
+const RECORDING_RGB: [u8; 3] = [0xFF, 0x3B, 0x30];
+
+/// 录音中的托盘图标（首次使用时生成）
+static RECORDING_ICON: OnceLock<Image<'static>> = OnceLock::new();
+
+/// 把图标的像素染成指定颜色，保留透明度
+fn tint_rgba(rgba: &[u8], rgb: [u8; 3]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|pixel| [rgb[0], rgb[1], rgb[2], pixel[3]])
+        .collect()
+}
+
+/// 在图标右下角叠加一个圆点
+fn with_dot(rgba: &[u8], width: u32, height: u32, rgb: [u8; 3]) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let radius = width.min(height) as f32 * 0.22;
+    let (cx, cy) = (width as f32 - radius, height as f32 - radius);
+    for y in 0..height {
+        for x in 0..width {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                out[i..i + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], 0xFF]);
+            }
+        }
+    }
+    out
+}
+
+/// macOS 上整个图标变红，Windows 上在图标角落加红点
+fn recording_icon() -> Image<'static> {
+    RECORDING_ICON
+        .get_or_init(|| {
+            let (width, height) = (TRAY_ICON.width(), TRAY_ICON.height());
+            let rgba = if cfg!(target_os = "macos") {
+                tint_rgba(TRAY_ICON.rgba(), RECORDING_RGB)
+            } else {
+                with_dot(TRAY_ICON.rgba(), width, height, RECORDING_RGB)
+            };
+            Image::new_owned(rgba, width, height)
+        })
+        .clone()
+}
+
+/// 按麦克风是否在录音切换托盘图标
+pub fn set_recording_icon(app: &AppHandle, recording: bool) {
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        if let Some(tray) = app_for_thread.tray_by_id(TRAY_ID) {
+            let icon = if recording { recording_icon() } else { TRAY_ICON };
+            let _ = tray.set_icon(Some(icon));
+            // 模板图标由系统按菜单栏配色着色，录音时改用原色
+            let _ = tray.set_icon_as_template(!recording);
+        }
+    });
+}
+
+/// 在图标旁显示会话的实时状态（内容不变时不刷新）
+pub fn set_title(app: &AppHandle, text: &str) {
+    let changed = {
+        let mut live = LIVE_TITLE.lock().unwrap();
+        let changed = live.as_deref() != Some(text);
+        *live = Some(text.to_string());
+        changed
+    };
+    if changed {
+        refresh_title(app);
+    }
+}
+
+/// 清除会话的实时状态
+pub fn clear_title(app: &AppHandle) {
+    if LIVE_TITLE.lock().unwrap().take().is_some() {
+        refresh_title(app);
+    }
+}
+
+/// 在菜单、托盘提示和图标旁的标题上显示听写是否暂停
+pub fn update_dictation_enabled(app: &AppHandle) {
+    refresh_title(app);
+    if let Some(item) = DICTATION_ITEM.get() {
+        let paused = !crate::settings::get().dictation_enabled;
+        let _ = item.set_text(toggle_text("暂停听写", paused));
+    }
+}
+
+/// 在菜单、托盘提示和图标旁的标题上显示「听写到便签」模式
+pub fn update_scratchpad_mode(app: &AppHandle) {
+    refresh_title(app);
+    if let Some(item) = SCRATCHPAD_ITEM.get() {
+        let _ = item.set_text(toggle_text("听写到便签", crate::scratchpad::is_active()));
+    }
+}
+
+// ============ 更新提示 ============
+
+fn update_item_text(version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("⬆ 下载新版本 {}", version),
+        None => "检查更新".to_string(),
+    }
+}
+
+/// 检查到新版本：菜单项改为下载，托盘提示中显示版本号
+pub fn update_available(app: &AppHandle, version: &str) {
+    let text = update_item_text(Some(version));
+    let _ = app.run_on_main_thread(move || {
+        if let Some(item) = UPDATE_ITEM.get() {
+            let _ = item.set_text(text);
+        }
+    });
+    update_health(app, health());
+}
+
+// ============ 输入设备子菜单 ============
+
+const INPUT_DEVICE_DEFAULT: &str = "input_device_default";
+const INPUT_DEVICE_PREFIX: &str = "input_device:";
+
+static INPUT_DEVICE_MENU: OnceLock<Submenu<Wry>> = OnceLock::new();
+
+/// 系统的设备插拔通知可用，不需要在鼠标移到图标上时刷新
+static DEVICE_NOTIFICATIONS: AtomicBool = AtomicBool::new(false);
+
+/// 子菜单当前显示的设备和选择，没有变化时不重建
+static SHOWN_DEVICES: Mutex<Option<(Vec<String>, Option<String>)>> = Mutex::new(None);
+
+/// 子菜单条目 (id, 文字)：选择的设备不在列表中（已拔出）时勾选系统默认
+fn device_entries(devices: &[String], selected: Option<&str>) -> Vec<(String, String)> {
+    let selected = selected.filter(|name| devices.iter().any(|device| device == name));
+    let mut entries = vec![(
+        INPUT_DEVICE_DEFAULT.to_string(),
+        toggle_text("系统默认", selected.is_none()),
+    )];
+    entries.extend(devices.iter().map(|name| {
+        (
+            format!("{}{}", INPUT_DEVICE_PREFIX, name),
+            toggle_text(name, selected == Some(name.as_str())),
+        )
+    }));
+    entries
+}
+
+/// 菜单项 id 对应的设备选择，不是设备菜单项时返回 None
+fn device_choice(id: &str) -> Option<Option<String>> {
+    if id == INPUT_DEVICE_DEFAULT {
+        return Some(None);
+    }
+    id.strip_prefix(INPUT_DEVICE_PREFIX)
+        .map(|name| Some(name.to_string()))
+}
+
+/// 重新列出输入设备，有变化时重建子菜单（枚举设备较慢，不占用主线程）
+pub fn refresh_input_devices(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || refresh_input_devices_now(&app));
+}
+
+fn refresh_input_devices_now(app: &AppHandle) {
+    let devices = match crate::audio::list_input_devices() {
+        Ok(devices) => devices.into_iter().map(|device| device.name).collect(),
+        Err(e) => {
+            log::warn!("[Tray] Failed to list input devices: {}", e.user_message());
+            Vec::new()
+        }
+    };
+    let shown = (devices, crate::settings::get().input_device);
+    {
+        let mut last = SHOWN_DEVICES.lock().unwrap();
+        if last.as_ref() == Some(&shown) {
+            return;
+        }
+        *last = Some(shown.clone());
+    }
+
+    let app_for_thread = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        let (devices, selected) = shown;
+        if let Err(e) = rebuild_input_device_menu(&app_for_thread, &devices, selected.as_deref()) {
+            log::warn!("[Tray] Failed to rebuild input device menu: {}", e);
+            // 下次刷新时重试
+            *SHOWN_DEVICES.lock().unwrap() = None;
+        }
+    });
+}
+
+fn rebuild_input_device_menu(
+    app: &AppHandle,
+    devices: &[String],
+    selected: Option<&str>,
+) -> tauri::Result<()> {
+    let Some(menu) = INPUT_DEVICE_MENU.get() else {
+        return Ok(());
+    };
+    for item in menu.items()? {
+        menu.remove(&item)?;
+    }
+    for (id, text) in device_entries(devices, selected) {
+        menu.append(&MenuItem::with_id(app, id, text, true, None::<&str>)?)?;
+    }
+    Ok(())
+}
+
+/// 设备插拔时刷新子菜单；系统不提供通知时改为鼠标移到图标上时刷新
+fn watch_input_devices(app: &AppHandle) {
+    let app = app.clone();
+    let watching = crate::audio::watch_input_devices(move || refresh_input_devices(&app));
+    DEVICE_NOTIFICATIONS.store(watching, Ordering::SeqCst);
+}
+
+fn select_input_device(app: &AppHandle, name: Option<String>) {
+    if let Err(e) = crate::set_input_device(app, name) {
+        log::error!("[Tray] Failed to save setting: {}", e);
+    }
+}
+
+// ============ 设置子菜单（仅菜单栏模式） ============
+
+/// 「设置」子菜单中的开关
+struct SettingToggle {
+    id: &'static str,
+    label: &'static str,
+    get: fn(&Settings) -> bool,
+    set: fn(&mut Settings, bool),
+}
+
+const SETTING_TOGGLES: [SettingToggle; 5] = [
+    SettingToggle {
+        id: "setting_review",
+        label: "粘贴前确认",
+        get: |s| s.review_before_paste,
+        set: |s, v| s.review_before_paste = v,
+    },
+    SettingToggle {
+        id: "setting_draft",
+        label: "草稿模式",
+        get: |s| s.draft_mode,
+        set: |s, v| s.draft_mode = v,
+    },
+    SettingToggle {
+        id: "setting_trim_silence",
+        label: "裁剪首尾静音",
+        get: |s| s.trim_silence,
+        set: |s, v| s.trim_silence = v,
+    },
+    SettingToggle {
+        id: "setting_sound_cues",
+        label: "提示音",
+        get: |s| s.sound_cues,
+        set: |s, v| s.sound_cues = v,
+    },
+    SettingToggle {
+        id: "setting_menu_bar_only",
+        label: "仅菜单栏模式（重启后生效）",
+        get: |s| s.menu_bar_only,
+        set: |s, v| s.menu_bar_only = v,
+    },
+];
+
+fn toggle_text(label: &str, enabled: bool) -> String {
+    if enabled {
+        format!("✓ {}", label)
+    } else {
+        label.to_string()
+    }
+}
+
+/// 切换设置开关，返回新的菜单文字
+fn flip_setting(toggle: &SettingToggle) -> Result<String, String> {
+    let settings = crate::settings::update(|s| {
+        let enabled = !(toggle.get)(s);
+        (toggle.set)(s, enabled)
+    })?;
+    let enabled = (toggle.get)(&settings);
+    log::info!("[Tray] Setting {}: {}", toggle.id, enabled);
+    Ok(toggle_text(toggle.label, enabled))
+}
+
+// ============ 托盘 ============
+
 pub fn init(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     // 检查当前自动启动状态
     let autostart_enabled = app.autolaunch().is_enabled().unwrap_or(false);
@@ -20,41 +475,207 @@ pub fn init(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
         "开机自动启动"
     };
 
+    let quit_doubao_text = if crate::settings::get().quit_doubao_on_exit {
+        "✓ 退出时关闭豆包"
+    } else {
+        "退出时关闭豆包"
+    };
+
     // 创建菜单项（只保留操作按钮）
     let open = MenuItem::with_id(app, "open", "打开 TypeFree", true, None::<&str>)?;
+    let pause_dictation = MenuItem::with_id(
+        app,
+        "pause_dictation",
+        toggle_text("暂停听写", !crate::settings::get().dictation_enabled),
+        true,
+        None::<&str>,
+    )?;
+    let _ = DICTATION_ITEM.set(pause_dictation.clone());
+    let repaste = MenuItem::with_id(app, "repaste", "重新粘贴上次结果", true, None::<&str>)?;
+    let undo_paste =
+        MenuItem::with_id(app, "undo_paste", "撤销上次粘贴（移动过光标会删错）", true, None::<&str>)?;
+    let redictate = MenuItem::with_id(app, "redictate", "重新听写", true, None::<&str>)?;
+    let clear_target = MenuItem::with_id(app, "clear_target", "清除粘贴目标", true, None::<&str>)?;
+    let scratchpad_mode = MenuItem::with_id(
+        app,
+        "scratchpad_mode",
+        toggle_text("听写到便签", crate::scratchpad::is_active()),
+        true,
+        None::<&str>,
+    )?;
+    let open_scratchpad = MenuItem::with_id(app, "open_scratchpad", "打开便签", true, None::<&str>)?;
+    let _ = SCRATCHPAD_ITEM.set(scratchpad_mode.clone());
+    let commit_draft = MenuItem::with_id(app, "commit_draft", "插入全部草稿", true, None::<&str>)?;
+    let export_today =
+        MenuItem::with_id(app, "export_today", "导出今天的记录到桌面", true, None::<&str>)?;
     let autostart_item =
         MenuItem::with_id(app, "autostart", autostart_text, true, None::<&str>)?;
+    let quit_doubao_item =
+        MenuItem::with_id(app, "quit_doubao", quit_doubao_text, true, None::<&str>)?;
+    let data_dir_item =
+        MenuItem::with_id(app, "data_dir", "打开配置目录", true, None::<&str>)?;
+    let latest_version = crate::update::latest().map(|info| info.version);
+    let update_item = MenuItem::with_id(
+        app,
+        "check_update",
+        update_item_text(latest_version.as_deref()),
+        true,
+        None::<&str>,
+    )?;
+    let _ = UPDATE_ITEM.set(update_item.clone());
     let quit = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
 
+    // 仅菜单栏模式：没有主窗口，用「设置」和「权限设置」子菜单代替「打开 TypeFree」
+    let menu_bar_only = crate::is_menu_bar_only();
+    let current = crate::settings::get();
+    let toggle_items = SETTING_TOGGLES
+        .iter()
+        .map(|t| {
+            let text = toggle_text(t.label, (t.get)(&current));
+            MenuItem::with_id(app, t.id, text, true, None::<&str>)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let settings_sep = PredefinedMenuItem::separator(app)?;
+    let edit_settings =
+        MenuItem::with_id(app, "edit_settings", "编辑设置文件…", true, None::<&str>)?;
+    let mut settings_entries: Vec<&dyn IsMenuItem<Wry>> = toggle_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    settings_entries.push(&settings_sep);
+    settings_entries.push(&edit_settings);
+    let settings_menu = Submenu::with_items(app, "设置", true, &settings_entries)?;
+
+    let permission_menu = Submenu::with_items(
+        app,
+        "权限设置",
+        true,
+        &[
+            &MenuItem::with_id(app, "perm_input", "输入监控…", true, None::<&str>)?,
+            &MenuItem::with_id(app, "perm_accessibility", "辅助功能…", true, None::<&str>)?,
+            &MenuItem::with_id(app, "perm_automation", "自动化（粘贴）…", true, None::<&str>)?,
+            &MenuItem::with_id(app, "perm_microphone", "麦克风…", true, None::<&str>)?,
+        ],
+    )?;
+
+    // 只读的状态子菜单
+    let snapshot = health();
+    let status_item =
+        |id: &str, text: String| MenuItem::with_id(app, id, text, false, None::<&str>);
+    let status_items = StatusItems {
+        doubao: status_item("status_doubao", snapshot.doubao_line())?,
+        login: status_item("status_login", snapshot.login_line())?,
+        mic: status_item("status_mic", snapshot.mic_line())?,
+        error: status_item("status_error", snapshot.error_line())?,
+    };
+    let status_menu = Submenu::with_items(
+        app,
+        "状态",
+        true,
+        &[
+            &status_items.doubao,
+            &status_items.login,
+            &status_items.mic,
+            &status_items.error,
+        ],
+    )?;
+    let _ = STATUS_ITEMS.set(status_items);
+
+    // 输入设备子菜单：先只有系统默认，设备列表在后台枚举后填入
+    let device_items = device_entries(&[], current.input_device.as_deref())
+        .into_iter()
+        .map(|(id, text)| MenuItem::with_id(app, id, text, true, None::<&str>))
+        .collect::<Result<Vec<_>, _>>()?;
+    let device_menu_entries: Vec<&dyn IsMenuItem<Wry>> = device_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    let input_device_menu = Submenu::with_items(app, "输入设备", true, &device_menu_entries)?;
+    let _ = INPUT_DEVICE_MENU.set(input_device_menu.clone());
+
     // 分隔符
     let sep1 = PredefinedMenuItem::separator(app)?;
     let sep2 = PredefinedMenuItem::separator(app)?;
 
     // 菜单结构
-    let menu = Menu::with_items(
-        app,
-        &[&open, &sep1, &autostart_item, &sep2, &quit],
-    )?;
+    let mut entries: Vec<&dyn IsMenuItem<Wry>> = Vec::new();
+    if !menu_bar_only {
+        entries.push(&open);
+    }
+    entries.extend([
+        &pause_dictation as &dyn IsMenuItem<Wry>,
+        &repaste,
+        &undo_paste,
+        &redictate,
+        &clear_target,
+        &commit_draft,
+        &scratchpad_mode,
+        &open_scratchpad,
+        &export_today,
+        &input_device_menu,
+        &status_menu,
+    ]);
+    if menu_bar_only {
+        entries.extend([&settings_menu as &dyn IsMenuItem<Wry>, &permission_menu]);
+    }
+    entries.extend([
+        &sep1 as &dyn IsMenuItem<Wry>,
+        &autostart_item,
+        &quit_doubao_item,
+        &data_dir_item,
+        &update_item,
+        &sep2,
+        &quit,
+    ]);
+    let menu = Menu::with_items(app, &entries)?;
 
     // 克隆用于闭包
     let autostart_for_closure = autostart_item.clone();
+    let quit_doubao_for_closure = quit_doubao_item.clone();
+    let toggles_for_closure = toggle_items.clone();
 
     // 构建托盘图标
-    let _tray = TrayIconBuilder::with_id("main")
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(TRAY_ICON)
         .icon_as_template(true)
         .menu(&menu)
-        .tooltip("TypeFree")
+        .tooltip(snapshot.tooltip())
+        .on_tray_icon_event(|tray, event| {
+            // 没有插拔通知时，鼠标移到图标上再刷新设备列表，打开菜单时已是最新
+            if let TrayIconEvent::Enter { .. } = event {
+                if !DEVICE_NOTIFICATIONS.load(Ordering::SeqCst) {
+                    refresh_input_devices(tray.app_handle());
+                }
+            }
+        })
         .on_menu_event(move |app, event| {
             let id = event.id.as_ref();
             log::info!("[Tray] Menu event: {}", id);
 
             match id {
-                "open" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        let _ = window.show();
-                        let _ = window.set_focus();
-                    }
+                "open" => crate::show_main_window(app),
+                "pause_dictation" => crate::toggle_dictation(app),
+                "repaste" => {
+                    // 粘贴会阻塞，不占用主线程
+                    let app = app.clone();
+                    std::thread::spawn(move || crate::repaste_last_result(&app));
+                }
+                "undo_paste" => {
+                    let app = app.clone();
+                    std::thread::spawn(move || crate::undo_last_paste(&app));
+                }
+                "redictate" => {
+                    let app = app.clone();
+                    std::thread::spawn(move || crate::redictate(&app));
+                }
+                "clear_target" => crate::clear_paste_target(app),
+                "scratchpad_mode" => crate::toggle_scratchpad_mode(app),
+                "open_scratchpad" => crate::show_scratchpad_window(app),
+                "export_today" => crate::export_today_to_desktop(app),
+                "check_update" => crate::check_updates_from_tray(app),
+                "commit_draft" => {
+                    let app = app.clone();
+                    std::thread::spawn(move || crate::commit_draft_now(&app));
                 }
                 "autostart" => {
                     let autolaunch = app.autolaunch();
@@ -85,15 +706,133 @@ pub fn init(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
+                "quit_doubao" => {
+                    match crate::settings::update(|s| s.quit_doubao_on_exit = !s.quit_doubao_on_exit) {
+                        Ok(s) => {
+                            let text = if s.quit_doubao_on_exit {
+                                "✓ 退出时关闭豆包"
+                            } else {
+                                "退出时关闭豆包"
+                            };
+                            let _ = quit_doubao_for_closure.set_text(text);
+                            log::info!("[Tray] Quit Doubao on exit: {}", s.quit_doubao_on_exit);
+                        }
+                        Err(e) => {
+                            log::error!("[Tray] Failed to save setting: {}", e);
+                        }
+                    }
+                }
+                "data_dir" => {
+                    if let Err(e) = crate::reveal_data_dir() {
+                        log::error!("[Tray] Failed to open data dir: {}", e);
+                    }
+                }
+                "edit_settings" => {
+                    if let Err(e) = crate::reveal_settings_file() {
+                        log::error!("[Tray] Failed to open settings file: {}", e);
+                    }
+                }
+                "perm_input" => crate::open_input_monitoring_settings(),
+                "perm_accessibility" => crate::open_accessibility_settings(),
+                "perm_automation" => crate::open_automation_settings(),
+                "perm_microphone" => crate::open_microphone_settings(),
                 "quit" => {
                     log::info!("[Tray] Quit");
                     app.exit(0);
                 }
-                _ => {}
+                _ => {
+                    if let Some(name) = device_choice(id) {
+                        select_input_device(app, name);
+                        return;
+                    }
+                    let toggle = SETTING_TOGGLES
+                        .iter()
+                        .zip(&toggles_for_closure)
+                        .find(|(t, _)| t.id == id);
+                    if let Some((toggle, item)) = toggle {
+                        match flip_setting(toggle) {
+                            Ok(text) => {
+                                let _ = item.set_text(text);
+                            }
+                            Err(e) => log::error!("[Tray] Failed to save setting: {}", e),
+                        }
+                    }
+                }
             }
         })
         .build(app)?;
+    // 启动时听写可能处于暂停状态
+    refresh_title(app);
+    refresh_input_devices(app);
+    watch_input_devices(app);
 
     log::info!("[Tray] Initialized");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_entries() {
+        let devices = ["MacBook 麦克风".to_string(), "USB Mic".to_string()];
+        let texts = |selected| {
+            device_entries(&devices, selected)
+                .into_iter()
+                .map(|(_, text)| text)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts(None), ["✓ 系统默认", "MacBook 麦克风", "USB Mic"]);
+        assert_eq!(
+            texts(Some("USB Mic")),
+            ["系统默认", "MacBook 麦克风", "✓ USB Mic"]
+        );
+        // 选择的设备已拔出：勾选系统默认
+        assert_eq!(
+            texts(Some("AirPods")),
+            ["✓ 系统默认", "MacBook 麦克风", "USB Mic"]
+        );
+    }
+
+    #[test]
+    fn test_recording_icon_pixels() {
+        let rgba = [0, 0, 0, 255, 0, 0, 0, 0];
+        assert_eq!(
+            tint_rgba(&rgba, RECORDING_RGB),
+            vec![0xFF, 0x3B, 0x30, 255, 0xFF, 0x3B, 0x30, 0]
+        );
+
+        // 10x10 透明图标：右下角变为不透明红点，左上角不变
+        let dotted = with_dot(&[0; 400], 10, 10, RECORDING_RGB);
+        let pixel = |x: usize, y: usize| &dotted[(y * 10 + x) * 4..(y * 10 + x) * 4 + 4];
+        assert_eq!(pixel(8, 8), &[0xFF, 0x3B, 0x30, 0xFF]);
+        assert_eq!(pixel(0, 0), &[0, 0, 0, 0]);
+        assert_eq!(pixel(9, 0), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_live_titles() {
+        assert_eq!(recording_title(Duration::from_millis(12_900)), "🔴 12s");
+        assert_eq!(result_title(34), "✓ 34字");
+    }
+
+    #[test]
+    fn test_update_item_text() {
+        assert_eq!(update_item_text(None), "检查更新");
+        assert_eq!(update_item_text(Some("0.2.0")), "⬆ 下载新版本 0.2.0");
+    }
+
+    #[test]
+    fn test_device_choice() {
+        for (id, _) in device_entries(&["USB Mic".to_string()], None) {
+            assert!(device_choice(&id).is_some());
+        }
+        assert_eq!(device_choice(INPUT_DEVICE_DEFAULT), Some(None));
+        assert_eq!(
+            device_choice("input_device:USB Mic"),
+            Some(Some("USB Mic".to_string()))
+        );
+        assert_eq!(device_choice("pause_dictation"), None);
+    }
+}
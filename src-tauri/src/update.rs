@@ -0,0 +1,479 @@
+//! 更新检查 - 查询 GitHub Releases 是否有新版本
+//!
+//! 只提示并打开下载页，不自动安装。后台每天最多检查一次，
+//! 上次检查时间保存在配置目录，重启后不会立即重复检查
+
+use crate::settings::{self, UpdateChannel};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 发布列表接口（含预发布版本）
+const RELEASES_URL: &str = "https://api.github.com/repos/ChaosRealmsAI/TypeFree/releases";
+
+/// 当前版本
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 后台检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 检查失败后的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 后台任务单次最长等待（关闭后重新开启自动检查时不用等满一天）
+const MAX_SLEEP: Duration = Duration::from_secs(60 * 60);
+
+/// 启动后首次检查前的等待，避开启动时的豆包初始化
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+
+/// 记录上次检查时间的文件（配置目录下）
+const LAST_CHECK_FILE: &str = "last_update_check";
+
+/// 检查结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpdateInfo {
+    /// 是否有比当前更新的版本
+    pub available: bool,
+    /// 最新版本号（没有新版本时为当前版本）
+    pub version: String,
+    /// 发布说明
+    pub notes: String,
+    /// 下载页地址
+    pub url: String,
+}
+
+/// 最近一次检查到的新版本（托盘「下载新版本」打开它的地址）
+static LATEST: Mutex<Option<UpdateInfo>> = Mutex::new(None);
+
+pub fn latest() -> Option<UpdateInfo> {
+    LATEST.lock().unwrap().clone()
+}
+
+// ============ 版本号 ============
+
+/// 语义化版本号（忽略构建元数据）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    /// 预发布标识（如 beta.2），正式版为空
+    pre: Vec<String>,
+}
+
+impl Version {
+    /// 解析 "1.2.3"、"v1.2.3-beta.1"，缺少的次版本号和修订号按 0 处理
+    pub fn parse(text: &str) -> Option<Version> {
+        let text = text.trim();
+        let text = text.strip_prefix(['v', 'V']).unwrap_or(text);
+        let text = text.split('+').next()?;
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (text, None),
+        };
+
+        let mut parts = core.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        let patch = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let pre = match pre {
+            Some(pre) => {
+                let ids: Vec<String> = pre.split('.').map(str::to_string).collect();
+                if ids.iter().any(|id| id.is_empty()) {
+                    return None;
+                }
+                ids
+            }
+            None => Vec::new(),
+        };
+        Some(Version {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+/// 预发布标识逐段比较：数字按数值，数字小于字母，前缀相同时段数少的小
+fn compare_pre(a: &[String], b: &[String]) -> Ordering {
+    for (x, y) in a.iter().zip(b) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // 正式版高于同号的预发布版
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => compare_pre(&self.pre, &other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// ============ 发布列表 ============
+
+/// GitHub Releases 接口返回的发布（只取用到的字段）
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+}
+
+/// 按通道选出最新的发布：正式通道跳过预发布，草稿和版本号无法解析的都跳过
+fn pick_release(releases: &[Release], channel: UpdateChannel) -> Option<(Version, &Release)> {
+    releases
+        .iter()
+        .filter(|r| !r.draft)
+        .filter_map(|r| Version::parse(&r.tag_name).map(|v| (v, r)))
+        .filter(|(v, r)| channel == UpdateChannel::Beta || (!r.prerelease && !v.is_prerelease()))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+/// 把发布列表和当前版本比较，得出检查结果
+fn evaluate(
+    releases: &[Release],
+    channel: UpdateChannel,
+    current: &str,
+) -> Result<UpdateInfo, String> {
+    let current_version =
+        Version::parse(current).ok_or_else(|| format!("Invalid current version: {}", current))?;
+    let info = match pick_release(releases, channel) {
+        Some((version, release)) if version > current_version => UpdateInfo {
+            available: true,
+            version: release.tag_name.trim_start_matches(['v', 'V']).to_string(),
+            notes: release.body.clone().unwrap_or_default().trim().to_string(),
+            url: release.html_url.clone(),
+        },
+        _ => UpdateInfo {
+            available: false,
+            version: current.to_string(),
+            notes: String::new(),
+            url: String::new(),
+        },
+    };
+    Ok(info)
+}
+
+/// 请求发布列表
+async fn fetch_releases(url: &str) -> Result<Vec<Release>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        // GitHub API 要求带 User-Agent
+        .user_agent(concat!("TypeFree/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let response = client
+        .get(url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                "Update check timed out".to_string()
+            } else {
+                format!("Failed to reach release server: {}", e)
+            }
+        })?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        return Err("Release server rate limit exceeded, try again later".to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("Release server returned HTTP {}", status.as_u16()));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read release list: {}", e))?;
+    serde_json::from_str(&body).map_err(|e| format!("Invalid release list: {}", e))
+}
+
+async fn check_at(url: &str, channel: UpdateChannel) -> Result<UpdateInfo, String> {
+    let releases = fetch_releases(url).await?;
+    let info = evaluate(&releases, channel, CURRENT_VERSION)?;
+    log::info!(
+        "[Update] Checked {:?} channel: current {}, latest {}{}",
+        channel,
+        CURRENT_VERSION,
+        info.version,
+        if info.available { " (available)" } else { "" }
+    );
+    Ok(info)
+}
+
+/// 按设置中的发布通道检查更新，有新版本时记录下来
+pub async fn check() -> Result<UpdateInfo, String> {
+    let channel = settings::get().update_channel;
+    let info = check_at(RELEASES_URL, channel).await?;
+    record_check(now_ms());
+    if info.available {
+        *LATEST.lock().unwrap() = Some(info.clone());
+    }
+    Ok(info)
+}
+
+// ============ 后台定时检查 ============
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn last_check_path() -> Option<PathBuf> {
+    settings::config_dir().map(|dir| dir.join(LAST_CHECK_FILE))
+}
+
+fn last_check() -> Option<u64> {
+    std::fs::read_to_string(last_check_path()?)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn record_check(at_ms: u64) {
+    let Some(path) = last_check_path() else {
+        return;
+    };
+    if let Err(e) = std::fs::write(&path, at_ms.to_string()) {
+        log::warn!("[Update] Failed to record check time: {}", e);
+    }
+}
+
+/// 距下次检查还要等多久（从没检查过，或时钟被调回时立即检查）
+fn next_check_delay(last_ms: Option<u64>, now_ms: u64) -> Duration {
+    let Some(last_ms) = last_ms.filter(|&last| last <= now_ms) else {
+        return Duration::ZERO;
+    };
+    CHECK_INTERVAL.saturating_sub(Duration::from_millis(now_ms - last_ms))
+}
+
+/// 后台检查循环：开启自动检查时每天一次，发现新版本时回调
+pub async fn run_daily_check(on_available: impl Fn(UpdateInfo)) {
+    tokio::time::sleep(STARTUP_DELAY).await;
+    loop {
+        if !settings::get().auto_check_updates {
+            tokio::time::sleep(MAX_SLEEP).await;
+            continue;
+        }
+        let delay = next_check_delay(last_check(), now_ms());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay.min(MAX_SLEEP)).await;
+            continue;
+        }
+
+        match check().await {
+            Ok(info) if info.available => on_available(info),
+            Ok(_) => {}
+            Err(e) => {
+                log::warn!("[Update] Background check failed: {}", e);
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    fn release(tag: &str, prerelease: bool) -> Release {
+        Release {
+            tag_name: tag.to_string(),
+            body: Some(format!("{} 更新说明", tag)),
+            html_url: format!(
+                "https://github.com/ChaosRealmsAI/TypeFree/releases/tag/{}",
+                tag
+            ),
+            draft: false,
+            prerelease,
+        }
+    }
+
+    #[test]
+    fn test_version_parse() {
+        assert_eq!(v("v1.2.3"), v("1.2.3"));
+        assert_eq!(v("1.2"), v("1.2.0"));
+        assert_eq!(v("1.2.3+build.5"), v("1.2.3"));
+        assert!(v("1.0.0-beta.1").is_prerelease());
+        assert!(Version::parse("").is_none());
+        assert!(Version::parse("latest").is_none());
+        assert!(Version::parse("1.2.3.4").is_none());
+        assert!(Version::parse("1.0.0-").is_none());
+    }
+
+    #[test]
+    fn test_version_order() {
+        // semver 规范中的示例顺序
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.2.0",
+            "1.10.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_channels() {
+        let releases = vec![
+            release("v0.3.0-beta.1", true),
+            release("v0.2.0", false),
+            release("v0.1.0", false),
+            Release {
+                draft: true,
+                ..release("v9.0.0", false)
+            },
+            release("nightly", true),
+        ];
+
+        let stable = evaluate(&releases, UpdateChannel::Stable, "0.1.0").unwrap();
+        assert!(stable.available);
+        assert_eq!(stable.version, "0.2.0");
+        assert_eq!(stable.notes, "v0.2.0 更新说明");
+        assert!(stable.url.ends_with("/v0.2.0"));
+
+        let beta = evaluate(&releases, UpdateChannel::Beta, "0.1.0").unwrap();
+        assert_eq!(beta.version, "0.3.0-beta.1");
+
+        // 已是最新（或比发布的还新）
+        let current = evaluate(&releases, UpdateChannel::Stable, "0.2.0").unwrap();
+        assert!(!current.available);
+        assert_eq!(current.version, "0.2.0");
+        assert!(
+            !evaluate(&releases, UpdateChannel::Beta, "0.3.0")
+                .unwrap()
+                .available
+        );
+        assert!(
+            !evaluate(&[], UpdateChannel::Stable, "0.1.0")
+                .unwrap()
+                .available
+        );
+    }
+
+    #[test]
+    fn test_next_check_delay() {
+        let day = CHECK_INTERVAL.as_millis() as u64;
+        assert_eq!(next_check_delay(None, 1_000), Duration::ZERO);
+        assert_eq!(next_check_delay(Some(0), day), Duration::ZERO);
+        assert_eq!(next_check_delay(Some(0), day * 3), Duration::ZERO);
+        assert_eq!(
+            next_check_delay(Some(1_000), 1_000 + day / 2),
+            Duration::from_millis(day - day / 2)
+        );
+        // 时钟被调回
+        assert_eq!(next_check_delay(Some(5_000), 1_000), Duration::ZERO);
+    }
+
+    /// 在本地端口返回一次固定的 HTTP 响应
+    fn serve_once(status: &str, body: &str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        std::thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{}/releases", addr)
+    }
+
+    #[tokio::test]
+    async fn test_check_http_handling() {
+        let body = r#"[{"tag_name":"v99.0.0","body":"修复豆包选择器","html_url":"https://example.com/r","draft":false,"prerelease":false,"assets":[]}]"#;
+        let info = check_at(&serve_once("200 OK", body), UpdateChannel::Stable)
+            .await
+            .unwrap();
+        assert!(info.available);
+        assert_eq!(info.version, "99.0.0");
+        assert_eq!(info.notes, "修复豆包选择器");
+
+        let err = check_at(&serve_once("403 Forbidden", "{}"), UpdateChannel::Stable)
+            .await
+            .unwrap_err();
+        assert!(err.contains("rate limit"), "{}", err);
+
+        let err = check_at(
+            &serve_once("500 Internal Server Error", ""),
+            UpdateChannel::Stable,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("500"), "{}", err);
+
+        let err = check_at(&serve_once("200 OK", "<html>"), UpdateChannel::Stable)
+            .await
+            .unwrap_err();
+        assert!(err.contains("Invalid release list"), "{}", err);
+    }
+}
@@ -0,0 +1,841 @@
+//! 音频采集 - 累积到一帧（默认 1600 samples，即 100ms）再发送
+//!
+//! 重采样算法由设置 `resample_method` 选择，每次录音开始时确定
+
+use crate::cue::{self, Cue, StartCueGate};
+use crate::latency::{SessionTimeline, Stage};
+use crate::limiter::{self, ClipMode, SampleConverter};
+use crate::resample::{ResampleMethod, SincBudget, StreamResampler};
+use crate::ring_buffer;
+use crate::silence::rms;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// ASR 需要的采样率
+const TARGET_SAMPLE_RATE: u32 = 16000;
+
+/// 帧大小允许范围（16kHz 采样数，20ms ~ 512ms）
+const MIN_CHUNK_SAMPLES: usize = 320;
+const MAX_CHUNK_SAMPLES: usize = 8192;
+
+/// 判定为语音的 RMS 阈值（16-bit PCM）
+const SPEECH_RMS_THRESHOLD: f64 = 500.0;
+
+/// 回调与录音线程之间的缓冲时长（秒），录音线程卡顿超过这么久才会丢数据
+const RING_SECONDS: usize = 2;
+
+/// 录音线程取数据的间隔
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+// ============ 错误 ============
+
+/// 麦克风打开失败的原因（解决办法不同，分别提示）
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioError {
+    /// 没有输入设备
+    NoDevice,
+    /// 没有麦克风权限
+    PermissionDenied,
+    /// 设备的采样格式或配置不支持
+    UnsupportedFormat(String),
+    /// 设置的输入通道超出设备通道数（通道从 0 开始）
+    ChannelOutOfRange { channel: u16, channels: u16 },
+    /// 其他设备或音频流错误
+    Device(String),
+}
+
+impl AudioError {
+    /// 给用户看的提示
+    pub fn user_message(&self) -> String {
+        match self {
+            AudioError::NoDevice => "未找到麦克风设备".to_string(),
+            AudioError::PermissionDenied => {
+                "没有麦克风权限，请在系统设置中允许 TypeFree 使用麦克风".to_string()
+            }
+            AudioError::UnsupportedFormat(format) => format!("麦克风格式不支持（{}）", format),
+            AudioError::ChannelOutOfRange { channel, channels } => format!(
+                "所选输入通道 {} 不存在（设备只有 {} 个通道），请在设置中重新选择",
+                channel + 1,
+                channels
+            ),
+            AudioError::Device(e) => format!("麦克风打开失败：{}", e),
+        }
+    }
+}
+
+impl std::fmt::Display for AudioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AudioError::NoDevice => write!(f, "No input device"),
+            AudioError::PermissionDenied => write!(f, "Microphone permission denied"),
+            AudioError::UnsupportedFormat(format) => write!(f, "Unsupported format: {}", format),
+            AudioError::ChannelOutOfRange { channel, channels } => {
+                write!(f, "Channel {} out of range ({} channels)", channel, channels)
+            }
+            AudioError::Device(e) => write!(f, "Device error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// 无法细分的设备错误：没有麦克风权限时归为权限问题
+fn device_error(e: impl std::fmt::Display) -> AudioError {
+    if crate::permissions::check_microphone() {
+        AudioError::Device(e.to_string())
+    } else {
+        AudioError::PermissionDenied
+    }
+}
+
+impl From<cpal::DefaultStreamConfigError> for AudioError {
+    fn from(e: cpal::DefaultStreamConfigError) -> Self {
+        match e {
+            cpal::DefaultStreamConfigError::DeviceNotAvailable => AudioError::NoDevice,
+            cpal::DefaultStreamConfigError::StreamTypeNotSupported => {
+                AudioError::UnsupportedFormat(e.to_string())
+            }
+            e => device_error(e),
+        }
+    }
+}
+
+impl From<cpal::BuildStreamError> for AudioError {
+    fn from(e: cpal::BuildStreamError) -> Self {
+        match e {
+            cpal::BuildStreamError::DeviceNotAvailable => AudioError::NoDevice,
+            cpal::BuildStreamError::StreamConfigNotSupported => {
+                AudioError::UnsupportedFormat(e.to_string())
+            }
+            e => device_error(e),
+        }
+    }
+}
+
+impl From<cpal::PlayStreamError> for AudioError {
+    fn from(e: cpal::PlayStreamError) -> Self {
+        match e {
+            cpal::PlayStreamError::DeviceNotAvailable => AudioError::NoDevice,
+            e => device_error(e),
+        }
+    }
+}
+
+/// 最近一次打开麦克风的错误（设置页诊断用），成功打开后清除
+static LAST_ERROR: Mutex<Option<AudioError>> = Mutex::new(None);
+
+/// 最近一次打开麦克风的错误
+pub fn last_error() -> Option<AudioError> {
+    LAST_ERROR.lock().unwrap().clone()
+}
+
+/// 记录打开麦克风的结果
+fn record_result<T>(result: Result<T, AudioError>) -> Result<T, AudioError> {
+    match &result {
+        Ok(_) => *LAST_ERROR.lock().unwrap() = None,
+        Err(e) => {
+            log::error!("[Audio] {}", e);
+            *LAST_ERROR.lock().unwrap() = Some(e.clone());
+        }
+    }
+    result
+}
+
+// ============ 采集 ============
+
+/// 最近一次录音使用的输入设备
+static LAST_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// 最近一次录音使用的输入设备名称
+pub fn last_device_name() -> Option<String> {
+    LAST_DEVICE.lock().unwrap().clone()
+}
+
+/// 输入设备信息（设置页选择通道用）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InputDevice {
+    pub name: String,
+    /// 默认配置下的通道数
+    pub channels: u16,
+    pub is_default: bool,
+}
+
+/// 设置中选择的输入设备，未设置或已拔出时使用系统默认设备
+fn input_device(host: &cpal::Host) -> Result<cpal::Device, AudioError> {
+    if let Some(preferred) = crate::settings::get().input_device {
+        let found = host
+            .input_devices()
+            .map_err(device_error)?
+            .find(|device| device.name().is_ok_and(|name| name == preferred));
+        match found {
+            Some(device) => return Ok(device),
+            None => log::warn!(
+                "[Audio] Input device '{}' not found, using system default",
+                preferred
+            ),
+        }
+    }
+    host.default_input_device().ok_or(AudioError::NoDevice)
+}
+
+/// 列出所有输入设备及其通道数
+pub fn list_input_devices() -> Result<Vec<InputDevice>, AudioError> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+    let devices = host.input_devices().map_err(device_error)?;
+
+    Ok(devices
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let channels = device.default_input_config().ok()?.channels();
+            Some(InputDevice {
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                name,
+                channels,
+            })
+        })
+        .collect())
+}
+
+// ============ 设备插拔通知 ============
+
+static DEVICE_CHANGE_CALLBACK: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+/// 监听输入设备插拔，有变化时调用 `on_change`（在系统线程上调用，应尽快返回）
+///
+/// 只能注册一次；返回 false 表示当前平台不支持或注册失败，调用方需要自己刷新设备列表
+pub fn watch_input_devices(on_change: impl Fn() + Send + Sync + 'static) -> bool {
+    if DEVICE_CHANGE_CALLBACK.set(Box::new(on_change)).is_err() {
+        log::warn!("[Audio] Device watcher already registered");
+        return false;
+    }
+    let watching = register_device_notifications();
+    if watching {
+        log::info!("[Audio] Watching input device changes");
+    } else {
+        log::warn!("[Audio] Device change notifications unavailable");
+    }
+    watching
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn notify_device_change() {
+    if let Some(on_change) = DEVICE_CHANGE_CALLBACK.get() {
+        on_change();
+    }
+}
+
+/// 监听 CoreAudio 设备列表的变化
+#[cfg(target_os = "macos")]
+fn register_device_notifications() -> bool {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    struct AudioObjectPropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+
+    type ListenerProc =
+        unsafe extern "C" fn(u32, u32, *const AudioObjectPropertyAddress, *mut c_void) -> i32;
+
+    #[link(name = "CoreAudio", kind = "framework")]
+    extern "C" {
+        fn AudioObjectAddPropertyListener(
+            object: u32,
+            address: *const AudioObjectPropertyAddress,
+            listener: ListenerProc,
+            client_data: *mut c_void,
+        ) -> i32;
+    }
+
+    const SYSTEM_OBJECT: u32 = 1;
+    /// kAudioHardwarePropertyDevices ('dev#')
+    const PROPERTY_DEVICES: u32 = u32::from_be_bytes(*b"dev#");
+    /// kAudioObjectPropertyScopeGlobal ('glob')
+    const SCOPE_GLOBAL: u32 = u32::from_be_bytes(*b"glob");
+    const ELEMENT_MAIN: u32 = 0;
+
+    unsafe extern "C" fn on_devices_changed(
+        _: u32,
+        _: u32,
+        _: *const AudioObjectPropertyAddress,
+        _: *mut c_void,
+    ) -> i32 {
+        notify_device_change();
+        0
+    }
+
+    let address = AudioObjectPropertyAddress {
+        selector: PROPERTY_DEVICES,
+        scope: SCOPE_GLOBAL,
+        element: ELEMENT_MAIN,
+    };
+    let status = unsafe {
+        AudioObjectAddPropertyListener(
+            SYSTEM_OBJECT,
+            &address,
+            on_devices_changed,
+            std::ptr::null_mut(),
+        )
+    };
+    if status != 0 {
+        log::warn!("[Audio] AudioObjectAddPropertyListener failed: {}", status);
+    }
+    status == 0
+}
+
+/// 用仅接收消息的窗口接收音频设备接口的插拔通知（USB、蓝牙麦克风等）
+#[cfg(target_os = "windows")]
+fn register_device_notifications() -> bool {
+    use winapi::shared::guiddef::GUID;
+    use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+    use winapi::shared::windef::HWND;
+    use winapi::um::dbt::{
+        DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_DEVICEINTERFACE,
+        DEV_BROADCAST_DEVICEINTERFACE_W,
+    };
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::winuser::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW,
+        RegisterDeviceNotificationW, TranslateMessage, DEVICE_NOTIFY_WINDOW_HANDLE, HWND_MESSAGE,
+        MSG, WM_DEVICECHANGE, WNDCLASSW,
+    };
+
+    /// 音频设备接口类 KSCATEGORY_AUDIO
+    const KSCATEGORY_AUDIO: GUID = GUID {
+        Data1: 0x6994_ad04,
+        Data2: 0x93ef,
+        Data3: 0x11d0,
+        Data4: [0xa3, 0xcc, 0x00, 0xa0, 0xc9, 0x22, 0x31, 0x96],
+    };
+
+    unsafe extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        if msg == WM_DEVICECHANGE
+            && matches!(wparam as u32, DBT_DEVICEARRIVAL | DBT_DEVICEREMOVECOMPLETE)
+        {
+            notify_device_change();
+        }
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || unsafe {
+        let class_name: Vec<u16> = "TypeFreeDeviceWatcher\0".encode_utf16().collect();
+        let instance = GetModuleHandleW(std::ptr::null());
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance,
+            lpszClassName: class_name.as_ptr(),
+            ..std::mem::zeroed()
+        };
+        RegisterClassW(&class);
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            std::ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null_mut(),
+        );
+        if hwnd.is_null() {
+            let _ = ready_tx.send(false);
+            return;
+        }
+
+        let mut filter: DEV_BROADCAST_DEVICEINTERFACE_W = std::mem::zeroed();
+        filter.dbcc_size = std::mem::size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as u32;
+        filter.dbcc_devicetype = DBT_DEVTYP_DEVICEINTERFACE;
+        filter.dbcc_classguid = KSCATEGORY_AUDIO;
+        let registered = !RegisterDeviceNotificationW(
+            hwnd as _,
+            &mut filter as *mut _ as *mut _,
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        )
+        .is_null();
+        let _ = ready_tx.send(registered);
+        if !registered {
+            return;
+        }
+
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    });
+    ready_rx.recv().unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn register_device_notifications() -> bool {
+    false
+}
+
+/// 录音活动信息（录音线程写入，会话读取）
+#[derive(Default)]
+pub struct AudioActivity {
+    /// 第一次检测到语音能量的时间
+    first_speech: OnceLock<Instant>,
+    /// 已采集的采样数（16kHz）
+    captured_samples: AtomicUsize,
+    /// 最后一段语音结束时已采集的时长（毫秒），0 表示还没有语音
+    speech_end_ms: AtomicU64,
+    /// 能量达到语音阈值的采样数（16kHz，累计）
+    speech_samples: AtomicUsize,
+    /// 各帧 RMS 的最大值（f64 的位表示，非负数的位表示与数值同序）
+    peak_rms: AtomicU64,
+    /// Sinc 重采样太慢，本次会话已降级为线性
+    resample_fallback: AtomicBool,
+    /// 缓冲已满、被整段丢弃的音频回调数
+    dropped_callbacks: AtomicUsize,
+    /// 会话时间线（记录第一段音频的时间）
+    timeline: SessionTimeline,
+}
+
+impl AudioActivity {
+    pub fn with_timeline(timeline: SessionTimeline) -> Self {
+        Self {
+            timeline,
+            ..Default::default()
+        }
+    }
+
+    /// 第一次检测到语音的时间，None 表示还没有语音
+    pub fn first_speech(&self) -> Option<Instant> {
+        self.first_speech.get().copied()
+    }
+
+    /// 已采集的音频时长（毫秒）
+    pub fn captured_ms(&self) -> u64 {
+        (self.captured_samples.load(Ordering::SeqCst) / 16) as u64
+    }
+
+    /// 最后一段语音结束时已采集的时长（毫秒），None 表示还没有语音
+    pub fn speech_end_ms(&self) -> Option<u64> {
+        Some(self.speech_end_ms.load(Ordering::SeqCst)).filter(|&ms| ms > 0)
+    }
+
+    /// 累计的语音时长（毫秒，只计能量达到语音阈值的音频）
+    pub fn speech_ms(&self) -> u64 {
+        (self.speech_samples.load(Ordering::SeqCst) / 16) as u64
+    }
+
+    /// 录音中出现过的最大帧能量
+    pub fn peak_rms(&self) -> f64 {
+        f64::from_bits(self.peak_rms.load(Ordering::Relaxed))
+    }
+
+    /// 本次会话是否因 CPU 压力降级了重采样
+    pub fn resample_fell_back(&self) -> bool {
+        self.resample_fallback.load(Ordering::SeqCst)
+    }
+
+    /// 被丢弃的音频回调数（录音线程跟不上时增加）
+    pub fn dropped_callbacks(&self) -> usize {
+        self.dropped_callbacks.load(Ordering::Relaxed)
+    }
+
+    /// 根据 chunk 能量更新活动状态（不加锁，其他线程 panic 也不影响录音）
+    fn observe(&self, samples: &[i16]) {
+        self.timeline.mark(Stage::FirstAudio);
+        let level = rms(samples);
+        let captured =
+            self.captured_samples.fetch_add(samples.len(), Ordering::SeqCst) + samples.len();
+        self.peak_rms.fetch_max(level.to_bits(), Ordering::Relaxed);
+
+        if level >= SPEECH_RMS_THRESHOLD {
+            self.speech_samples.fetch_add(samples.len(), Ordering::SeqCst);
+            self.speech_end_ms.store((captured / 16) as u64, Ordering::SeqCst);
+            if self.first_speech.set(Instant::now()).is_ok() {
+                log::info!("[Audio] Speech detected");
+            }
+        }
+    }
+}
+
+/// 会话内的重采样状态：Sinc/多相持续跟不上实时就降级为线性
+struct SessionResample {
+    resampler: StreamResampler,
+    budget: SincBudget,
+}
+
+impl SessionResample {
+    fn new(method: ResampleMethod, sample_rate: u32) -> Self {
+        Self {
+            resampler: StreamResampler::new(method, sample_rate, TARGET_SAMPLE_RATE),
+            budget: SincBudget::default(),
+        }
+    }
+
+    /// 把单声道采样转换为 16kHz，非线性算法时统计耗时
+    fn run(&mut self, mono: &[i16], sample_rate: u32, activity: &AudioActivity) -> Vec<i16> {
+        if self.resampler.method() == ResampleMethod::Linear {
+            return self.resampler.process(mono);
+        }
+
+        let start = Instant::now();
+        let samples = self.resampler.process(mono);
+        let audio = Duration::from_secs_f64(mono.len() as f64 / sample_rate as f64);
+        if self.budget.record(start.elapsed(), audio) {
+            self.resampler.fall_back_to_linear();
+            activity.resample_fallback.store(true, Ordering::SeqCst);
+        }
+        samples
+    }
+}
+
+/// 回调写入的原始采样（按设备采样格式），附带复用的取出缓冲
+enum RawSamples {
+    /// f32 采样另带转换器，限制器的增益跨回调保持
+    F32(ring_buffer::Consumer<f32>, Vec<f32>, SampleConverter),
+    I16(ring_buffer::Consumer<i16>, Vec<i16>),
+}
+
+impl RawSamples {
+    /// 取出已到达的全部原始采样，转换为 16kHz 单声道
+    fn drain(
+        &mut self,
+        sample_rate: u32,
+        channels: u16,
+        channel: Option<u16>,
+        resample: &mut SessionResample,
+        activity: &AudioActivity,
+    ) -> Vec<i16> {
+        match self {
+            RawSamples::F32(consumer, raw, converter) => {
+                raw.clear();
+                if consumer.pop_into(raw) == 0 {
+                    return Vec::new();
+                }
+                let mono = f32_to_mono(raw, channels, channel, converter);
+                resample.run(&mono, sample_rate, activity)
+            }
+            RawSamples::I16(consumer, raw) => {
+                raw.clear();
+                if consumer.pop_into(raw) == 0 {
+                    return Vec::new();
+                }
+                let mono = i16_to_mono(raw, channels, channel);
+                resample.run(&mono, sample_rate, activity)
+            }
+        }
+    }
+}
+
+/// 提高录音线程的调度优先级，系统繁忙（如后台编译）时也能及时取走音频
+fn raise_thread_priority() {
+    #[cfg(target_os = "macos")]
+    {
+        const QOS_CLASS_USER_INTERACTIVE: u32 = 0x21;
+        extern "C" {
+            fn pthread_set_qos_class_self_np(qos_class: u32, relative_priority: i32) -> i32;
+        }
+        let result = unsafe { pthread_set_qos_class_self_np(QOS_CLASS_USER_INTERACTIVE, 0) };
+        if result != 0 {
+            log::warn!("[Audio] Failed to set thread QoS (error: {})", result);
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winapi::um::processthreadsapi::{GetCurrentThread, SetThreadPriority};
+        use winapi::um::winbase::THREAD_PRIORITY_TIME_CRITICAL;
+
+        let ok = unsafe {
+            SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL as i32)
+        };
+        if ok == 0 {
+            log::warn!("[Audio] Failed to raise thread priority");
+        }
+    }
+}
+
+/// 预热麦克风 - 在启动时调用，触发系统权限弹窗
+/// 这样用户第一次使用时就不会卡掉语音
+pub fn warmup_microphone() {
+    log::info!("[Audio] Warming up microphone to trigger permission prompt...");
+
+    std::thread::spawn(|| {
+        let _ = record_result(run_warmup());
+    });
+}
+
+/// 打开一个短暂的输入流来触发权限弹窗
+fn run_warmup() -> Result<(), AudioError> {
+    let host = cpal::default_host();
+    let device = host.default_input_device().ok_or(AudioError::NoDevice)?;
+    let config = device.default_input_config()?;
+
+    let stream = device.build_input_stream(
+        &cpal::StreamConfig {
+            channels: config.channels(),
+            sample_rate: config.sample_rate(),
+            buffer_size: cpal::BufferSize::Default,
+        },
+        |_data: &[f32], _: &cpal::InputCallbackInfo| {
+            // 不做任何处理，只是为了触发权限
+        },
+        |err| log::warn!("[Audio] Warmup stream error: {}", err),
+        None,
+    )?;
+    stream.play()?;
+
+    // 运行 100ms 就够了
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    log::info!("[Audio] Microphone warmup complete");
+    Ok(())
+}
+
+/// 单次录音的参数（每次会话从设置读取）
+#[derive(Debug, Clone, Copy)]
+pub struct RecordingOptions {
+    /// 每帧的 16kHz 采样数
+    pub chunk_samples: usize,
+    /// 只取该通道（从 0 开始），None 表示混合所有通道
+    pub channel: Option<u16>,
+    /// 重采样算法，Auto 在打开设备后按采样率确定
+    pub resample: ResampleMethod,
+    /// f32 输入超出范围时的处理
+    pub clip: ClipMode,
+    /// 发出第一帧时播放开始提示音
+    pub start_cue: bool,
+}
+
+impl RecordingOptions {
+    pub fn from_settings(settings: &crate::settings::Settings) -> Self {
+        Self {
+            chunk_samples: settings.audio_chunk_samples,
+            channel: settings.input_channel,
+            resample: settings.resample_method,
+            clip: settings.clip_mode,
+            start_cue: settings.sound_cues,
+        }
+    }
+}
+
+/// 开始录音
+///
+/// 每累积 `chunk_samples` 个 16kHz 采样发送一帧，停止时剩余数据无论多少都会发送。
+/// 音频流开始播放后才返回，打开失败时返回具体原因。
+pub fn start_recording(
+    tx: Sender<Vec<u8>>,
+    stop_flag: Arc<AtomicBool>,
+    activity: Arc<AudioActivity>,
+    options: RecordingOptions,
+) -> Result<std::thread::JoinHandle<()>, AudioError> {
+    record_result(open_recording(tx, stop_flag, activity, options))
+}
+
+fn open_recording(
+    tx: Sender<Vec<u8>>,
+    stop_flag: Arc<AtomicBool>,
+    activity: Arc<AudioActivity>,
+    options: RecordingOptions,
+) -> Result<std::thread::JoinHandle<()>, AudioError> {
+    let chunk_size = options.chunk_samples.clamp(MIN_CHUNK_SAMPLES, MAX_CHUNK_SAMPLES);
+    let channel = options.channel;
+    let host = cpal::default_host();
+    let device = input_device(&host)?;
+
+    let device_name = device.name().map_err(device_error)?;
+    log::info!("[Audio] Device: {}", device_name);
+    *LAST_DEVICE.lock().unwrap() = Some(device_name);
+
+    let config = device.default_input_config()?;
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels();
+
+    if let Some(channel) = channel {
+        if channel >= channels {
+            return Err(AudioError::ChannelOutOfRange { channel, channels });
+        }
+    }
+
+    // 本次会话的重采样算法（Auto 按采样率确定）
+    let method = options.resample.resolve(sample_rate, TARGET_SAMPLE_RATE);
+
+    log::info!(
+        "[Audio] Config: {}Hz, {} channels, format: {:?}, chunk: {} samples, channel: {:?}, resample: {:?}",
+        sample_rate,
+        channels,
+        config.sample_format(),
+        chunk_size,
+        channel,
+        method
+    );
+
+    // 音频流只能在录音线程中创建，结果通过 ready 通道返回
+    let (ready_tx, ready_rx) = std::sync::mpsc::sync_channel::<Result<(), AudioError>>(1);
+
+    let handle = std::thread::spawn(move || {
+        raise_thread_priority();
+
+        let stream_config = cpal::StreamConfig {
+            channels,
+            sample_rate: config.sample_rate(),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let capacity = sample_rate as usize * channels as usize * RING_SECONDS;
+
+        // 回调只把原始采样拷进环形缓冲，混音和重采样在本线程进行
+        let (stream, mut raw) = match config.sample_format() {
+            cpal::SampleFormat::F32 => {
+                let (mut producer, consumer) = ring_buffer::channel::<f32>(capacity);
+                let activity_clone = activity.clone();
+                let stream = device.build_input_stream(
+                    &stream_config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                        if !producer.push_slice(data) {
+                            activity_clone.dropped_callbacks.fetch_add(1, Ordering::Relaxed);
+                        }
+                    },
+                    |err| log::error!("[Audio] Stream error (F32): {}", err),
+                    None,
+                );
+                let converter = SampleConverter::new(options.clip, sample_rate);
+                (stream, RawSamples::F32(consumer, Vec::new(), converter))
+            }
+            cpal::SampleFormat::I16 => {
+                let (mut producer, consumer) = ring_buffer::channel::<i16>(capacity);
+                let activity_clone = activity.clone();
+                let stream = device.build_input_stream(
+                    &stream_config,
+                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                        if !producer.push_slice(data) {
+                            activity_clone.dropped_callbacks.fetch_add(1, Ordering::Relaxed);
+                        }
+                    },
+                    |err| log::error!("[Audio] Stream error (I16): {}", err),
+                    None,
+                );
+                (stream, RawSamples::I16(consumer, Vec::new()))
+            }
+            format => {
+                let error = AudioError::UnsupportedFormat(format!("{:?}", format));
+                let _ = ready_tx.send(Err(error));
+                return;
+            }
+        };
+
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e.into()));
+                return;
+            }
+        };
+
+        if let Err(e) = stream.play() {
+            let _ = ready_tx.send(Err(e.into()));
+            return;
+        }
+
+        log::info!("[Audio] Recording started");
+        let _ = ready_tx.send(Ok(()));
+
+        let mut session_resample = SessionResample::new(method, sample_rate);
+        let mut cue_gate = StartCueGate::new(options.start_cue);
+        let mut buffer: Vec<i16> = Vec::with_capacity(chunk_size * 2);
+        loop {
+            // 先读停止标志再取数据，保证停止前到达的采样都会发送
+            let stopping = stop_flag.load(Ordering::SeqCst);
+            let samples =
+                raw.drain(sample_rate, channels, channel, &mut session_resample, &activity);
+            buffer.extend(samples);
+
+            // 达到一帧就发送
+            while buffer.len() >= chunk_size {
+                let mut chunk: Vec<i16> = buffer.drain(..chunk_size).collect();
+                if cue_gate.apply(&mut chunk) {
+                    cue::play(Cue::Start);
+                }
+                activity.observe(&chunk);
+                let bytes: Vec<u8> = chunk.iter().flat_map(|&s| s.to_le_bytes()).collect();
+                let _ = tx.send(bytes);
+            }
+
+            if stopping {
+                break;
+            }
+            std::thread::sleep(DRAIN_INTERVAL);
+        }
+        drop(stream);
+
+        log::info!("[Audio] Stop flag received, flushing buffer");
+
+        // 发送剩余数据
+        if !buffer.is_empty() {
+            log::info!("[Audio] Sending remaining {} samples", buffer.len());
+            let bytes: Vec<u8> = buffer.iter().flat_map(|&s| s.to_le_bytes()).collect();
+            let _ = tx.send(bytes);
+        }
+
+        let dropped = activity.dropped_callbacks();
+        if dropped > 0 {
+            log::warn!("[Audio] Dropped {} callbacks (ring buffer full)", dropped);
+        }
+        log::info!("[Audio] Recording stopped");
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Ok(handle),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(AudioError::Device("录音线程异常退出".to_string())),
+    }
+}
+
+/// f32 → mono i16 samples
+///
+/// 先在 f32 下混合通道，再由 `converter` 限幅并四舍五入，只取整一次
+fn f32_to_mono(
+    data: &[f32],
+    channels: u16,
+    channel: Option<u16>,
+    converter: &mut SampleConverter,
+) -> Vec<i16> {
+    match channel {
+        Some(channel) if channels > 1 => data
+            .chunks_exact(channels as usize)
+            .map(|frame| converter.convert(frame[channel as usize]))
+            .collect(),
+        None if channels > 1 => data
+            .chunks(channels as usize)
+            .map(|chunk| converter.convert(chunk.iter().sum::<f32>() / chunk.len() as f32))
+            .collect(),
+        _ => data.iter().map(|&s| converter.convert(s)).collect(),
+    }
+}
+
+/// i16 → mono samples（重采样由 SessionResample 完成）
+///
+/// `channel` 为 None 时混合所有通道，否则只取该通道（调用方已校验范围）
+fn i16_to_mono(data: &[i16], channels: u16, channel: Option<u16>) -> Vec<i16> {
+    match channel {
+        Some(channel) if channels > 1 => data
+            .chunks_exact(channels as usize)
+            .map(|frame| frame[channel as usize])
+            .collect(),
+        // 多通道 → mono
+        None if channels > 1 => data
+            .chunks(channels as usize)
+            .map(limiter::mix_i16)
+            .collect(),
+        _ => data.to_vec(),
+    }
+}
@@ -0,0 +1,177 @@
+//! 调试录音 - 把发给 ASR 的 16kHz 单声道 PCM 按会话保存为 WAV
+//!
+//! 识别出错时用来判断是录音（重采样、混音、增益）的问题还是识别本身的问题。
+//! 文件在日志目录下的 audio 目录，只保留最近几次
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const SAMPLE_RATE: u32 = 16000;
+const FILE_PREFIX: &str = "session-";
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// 设置保存目录（启动时调用一次）
+pub fn init(log_dir: PathBuf) {
+    let _ = DIR.set(log_dir.join("audio"));
+}
+
+pub fn dir() -> Option<PathBuf> {
+    DIR.get().cloned()
+}
+
+/// 16-bit 单声道 PCM 的 WAV 文件头
+fn wav_header(data_len: u32) -> [u8; 44] {
+    let byte_rate = SAMPLE_RATE * 2;
+    let mut header = [0u8; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&(36 + data_len).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes()); // PCM
+    header[22..24].copy_from_slice(&1u16.to_le_bytes()); // 单声道
+    header[24..28].copy_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    header[32..34].copy_from_slice(&2u16.to_le_bytes()); // 每帧字节数
+    header[34..36].copy_from_slice(&16u16.to_le_bytes()); // 位深
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// 一次会话的录音文件
+pub struct AudioDump {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    data_len: u32,
+}
+
+impl AudioDump {
+    /// 设置开启时为本次会话创建文件，创建失败只记录日志
+    pub fn start() -> Option<Self> {
+        if !crate::settings::get().debug_audio_dump {
+            return None;
+        }
+        let dir = DIR.get()?;
+        match Self::create(dir) {
+            Ok(dump) => Some(dump),
+            Err(e) => {
+                log::warn!("[AudioDump] Failed to create dump file: {}", e);
+                None
+            }
+        }
+    }
+
+    fn create(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = dir.join(format!("{}{}.wav", FILE_PREFIX, millis));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        // 长度先写 0，结束时补上
+        writer.write_all(&wav_header(0))?;
+        Ok(Self {
+            writer,
+            path,
+            data_len: 0,
+        })
+    }
+
+    /// 追加一段 PCM（写入失败时丢弃，不影响识别）
+    pub fn write(&mut self, pcm: &[u8]) {
+        if self.writer.write_all(pcm).is_ok() {
+            self.data_len = self.data_len.saturating_add(pcm.len() as u32);
+        }
+    }
+
+    /// 补全文件头并清理旧文件
+    pub fn finish(mut self) {
+        let result = self
+            .writer
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.writer.write_all(&wav_header(self.data_len)))
+            .and_then(|_| self.writer.flush());
+        match result {
+            Ok(()) => log::info!(
+                "[AudioDump] Saved {}ms to {}",
+                self.data_len as u64 * 1000 / (SAMPLE_RATE as u64 * 2),
+                self.path.display()
+            ),
+            Err(e) => log::warn!(
+                "[AudioDump] Failed to finish {}: {}",
+                self.path.display(),
+                e
+            ),
+        }
+
+        if let Some(dir) = self.path.parent() {
+            prune(dir, crate::settings::get().debug_audio_keep);
+        }
+    }
+}
+
+/// 超出保留数量的旧文件名（文件名含时间戳，按名称排序即按时间排序）
+fn expired(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.retain(|name| name.starts_with(FILE_PREFIX) && name.ends_with(".wav"));
+    names.sort();
+    let excess = names.len().saturating_sub(keep);
+    names.truncate(excess);
+    names
+}
+
+fn prune(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let names = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    for name in expired(names, keep) {
+        if let Err(e) = std::fs::remove_file(dir.join(&name)) {
+            log::warn!("[AudioDump] Failed to remove {}: {}", name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_header() {
+        let header = wav_header(3200);
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(header[4..8].try_into().unwrap()), 3236);
+        assert_eq!(
+            u32::from_le_bytes(header[24..28].try_into().unwrap()),
+            16000
+        );
+        assert_eq!(
+            u32::from_le_bytes(header[28..32].try_into().unwrap()),
+            32000
+        );
+        assert_eq!(&header[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(header[40..44].try_into().unwrap()), 3200);
+    }
+
+    #[test]
+    fn test_expired() {
+        let names = [
+            "session-1700000000300.wav",
+            "session-1700000000100.wav",
+            "notes.txt",
+            "session-1700000000200.wav",
+        ]
+        .map(String::from)
+        .to_vec();
+        // 只删除最早的，其他文件不动
+        assert_eq!(expired(names.clone(), 2), ["session-1700000000100.wav"]);
+        assert!(expired(names.clone(), 5).is_empty());
+        assert_eq!(expired(names, 0).len(), 3);
+    }
+}
@@ -0,0 +1,242 @@
+//! 测试用的模拟 CDP 服务
+//!
+//! 在本地端口上提供 /json/list、/json/version 和页面调试 WebSocket，
+//! 按每个测试的脚本响应，不需要安装豆包桌面端；Page.navigate 会修改页面 URL
+
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+/// 豆包桌面端的 User-Agent
+pub const DOUBAO_UA: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 \
+     (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36 SamanthaDoubao/1.85.8";
+
+/// 模拟服务的响应脚本
+#[derive(Debug, Clone)]
+pub struct MockScript {
+    /// /json/version 的 Browser 字段
+    pub browser: String,
+    /// /json/version 的 User-Agent 字段
+    pub user_agent: String,
+    /// 页面 URL（初始值），webSocketDebuggerUrl 自动指向模拟服务
+    pub pages: Vec<String>,
+    /// Network.getCookies 返回的 Cookie：(name, value, domain)
+    pub cookies: Vec<(&'static str, &'static str, &'static str)>,
+    /// Runtime.evaluate 的返回值：表达式包含关键字时返回对应的值，都不匹配返回 null
+    pub evaluate: Vec<(&'static str, Value)>,
+    /// 点击语音按钮后推送的 Network.webSocketCreated 事件 URL
+    pub websocket_created: Option<String>,
+    /// Page.navigate 后页面实际停留的 URL（模拟未登录时跳到登录页），None 为导航目标
+    pub redirect: Option<String>,
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self {
+            browser: "Chrome/135.0.7049.115".to_string(),
+            user_agent: DOUBAO_UA.to_string(),
+            pages: vec!["https://www.doubao.com/chat/1".to_string()],
+            cookies: Vec::new(),
+            evaluate: Vec::new(),
+            websocket_created: None,
+            redirect: None,
+        }
+    }
+}
+
+/// 运行中的模拟服务（线程随测试进程退出）
+pub struct MockCdp {
+    addr: SocketAddr,
+    methods: Arc<Mutex<Vec<String>>>,
+}
+
+/// 模拟服务的共享状态
+struct MockState {
+    script: MockScript,
+    /// 当前页面 URL
+    pages: Mutex<Vec<String>>,
+    methods: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCdp {
+    pub fn start(script: MockScript) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let methods = Arc::new(Mutex::new(Vec::new()));
+        let state = Arc::new(MockState {
+            pages: Mutex::new(script.pages.clone()),
+            script,
+            methods: methods.clone(),
+        });
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = state.clone();
+                std::thread::spawn(move || handle_connection(stream, addr, &state));
+            }
+        });
+
+        Self { addr, methods }
+    }
+
+    /// HTTP 接口地址（替代 http://127.0.0.1:9222）
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// 收到的 CDP 命令（按顺序）
+    pub fn methods(&self) -> Vec<String> {
+        self.methods.lock().unwrap().clone()
+    }
+}
+
+/// 按请求路径分发：/devtools/ 走 WebSocket，其余按 HTTP 处理
+fn handle_connection(stream: TcpStream, addr: SocketAddr, state: &MockState) {
+    let mut head = [0u8; 64];
+    let Ok(n) = stream.peek(&mut head) else {
+        return;
+    };
+    let request_line = String::from_utf8_lossy(&head[..n]);
+    if let Some(path) = request_line.split_whitespace().nth(1) {
+        if let Some(target) = path.strip_prefix("/devtools/") {
+            // 页面调试地址 /devtools/page/<序号>
+            let page = target
+                .strip_prefix("page/")
+                .and_then(|index| index.parse().ok());
+            serve_devtools(stream, page, state);
+            return;
+        }
+    }
+    serve_http(stream, addr, state);
+}
+
+fn serve_http(mut stream: TcpStream, addr: SocketAddr, state: &MockState) {
+    let script = &state.script;
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+
+    let body = match path {
+        "/json/version" => json!({
+            "Browser": script.browser,
+            "Protocol-Version": "1.3",
+            "User-Agent": script.user_agent,
+            "webSocketDebuggerUrl": format!("ws://{}/devtools/browser/mock", addr),
+        }),
+        "/json/list" | "/json" => state
+            .pages
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(i, url)| {
+                json!({
+                    "id": i.to_string(),
+                    "type": "page",
+                    "title": "豆包",
+                    "url": url,
+                    "webSocketDebuggerUrl": format!("ws://{}/devtools/page/{}", addr, i),
+                })
+            })
+            .collect(),
+        _ => {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            return;
+        }
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn serve_devtools(stream: TcpStream, page: Option<usize>, state: &MockState) {
+    let script = &state.script;
+    let Ok(mut ws) = tungstenite::accept(stream) else {
+        return;
+    };
+
+    while let Ok(msg) = ws.read() {
+        let Message::Text(text) = msg else {
+            continue;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        state.methods.lock().unwrap().push(method.clone());
+
+        let mut events = Vec::new();
+        let result = match method.as_str() {
+            "Network.getCookies" => {
+                let cookies: Vec<Value> = script
+                    .cookies
+                    .iter()
+                    .map(|(name, value, domain)| {
+                        json!({ "name": name, "value": value, "domain": domain })
+                    })
+                    .collect();
+                json!({ "cookies": cookies })
+            }
+            "Runtime.evaluate" => {
+                let expression = request["params"]["expression"].as_str().unwrap_or_default();
+                // 开始录音的点击脚本返回 'clicked'，页面随后建立 ASR 连接
+                if expression.contains("'clicked'") {
+                    if let Some(url) = &script.websocket_created {
+                        events.push(json!({
+                            "method": "Network.webSocketCreated",
+                            "params": { "requestId": "mock.1", "url": url },
+                        }));
+                    }
+                }
+                let value = script
+                    .evaluate
+                    .iter()
+                    .find(|(key, _)| expression.contains(key))
+                    .map(|(_, value)| value.clone())
+                    .unwrap_or(Value::Null);
+                json!({ "result": { "value": value } })
+            }
+            "Page.navigate" => {
+                let url = script
+                    .redirect
+                    .as_deref()
+                    .or(request["params"]["url"].as_str())
+                    .unwrap_or_default();
+                if let Some(i) = page {
+                    if let Some(current) = state.pages.lock().unwrap().get_mut(i) {
+                        *current = url.to_string();
+                    }
+                }
+                let loaded =
+                    json!({ "method": "Page.loadEventFired", "params": { "timestamp": 0 } });
+                events.push(loaded);
+                json!({ "frameId": "mock" })
+            }
+            _ => json!({}),
+        };
+
+        let response = json!({ "id": request["id"], "result": result });
+        if ws.send(Message::Text(response.to_string())).is_err() {
+            return;
+        }
+        for event in events {
+            if ws.send(Message::Text(event.to_string())).is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! 上传音频的编码 - 默认 PCM，编译时启用 `opus` feature 后可选 Opus 节省上行流量
+
+use serde::{Deserialize, Serialize};
+
+/// 录音输出的采样率
+#[cfg(feature = "opus")]
+const SAMPLE_RATE: u32 = 16000;
+/// Opus 每包 20ms
+#[cfg(any(feature = "opus", test))]
+const OPUS_FRAME_SAMPLES: usize = 320;
+/// 单个 Opus 包的最大字节数
+#[cfg(feature = "opus")]
+const OPUS_MAX_PACKET: usize = 1275;
+
+/// 上传音频的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    /// 16kHz 16-bit 单声道 PCM（服务端确认支持）
+    #[default]
+    Pcm,
+    /// Opus，每 20ms 一个包
+    Opus,
+}
+
+impl AudioFormat {
+    /// ASR URL 中 `format` 参数的值
+    pub fn url_value(self) -> &'static str {
+        match self {
+            AudioFormat::Pcm => "pcm",
+            AudioFormat::Opus => "opus",
+        }
+    }
+}
+
+/// 按格式编码要发送的音频
+pub enum Encoder {
+    Pcm,
+    #[cfg(feature = "opus")]
+    Opus {
+        encoder: opus::Encoder,
+        /// 不足一包的采样
+        pending: Vec<i16>,
+    },
+}
+
+impl Encoder {
+    /// 创建编码器，格式不可用时退回 PCM
+    pub fn new(format: AudioFormat) -> Self {
+        match format {
+            AudioFormat::Pcm => Encoder::Pcm,
+            #[cfg(feature = "opus")]
+            AudioFormat::Opus => {
+                let encoder =
+                    opus::Encoder::new(SAMPLE_RATE, opus::Channels::Mono, opus::Application::Voip);
+                match encoder {
+                    Ok(encoder) => Encoder::Opus {
+                        encoder,
+                        pending: Vec::new(),
+                    },
+                    Err(e) => {
+                        log::warn!("[Codec] Failed to create Opus encoder: {}, using PCM", e);
+                        Encoder::Pcm
+                    }
+                }
+            }
+            #[cfg(not(feature = "opus"))]
+            AudioFormat::Opus => {
+                log::warn!("[Codec] Built without Opus support, using PCM");
+                Encoder::Pcm
+            }
+        }
+    }
+
+    /// 实际使用的格式
+    pub fn format(&self) -> AudioFormat {
+        match self {
+            Encoder::Pcm => AudioFormat::Pcm,
+            #[cfg(feature = "opus")]
+            Encoder::Opus { .. } => AudioFormat::Opus,
+        }
+    }
+
+    /// 编码一条消息（16-bit LE PCM），返回要发送的数据包
+    pub fn encode(&mut self, pcm: Vec<u8>) -> Vec<Vec<u8>> {
+        match self {
+            Encoder::Pcm => vec![pcm],
+            #[cfg(feature = "opus")]
+            Encoder::Opus { encoder, pending } => {
+                pending.extend(pcm.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])));
+                take_frames(pending, OPUS_FRAME_SAMPLES)
+                    .iter()
+                    .filter_map(|frame| encode_opus(encoder, frame))
+                    .collect()
+            }
+        }
+    }
+
+    /// 结束时把不足一包的剩余音频补静音后编码
+    pub fn flush(&mut self) -> Vec<Vec<u8>> {
+        match self {
+            Encoder::Pcm => Vec::new(),
+            #[cfg(feature = "opus")]
+            Encoder::Opus { encoder, pending } => {
+                if pending.is_empty() {
+                    return Vec::new();
+                }
+                let mut frame = std::mem::take(pending);
+                frame.resize(OPUS_FRAME_SAMPLES, 0);
+                encode_opus(encoder, &frame).into_iter().collect()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "opus")]
+fn encode_opus(encoder: &mut opus::Encoder, frame: &[i16]) -> Option<Vec<u8>> {
+    match encoder.encode_vec(frame, OPUS_MAX_PACKET) {
+        Ok(packet) => Some(packet),
+        Err(e) => {
+            log::error!("[Codec] Opus encode error: {}", e);
+            None
+        }
+    }
+}
+
+/// 取出所有完整的帧，不足一帧的留在 `pending`
+#[cfg(any(feature = "opus", test))]
+fn take_frames(pending: &mut Vec<i16>, frame_samples: usize) -> Vec<Vec<i16>> {
+    let complete = pending.len() / frame_samples * frame_samples;
+    let rest = pending.split_off(complete);
+    let frames = pending.chunks(frame_samples).map(<[i16]>::to_vec).collect();
+    *pending = rest;
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcm_passthrough() {
+        let mut encoder = Encoder::new(AudioFormat::Pcm);
+        assert_eq!(encoder.format(), AudioFormat::Pcm);
+        assert_eq!(encoder.encode(vec![1, 2, 3, 4]), vec![vec![1, 2, 3, 4]]);
+        assert!(encoder.flush().is_empty());
+    }
+
+    #[test]
+    fn test_take_frames_keeps_remainder() {
+        let mut pending: Vec<i16> = (0..(OPUS_FRAME_SAMPLES * 2 + 5) as i16).collect();
+        let frames = take_frames(&mut pending, OPUS_FRAME_SAMPLES);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1][0], OPUS_FRAME_SAMPLES as i16);
+        assert_eq!(pending.len(), 5);
+    }
+
+    #[cfg(feature = "opus")]
+    #[test]
+    fn test_opus_packets_per_20ms() {
+        let mut encoder = Encoder::new(AudioFormat::Opus);
+        assert_eq!(encoder.format(), AudioFormat::Opus);
+        // 100ms 音频 = 5 个包
+        let pcm: Vec<u8> = (0..1600).flat_map(|i: i16| (i % 200).to_le_bytes()).collect();
+        let packets = encoder.encode(pcm);
+        assert_eq!(packets.len(), 5);
+        assert!(packets.iter().all(|p| !p.is_empty() && p.len() < 640));
+        assert!(encoder.flush().is_empty());
+    }
+}
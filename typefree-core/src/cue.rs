@@ -0,0 +1,223 @@
+//! 提示音 - 录音真正开始和结果粘贴后播放的短音
+//!
+//! 音色在代码中合成，在单独的线程上通过默认输出设备播放，不阻塞录音和粘贴；
+//! 系统处于勿扰模式或演示时不播放
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// 每个音符的时长
+const NOTE_MS: u32 = 40;
+
+/// 音符首尾的淡入淡出时长（避免爆音）
+const FADE_MS: u32 = 5;
+
+/// 开始提示音播放后静音的录音时长（16kHz 采样数）：音长 + 输出延迟余量，
+/// 避免扬声器外放的提示音被麦克风录进去
+const START_GATE_SAMPLES: usize = 16 * 200;
+
+/// 提示音
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cue {
+    /// 开始采集（上扬的两个音）
+    Start,
+    /// 结果已粘贴（下降的两个音）
+    Stop,
+}
+
+impl Cue {
+    /// 两个音符的频率
+    fn notes(self) -> [f32; 2] {
+        match self {
+            Cue::Start => [660.0, 990.0],
+            Cue::Stop => [990.0, 660.0],
+        }
+    }
+}
+
+/// 合成提示音（单声道，幅度不超过 volume）
+fn synthesize(cue: Cue, sample_rate: u32, volume: f32) -> Vec<f32> {
+    let volume = volume.clamp(0.0, 1.0);
+    let note_len = (sample_rate * NOTE_MS / 1000) as usize;
+    let fade_len = (sample_rate * FADE_MS / 1000).max(1) as usize;
+
+    let mut samples = Vec::with_capacity(note_len * 2);
+    for freq in cue.notes() {
+        for i in 0..note_len {
+            let envelope = (i.min(note_len - 1 - i) as f32 / fade_len as f32).min(1.0);
+            let phase = 2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32;
+            samples.push(phase.sin() * envelope * volume);
+        }
+    }
+    samples
+}
+
+// ============ 录音静音闸门 ============
+
+/// 录音中的开始提示音闸门
+///
+/// 第一帧照常发送并触发提示音，之后一小段时间内的采样置零
+#[derive(Debug)]
+pub struct StartCueGate {
+    /// None 表示还没发出第一帧
+    muting: Option<usize>,
+    enabled: bool,
+}
+
+impl StartCueGate {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            muting: None,
+            enabled,
+        }
+    }
+
+    /// 处理一帧，返回是否应该现在播放开始提示音
+    pub fn apply(&mut self, chunk: &mut [i16]) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match &mut self.muting {
+            None => {
+                self.muting = Some(START_GATE_SAMPLES);
+                true
+            }
+            Some(remaining) => {
+                let n = (*remaining).min(chunk.len());
+                chunk[..n].fill(0);
+                *remaining -= n;
+                false
+            }
+        }
+    }
+}
+
+// ============ 播放 ============
+
+/// 播放线程（按顺序播放，避免重叠）
+static PLAYER: LazyLock<Sender<Cue>> = LazyLock::new(|| {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || run_player(rx));
+    tx
+});
+
+/// 播放提示音（立即返回；设置关闭或勿扰、演示时不播放）
+pub fn play(cue: Cue) {
+    let _ = PLAYER.send(cue);
+}
+
+fn run_player(rx: Receiver<Cue>) {
+    for cue in rx {
+        let settings = crate::settings::get();
+        if !settings.sound_cues {
+            continue;
+        }
+        if crate::focus_state::is_quiet() {
+            log::info!("[Cue] Quiet mode is on, skipping {:?} cue", cue);
+            continue;
+        }
+        if let Err(e) = play_blocking(cue, settings.sound_cue_volume) {
+            log::warn!("[Cue] Failed to play {:?} cue: {}", cue, e);
+        }
+    }
+}
+
+/// 在默认输出设备上播放，播完才返回
+fn play_blocking(cue: Cue, volume: f32) -> Result<(), String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("No output device")?;
+    let config = device.default_output_config().map_err(|e| e.to_string())?;
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels() as usize;
+
+    let samples = synthesize(cue, sample_rate, volume);
+    let duration = Duration::from_secs_f64(samples.len() as f64 / sample_rate as f64);
+    let mut position = 0;
+    let mut next_sample = move || {
+        let sample = samples.get(position).copied().unwrap_or(0.0);
+        position += 1;
+        sample
+    };
+
+    let stream_config = cpal::StreamConfig {
+        channels: config.channels(),
+        sample_rate: config.sample_rate(),
+        buffer_size: cpal::BufferSize::Default,
+    };
+    let on_error = |err: cpal::StreamError| log::warn!("[Cue] Output stream error: {}", err);
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_output_stream(
+            &stream_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill(next_sample());
+                }
+            },
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::I16 => device.build_output_stream(
+            &stream_config,
+            move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    frame.fill((next_sample() * i16::MAX as f32) as i16);
+                }
+            },
+            on_error,
+            None,
+        ),
+        format => return Err(format!("Unsupported output format: {:?}", format)),
+    }
+    .map_err(|e| e.to_string())?;
+
+    stream.play().map_err(|e| e.to_string())?;
+    // 多等一点，让输出缓冲里的尾音播完
+    std::thread::sleep(duration + Duration::from_millis(100));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthesize() {
+        let samples = synthesize(Cue::Start, 48000, 0.5);
+        assert_eq!(samples.len(), 48 * NOTE_MS as usize * 2);
+        assert!(samples.iter().all(|s| s.abs() <= 0.5));
+        // 首尾淡入淡出
+        assert!(samples[0].abs() < 0.01);
+        assert!(samples.last().unwrap().abs() < 0.01);
+        assert!(samples.iter().any(|s| s.abs() > 0.4));
+
+        assert!(synthesize(Cue::Stop, 16000, 3.0)
+            .iter()
+            .all(|s| s.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_start_gate() {
+        let mut gate = StartCueGate::new(true);
+        let mut first = vec![100i16; 1600];
+        assert!(gate.apply(&mut first));
+        assert!(first.iter().all(|&s| s == 100));
+
+        // 接下来 200ms 置零，之后恢复
+        let mut chunks = vec![vec![100i16; 1600]; 3];
+        for chunk in &mut chunks {
+            assert!(!gate.apply(chunk));
+        }
+        assert!(chunks[0].iter().all(|&s| s == 0));
+        assert!(chunks[1].iter().all(|&s| s == 0));
+        assert!(chunks[2].iter().all(|&s| s == 100));
+
+        let mut disabled = StartCueGate::new(false);
+        let mut chunk = vec![100i16; 1600];
+        assert!(!disabled.apply(&mut chunk));
+        assert!(!disabled.apply(&mut chunk));
+        assert!(chunk.iter().all(|&s| s == 100));
+    }
+}
@@ -0,0 +1,1886 @@
+//! 豆包 ASR (语音识别) 客户端
+//!
+//! 使用 Rust WebSocket 直接连接豆包 ASR 服务
+
+use crate::audio_dump::AudioDump;
+use crate::codec::{self, AudioFormat};
+use crate::doubao_cdp;
+use crate::error::TypeFreeError;
+use crate::latency::{SessionTimeline, Stage as LatencyStage};
+use crate::silence::SilenceTrimmer;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc as tokio_mpsc;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+/// ASR 结果回调
+pub type ResultCallback = Box<dyn Fn(&str, bool) + Send + Sync>;
+
+/// 旧版帧大小（采样数），服务端确认接受
+const LEGACY_FRAME_SAMPLES: usize = 4096;
+
+/// 每条 WebSocket 消息合并的音频帧数（服务端拒绝小帧后改为 2）
+static FRAMES_PER_MESSAGE: AtomicUsize = AtomicUsize::new(1);
+
+/// 单次会话的选项
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    /// 覆盖缓存模板中的 URL 参数（如 language）
+    pub url_overrides: Vec<(String, String)>,
+    /// 上传音频的格式
+    pub audio_format: AudioFormat,
+    /// 高级设置中的覆盖，最后合并
+    pub advanced: AsrOverrides,
+    /// 本次会话的时间线（记录连接、首个结果、finish 等时间点）
+    pub timeline: SessionTimeline,
+}
+
+// ============ 高级覆盖 ============
+
+/// 不允许覆盖的请求头（WebSocket 握手需要；Host 由设置 asr_host 决定）
+const PROTECTED_HEADERS: [&str; 5] = [
+    "host",
+    "connection",
+    "upgrade",
+    "sec-websocket-key",
+    "sec-websocket-version",
+];
+
+/// 高级设置：ASR 请求的 URL 参数和请求头覆盖
+///
+/// 优先于捕获的参数模板和本次会话的参数（如 language、format），
+/// 豆包调整接口时可以不重新编译直接试验
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsrOverrides {
+    /// URL 查询参数
+    pub url_params: BTreeMap<String, String>,
+    /// 额外的 WebSocket 请求头（同名时替换）
+    pub headers: BTreeMap<String, String>,
+}
+
+/// URL 参数名只允许非保留字符
+fn is_url_safe(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+}
+
+impl AsrOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.url_params.is_empty() && self.headers.is_empty()
+    }
+
+    /// URL 参数覆盖（用于 override_url_params）
+    pub fn url_param_pairs(&self) -> Vec<(String, String)> {
+        self.url_params
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// 检查参数名、参数值和请求头（保存设置和建立连接前调用）
+    pub fn validate(&self) -> Result<(), String> {
+        for (key, value) in &self.url_params {
+            if !is_url_safe(key) {
+                return Err(format!("URL 参数名只能包含字母、数字和 - _ . ~：{}", key));
+            }
+            if value.contains(|c: char| c.is_whitespace() || matches!(c, '&' | '#')) {
+                return Err(format!("URL 参数 {} 的值不能包含空白、& 或 #", key));
+            }
+        }
+        for (name, value) in &self.headers {
+            if http::HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(format!("请求头名称不正确：{}", name));
+            }
+            if PROTECTED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                return Err(format!("请求头 {} 由连接过程决定，不能覆盖", name));
+            }
+            if http::HeaderValue::from_str(value).is_err() {
+                return Err(format!("请求头 {} 的值不正确", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 覆盖了哪些参数和请求头（只列名称，值可能敏感）
+    fn describe(&self) -> String {
+        let names = |map: &BTreeMap<String, String>| {
+            map.keys().cloned().collect::<Vec<_>>().join(", ")
+        };
+        format!(
+            "url params [{}], headers [{}]",
+            names(&self.url_params),
+            names(&self.headers)
+        )
+    }
+}
+
+/// 构建 ASR WebSocket 握手请求，高级设置中的请求头最后合并
+fn build_request(
+    url: &str,
+    asr_info: &doubao_cdp::AsrRequestInfo,
+    cookie: &str,
+    overrides: &AsrOverrides,
+) -> Result<http::Request<()>, String> {
+    let mut request = http::Request::builder()
+        .uri(url)
+        .header("Origin", &asr_info.origin)
+        .header("Cookie", cookie)
+        .header("User-Agent", &asr_info.user_agent)
+        .header("Host", &asr_info.host)
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", tokio_tungstenite::tungstenite::handshake::client::generate_key())
+        .body(())
+        .map_err(|e| with_overrides(format!("Failed to build request: {}", e), overrides))?;
+
+    for (name, value) in &overrides.headers {
+        let name = http::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("Invalid header override {}: {}", name, e))?;
+        let value = http::HeaderValue::from_str(value)
+            .map_err(|e| format!("Invalid value for header override {}: {}", name, e))?;
+        request.headers_mut().insert(name, value);
+    }
+    Ok(request)
+}
+
+// ============ 请求预览 ============
+
+/// 即将发送的 ASR 请求（排查认证问题用），默认隐藏设备标识和 Cookie 值
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestPreview {
+    pub url: String,
+    /// 请求头（名称, 值），Cookie 默认只显示名称，覆盖的请求头默认隐藏值
+    pub headers: Vec<(String, String)>,
+    /// URL 参数的来源（捕获、缓存或内置）
+    pub param_source: doubao_cdp::ParamSource,
+    /// 是否有上一次获取的 Cookie（没有时实际会话会重新获取）
+    pub cookies_cached: bool,
+    /// 高级设置覆盖的 URL 参数
+    pub overridden_params: Vec<String>,
+    /// 高级设置覆盖的请求头
+    pub overridden_headers: Vec<String>,
+    /// 是否隐藏了敏感值
+    pub masked: bool,
+}
+
+/// 按最近一次缓存的请求信息预览下一次会话的请求，不连接豆包
+pub fn request_preview(reveal: bool) -> Result<RequestPreview, TypeFreeError> {
+    let cookie = doubao_cdp::get_cached_cookies();
+    let advanced = crate::settings::get().asr_overrides;
+    build_preview(&get_asr_request_info(), cookie.as_deref(), &advanced, reveal)
+}
+
+fn build_preview(
+    info: &doubao_cdp::AsrRequestInfo,
+    cookie: Option<&str>,
+    advanced: &AsrOverrides,
+    reveal: bool,
+) -> Result<RequestPreview, TypeFreeError> {
+    let url = doubao_cdp::override_url_params(
+        &doubao_cdp::build_session_url(info),
+        &advanced.url_param_pairs(),
+    );
+    let request = build_request(&url, info, cookie.unwrap_or(""), advanced)
+        .map_err(TypeFreeError::InvalidOverride)?;
+    let headers = request
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_str().unwrap_or("<binary>");
+            let overridden = advanced
+                .headers
+                .keys()
+                .any(|key| name.as_str().eq_ignore_ascii_case(key));
+            let value = if reveal {
+                value.to_string()
+            } else if overridden {
+                // 覆盖的请求头可能是令牌，整值隐藏
+                "***".to_string()
+            } else if name == http::header::COOKIE {
+                mask_cookie(value)
+            } else {
+                value.to_string()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect();
+
+    Ok(RequestPreview {
+        url: if reveal { url } else { doubao_cdp::redact_url(&url) },
+        headers,
+        param_source: info.param_source,
+        cookies_cached: cookie.is_some(),
+        overridden_params: advanced.url_params.keys().cloned().collect(),
+        overridden_headers: advanced.headers.keys().cloned().collect(),
+        masked: !reveal,
+    })
+}
+
+/// 只保留 Cookie 名称
+fn mask_cookie(header: &str) -> String {
+    header
+        .split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| format!("{}=***", pair.split('=').next().unwrap_or(pair)))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 有高级覆盖时在错误中注明，便于判断是不是覆盖导致的
+fn with_overrides(error: String, overrides: &AsrOverrides) -> String {
+    if overrides.is_empty() {
+        error
+    } else {
+        format!("{} (with advanced overrides: {})", error, overrides.describe())
+    }
+}
+
+/// 区分握手失败的原因：带覆盖时归咎于覆盖，401/403 说明登录已失效
+fn connect_error(error: &tokio_tungstenite::tungstenite::Error, overrides: &AsrOverrides) -> TypeFreeError {
+    let detail = format!("Failed to connect ASR WebSocket: {}", error);
+    if !overrides.is_empty() {
+        return TypeFreeError::InvalidOverride(with_overrides(detail, overrides));
+    }
+    match error {
+        tokio_tungstenite::tungstenite::Error::Http(response)
+            if matches!(response.status().as_u16(), 401 | 403) =>
+        {
+            TypeFreeError::LoginExpired(detail)
+        }
+        _ => TypeFreeError::WsConnect(detail),
+    }
+}
+
+/// 单次 ASR 会话统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionStats {
+    /// 每帧采样数（16kHz）
+    pub frame_samples: usize,
+    /// 每条消息合并的帧数
+    pub frames_per_message: usize,
+    /// 发送的音频消息数
+    pub messages_sent: usize,
+    /// 发送的音频字节数
+    pub bytes_sent: usize,
+    /// 获取 Cookie 到 WebSocket 连接完成的耗时
+    pub connect_ms: u64,
+    /// 首条音频发出到第一个识别结果的耗时
+    pub first_partial_ms: Option<u64>,
+    /// 识别结果数量
+    pub partials: usize,
+    /// 服务端中途报错，最终结果是报错前的识别结果
+    pub degraded: bool,
+    /// 静音裁剪：开头裁掉的时长（毫秒）
+    pub trimmed_leading_ms: u64,
+    /// 静音裁剪：结尾裁掉的时长（毫秒）
+    pub trimmed_trailing_ms: u64,
+    /// 录音回调因缓冲区满丢弃的次数（由调用方在录音结束后填入）
+    pub dropped_callbacks: usize,
+    /// 会话结束时最后一个中间结果的文字
+    pub partial_final: Option<String>,
+    /// finish 消息自带的最终文字（服务端没给时为 None），优先于中间结果交付
+    pub server_final: Option<String>,
+    #[serde(skip)]
+    first_audio_at: Option<Instant>,
+    #[serde(skip)]
+    timeline: SessionTimeline,
+}
+
+// ============ 识别结果 ============
+
+/// 一次识别结果（`result` 字段），服务端没给的字段保持默认值
+///
+/// 兼容 `Text`/`Utterances` 和 `text`/`utterances` 两种字段命名
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AsrResult {
+    #[serde(rename(deserialize = "Text"), alias = "text", default)]
+    pub text: String,
+    /// 分句信息，服务端不返回时为空
+    #[serde(rename(deserialize = "Utterances"), alias = "utterances", default)]
+    pub utterances: Vec<Utterance>,
+    /// 实际使用的识别语言（交付时填写，服务端不返回）
+    #[serde(skip_deserializing)]
+    pub language: Option<String>,
+}
+
+/// 分句（时间单位为毫秒）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Utterance {
+    #[serde(rename(deserialize = "Text"), alias = "text", default)]
+    pub text: String,
+    #[serde(rename(deserialize = "StartTime"), alias = "start_time", default)]
+    pub start_ms: Option<u64>,
+    #[serde(rename(deserialize = "EndTime"), alias = "end_time", default)]
+    pub end_ms: Option<u64>,
+    /// 该句已确定，后续结果不会再修改
+    #[serde(rename(deserialize = "Definite"), alias = "definite", default)]
+    pub definite: bool,
+    /// 逐词时间，服务端不返回时为空
+    #[serde(rename(deserialize = "Words"), alias = "words", default)]
+    pub words: Vec<Word>,
+}
+
+/// 单个词（时间单位为毫秒）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    #[serde(rename(deserialize = "Text"), alias = "text", default)]
+    pub text: String,
+    #[serde(rename(deserialize = "StartTime"), alias = "start_time", default)]
+    pub start_ms: Option<u64>,
+    #[serde(rename(deserialize = "EndTime"), alias = "end_time", default)]
+    pub end_ms: Option<u64>,
+}
+
+/// 识别结果在消息中的位置（按顺序尝试）
+const RESULT_PATHS: [&[&str]; 4] = [
+    &["result"],
+    &["results"],
+    &["payload", "result"],
+    &["data", "result"],
+];
+
+/// 有这些字段的对象就是识别结果本身
+const RESULT_KEYS: [&str; 4] = ["Text", "text", "Utterances", "utterances"];
+
+/// 识别结果再包一层时的字段
+const NESTED_RESULT_KEYS: [&str; 2] = ["results", "result"];
+
+impl AsrResult {
+    /// 从一条 result 消息中取出识别结果，兼容服务端用过的几种结构：
+    /// `result` 为对象、字符串或候选数组（取第一个），顶层 `results` 数组，
+    /// 以及包在 `payload`/`data` 里的。都不匹配时在 debug 日志中记录原始消息，便于发现新格式
+    pub fn from_message(data: &serde_json::Value) -> Option<Self> {
+        let found = RESULT_PATHS.iter().find_map(|path| {
+            path.iter()
+                .try_fold(data, |value, key| value.get(key))
+                .and_then(Self::from_shape)
+        });
+        if found.is_none() {
+            log::debug!("[DoubaoASR] Unrecognized result payload: {}", data);
+        }
+        found
+    }
+
+    fn from_shape(value: &serde_json::Value) -> Option<Self> {
+        use serde_json::Value;
+        match value {
+            Value::String(text) => Some(Self {
+                text: text.clone(),
+                ..Default::default()
+            }),
+            // 多个候选时第一个是最优结果
+            Value::Array(items) => items.first().and_then(Self::from_shape),
+            Value::Object(map) if RESULT_KEYS.iter().any(|key| map.contains_key(*key)) => {
+                Self::parse(value)
+            }
+            Value::Object(map) => NESTED_RESULT_KEYS
+                .iter()
+                .find_map(|key| map.get(*key))
+                .and_then(Self::from_shape),
+            _ => None,
+        }
+    }
+
+    /// 解析 `result` 字段；结构化解析失败时退回只取文本，只有分句时用分句拼出全文
+    pub fn parse(value: &serde_json::Value) -> Option<Self> {
+        match serde_json::from_value::<Self>(value.clone()) {
+            Ok(mut result) => {
+                if result.text.is_empty() {
+                    result.text = join_utterances(&result.utterances);
+                }
+                Some(result)
+            }
+            Err(e) => {
+                log::debug!("[DoubaoASR] Unexpected result shape ({}), using text only", e);
+                let text = value.get("Text").or_else(|| value.get("text"))?.as_str()?;
+                Some(Self {
+                    text: text.to_string(),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    /// 已确定的分句
+    pub fn definite_utterances(&self) -> impl Iterator<Item = &Utterance> {
+        self.utterances.iter().filter(|u| u.definite)
+    }
+}
+
+/// 拼接分句文字，英文单词之间补空格
+fn join_utterances(utterances: &[Utterance]) -> String {
+    let mut text = String::new();
+    for utterance in utterances {
+        let part = utterance.text.trim();
+        let needs_space = text.ends_with(|c: char| c.is_ascii_graphic())
+            && part.starts_with(|c: char| c.is_ascii_alphanumeric());
+        if needs_space {
+            text.push(' ');
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+/// 服务端返回的错误
+#[derive(Debug, Clone)]
+pub struct ServerError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ServerError {
+    /// 给用户看的提示
+    pub fn user_message(&self) -> &'static str {
+        server_message(self.code)
+    }
+}
+
+/// 服务端错误码对应的提示
+pub fn server_message(code: i64) -> &'static str {
+    match code {
+        671000003 => "请求太频繁，请稍后再试",
+        710022002 => "服务暂时不可用，请稍后再试",
+        _ => "语音识别出错，请重试",
+    }
+}
+
+/// ASR 会话错误
+#[derive(Debug)]
+pub enum AsrError {
+    /// 会话建立前失败（获取 Cookie、连接 WebSocket 等）
+    Connect(TypeFreeError),
+    /// 会话中服务端报错，报错前的识别结果已交付（stats.degraded）
+    Server {
+        error: ServerError,
+        stats: SessionStats,
+    },
+}
+
+impl std::fmt::Display for AsrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsrError::Connect(e) => write!(f, "{}", e),
+            AsrError::Server { error, .. } => {
+                write!(f, "ASR error: code={}, message={}", error.code, error.message)
+            }
+        }
+    }
+}
+
+impl From<TypeFreeError> for AsrError {
+    fn from(e: TypeFreeError) -> Self {
+        AsrError::Connect(e)
+    }
+}
+
+// ============ 连续失败冷却 ============
+
+/// 窗口内失败多少次后暂停自动重试
+const COOLDOWN_FAILURES: usize = 3;
+
+/// 统计连续失败的时间窗口
+const COOLDOWN_WINDOW: Duration = Duration::from_secs(120);
+
+/// 暂停时长，到期后放行一次尝试
+const COOLDOWN_DURATION: Duration = Duration::from_secs(300);
+
+/// 连续失败计数
+///
+/// Cookie 失效时每次按键都会重新取 Cookie、捕获参数、连接服务，
+/// 窗口内失败够多次后直接拒绝新会话，直到手动重试、连接检测通过或 Cookie 变化（重新登录）
+#[derive(Debug)]
+struct FailureGate {
+    /// 窗口内的失败时间
+    failures: Vec<Instant>,
+    cooling_since: Option<Instant>,
+    /// 进入冷却时缓存的 Cookie
+    cookies: Option<String>,
+    /// 冷却到期后的试探：再失败一次立即重新冷却
+    probing: bool,
+}
+
+impl FailureGate {
+    const fn new() -> Self {
+        Self {
+            failures: Vec::new(),
+            cooling_since: None,
+            cookies: None,
+            probing: false,
+        }
+    }
+
+    /// 记录一次失败，返回是否因此进入冷却
+    fn record_failure(&mut self, now: Instant, cookies: Option<&str>) -> bool {
+        self.failures.retain(|&at| now.duration_since(at) <= COOLDOWN_WINDOW);
+        self.failures.push(now);
+        if self.cooling_since.is_none()
+            && (self.probing || self.failures.len() >= COOLDOWN_FAILURES)
+        {
+            self.cooling_since = Some(now);
+            self.cookies = cookies.map(str::to_string);
+            self.probing = false;
+            return true;
+        }
+        false
+    }
+
+    /// 冷却中返回失败次数；到期或拿到了新的 Cookie 时解除（到期后转为试探）
+    fn check(&mut self, now: Instant, cookies: Option<&str>) -> Option<usize> {
+        let since = self.cooling_since?;
+        if cookies.is_some() && cookies != self.cookies.as_deref() {
+            log::info!("[DoubaoASR] Cookies changed, cooldown cleared");
+            self.reset();
+            return None;
+        }
+        if now.duration_since(since) < COOLDOWN_DURATION {
+            return Some(self.failures.len().max(COOLDOWN_FAILURES));
+        }
+        self.cooling_since = None;
+        self.failures.clear();
+        self.probing = true;
+        None
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+static FAILURE_GATE: Mutex<FailureGate> = Mutex::new(FailureGate::new());
+
+/// 当前是否暂停了自动重试（冷却中返回 `TypeFreeError::CoolingDown`）
+pub fn check_cooldown() -> Result<(), TypeFreeError> {
+    let cookies = doubao_cdp::get_cached_cookies();
+    match FAILURE_GATE
+        .lock()
+        .unwrap()
+        .check(Instant::now(), cookies.as_deref())
+    {
+        Some(failures) => Err(TypeFreeError::CoolingDown(failures)),
+        None => Ok(()),
+    }
+}
+
+/// 上次成功以来窗口内的失败次数（字幕模式据此退避）
+pub fn recent_failures() -> usize {
+    FAILURE_GATE.lock().unwrap().failures.len()
+}
+
+/// 手动重试（重新捕获参数、恢复豆包等）或连接检测通过时解除冷却
+pub fn reset_cooldown() {
+    let mut gate = FAILURE_GATE.lock().unwrap();
+    if gate.cooling_since.is_some() {
+        log::info!("[DoubaoASR] Cooldown cleared");
+    }
+    gate.reset();
+}
+
+/// 是否计入连续失败：参数覆盖写错、让位于听写不是服务端的问题；
+/// 服务端中途报错但已交付结果说明连接可用
+fn counts_as_failure(error: &AsrError) -> bool {
+    match error {
+        AsrError::Connect(TypeFreeError::InvalidOverride(_) | TypeFreeError::CaptureCancelled) => {
+            false
+        }
+        AsrError::Connect(_) => true,
+        AsrError::Server { stats, .. } => !stats.degraded,
+    }
+}
+
+/// 记录会话结果
+fn record_session_result(result: &Result<SessionStats, AsrError>) {
+    let cookies = doubao_cdp::get_cached_cookies();
+    let mut gate = FAILURE_GATE.lock().unwrap();
+    match result {
+        Err(e) if counts_as_failure(e) => {
+            if gate.record_failure(Instant::now(), cookies.as_deref()) {
+                log::warn!(
+                    "[DoubaoASR] {} failures within {}s, pausing sessions for {}s",
+                    gate.failures.len(),
+                    COOLDOWN_WINDOW.as_secs(),
+                    COOLDOWN_DURATION.as_secs()
+                );
+            }
+        }
+        Err(_) => {}
+        Ok(_) => gate.reset(),
+    }
+}
+
+/// 获取 ASR 请求信息（优先使用缓存，否则用默认值）
+fn get_asr_request_info() -> doubao_cdp::AsrRequestInfo {
+    doubao_cdp::get_cached_asr_request().unwrap_or_default()
+}
+
+/// 运行 ASR 会话
+///
+/// - `audio_rx`: 音频数据接收端 (PCM 16-bit, 16kHz, mono)
+/// - `stop_flag`: 停止标志
+/// - `superseded`: 被新会话取代，不再等待最终结果，立即交付已有文本
+/// - `on_partial`: 中间结果文本
+/// - `on_final`: 最终结果（含分句信息）
+///
+/// 返回本次会话的统计信息；连续失败后进入冷却，期间直接返回 `CoolingDown`
+pub async fn run_asr_session(
+    audio_rx: Receiver<Vec<u8>>,
+    options: SessionOptions,
+    trimmer: Option<SilenceTrimmer>,
+    stop_flag: Arc<AtomicBool>,
+    superseded: Arc<AtomicBool>,
+    on_partial: impl Fn(&str) + Send + 'static,
+    on_final: impl Fn(&AsrResult) + Send + 'static,
+) -> Result<SessionStats, AsrError> {
+    check_cooldown()?;
+    let result = connect_and_run(
+        audio_rx, options, trimmer, stop_flag, superseded, on_partial, on_final,
+    )
+    .await;
+    record_session_result(&result);
+    result
+}
+
+/// 在调用方建立的连接上运行会话（不获取 Cookie，不计入失败冷却），
+/// 用于自定义传输层和测试；其余参数同 [`run_asr_session`]
+pub async fn run_on_transport<T: AsrTransport>(
+    transport: T,
+    audio_rx: Receiver<Vec<u8>>,
+    audio_format: AudioFormat,
+    stop_flag: Arc<AtomicBool>,
+    superseded: Arc<AtomicBool>,
+    on_partial: impl Fn(&str) + Send + 'static,
+    on_final: impl Fn(&AsrResult) + Send + 'static,
+) -> Result<SessionStats, AsrError> {
+    let stats = Arc::new(Mutex::new(SessionStats {
+        frames_per_message: FRAMES_PER_MESSAGE.load(Ordering::SeqCst),
+        ..Default::default()
+    }));
+    let audio = SessionAudio {
+        rx: audio_rx,
+        trimmer: None,
+        encoder: codec::Encoder::new(audio_format),
+        dump: None,
+    };
+    run_session(transport, audio, stats, stop_flag, superseded, on_partial, on_final).await
+}
+
+/// 获取 Cookie 和 ASR 信息、建立连接并运行会话
+async fn connect_and_run(
+    audio_rx: Receiver<Vec<u8>>,
+    options: SessionOptions,
+    trimmer: Option<SilenceTrimmer>,
+    stop_flag: Arc<AtomicBool>,
+    superseded: Arc<AtomicBool>,
+    on_partial: impl Fn(&str) + Send + 'static,
+    on_final: impl Fn(&AsrResult) + Send + 'static,
+) -> Result<SessionStats, AsrError> {
+    let session_start = Instant::now();
+    let frames_per_message = FRAMES_PER_MESSAGE.load(Ordering::SeqCst);
+    let timeline = options.timeline.clone();
+    let stats = Arc::new(Mutex::new(SessionStats {
+        frames_per_message,
+        timeline: timeline.clone(),
+        ..Default::default()
+    }));
+
+    // 每次都实时获取 Cookie 和 ASR 信息（保证最新）
+    log::info!("[DoubaoASR] Fetching fresh Cookie and ASR info from Doubao desktop...");
+    let (cookie, asr_info) = doubao_cdp::fetch_asr_info_auto().await?;
+
+    // 非 PCM 格式需要同时修改 URL 中的 format 参数
+    let encoder = codec::Encoder::new(options.audio_format);
+    let mut url_overrides = options.url_overrides;
+    if encoder.format() != AudioFormat::Pcm {
+        url_overrides.push(("format".to_string(), encoder.format().url_value().to_string()));
+    }
+    // 高级覆盖最后合并，优先于本次会话的参数
+    let advanced = options.advanced;
+    advanced
+        .validate()
+        .map_err(|e| TypeFreeError::InvalidOverride(format!("Invalid advanced override: {}", e)))?;
+    url_overrides.extend(advanced.url_param_pairs());
+    let url = doubao_cdp::override_url_params(&doubao_cdp::build_session_url(&asr_info), &url_overrides);
+
+    log::info!("[DoubaoASR] Connecting to: {}", doubao_cdp::redact_url(&url));
+    if !advanced.is_empty() {
+        log::info!("[DoubaoASR] Advanced overrides: {}", advanced.describe());
+    }
+
+    // 构建请求
+    let request = build_request(&url, &asr_info, &cookie, &advanced).map_err(TypeFreeError::InvalidOverride)?;
+
+    // 连接 WebSocket
+    let (ws_stream, _) = tokio_tungstenite::connect_async(request)
+        .await
+        .map_err(|e| connect_error(&e, &advanced))?;
+
+    log::info!("[DoubaoASR] WebSocket connected!");
+    timeline.mark(LatencyStage::WsConnected);
+    stats.lock().unwrap().connect_ms = session_start.elapsed().as_millis() as u64;
+
+    let audio = SessionAudio {
+        rx: audio_rx,
+        trimmer,
+        encoder,
+        dump: AudioDump::start(),
+    };
+    run_session(ws_stream, audio, stats, stop_flag, superseded, on_partial, on_final).await
+}
+
+// ============ 传输层 ============
+
+/// ASR 连接的收发两端（生产环境为 WebSocket，测试中替换为内存实现）
+pub trait AsrTransport {
+    type Tx: futures_util::Sink<Message, Error = Self::SendError> + Unpin + Send + 'static;
+    type SendError: std::fmt::Display;
+    type Rx: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+        + Unpin
+        + Send
+        + 'static;
+
+    fn into_parts(self) -> (Self::Tx, Self::Rx);
+}
+
+type AsrWebSocket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+impl AsrTransport for AsrWebSocket {
+    type Tx = SplitSink<AsrWebSocket, Message>;
+    type SendError = tokio_tungstenite::tungstenite::Error;
+    type Rx = SplitStream<AsrWebSocket>;
+
+    fn into_parts(self) -> (Self::Tx, Self::Rx) {
+        StreamExt::split(self)
+    }
+}
+
+/// 会话的音频输入
+struct SessionAudio {
+    rx: Receiver<Vec<u8>>,
+    trimmer: Option<SilenceTrimmer>,
+    encoder: codec::Encoder,
+    /// 调试录音：保存实际发出的 PCM
+    dump: Option<AudioDump>,
+}
+
+/// 在已建立的连接上运行会话：转发音频、发送 finish、接收结果
+async fn run_session<T: AsrTransport>(
+    transport: T,
+    audio: SessionAudio,
+    stats: Arc<Mutex<SessionStats>>,
+    stop_flag: Arc<AtomicBool>,
+    superseded: Arc<AtomicBool>,
+    on_partial: impl Fn(&str) + Send + 'static,
+    on_final: impl Fn(&AsrResult) + Send + 'static,
+) -> Result<SessionStats, AsrError> {
+    let SessionAudio {
+        rx: audio_rx,
+        trimmer,
+        encoder,
+        dump,
+    } = audio;
+    let frames_per_message = stats.lock().unwrap().frames_per_message;
+    let (mut ws_tx, mut ws_rx) = transport.into_parts();
+
+    // 用于在任务间传递音频数据
+    let (audio_tx, audio_rx_async) = tokio_mpsc::channel::<AudioFrame>(100);
+
+    // 启动音频转发任务 (sync -> async)，录音线程结束后发出结束标记
+    let stop_flag_audio = stop_flag.clone();
+    let stats_forward = stats.clone();
+    let forward_task = tokio::task::spawn_blocking(move || {
+        forward_audio(audio_rx, audio_tx, stop_flag_audio, trimmer, dump, &stats_forward);
+    });
+
+    // 发送任务：发完全部音频后才发送 finish
+    let stats_send = stats.clone();
+    let send_task = tokio::spawn(async move {
+        send_audio(audio_rx_async, &mut ws_tx, frames_per_message, encoder, &stats_send).await;
+    });
+
+    // 接收任务
+    let stop_flag_recv = stop_flag.clone();
+    let stats_recv = stats.clone();
+    let recv_task = tokio::spawn(async move {
+        let result = receive_results(
+            &mut ws_rx,
+            &stop_flag_recv,
+            &superseded,
+            &stats_recv,
+            on_partial,
+            on_final,
+        )
+        .await;
+        log::info!("[DoubaoASR] Receive task ended");
+        result
+    });
+
+    // 等待任务完成
+    let (_, _, recv_result) = tokio::join!(forward_task, send_task, recv_task);
+    let server_error = recv_result.ok().flatten();
+
+    let stats = stats.lock().unwrap().clone();
+    log::info!(
+        "[DoubaoASR] Session ended: frame={} samples x{}, sent {} msgs / {} bytes, connect {}ms, first partial {}, {} partials, degraded={}, trimmed {}ms/{}ms",
+        stats.frame_samples,
+        stats.frames_per_message,
+        stats.messages_sent,
+        stats.bytes_sent,
+        stats.connect_ms,
+        stats.first_partial_ms.map(|ms| format!("{}ms", ms)).unwrap_or_else(|| "-".to_string()),
+        stats.partials,
+        stats.degraded,
+        stats.trimmed_leading_ms,
+        stats.trimmed_trailing_ms
+    );
+
+    match server_error {
+        Some(error) => Err(AsrError::Server { error, stats }),
+        None => Ok(stats),
+    }
+}
+
+/// 收到 finish 后继续等待的时间，服务端有时在 finish 前后同一批返回更完整的 result
+const FINISH_DRAIN_WINDOW: tokio::time::Duration = tokio::time::Duration::from_millis(100);
+
+/// 接收识别结果，直到 finish（及其后的短暂等待）、连接关闭、停止后超时、被取代或服务端报错
+///
+/// 无论以哪种方式结束，已有的识别结果都会通过 `on_final` 交付一次。
+/// 服务端报错时同时结束录音，并在交付后返回错误。
+async fn receive_results<S, P, F>(
+    ws_rx: &mut S,
+    stop_flag: &AtomicBool,
+    superseded: &AtomicBool,
+    stats: &Mutex<SessionStats>,
+    on_partial: P,
+    on_final: F,
+) -> Option<ServerError>
+where
+    S: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    P: Fn(&str),
+    F: Fn(&AsrResult),
+{
+    let mut latest = AsrResult::default();
+    // finish 消息自带的最终结果，收到后不再被之后的 result 替换
+    let mut server_final: Option<AsrResult> = None;
+    let mut finish_timeout: Option<tokio::time::Instant> = None;
+    // 收到 finish 后的截止时间，期间到达的 result 仍然采用
+    let mut drain_deadline: Option<tokio::time::Instant> = None;
+    let mut server_error: Option<ServerError> = None;
+
+    loop {
+        // 被新会话取代：用已有的识别结果作为最终结果
+        if superseded.load(Ordering::SeqCst) {
+            log::info!("[DoubaoASR] Superseded, using partial as final: {}", latest.text);
+            break;
+        }
+
+        // 检查是否已停止录音，启动1秒超时
+        if stop_flag.load(Ordering::SeqCst) && finish_timeout.is_none() {
+            finish_timeout = Some(tokio::time::Instant::now() + tokio::time::Duration::from_secs(1));
+            stats.lock().unwrap().timeline.mark(LatencyStage::StopRequested);
+            log::info!("[DoubaoASR] Stop detected, waiting 1s for final result...");
+        }
+
+        // 检查超时
+        if let Some(deadline) = finish_timeout {
+            if tokio::time::Instant::now() >= deadline {
+                log::info!("[DoubaoASR] Timeout, using partial as final: {}", latest.text);
+                break;
+            }
+        }
+
+        let mut wait = tokio::time::Duration::from_millis(100);
+        if let Some(deadline) = drain_deadline {
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                log::info!("[DoubaoASR] Finish drained, final: {}", latest.text);
+                break;
+            }
+            wait = wait.min(deadline - now);
+        }
+
+        // 使用 timeout 接收消息，避免阻塞
+        let recv_result = tokio::time::timeout(wait, ws_rx.next()).await;
+
+        match recv_result {
+            Ok(Some(Ok(Message::Text(text)))) => {
+                // 原始消息，用于录制回放测试的样本（tests/fixtures/asr）
+                log::debug!("[DoubaoASR] Frame: {}", text);
+                let Ok(data) = serde_json::from_str::<serde_json::Value>(&text) else {
+                    continue;
+                };
+                let event = data.get("event").and_then(|e| e.as_str()).unwrap_or("");
+
+                match event {
+                    "result" => {
+                        if let Some(result) = AsrResult::from_message(&data) {
+                            if !result.text.is_empty() {
+                                record_partial(stats);
+                                log::info!("[DoubaoASR] Partial: {}", result.text);
+                                on_partial(&result.text);
+                                latest = result;
+                            }
+                        }
+                    }
+                    "finish" => {
+                        // finish 可能带有服务端修正后的最终文字
+                        if let Some(result) = AsrResult::from_message(&data) {
+                            if !result.text.is_empty() && server_final.is_none() {
+                                log::info!("[DoubaoASR] Finish carries final: {}", result.text);
+                                server_final = Some(result);
+                            }
+                        }
+                        // 不立即结束，短暂等待可能紧随其后的 result
+                        if drain_deadline.is_none() {
+                            log::info!("[DoubaoASR] Finish received, current: {}", latest.text);
+                            drain_deadline = Some(tokio::time::Instant::now() + FINISH_DRAIN_WINDOW);
+                        }
+                    }
+                    "" => {
+                        // 检查是否是服务端错误
+                        if let Some(code) = data.get("code").and_then(|c| c.as_i64()) {
+                            if code != 0 {
+                                let message = data
+                                    .get("message")
+                                    .and_then(|m| m.as_str())
+                                    .unwrap_or("unknown")
+                                    .to_string();
+                                log::error!("[DoubaoASR] Error: code={}, message={}", code, message);
+                                maybe_fall_back_to_batched_frames(&stats.lock().unwrap(), code);
+
+                                // 服务端不会再返回结果，结束录音
+                                stop_flag.store(true, Ordering::SeqCst);
+                                server_error = Some(ServerError { code, message });
+                                break;
+                            }
+                        }
+                    }
+                    _ => {
+                        log::debug!("[DoubaoASR] Unknown event: {}", event);
+                    }
+                }
+            }
+            Ok(Some(Ok(Message::Close(_)))) => {
+                log::info!("[DoubaoASR] WebSocket closed");
+                break;
+            }
+            Ok(Some(Err(e))) => {
+                log::error!("[DoubaoASR] Receive error: {}", e);
+                break;
+            }
+            Ok(Some(Ok(_))) => {}
+            Ok(None) => {
+                // WebSocket 流结束
+                log::info!("[DoubaoASR] WebSocket stream ended");
+                break;
+            }
+            Err(_) => {
+                // 超时，继续循环检查
+            }
+        }
+    }
+
+    stats.lock().unwrap().timeline.mark(LatencyStage::Finished);
+    {
+        let mut stats = stats.lock().unwrap();
+        stats.partial_final = (!latest.text.is_empty()).then(|| latest.text.clone());
+        stats.server_final = server_final.as_ref().map(|r| r.text.clone());
+    }
+    if let Some(result) = server_final {
+        if result.text != latest.text {
+            log::info!(
+                "[DoubaoASR] Server final differs from last partial: {} -> {}",
+                latest.text,
+                result.text
+            );
+        }
+        latest = result;
+    }
+    if !latest.text.is_empty() {
+        if server_error.is_some() {
+            log::warn!("[DoubaoASR] Delivering partial as final despite server error: {}", latest.text);
+            stats.lock().unwrap().degraded = true;
+        }
+        on_final(&latest);
+    }
+
+    server_error
+}
+
+/// 转发给发送任务的音频
+#[derive(Debug)]
+enum AudioFrame {
+    Data(Vec<u8>),
+    /// 录音已结束，之后不会再有音频
+    End,
+}
+
+/// 停止后等待录音线程交出剩余音频的最长时间
+const AUDIO_DRAIN_GRACE: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 把录音线程的音频转发到异步通道（阻塞执行）
+///
+/// 录音线程结束（发送端全部释放）后发出 `AudioFrame::End`；
+/// 录音线程卡住时，停止后最多再等 `AUDIO_DRAIN_GRACE`。
+/// 启用静音裁剪时，只转发裁剪后的帧，结尾的静音在发出结束标记前丢弃。
+/// 开启调试录音时，转发的帧同时写入 `dump`
+fn forward_audio(
+    audio_rx: Receiver<Vec<u8>>,
+    tx: tokio_mpsc::Sender<AudioFrame>,
+    stop_flag: Arc<AtomicBool>,
+    mut trimmer: Option<SilenceTrimmer>,
+    mut dump: Option<AudioDump>,
+    stats: &Mutex<SessionStats>,
+) {
+    let mut stopped_at: Option<Instant> = None;
+    'forward: loop {
+        match audio_rx.recv_timeout(std::time::Duration::from_millis(100)) {
+            Ok(data) => {
+                let frames = match trimmer.as_mut() {
+                    Some(trimmer) => trimmer.push(data),
+                    None => vec![data],
+                };
+                for frame in frames {
+                    if let Some(dump) = dump.as_mut() {
+                        dump.write(&frame);
+                    }
+                    if tx.blocking_send(AudioFrame::Data(frame)).is_err() {
+                        break 'forward;
+                    }
+                }
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                if stop_flag.load(Ordering::SeqCst) {
+                    let since = *stopped_at.get_or_insert_with(Instant::now);
+                    if since.elapsed() >= AUDIO_DRAIN_GRACE {
+                        log::warn!("[DoubaoASR] Recorder did not finish in time, ending audio stream");
+                        break;
+                    }
+                }
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    if let Some(trimmer) = trimmer.as_mut() {
+        trimmer.finish();
+        let mut stats = stats.lock().unwrap();
+        stats.trimmed_leading_ms = trimmer.trimmed_leading_ms();
+        stats.trimmed_trailing_ms = trimmer.trimmed_trailing_ms();
+        log::info!(
+            "[DoubaoASR] Trimmed silence: {}ms leading, {}ms trailing",
+            stats.trimmed_leading_ms,
+            stats.trimmed_trailing_ms
+        );
+    }
+    let _ = tx.blocking_send(AudioFrame::End);
+    if let Some(dump) = dump {
+        dump.finish();
+    }
+    log::info!("[DoubaoASR] Audio forward task ended");
+}
+
+/// 发送音频到 ASR WebSocket
+///
+/// 收到结束标记（或通道关闭）后先补发剩余的帧，再发送一次 finish 信号。
+/// 每条消息经 `encoder` 编码后发送（PCM 原样发送）。
+async fn send_audio<S>(
+    mut audio_rx: tokio_mpsc::Receiver<AudioFrame>,
+    ws_tx: &mut S,
+    frames_per_message: usize,
+    mut encoder: codec::Encoder,
+    stats: &Mutex<SessionStats>,
+) where
+    S: futures_util::Sink<Message> + Unpin,
+    S::Error: std::fmt::Display,
+{
+    let mut chunk_count = 0;
+    // 待合并发送的帧
+    let mut pending: Vec<u8> = Vec::new();
+    let mut pending_frames = 0;
+
+    loop {
+        let data = match audio_rx.recv().await {
+            Some(AudioFrame::Data(data)) => data,
+            Some(AudioFrame::End) | None => break,
+        };
+
+        if chunk_count == 0 && pending_frames == 0 {
+            stats.lock().unwrap().frame_samples = data.len() / 2;
+        }
+        pending.extend_from_slice(&data);
+        pending_frames += 1;
+        if pending_frames < frames_per_message {
+            continue;
+        }
+
+        let message = std::mem::take(&mut pending);
+        pending_frames = 0;
+        if !send_packets(ws_tx, encoder.encode(message), stats).await {
+            return;
+        }
+        chunk_count += 1;
+        if chunk_count % 10 == 0 {
+            log::debug!("[DoubaoASR] Sent {} chunks", chunk_count);
+        }
+    }
+
+    // 剩余的帧不论多少都要发出
+    if !pending.is_empty() {
+        if !send_packets(ws_tx, encoder.encode(pending), stats).await {
+            return;
+        }
+        chunk_count += 1;
+    }
+    if !send_packets(ws_tx, encoder.flush(), stats).await {
+        return;
+    }
+
+    // 发送 finish 信号
+    log::info!("[DoubaoASR] Sending finish signal after {} chunks...", chunk_count);
+    let finish_msg = serde_json::json!({"event": "finish"});
+    let _ = ws_tx.send(Message::Text(finish_msg.to_string())).await;
+    stats.lock().unwrap().timeline.mark(LatencyStage::FinishSent);
+
+    log::info!("[DoubaoASR] Send task ended, total chunks: {}", chunk_count);
+}
+
+/// 逐个发送编码后的音频包，发送失败返回 false
+async fn send_packets<S>(ws_tx: &mut S, packets: Vec<Vec<u8>>, stats: &Mutex<SessionStats>) -> bool
+where
+    S: futures_util::Sink<Message> + Unpin,
+    S::Error: std::fmt::Display,
+{
+    for packet in packets {
+        let len = packet.len();
+        if let Err(e) = ws_tx.send(Message::Binary(packet)).await {
+            log::error!("[DoubaoASR] Send error: {}", e);
+            return false;
+        }
+        record_sent(stats, len);
+    }
+    true
+}
+
+/// 记录一条已发送的音频消息
+fn record_sent(stats: &Mutex<SessionStats>, bytes: usize) {
+    let mut stats = stats.lock().unwrap();
+    if stats.first_audio_at.is_none() {
+        stats.first_audio_at = Some(Instant::now());
+    }
+    stats.messages_sent += 1;
+    stats.bytes_sent += bytes;
+}
+
+/// 记录一次识别结果，首次结果计算延迟
+fn record_partial(stats: &Mutex<SessionStats>) {
+    let mut stats = stats.lock().unwrap();
+    stats.partials += 1;
+    if stats.first_partial_ms.is_none() {
+        stats.first_partial_ms = stats.first_audio_at.map(|t| t.elapsed().as_millis() as u64);
+        stats.timeline.mark(LatencyStage::FirstPartial);
+    }
+}
+
+/// 还没有任何识别结果就报错（非限流/服务不可用），且帧比旧版小时，
+/// 认为服务端不接受小帧，之后的会话每条消息合并两帧
+fn maybe_fall_back_to_batched_frames(stats: &SessionStats, code: i64) {
+    if matches!(code, 671000003 | 710022002) || stats.partials > 0 {
+        return;
+    }
+    if stats.frames_per_message == 1 && stats.frame_samples > 0 && stats.frame_samples < LEGACY_FRAME_SAMPLES {
+        log::warn!(
+            "[DoubaoASR] Server rejected {}-sample frames, batching 2 frames per message from now on",
+            stats.frame_samples
+        );
+        FRAMES_PER_MESSAGE.store(2, Ordering::SeqCst);
+    }
+}
+
+/// 检查 ASR 是否可用
+/// 优先检查缓存，有缓存就可用；否则检查豆包是否运行
+pub async fn is_available() -> bool {
+    // 有缓存的 Cookie 和 URL 参数就可以使用
+    let state = doubao_cdp::snapshot();
+    if state.cookies.is_some() && state.url_params.is_some() {
+        return true;
+    }
+    // 没有缓存，检查豆包是否运行
+    doubao_cdp::is_doubao_debug_available().await
+}
+
+// ============ 连接自检 ============
+
+/// 连接自检的各个阶段（按执行顺序）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Cdp,
+    Cookies,
+    UrlParams,
+    Handshake,
+    FinishResponse,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Cdp,
+        Stage::Cookies,
+        Stage::UrlParams,
+        Stage::Handshake,
+        Stage::FinishResponse,
+    ];
+
+    /// 设置页展示的名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Cdp => "调试端口",
+            Stage::Cookies => "Cookie",
+            Stage::UrlParams => "URL 参数",
+            Stage::Handshake => "WebSocket 握手",
+            Stage::FinishResponse => "结束响应",
+        }
+    }
+}
+
+/// 单个阶段的检测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct StageResult {
+    pub stage: Stage,
+    pub label: &'static str,
+    /// None 表示前面的阶段失败，本阶段未执行
+    pub ok: Option<bool>,
+    pub detail: String,
+}
+
+impl StageResult {
+    fn passed(stage: Stage, detail: impl Into<String>) -> Self {
+        Self { stage, label: stage.label(), ok: Some(true), detail: detail.into() }
+    }
+
+    fn failed(stage: Stage, detail: String) -> Self {
+        Self { stage, label: stage.label(), ok: Some(false), detail }
+    }
+
+    fn skipped(stage: Stage) -> Self {
+        Self { stage, label: stage.label(), ok: None, detail: String::new() }
+    }
+}
+
+/// 第一个失败的阶段
+pub fn first_failure(results: &[StageResult]) -> Option<&StageResult> {
+    results.iter().find(|r| r.ok == Some(false))
+}
+
+/// 记录失败阶段，并把之后的阶段标记为未执行
+fn finish_report(mut results: Vec<StageResult>, failure: Option<(Stage, String)>) -> Vec<StageResult> {
+    if let Some((stage, detail)) = failure {
+        results.push(StageResult::failed(stage, detail));
+        let done = results.len();
+        results.extend(Stage::ALL.iter().skip(done).map(|&s| StageResult::skipped(s)));
+    }
+    results
+}
+
+/// 测试 WebSocket 连接是否可用
+///
+/// 逐个阶段检测（调试端口 → Cookie → URL 参数 → 握手 → finish 响应），
+/// 返回每个阶段的结果，便于设置页定位具体哪一步失败
+pub async fn test_connection() -> Vec<StageResult> {
+    log::info!("[DoubaoASR] Testing WebSocket connection...");
+
+    let mut results = Vec::new();
+    let failure = run_test_stages(&mut results).await.err();
+
+    match &failure {
+        Some((stage, e)) => {
+            log::error!("[DoubaoASR] Connection test FAILED at {:?}: {}", stage, e)
+        }
+        None => {
+            log::info!("[DoubaoASR] Connection test PASSED");
+            reset_cooldown();
+        }
+    }
+
+    finish_report(results, failure)
+}
+
+/// 依次执行各阶段，通过的阶段写入 results，失败时返回 (阶段, 原因)
+async fn run_test_stages(results: &mut Vec<StageResult>) -> Result<(), (Stage, String)> {
+    doubao_cdp::verify_cdp_endpoint()
+        .await
+        .map_err(|e| (Stage::Cdp, e.to_string()))?;
+    results.push(StageResult::passed(Stage::Cdp, "豆包调试端口可访问"));
+
+    // 每次都实时获取 Cookie 和 ASR 信息
+    let cookie = doubao_cdp::fetch_cookies()
+        .await
+        .map_err(|e| (Stage::Cookies, e.to_string()))?;
+    results.push(StageResult::passed(
+        Stage::Cookies,
+        format!("已获取 Cookie（{} 字节）", cookie.len()),
+    ));
+
+    let (cookie, asr_info) = doubao_cdp::fetch_asr_info_auto()
+        .await
+        .map_err(|e| (Stage::UrlParams, e.to_string()))?;
+    let params_detail = if doubao_cdp::get_cached_url_params().is_some() {
+        "使用捕获的参数模板"
+    } else {
+        "未能捕获真实参数，使用内置参数"
+    };
+    results.push(StageResult::passed(Stage::UrlParams, params_detail));
+
+    let url = doubao_cdp::build_session_url(&asr_info);
+    log::info!("[DoubaoASR] Test connecting to: {}", doubao_cdp::redact_url(&url));
+
+    // 构建请求（URL 参数覆盖已在 fetch_asr_info_auto 中合并）
+    let advanced = crate::settings::get().asr_overrides;
+    advanced.validate().map_err(|e| (Stage::Handshake, e))?;
+    let request =
+        build_request(&url, &asr_info, &cookie, &advanced).map_err(|e| (Stage::Handshake, e))?;
+
+    // 尝试连接
+    let (ws_stream, _) = tokio_tungstenite::connect_async(request)
+        .await
+        .map_err(|e| {
+            let error = format!("WebSocket connection failed: {}", e);
+            (Stage::Handshake, with_overrides(error, &advanced))
+        })?;
+    results.push(StageResult::passed(Stage::Handshake, "握手成功"));
+
+    log::info!("[DoubaoASR] WebSocket connected, testing with finish signal...");
+
+    let (mut ws_tx, mut ws_rx) = ws_stream.split();
+
+    // 发送 finish 信号测试
+    let finish_msg = serde_json::json!({"event": "finish"});
+    ws_tx.send(Message::Text(finish_msg.to_string()))
+        .await
+        .map_err(|e| (Stage::FinishResponse, format!("Failed to send test message: {}", e)))?;
+
+    // 等待响应，检查是否有 block 错误
+    let timeout = tokio::time::Duration::from_secs(5);
+    let result = tokio::time::timeout(timeout, async {
+        while let Some(msg_result) = ws_rx.next().await {
+            match msg_result {
+                Ok(Message::Text(text)) => {
+                    log::info!("[DoubaoASR] Test response: {}", text);
+                    if let Ok(data) = serde_json::from_str::<serde_json::Value>(&text) {
+                        // 检查是否有错误码
+                        if let Some(code) = data.get("code").and_then(|c| c.as_i64()) {
+                            if code != 0 {
+                                let msg = data.get("message").and_then(|m| m.as_str()).unwrap_or("unknown");
+                                doubao_cdp::clear_cached_cookies();
+                                return Err(format!("ASR error: code={}, message={}", code, msg));
+                            }
+                        }
+                        // 检查是否是 finish 响应
+                        let event = data.get("event").and_then(|e| e.as_str()).unwrap_or("");
+                        if event == "finish" {
+                            return Ok("收到 finish 响应");
+                        }
+                    }
+                }
+                Ok(Message::Close(_)) => {
+                    return Ok("服务端正常关闭连接");
+                }
+                Err(e) => {
+                    return Err(format!("WebSocket error: {}", e));
+                }
+                _ => {}
+            }
+        }
+        Ok("连接已结束")
+    }).await;
+
+    match result {
+        Ok(Ok(detail)) => {
+            results.push(StageResult::passed(Stage::FinishResponse, detail));
+            Ok(())
+        }
+        Ok(Err(e)) => Err((Stage::FinishResponse, e)),
+        Err(_) => {
+            // 超时不一定是错误
+            log::warn!("[DoubaoASR] Connection test timeout (might be OK)");
+            results.push(StageResult::passed(Stage::FinishResponse, "5 秒内无响应（可能正常）"));
+            Ok(())
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 用给定的服务端消息运行接收逻辑，返回 (partials, finals, error, stats, stopped)
+    async fn run_receive(
+        messages: &[&str],
+    ) -> (Vec<String>, Vec<String>, Option<ServerError>, SessionStats, bool) {
+        let messages: Vec<Result<Message, tokio_tungstenite::tungstenite::Error>> = messages
+            .iter()
+            .map(|m| Message::Text(m.to_string()))
+            .map(Ok)
+            .collect();
+        let mut stream = futures_util::stream::iter(messages);
+        let stop_flag = AtomicBool::new(false);
+        let superseded = AtomicBool::new(false);
+        let stats = Mutex::new(SessionStats::default());
+        let partials = Mutex::new(Vec::new());
+        let finals = Mutex::new(Vec::new());
+
+        let error = receive_results(
+            &mut stream,
+            &stop_flag,
+            &superseded,
+            &stats,
+            |t: &str| partials.lock().unwrap().push(t.to_string()),
+            |r: &AsrResult| finals.lock().unwrap().push(r.text.clone()),
+        )
+        .await;
+
+        let stats = stats.lock().unwrap().clone();
+        (
+            partials.into_inner().unwrap(),
+            finals.into_inner().unwrap(),
+            error,
+            stats,
+            stop_flag.load(Ordering::SeqCst),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_error_after_partials_still_delivers_final() {
+        let (partials, finals, error, stats, stopped) = run_receive(&[
+            r#"{"event":"result","result":{"Text":"你好"}}"#,
+            r#"{"event":"result","result":{"Text":"你好世界"}}"#,
+            r#"{"code":710022002,"message":"service busy"}"#,
+        ])
+        .await;
+
+        assert_eq!(partials, vec!["你好", "你好世界"]);
+        assert_eq!(finals, vec!["你好世界"]);
+        assert_eq!(error.unwrap().code, 710022002);
+        assert!(stats.degraded);
+        assert!(stopped);
+    }
+
+    #[tokio::test]
+    async fn test_error_before_any_partial_skips_final() {
+        let (partials, finals, error, stats, _) = run_receive(&[
+            r#"{"code":671000003,"message":"too many requests"}"#,
+            r#"{"event":"result","result":{"Text":"不应出现"}}"#,
+        ])
+        .await;
+
+        assert!(partials.is_empty());
+        assert!(finals.is_empty());
+        assert_eq!(error.unwrap().user_message(), "请求太频繁，请稍后再试");
+        assert!(!stats.degraded);
+    }
+
+    #[tokio::test]
+    async fn test_finish_delivers_final_once() {
+        let (_, finals, error, _, _) = run_receive(&[
+            r#"{"event":"result","result":{"Text":"好的"}}"#,
+            r#"{"event":"finish"}"#,
+            r#"{"event":"finish"}"#,
+        ])
+        .await;
+
+        assert_eq!(finals, vec!["好的"]);
+        assert!(error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_result_right_after_finish_is_used() {
+        let (_, finals, error, _, _) = run_receive(&[
+            r#"{"event":"result","result":{"Text":"今天天"}}"#,
+            r#"{"event":"finish"}"#,
+            r#"{"event":"result","result":{"Text":"今天天气不错"}}"#,
+            r#"{"event":"result","result":{"Text":""}}"#,
+        ])
+        .await;
+
+        assert_eq!(finals, vec!["今天天气不错"]);
+        assert!(error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finish_payload_corrects_last_partial() {
+        let (partials, finals, error, stats, _) = run_receive(&[
+            r#"{"event":"result","result":{"Text":"今天天汽不错"}}"#,
+            r#"{"event":"finish","result":{"Text":"今天天气不错。"}}"#,
+        ])
+        .await;
+
+        assert_eq!(partials, vec!["今天天汽不错"]);
+        assert_eq!(finals, vec!["今天天气不错。"]);
+        assert!(error.is_none());
+        assert_eq!(stats.partial_final.as_deref(), Some("今天天汽不错"));
+        assert_eq!(stats.server_final.as_deref(), Some("今天天气不错。"));
+
+        // finish 的最终结果不被之后的 result 替换；空文字时沿用中间结果
+        let (_, finals, _, _, _) = run_receive(&[
+            r#"{"event":"result","result":{"Text":"好"}}"#,
+            r#"{"event":"finish","payload":{"result":{"text":"好的。"}}}"#,
+            r#"{"event":"result","result":{"Text":"好的"}}"#,
+        ])
+        .await;
+        assert_eq!(finals, vec!["好的。"]);
+
+        let (_, finals, _, stats, _) = run_receive(&[
+            r#"{"event":"result","result":{"Text":"好的"}}"#,
+            r#"{"event":"finish","result":{"Text":""}}"#,
+        ])
+        .await;
+        assert_eq!(finals, vec!["好的"]);
+        assert_eq!(stats.server_final, None);
+    }
+
+    fn overrides(params: &[(&str, &str)], headers: &[(&str, &str)]) -> AsrOverrides {
+        let map = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        AsrOverrides {
+            url_params: map(params),
+            headers: map(headers),
+        }
+    }
+
+    #[test]
+    fn test_overrides_validate() {
+        assert!(AsrOverrides::default().validate().is_ok());
+        assert!(overrides(&[("language", "en"), ("version_code", "20900")], &[("X-Debug", "1")])
+            .validate()
+            .is_ok());
+
+        assert!(overrides(&[("lang uage", "en")], &[]).validate().is_err());
+        assert!(overrides(&[("a&b", "1")], &[]).validate().is_err());
+        assert!(overrides(&[("language", "en&x=1")], &[]).validate().is_err());
+        assert!(overrides(&[], &[("Bad Header", "1")]).validate().is_err());
+        assert!(overrides(&[], &[("X-Debug", "line\nbreak")]).validate().is_err());
+        // 握手相关的请求头不能覆盖
+        assert!(overrides(&[], &[("sec-websocket-key", "x")]).validate().is_err());
+        assert!(overrides(&[], &[("Host", "example.com")]).validate().is_err());
+    }
+
+    #[test]
+    fn test_request_preview_masks_by_default() {
+        let info = doubao_cdp::AsrRequestInfo {
+            url_template: "wss://ws-samantha.doubao.com/samantha/audio/asr?device_id=123&language=zh"
+                .to_string(),
+            ..Default::default()
+        };
+        let advanced = overrides(&[("language", "en")], &[("X-Debug", "1")]);
+        let cookie = "sessionid=secret; sid_tt=token";
+
+        let preview = build_preview(&info, Some(cookie), &advanced, false).unwrap();
+        assert!(preview.url.contains("device_id=***"));
+        assert!(preview.url.contains("language=en"));
+        assert!(preview.url.contains("web_tab_id="));
+        let header = |preview: &RequestPreview, name: &str| {
+            preview.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+        };
+        assert_eq!(header(&preview, "cookie").unwrap(), "sessionid=***; sid_tt=***");
+        assert_eq!(header(&preview, "x-debug").unwrap(), "***");
+        assert_eq!(preview.param_source, doubao_cdp::ParamSource::Fallback);
+        assert!(preview.cookies_cached);
+        assert_eq!(preview.overridden_params, vec!["language"]);
+        assert_eq!(preview.overridden_headers, vec!["X-Debug"]);
+
+        // 完整显示
+        let preview = build_preview(&info, Some(cookie), &advanced, true).unwrap();
+        assert!(preview.url.contains("device_id=123"));
+        assert_eq!(header(&preview, "cookie").unwrap(), cookie);
+        assert_eq!(header(&preview, "x-debug").unwrap(), "1");
+
+        let preview = build_preview(&info, None, &AsrOverrides::default(), false).unwrap();
+        assert!(!preview.cookies_cached);
+        assert_eq!(header(&preview, "cookie").unwrap(), "");
+    }
+
+    #[test]
+    fn test_overrides_win_in_request() {
+        let info = doubao_cdp::AsrRequestInfo::default();
+        let advanced = overrides(&[], &[("User-Agent", "Custom/1.0"), ("X-Debug", "1")]);
+        let request = build_request(&info.url_template, &info, "sessionid=1", &advanced).unwrap();
+        assert_eq!(request.headers()["User-Agent"], "Custom/1.0");
+        assert_eq!(request.headers().get_all("User-Agent").iter().count(), 1);
+        assert_eq!(request.headers()["X-Debug"], "1");
+        assert_eq!(request.headers()["Host"], doubao_cdp::DEFAULT_ASR_HOST);
+
+        // URL 参数覆盖排在会话参数之后，优先生效
+        let advanced = overrides(&[("language", "ja")], &[]);
+        let mut session = vec![("language".to_string(), "en".to_string())];
+        session.extend(advanced.url_param_pairs());
+        let url = doubao_cdp::override_url_params(&info.url_template, &session);
+        assert!(url.contains("language=ja"));
+
+        let error = with_overrides("Failed to connect ASR WebSocket: 400".to_string(), &advanced);
+        assert_eq!(
+            error,
+            "Failed to connect ASR WebSocket: 400 (with advanced overrides: url params [language], headers [])"
+        );
+    }
+
+    #[test]
+    fn test_connect_error_kinds() {
+        use tokio_tungstenite::tungstenite::Error;
+        let http = |status: u16| {
+            Error::Http(http::Response::builder().status(status).body(None).unwrap())
+        };
+        let none = AsrOverrides::default();
+        assert!(matches!(connect_error(&http(401), &none), TypeFreeError::LoginExpired(_)));
+        assert!(matches!(connect_error(&http(403), &none), TypeFreeError::LoginExpired(_)));
+        assert!(matches!(connect_error(&http(500), &none), TypeFreeError::WsConnect(_)));
+        assert!(matches!(
+            connect_error(&Error::ConnectionClosed, &none),
+            TypeFreeError::WsConnect(_)
+        ));
+        // 带覆盖时优先提示检查覆盖
+        let advanced = overrides(&[("language", "ja")], &[]);
+        assert!(matches!(
+            connect_error(&http(401), &advanced),
+            TypeFreeError::InvalidOverride(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_result_with_utterances() {
+        let data: serde_json::Value = serde_json::from_str(
+            r#"{"event":"result","result":{"Text":"今天天气不错。我们出去走走","Utterances":[
+                {"Text":"今天天气不错。","StartTime":120,"EndTime":1480,"Definite":true,
+                 "Words":[{"Text":"今天","StartTime":120,"EndTime":460}]},
+                {"Text":"我们出去走走","StartTime":1700,"EndTime":2900,"Definite":false}
+            ]}}"#,
+        )
+        .unwrap();
+        let result = AsrResult::parse(&data["result"]).unwrap();
+
+        assert_eq!(result.text, "今天天气不错。我们出去走走");
+        assert_eq!(result.utterances.len(), 2);
+        assert_eq!(result.utterances[0].start_ms, Some(120));
+        assert_eq!(result.utterances[0].words[0].end_ms, Some(460));
+        let definite: Vec<_> = result.definite_utterances().map(|u| u.text.as_str()).collect();
+        assert_eq!(definite, vec!["今天天气不错。"]);
+    }
+
+    #[test]
+    fn test_parse_result_degrades_gracefully() {
+        // 小写字段
+        let lower = serde_json::json!({"text": "hello", "utterances": [{"text": "hello"}]});
+        let result = AsrResult::parse(&lower).unwrap();
+        assert_eq!(result.text, "hello");
+        assert_eq!(result.utterances[0].start_ms, None);
+        assert!(!result.utterances[0].definite);
+
+        // 只有文本
+        let plain = serde_json::json!({"Text": "好的", "Extra": {"foo": 1}});
+        let result = AsrResult::parse(&plain).unwrap();
+        assert_eq!(result.text, "好的");
+        assert!(result.utterances.is_empty());
+
+        // 分句字段类型不对时仍保留文本
+        let odd = serde_json::json!({"Text": "好的", "Utterances": "n/a"});
+        assert_eq!(AsrResult::parse(&odd).unwrap().text, "好的");
+
+        assert!(AsrResult::parse(&serde_json::json!({"Text": 1})).is_none());
+    }
+
+    #[test]
+    fn test_result_message_shapes() {
+        let text_of = |raw: &str| {
+            let data: serde_json::Value = serde_json::from_str(raw).unwrap();
+            AsrResult::from_message(&data).map(|r| r.text)
+        };
+        // 按已知字段命名构造的几种结构（非真实抓包）
+        let samples = [
+            r#"{"event":"result","result":{"Text":"今天天气不错"}}"#,
+            r#"{"event":"result","result":{"text":"今天天气不错","utterances":[]}}"#,
+            r#"{"event":"result","result":[{"Text":"今天天气不错","Confidence":0.93},{"Text":"今天天气不措"}]}"#,
+            r#"{"event":"result","results":[{"text":"今天天气不错"}]}"#,
+            r#"{"event":"result","result":{"results":[{"Text":"今天天气不错"}],"is_final":false}}"#,
+            r#"{"event":"result","payload":{"result":{"Text":"今天天气不错"}}}"#,
+            r#"{"event":"result","data":{"result":{"text":"今天天气不错"}}}"#,
+            r#"{"event":"result","result":"今天天气不错"}"#,
+            r#"{"event":"result","result":{"Utterances":[{"Text":"今天","Definite":true},{"Text":"天气不错"}]}}"#,
+        ];
+        for raw in samples {
+            assert_eq!(text_of(raw).as_deref(), Some("今天天气不错"), "{}", raw);
+        }
+
+        // 只有分句的英文结果按单词补空格
+        let english = r#"{"event":"result","result":{"utterances":[{"text":"Hello there."},{"text":"How are you"}]}}"#;
+        assert_eq!(text_of(english).as_deref(), Some("Hello there. How are you"));
+
+        // 明确为空的结果不算未知格式
+        assert_eq!(text_of(r#"{"event":"result","result":{"Text":""}}"#).as_deref(), Some(""));
+
+        // 未知格式
+        assert_eq!(text_of(r#"{"event":"result","result":{"Sentence":"今天"}}"#), None);
+        assert_eq!(text_of(r#"{"event":"result","result":[]}"#), None);
+        assert_eq!(text_of(r#"{"event":"result"}"#), None);
+        assert_eq!(text_of(r#"{"event":"result","result":{"Text":1}}"#), None);
+    }
+
+    fn pcm_encoder() -> codec::Encoder {
+        codec::Encoder::new(AudioFormat::Pcm)
+    }
+
+    fn finish_count(messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .filter(|m| matches!(m, Message::Text(t) if t.contains("finish")))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_all_audio_sent_before_finish() {
+        let (audio_tx, audio_rx) = std::sync::mpsc::channel::<Vec<u8>>();
+        let (frame_tx, frame_rx) = tokio_mpsc::channel::<AudioFrame>(100);
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stats = Arc::new(Mutex::new(SessionStats::default()));
+
+        let stop_for_forward = stop_flag.clone();
+        let stats_forward = stats.clone();
+        let forward = tokio::task::spawn_blocking(move || {
+            forward_audio(audio_rx, frame_tx, stop_for_forward, None, None, &stats_forward)
+        });
+
+        // 录音线程：先停止，再刷出尾部音频，最后释放发送端
+        let recorder = std::thread::spawn(move || {
+            audio_tx.send(vec![1u8; 3200]).unwrap();
+            audio_tx.send(vec![2u8; 3200]).unwrap();
+            stop_flag.store(true, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(250));
+            audio_tx.send(vec![3u8; 100]).unwrap();
+        });
+
+        let mut sink: Vec<Message> = Vec::new();
+        send_audio(frame_rx, &mut sink, 1, pcm_encoder(), &stats).await;
+        forward.await.unwrap();
+        recorder.join().unwrap();
+
+        assert_eq!(sink.len(), 4);
+        assert!(matches!(&sink[0], Message::Binary(b) if b[0] == 1));
+        assert!(matches!(&sink[1], Message::Binary(b) if b[0] == 2));
+        assert!(matches!(&sink[2], Message::Binary(b) if b.len() == 100));
+        assert!(matches!(&sink[3], Message::Text(_)));
+        assert_eq!(finish_count(&sink), 1);
+        assert_eq!(stats.lock().unwrap().messages_sent, 3);
+    }
+
+    #[tokio::test]
+    async fn test_batched_frames_flush_remainder_before_finish() {
+        let (frame_tx, frame_rx) = tokio_mpsc::channel::<AudioFrame>(100);
+        for i in 0..3u8 {
+            frame_tx.send(AudioFrame::Data(vec![i; 4])).await.unwrap();
+        }
+        frame_tx.send(AudioFrame::End).await.unwrap();
+        // 结束标记之后的数据不再发送
+        frame_tx.send(AudioFrame::Data(vec![9; 4])).await.unwrap();
+
+        let stats = Mutex::new(SessionStats::default());
+        let mut sink: Vec<Message> = Vec::new();
+        send_audio(frame_rx, &mut sink, 2, pcm_encoder(), &stats).await;
+
+        assert_eq!(sink.len(), 3);
+        assert!(matches!(&sink[0], Message::Binary(b) if b.len() == 8));
+        assert!(matches!(&sink[1], Message::Binary(b) if b.len() == 4 && b[0] == 2));
+        assert_eq!(finish_count(&sink), 1);
+    }
+
+    #[test]
+    fn test_finish_report_marks_later_stages_skipped() {
+        let passed = vec![StageResult::passed(Stage::Cdp, "ok")];
+        let report = finish_report(passed, Some((Stage::Cookies, "No valid cookies found".into())));
+
+        assert_eq!(report.len(), Stage::ALL.len());
+        assert_eq!(report[0].ok, Some(true));
+        assert_eq!(first_failure(&report).map(|r| r.stage), Some(Stage::Cookies));
+        assert!(report[2..].iter().all(|r| r.ok.is_none()));
+
+        let all_ok = finish_report(vec![StageResult::passed(Stage::Cdp, "ok")], None);
+        assert!(first_failure(&all_ok).is_none());
+    }
+
+    #[test]
+    fn test_failure_gate_cools_down_and_probes() {
+        let start = Instant::now();
+        let mut gate = FailureGate::new();
+        let old = Some("sessionid=old");
+
+        // 窗口外的旧失败不计入
+        assert!(!gate.record_failure(start, old));
+        assert!(!gate.record_failure(start + COOLDOWN_WINDOW + Duration::from_secs(1), old));
+        let now = start + COOLDOWN_WINDOW + Duration::from_secs(2);
+        assert!(!gate.record_failure(now, old));
+        assert_eq!(gate.check(now, old), None);
+
+        assert!(gate.record_failure(now + Duration::from_secs(1), old));
+        assert_eq!(gate.check(now + Duration::from_secs(10), old), Some(3));
+        // Cookie 被清掉（None）不算变化
+        assert_eq!(gate.check(now + Duration::from_secs(10), None), Some(3));
+
+        // 到期后放行一次，再失败立即重新冷却
+        let expired = now + COOLDOWN_DURATION + Duration::from_secs(2);
+        assert_eq!(gate.check(expired, old), None);
+        assert!(gate.record_failure(expired, old));
+        assert!(gate.check(expired, old).is_some());
+
+        gate.reset();
+        assert_eq!(gate.check(expired, old), None);
+        assert!(!gate.record_failure(expired, old));
+    }
+
+    #[test]
+    fn test_new_cookies_clear_cooldown() {
+        let now = Instant::now();
+        let mut gate = FailureGate::new();
+        for _ in 0..COOLDOWN_FAILURES {
+            gate.record_failure(now, None);
+        }
+        assert!(gate.check(now, None).is_some());
+        // 重新登录后拿到了 Cookie
+        assert_eq!(gate.check(now, Some("sessionid=new")), None);
+        assert_eq!(gate.check(now, Some("sessionid=new")), None);
+    }
+
+    #[test]
+    fn test_only_service_failures_count() {
+        let connect = |e| counts_as_failure(&AsrError::Connect(e));
+        assert!(connect(TypeFreeError::NotLoggedIn));
+        assert!(connect(TypeFreeError::WsConnect("timeout".into())));
+        assert!(!connect(TypeFreeError::CaptureCancelled));
+        assert!(!connect(TypeFreeError::InvalidOverride("x".into())));
+
+        let server = |degraded| AsrError::Server {
+            error: ServerError {
+                code: 671000003,
+                message: "busy".to_string(),
+            },
+            stats: SessionStats {
+                degraded,
+                ..Default::default()
+            },
+        };
+        assert!(counts_as_failure(&server(false)));
+        assert!(!counts_as_failure(&server(true)));
+    }
+}
@@ -0,0 +1,2286 @@
+//! 豆包桌面端 CDP (Chrome DevTools Protocol) 模块
+//!
+//! 从豆包桌面端（以调试模式运行）获取 Cookie 和 ASR 请求参数
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use crate::error::TypeFreeError;
+use tokio_tungstenite::tungstenite::Message;
+
+/// 豆包调试端口上的 CDP HTTP 接口
+const DEFAULT_CDP_BASE_URL: &str = "http://127.0.0.1:9222";
+
+/// 覆盖的 CDP HTTP 接口地址（由 set_cdp_base_url 设置）
+static CDP_BASE_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// 获取 Cookie 时覆盖的站点（主站、ASR 子域和裸域的 host-only Cookie）
+const COOKIE_URLS: [&str; 3] = [
+    "https://www.doubao.com",
+    "https://ws-samantha.doubao.com",
+    "https://doubao.com",
+];
+
+/// CDP HTTP 请求超时（豆包卡死时不能阻塞按键流程）
+const CDP_HTTP_TIMEOUT: Duration = Duration::from_millis(1500);
+const CDP_CONNECT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// CDP WebSocket 连接和单次调用超时
+const CDP_WS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 瞬时错误（连接被重置等）重试前的等待
+const CDP_RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// 没有对话页时导航到的地址
+const CHAT_PAGE_URL: &str = "https://www.doubao.com/chat/";
+
+/// 导航到对话页并等待加载的总时限
+const CHAT_PAGE_RECOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 导航后轮询页面列表的间隔
+const CHAT_PAGE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 共享 HTTP 客户端（带超时）
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(CDP_HTTP_TIMEOUT)
+        .connect_timeout(CDP_CONNECT_TIMEOUT)
+        .no_proxy()
+        .build()
+        .unwrap_or_default()
+});
+
+/// ASR 认证依赖的 Cookie，缺失时识别通常会被拒绝
+const REQUIRED_AUTH_COOKIES: [&str; 3] = ["sessionid", "sid_tt", "uid_tt"];
+
+/// 从豆包获取并缓存的状态，整体放在一把锁后面，读到的各字段来自同一时刻
+#[derive(Debug, Clone, Default)]
+pub struct DoubaoState {
+    /// Cookie 头
+    pub cookies: Option<String>,
+    /// 登录状态
+    pub login_status: Option<bool>,
+    /// 最近一次的 ASR 请求信息
+    pub asr_request: Option<AsrRequestInfo>,
+    /// URL 参数模板（从真实请求捕获）
+    pub url_params: Option<HashMap<String, String>>,
+    /// 每次修改加一，比较两次快照的代数可知状态是否变过
+    pub generation: u64,
+}
+
+impl DoubaoState {
+    const fn new() -> Self {
+        Self {
+            cookies: None,
+            login_status: None,
+            asr_request: None,
+            url_params: None,
+            generation: 0,
+        }
+    }
+}
+
+static STATE: RwLock<DoubaoState> = RwLock::new(DoubaoState::new());
+
+/// 缓存状态的一致快照
+pub fn snapshot() -> DoubaoState {
+    read_state(DoubaoState::clone)
+}
+
+/// 在同一次加锁内修改缓存状态，代数加一
+pub fn update<R>(f: impl FnOnce(&mut DoubaoState) -> R) -> R {
+    let mut state = STATE.write().unwrap_or_else(|e| e.into_inner());
+    let result = f(&mut state);
+    state.generation += 1;
+    result
+}
+
+/// 当前缓存状态的代数
+pub fn state_generation() -> u64 {
+    read_state(|s| s.generation)
+}
+
+fn read_state<R>(f: impl FnOnce(&DoubaoState) -> R) -> R {
+    f(&STATE.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// ASR 请求信息（从豆包桌面端抓取）
+///
+/// 缓存的是参数模板，连接前用 [`build_session_url`] 生成本次会话的 URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsrRequestInfo {
+    /// 稳定参数和身份标识，不含每次会话重新生成的参数（见 [`SESSION_URL_PARAMS`]）
+    pub url_template: String,
+    pub user_agent: String,
+    pub origin: String,
+    /// WebSocket 请求的 Host 头（与 URL 中的主机一致）
+    pub host: String,
+    /// URL 参数的来源
+    pub param_source: ParamSource,
+}
+
+/// ASR URL 参数的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamSource {
+    /// 本次模拟点击捕获的真实请求
+    Captured,
+    /// 之前捕获并缓存的参数模板
+    Cached,
+    /// 内置参数（没能捕获真实请求）
+    Fallback,
+}
+
+/// 从 Cookie 列表中提取特定值
+fn extract_cookie_value(cookies: &[CdpCookie], name: &str) -> Option<String> {
+    cookies.iter()
+        .find(|c| c.name == name)
+        .map(|c| c.value.clone())
+}
+
+/// Cookie 是否属于 doubao.com（裸域 host-only、.doubao.com 及各子域）
+fn is_doubao_cookie_domain(domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.');
+    domain == "doubao.com" || domain.ends_with(".doubao.com")
+}
+
+/// 同名 Cookie 的优先级：ASR 子域 > 整站域 > 其他子域
+fn cookie_domain_priority(domain: &str) -> u8 {
+    match domain.trim_start_matches('.') {
+        "ws-samantha.doubao.com" => 2,
+        "doubao.com" => 1,
+        _ => 0,
+    }
+}
+
+/// 构建发送给 ws-samantha 的 Cookie 头
+///
+/// 包含 host-only 和子域 Cookie；同名 Cookie 只保留优先级最高的一个，保持首次出现的顺序
+fn build_cookie_header(cookies: &[CdpCookie]) -> String {
+    let mut selected: Vec<&CdpCookie> = Vec::new();
+
+    for cookie in cookies.iter().filter(|c| is_doubao_cookie_domain(&c.domain)) {
+        match selected.iter_mut().find(|c| c.name == cookie.name) {
+            Some(existing) => {
+                if cookie_domain_priority(&cookie.domain) > cookie_domain_priority(&existing.domain) {
+                    *existing = cookie;
+                }
+            }
+            None => selected.push(cookie),
+        }
+    }
+
+    let missing: Vec<&str> = REQUIRED_AUTH_COOKIES
+        .iter()
+        .copied()
+        .filter(|name| !selected.iter().any(|c| c.name == *name))
+        .collect();
+    if !missing.is_empty() {
+        log::warn!("[DoubaoCDP] Missing auth cookies: {:?}", missing);
+    }
+
+    selected
+        .iter()
+        .map(|c| format!("{}={}", c.name, c.value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// ============ 设备标识 ============
+
+/// device_id 的候选 Cookie（按优先级）
+const DEVICE_ID_COOKIES: [&str; 3] = ["device_id", "tt_webid", "s_v_web_id"];
+
+/// web_id 的候选 Cookie（按优先级）
+const WEB_ID_COOKIES: [&str; 2] = ["s_v_web_id", "tt_webid"];
+
+/// Cookie 都不可用时的内置标识（格式合法但不属于当前用户，识别多半会认证失败）
+const FALLBACK_DEVICE_ID: &str = "1707977353229076";
+const FALLBACK_WEB_ID: &str = "7589709632207275535";
+
+/// 设备标识的来源
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IdSource {
+    Cookie(&'static str),
+    Fallback,
+}
+
+/// 规范化设备标识：去掉 verify_ 前缀后应为 12 ~ 20 位数字，否则视为无效
+fn normalize_id(raw: &str) -> Option<String> {
+    let id = raw.trim().trim_start_matches("verify_");
+    let valid = (12..=20).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_digit());
+    valid.then(|| id.to_string())
+}
+
+/// 规范化 web_id：除数字外，也接受 s_v_web_id 的 verify_ 字母数字格式
+/// （如 verify_lx3k9a2b_AbCdEf，去掉前缀后为 8 ~ 64 位字母、数字或下划线）
+fn normalize_web_id(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    normalize_id(raw).or_else(|| {
+        let token = raw.strip_prefix("verify_")?;
+        let valid = (8..=64).contains(&token.len())
+            && token
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_');
+        valid.then(|| token.to_string())
+    })
+}
+
+/// 按优先级从 Cookie 中取第一个有效的标识，都无效时使用内置值
+fn resolve_id(
+    cookies: &[CdpCookie],
+    name: &str,
+    candidates: &[&'static str],
+    normalize: fn(&str) -> Option<String>,
+    fallback: &str,
+) -> (String, IdSource) {
+    for &cookie in candidates {
+        let Some(raw) = extract_cookie_value(cookies, cookie) else {
+            continue;
+        };
+        match normalize(&raw) {
+            Some(id) => return (id, IdSource::Cookie(cookie)),
+            None => log::warn!("[DoubaoCDP] Ignoring malformed {} in cookie {}", name, cookie),
+        }
+    }
+    (fallback.to_string(), IdSource::Fallback)
+}
+
+/// 构建 URL 时用了内置标识的通知（如提示用户重新登录）
+type FallbackIdNotifier = Box<dyn Fn(&str) + Send + Sync>;
+static FALLBACK_ID_NOTIFIER: OnceLock<FallbackIdNotifier> = OnceLock::new();
+
+/// 设置使用内置标识时的通知（参数为使用了内置值的标识名）
+pub fn set_fallback_id_notifier<F>(notifier: F)
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    let _ = FALLBACK_ID_NOTIFIER.set(Box::new(notifier));
+}
+
+/// 从 User-Agent 解析版本信息
+fn parse_user_agent(ua: &str) -> (String, String) {
+    // 解析 SamanthaDoubao/x.xx.x
+    let pc_version = ua
+        .split("SamanthaDoubao/")
+        .nth(1)
+        .and_then(|s| s.split_whitespace().next())
+        .unwrap_or("1.85.8")
+        .to_string();
+
+    // 解析 Chrome/xxx.x.xxxx.xx
+    let chromium_version = ua
+        .split("Chrome/")
+        .nth(1)
+        .and_then(|s| s.split_whitespace().next())
+        .unwrap_or("135.0.0.0")
+        .to_string();
+
+    (pc_version, chromium_version)
+}
+
+// ============ ASR 端点 ============
+
+/// 默认的 ASR WebSocket 主机
+pub const DEFAULT_ASR_HOST: &str = "ws-samantha.doubao.com";
+
+/// ASR 路径
+const ASR_PATH: &str = "/samantha/audio/asr";
+
+/// ASR 服务端点（区域和备用主机），来自设置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsrEndpoint {
+    /// 备用 WebSocket 主机，None 使用默认主机
+    pub host: Option<String>,
+    /// region / sys_region 参数，为空时保留模板原值
+    pub region: String,
+}
+
+impl AsrEndpoint {
+    /// 从当前设置读取
+    pub fn current() -> Self {
+        let settings = crate::settings::get();
+        Self {
+            host: settings.asr_host,
+            region: settings.asr_region,
+        }
+    }
+
+    /// 实际使用的主机
+    pub fn host(&self) -> &str {
+        match self.host.as_deref().map(str::trim) {
+            Some(host) if !host.is_empty() => host,
+            _ => DEFAULT_ASR_HOST,
+        }
+    }
+
+    /// 实际使用的区域（去掉首尾空白）
+    pub fn region(&self) -> &str {
+        self.region.trim()
+    }
+
+    /// 检查主机和区域（保存设置前调用）
+    pub fn validate(&self) -> Result<(), String> {
+        let host = self.host();
+        let (name, port) = match host.rsplit_once(':') {
+            Some((name, port)) => (name, Some(port)),
+            None => (host, None),
+        };
+        let valid_name = !name.is_empty()
+            && name.split('.').all(|label| {
+                !label.is_empty()
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+        if !valid_name || port.is_some_and(|p| p.parse::<u16>().is_err()) {
+            return Err(format!(
+                "ASR 主机格式不正确：{}（只填主机名，如 {}）",
+                host, DEFAULT_ASR_HOST
+            ));
+        }
+        // Cookie 只取 doubao.com 的，其他域名上的 ASR 服务无法认证
+        if !is_doubao_cookie_domain(&name.to_ascii_lowercase()) {
+            return Err(format!("ASR 主机必须是 doubao.com 的子域名：{}", host));
+        }
+
+        let region = self.region();
+        if region.len() > 16
+            || !region.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("区域只能包含字母、数字、- 和 _：{}", region));
+        }
+        Ok(())
+    }
+
+    /// 日志中显示的端点
+    fn describe(&self) -> String {
+        let region = if self.region().is_empty() { "(template)" } else { self.region() };
+        format!("wss://{}{} region={}", self.host(), ASR_PATH, region)
+    }
+}
+
+/// 每次会话重新生成的 URL 参数，不进入缓存的模板
+pub const SESSION_URL_PARAMS: [&str; 1] = ["web_tab_id"];
+
+/// 生成本次会话的 ASR URL：补上新的 web_tab_id
+///
+/// 模板里已有的会话参数（来自高级设置的覆盖）保持不变
+pub fn build_session_url(info: &AsrRequestInfo) -> String {
+    let params = parse_asr_url_params(&info.url_template);
+    let fresh: Vec<(String, String)> = SESSION_URL_PARAMS
+        .iter()
+        .filter(|key| !params.contains_key(**key))
+        .map(|key| (key.to_string(), uuid::Uuid::new_v4().to_string()))
+        .collect();
+    override_url_params(&info.url_template, &fresh)
+}
+
+/// 构建 ASR URL 模板（内置参数，不含会话参数）
+fn build_asr_url(
+    device_id: &str,
+    web_id: &str,
+    pc_version: &str,
+    chromium_version: &str,
+    endpoint: &AsrEndpoint,
+) -> String {
+    let host = endpoint.host();
+    let region = endpoint.region();
+
+    format!(
+        "wss://{host}{ASR_PATH}?\
+         version_code=20800&\
+         language=zh&\
+         device_platform=web&\
+         aid=582478&\
+         real_aid=582478&\
+         pkg_type=release_version&\
+         device_id={device_id}&\
+         pc_version={pc_version}&\
+         web_id={web_id}&\
+         tea_uuid={device_id}&\
+         region={region}&\
+         sys_region={region}&\
+         samantha_web=1&\
+         use-olympus-account=1&\
+         runtime=web&\
+         runtime_version=2.51.3&\
+         client_platform=pc_client&\
+         chromium_version={chromium_version}&\
+         fp=verify_{web_id}&\
+         format=pcm"
+    )
+}
+
+impl Default for AsrRequestInfo {
+    fn default() -> Self {
+        Self {
+            url_template: "wss://ws-samantha.doubao.com/samantha/audio/asr?version_code=20800&language=zh&device_platform=web&aid=582478&real_aid=582478&format=pcm".to_string(),
+            user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36 SamanthaDoubao/1.85.8".to_string(),
+            origin: "https://www.doubao.com".to_string(),
+            host: DEFAULT_ASR_HOST.to_string(),
+            param_source: ParamSource::Fallback,
+        }
+    }
+}
+
+/// CDP /json/version 响应（用于确认调试端口属于豆包）
+#[derive(Debug, Clone, Deserialize)]
+pub struct CdpVersion {
+    #[serde(rename = "Browser", default)]
+    pub browser: String,
+    #[serde(rename = "User-Agent", default)]
+    pub user_agent: String,
+}
+
+impl CdpVersion {
+    /// 是否为豆包桌面端（UA 中带 SamanthaDoubao/ 标识）
+    pub fn is_doubao(&self) -> bool {
+        self.user_agent.contains("SamanthaDoubao/")
+    }
+}
+
+/// 解析 /json/version 响应
+fn parse_cdp_version(body: &str) -> Result<CdpVersion, TypeFreeError> {
+    serde_json::from_str(body).map_err(|e| unavailable(format!("Failed to parse CDP version: {}", e)))
+}
+
+/// 改用其他 CDP HTTP 接口（如 `http://127.0.0.1:9333` 或模拟服务），None 恢复默认端口
+pub fn set_cdp_base_url(base: Option<String>) {
+    *CDP_BASE_URL.write().unwrap() = base;
+}
+
+/// CDP HTTP 接口的完整地址
+fn cdp_url(path: &str) -> String {
+    let base = CDP_BASE_URL.read().ok().and_then(|base| base.clone());
+    format!("{}{}", base.as_deref().unwrap_or(DEFAULT_CDP_BASE_URL), path)
+}
+
+/// 获取调试端口上的浏览器信息
+pub async fn fetch_cdp_version() -> Result<CdpVersion, TypeFreeError> {
+    let body = http_get_text(&cdp_url("/json/version")).await?;
+    parse_cdp_version(&body)
+}
+
+/// 确认调试端口属于豆包，否则返回"端口被其他浏览器占用"
+pub async fn verify_cdp_endpoint() -> Result<(), TypeFreeError> {
+    let version = fetch_cdp_version().await?;
+    if version.is_doubao() {
+        Ok(())
+    } else {
+        log::warn!(
+            "[DoubaoCDP] Debug port is owned by another browser: {} ({})",
+            version.browser,
+            version.user_agent
+        );
+        Err(TypeFreeError::ForeignBrowser(version.browser))
+    }
+}
+
+/// CDP 页面信息
+#[derive(Debug, Clone, Deserialize)]
+struct CdpPage {
+    url: String,
+    #[serde(default)]
+    title: String,
+    /// 目标类型（page、iframe、service_worker 等）
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(rename = "webSocketDebuggerUrl")]
+    websocket_debugger_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CdpResult {
+    cookies: Option<Vec<CdpCookie>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CdpCookie {
+    name: String,
+    value: String,
+    domain: String,
+}
+
+
+// ============ CDP 通用层 ============
+
+/// CDP 调用失败（连不上、超时、响应无效）
+fn unavailable(detail: impl Into<String>) -> TypeFreeError {
+    TypeFreeError::CdpUnavailable(detail.into())
+}
+
+/// 是否为值得重试一次的瞬时错误（连接被重置/中断）
+fn is_transient_io_error(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+            );
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// GET 请求 CDP HTTP 接口，瞬时错误重试一次
+async fn http_get_text(url: &str) -> Result<String, TypeFreeError> {
+    let mut retried = false;
+    loop {
+        let result = async {
+            HTTP_CLIENT.get(url).send().await?.error_for_status()?.text().await
+        }
+        .await;
+
+        match result {
+            Ok(text) => return Ok(text),
+            Err(e) if !retried && is_transient_io_error(&e) => {
+                log::warn!("[DoubaoCDP] Transient error on {}: {}, retrying", url, e);
+                retried = true;
+                tokio::time::sleep(CDP_RETRY_BACKOFF).await;
+            }
+            Err(e) if e.is_timeout() => return Err(unavailable(format!("CDP request timed out: {}", url))),
+            Err(e) => {
+                return Err(unavailable(format!(
+                    "Failed to connect to CDP: {}. Is Doubao running with --remote-debugging-port=9222?",
+                    e
+                )))
+            }
+        }
+    }
+}
+
+/// 获取 CDP 页面列表
+async fn fetch_pages() -> Result<Vec<CdpPage>, TypeFreeError> {
+    let body = http_get_text(&cdp_url("/json/list")).await?;
+    serde_json::from_str(&body).map_err(|e| unavailable(format!("Failed to parse CDP response: {}", e)))
+}
+
+/// 找到要使用的豆包页面：优先设置中选择的页面，已不存在时退回第一个 doubao.com/chat 页面
+fn find_chat_page<'a>(pages: &'a [CdpPage], selected: Option<&str>) -> Option<&'a CdpPage> {
+    if let Some(url) = selected {
+        if let Some(page) = pages.iter().find(|p| p.url == url && p.url.contains("doubao.com")) {
+            return Some(page);
+        }
+        log::info!("[DoubaoCDP] Selected page is gone ({}), using first chat page", url);
+    }
+    pages
+        .iter()
+        .find(|p| p.url.contains("doubao.com") && p.url.contains("chat"))
+}
+
+/// 设置中选择的豆包页面
+fn selected_page() -> Option<String> {
+    crate::settings::get().doubao_page
+}
+
+type CdpStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// CDP WebSocket 会话
+///
+/// 所有调用都有超时；等待响应期间收到的事件会缓存，供 next_event 读取
+struct CdpSession {
+    ws: CdpStream,
+    next_id: u64,
+    pending_events: VecDeque<serde_json::Value>,
+}
+
+impl CdpSession {
+    /// 连接页面调试 WebSocket（带超时，瞬时错误重试一次）
+    async fn connect(ws_url: &str) -> Result<Self, TypeFreeError> {
+        let mut retried = false;
+        loop {
+            match tokio::time::timeout(CDP_WS_TIMEOUT, tokio_tungstenite::connect_async(ws_url)).await {
+                Ok(Ok((ws, _))) => {
+                    return Ok(Self {
+                        ws,
+                        next_id: 1,
+                        pending_events: VecDeque::new(),
+                    })
+                }
+                Ok(Err(e)) if !retried && is_transient_io_error(&e) => {
+                    log::warn!("[DoubaoCDP] Transient CDP connect error: {}, retrying", e);
+                    retried = true;
+                    tokio::time::sleep(CDP_RETRY_BACKOFF).await;
+                }
+                Ok(Err(e)) => return Err(unavailable(format!("Failed to connect CDP WebSocket: {}", e))),
+                Err(_) => return Err(unavailable("CDP WebSocket connect timed out")),
+            }
+        }
+    }
+
+    /// 发送命令，不等待响应，返回命令 id
+    async fn send(&mut self, method: &str, params: serde_json::Value) -> Result<u64, TypeFreeError> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = serde_json::json!({
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+
+        tokio::time::timeout(CDP_WS_TIMEOUT, self.ws.send(Message::Text(request.to_string())))
+            .await
+            .map_err(|_| unavailable(format!("CDP {} send timed out", method)))?
+            .map_err(|e| unavailable(format!("Failed to send {}: {}", method, e)))?;
+
+        Ok(id)
+    }
+
+    /// 发送命令并等待对应 id 的响应，返回 result 字段
+    async fn call(&mut self, method: &str, params: serde_json::Value) -> Result<serde_json::Value, TypeFreeError> {
+        let id = self.send(method, params).await?;
+        let deadline = tokio::time::Instant::now() + CDP_WS_TIMEOUT;
+
+        loop {
+            let msg = tokio::time::timeout_at(deadline, self.ws.next())
+                .await
+                .map_err(|_| unavailable(format!("CDP {} timed out", method)))?
+                .ok_or_else(|| unavailable("No response from CDP"))?
+                .map_err(|e| unavailable(format!("CDP WebSocket error: {}", e)))?;
+
+            let Message::Text(text) = msg else {
+                continue;
+            };
+            let data: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|e| unavailable(format!("Failed to parse CDP response: {}", e)))?;
+
+            if data.get("id").and_then(|i| i.as_u64()) == Some(id) {
+                if let Some(error) = data.get("error") {
+                    return Err(unavailable(format!("CDP {} failed: {}", method, error)));
+                }
+                return Ok(data.get("result").cloned().unwrap_or(serde_json::Value::Null));
+            }
+
+            // 其他消息（事件或旧命令的响应）留给 next_event
+            if data.get("method").is_some() {
+                self.pending_events.push_back(data);
+            }
+        }
+    }
+
+    /// 读取下一条事件，超时返回 None
+    async fn next_event(&mut self, timeout: Duration) -> Option<serde_json::Value> {
+        if let Some(event) = self.pending_events.pop_front() {
+            return Some(event);
+        }
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match tokio::time::timeout_at(deadline, self.ws.next()).await {
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    if let Ok(data) = serde_json::from_str::<serde_json::Value>(&text) {
+                        if data.get("method").is_some() {
+                            return Some(data);
+                        }
+                    }
+                }
+                Ok(Some(Ok(_))) => continue,
+                _ => return None,
+            }
+        }
+    }
+
+    /// 执行 JS，返回值（returnByValue）
+    async fn evaluate(&mut self, expression: &str) -> Result<serde_json::Value, TypeFreeError> {
+        let result = self
+            .call(
+                "Runtime.evaluate",
+                serde_json::json!({ "expression": expression, "returnByValue": true }),
+            )
+            .await?;
+
+        Ok(result
+            .get("result")
+            .and_then(|r| r.get("value"))
+            .cloned()
+            .unwrap_or(serde_json::Value::Null))
+    }
+
+    /// 获取 doubao.com 相关 Cookie（含设置的备用 ASR 主机）
+    async fn get_cookies(&mut self) -> Result<Vec<CdpCookie>, TypeFreeError> {
+        let mut urls: Vec<String> = COOKIE_URLS.iter().map(|url| url.to_string()).collect();
+        let endpoint = AsrEndpoint::current();
+        if endpoint.host() != DEFAULT_ASR_HOST {
+            urls.push(format!("https://{}", endpoint.host()));
+        }
+        let result = self
+            .call("Network.getCookies", serde_json::json!({ "urls": urls }))
+            .await?;
+
+        let parsed: CdpResult = serde_json::from_value(result)
+            .map_err(|e| unavailable(format!("Failed to parse CDP response: {}", e)))?;
+        parsed.cookies.ok_or_else(|| unavailable("No cookies in CDP response"))
+    }
+}
+
+// ============ 对话页 ============
+
+/// 可以导航到对话页的目标：doubao.com 的普通页面
+fn find_navigable_page(pages: &[CdpPage]) -> Option<&CdpPage> {
+    pages.iter().find(|p| {
+        p.kind == "page" && p.url.contains("doubao.com") && p.websocket_debugger_url.is_some()
+    })
+}
+
+/// 获取要使用的对话页，没有时先把一个豆包页面导航到对话页（调用方需持有闸门）
+async fn resolve_chat_page() -> Result<CdpPage, TypeFreeError> {
+    let pages = fetch_pages().await?;
+    log::info!("[DoubaoCDP] Found {} pages", pages.len());
+    if let Some(page) = find_chat_page(&pages, selected_page().as_deref()) {
+        return Ok(page.clone());
+    }
+
+    for p in &pages {
+        log::info!("[DoubaoCDP]   - {}", p.url);
+    }
+    open_chat_page(&pages).await
+}
+
+/// 是否是登录页（未登录时打开对话页会跳到这里）
+fn is_login_url(url: &str) -> bool {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    host.starts_with("passport.")
+        || (host.contains("doubao.com")
+            && path.split(['/', '?', '#']).any(|segment| segment.starts_with("login")))
+}
+
+/// 导航后没有出现对话页时，判断是不是因为没有登录（跳到登录页，或页面上显示登录按钮）
+async fn shows_login(session: &mut CdpSession, pages: &[CdpPage]) -> bool {
+    if let Some(page) = pages.iter().find(|p| is_login_url(&p.url)) {
+        log::warn!("[DoubaoCDP] Redirected to login page: {}", page.url);
+        return true;
+    }
+    let rules = crate::settings::get().login_detection;
+    matches!(session.evaluate(&login_check_js(&rules)).await, Ok(state) if state == "logged_out")
+}
+
+/// 把一个豆包页面导航到对话页，等加载完成后重新查找（有总时限）
+///
+/// 豆包没有登录时不会出现对话页，此时返回 NotLoggedIn
+async fn open_chat_page(pages: &[CdpPage]) -> Result<CdpPage, TypeFreeError> {
+    let target = find_navigable_page(pages).ok_or(TypeFreeError::NoDoubaoPage)?;
+    let ws_url = target
+        .websocket_debugger_url
+        .as_deref()
+        .ok_or_else(|| unavailable("No WebSocket debugger URL"))?;
+    log::info!(
+        "[DoubaoCDP] No chat page, navigating {} to {}",
+        target.url,
+        CHAT_PAGE_URL
+    );
+
+    let recovery = async {
+        let mut session = CdpSession::connect(ws_url).await?;
+        session.call("Page.enable", serde_json::json!({})).await?;
+        let result = session
+            .call("Page.navigate", serde_json::json!({ "url": CHAT_PAGE_URL }))
+            .await?;
+        if let Some(error) = result.get("errorText").and_then(|e| e.as_str()) {
+            return Err(unavailable(format!("Page.navigate failed: {}", error)));
+        }
+
+        while let Some(event) = session.next_event(CHAT_PAGE_RECOVERY_TIMEOUT).await {
+            if event["method"] == "Page.loadEventFired" {
+                break;
+            }
+        }
+
+        // 加载完成后 /json/list 可能稍晚才更新 URL
+        loop {
+            let pages = fetch_pages().await?;
+            if let Some(page) = find_chat_page(&pages, None) {
+                log::info!("[DoubaoCDP] Opened chat page: {}", page.url);
+                return Ok(page.clone());
+            }
+            if shows_login(&mut session, &pages).await {
+                return Err(TypeFreeError::NotLoggedIn);
+            }
+            tokio::time::sleep(CHAT_PAGE_POLL_INTERVAL).await;
+        }
+    };
+
+    tokio::time::timeout(CHAT_PAGE_RECOVERY_TIMEOUT, recovery)
+        .await
+        .map_err(|_| TypeFreeError::NoChatPage("Timed out opening a doubao.com/chat page".to_string()))?
+        .map_err(|e| match e {
+            TypeFreeError::NotLoggedIn => e,
+            e => TypeFreeError::NoChatPage(format!("Failed to open a doubao.com/chat page: {}", e)),
+        })
+}
+
+// ============ CDP 并发闸门 ============
+
+/// 同一时间只允许一个流程操作豆包页面和 CDP 连接
+static CDP_GATE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// 捕获 ASR URL 的预计耗时（连接 + 等待 2 秒 + 停止 0.5 秒）
+const CAPTURE_EXPECTED_DURATION: Duration = Duration::from_millis(3000);
+
+/// 捕获剩余时间不超过该值时听写等它完成，否则取消捕获
+const CAPTURE_FINISH_GRACE: Duration = Duration::from_secs(1);
+
+/// 正在进行的 ASR URL 捕获
+struct ActiveCapture {
+    started: Instant,
+    cancel: Arc<Notify>,
+}
+
+static ACTIVE_CAPTURE: Mutex<Option<ActiveCapture>> = Mutex::new(None);
+
+/// 听写需要等待闸门时的通知（如浮层提示）
+static GATE_WAIT_NOTIFIER: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+/// 设置听写等待闸门时的通知
+pub fn set_gate_wait_notifier<F>(notifier: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let _ = GATE_WAIT_NOTIFIER.set(Box::new(notifier));
+}
+
+/// 普通 CDP 操作：排队等待闸门
+async fn acquire_gate() -> tokio::sync::MutexGuard<'static, ()> {
+    CDP_GATE.lock().await
+}
+
+/// 听写优先：正在捕获且离结束还远时取消捕获，否则等它完成
+async fn acquire_gate_for_dictation() -> tokio::sync::MutexGuard<'static, ()> {
+    if let Ok(guard) = CDP_GATE.try_lock() {
+        return guard;
+    }
+
+    let capture = ACTIVE_CAPTURE
+        .lock()
+        .ok()
+        .and_then(|c| c.as_ref().map(|c| (c.started.elapsed(), c.cancel.clone())));
+
+    match capture {
+        Some((elapsed, cancel)) if elapsed + CAPTURE_FINISH_GRACE < CAPTURE_EXPECTED_DURATION => {
+            log::info!(
+                "[DoubaoCDP] Cancelling ASR URL capture ({}ms in) in favor of dictation",
+                elapsed.as_millis()
+            );
+            cancel.notify_one();
+        }
+        _ => {
+            log::info!("[DoubaoCDP] Waiting for in-flight CDP operation to finish...");
+            if let Some(notify) = GATE_WAIT_NOTIFIER.get() {
+                notify();
+            }
+        }
+    }
+
+    CDP_GATE.lock().await
+}
+
+/// 从豆包桌面端获取 Cookie
+pub async fn fetch_cookies() -> Result<String, TypeFreeError> {
+    log::info!("[DoubaoCDP] Fetching cookies from Doubao desktop...");
+
+    let _gate = acquire_gate().await;
+    verify_cdp_endpoint().await?;
+
+    // 找到 doubao.com/chat 页面（没有时自动打开）
+    let chat_page = resolve_chat_page().await?;
+
+    let ws_url = chat_page
+        .websocket_debugger_url
+        .as_ref()
+        .ok_or_else(|| unavailable("No WebSocket debugger URL"))?;
+
+    log::info!("[DoubaoCDP] Connecting to: {}", ws_url);
+
+    // 连接 CDP 并获取 Cookie
+    let mut session = CdpSession::connect(ws_url).await?;
+    let cookies = session.get_cookies().await?;
+
+    log::info!("[DoubaoCDP] Got {} cookies", cookies.len());
+
+    // 构建 Cookie 字符串
+    let cookie_str = build_cookie_header(&cookies);
+
+    if cookie_str.is_empty() {
+        return Err(TypeFreeError::NotLoggedIn);
+    }
+
+    // 缓存 Cookie
+    update(|s| s.cookies = Some(cookie_str.clone()));
+
+    log::info!("[DoubaoCDP] Cookie string length: {}", cookie_str.len());
+    Ok(cookie_str)
+}
+
+// 单个字段的读写（同时用到多个字段时用 snapshot / update）
+
+/// 获取缓存的 Cookie
+pub fn get_cached_cookies() -> Option<String> {
+    read_state(|s| s.cookies.clone())
+}
+
+/// 获取缓存的登录状态
+pub fn get_cached_login_status() -> Option<bool> {
+    read_state(|s| s.login_status)
+}
+
+/// 设置缓存的登录状态
+pub fn set_cached_login_status(status: bool) {
+    update(|s| s.login_status = Some(status));
+}
+
+/// 获取缓存的 ASR 请求信息
+pub fn get_cached_asr_request() -> Option<AsrRequestInfo> {
+    read_state(|s| s.asr_request.clone())
+}
+
+/// 获取缓存的 URL 参数模板
+pub fn get_cached_url_params() -> Option<HashMap<String, String>> {
+    read_state(|s| s.url_params.clone())
+}
+
+/// 设置 URL 参数模板缓存
+pub fn set_cached_url_params(params: HashMap<String, String>) {
+    update(|s| s.url_params = Some(params));
+}
+
+/// 清除 URL 参数缓存
+pub fn clear_cached_url_params() {
+    update(|s| s.url_params = None);
+}
+
+/// 清除缓存的 Cookie
+pub fn clear_cached_cookies() {
+    update(|s| s.cookies = None);
+}
+
+/// 清除账号相关的缓存（Cookie、请求信息和参数模板），换账号时使用
+pub fn clear_account_cache() {
+    update(|s| {
+        s.cookies = None;
+        s.asr_request = None;
+        s.url_params = None;
+    });
+}
+
+/// 解析 ASR URL 中的参数
+pub fn parse_asr_url_params(url: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+
+    // 提取 query string
+    if let Some(query_start) = url.find('?') {
+        let query = &url[query_start + 1..];
+        for pair in query.split('&') {
+            if let Some(eq_pos) = pair.find('=') {
+                let key = &pair[..eq_pos];
+                let value = &pair[eq_pos + 1..];
+                params.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    params
+}
+
+/// 替换（或追加）URL 中的查询参数，用于单次会话的临时覆盖
+pub fn override_url_params(url: &str, overrides: &[(String, String)]) -> String {
+    if overrides.is_empty() {
+        return url.to_string();
+    }
+
+    let (base, query) = url.split_once('?').unwrap_or((url, ""));
+    let mut pairs: Vec<(String, String)> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (k.to_string(), v.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect();
+
+    for (key, value) in overrides {
+        match pairs.iter_mut().find(|(k, _)| k == key) {
+            Some(pair) => pair.1 = value.clone(),
+            None => pairs.push((key.clone(), value.clone())),
+        }
+    }
+
+    let query: Vec<String> = pairs.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    format!("{}?{}", base, query.join("&"))
+}
+
+/// 日志中隐藏的 URL 参数（设备标识）
+const SENSITIVE_URL_PARAMS: [&str; 4] = ["device_id", "web_id", "tea_uuid", "fp"];
+
+/// 隐藏设备标识后的 URL，用于日志
+pub fn redact_url(url: &str) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if SENSITIVE_URL_PARAMS.contains(&key) && !value.is_empty() => {
+                format!("{}=***", key)
+            }
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", base, query.join("&"))
+}
+
+/// 使用缓存的参数模板构建 URL 模板
+///
+/// template_params: 从真实请求捕获的参数模板
+/// 去掉 web_tab_id 等会话参数（连接时由 [`build_session_url`] 重新生成），
+/// 设置了区域时替换 region / sys_region，其他参数保持模板原值
+fn build_asr_url_from_template(
+    template_params: &HashMap<String, String>,
+    endpoint: &AsrEndpoint,
+) -> String {
+    let mut final_params: Vec<(String, String)> = Vec::new();
+
+    for (key, value) in template_params {
+        if SESSION_URL_PARAMS.contains(&key.as_str()) {
+            continue;
+        } else if (key == "region" || key == "sys_region") && !endpoint.region().is_empty() {
+            final_params.push((key.clone(), endpoint.region().to_string()));
+        } else {
+            // 其他参数保持模板原值
+            final_params.push((key.clone(), value.clone()));
+        }
+    }
+
+    // 构建 URL
+    let query: String = final_params
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("wss://{}{}?{}", endpoint.host(), ASR_PATH, query)
+}
+
+/// 通过模拟点击捕获真实 ASR URL
+///
+/// 流程：
+/// 1. 连接 CDP
+/// 2. 启用网络监控
+/// 3. 执行 JS 模拟点击语音按钮
+/// 4. 监听 Network.webSocketCreated 捕获 URL
+/// 5. 执行 JS 模拟点击停止按钮，确认按钮回到原状态（没恢复时重试）
+/// 6. 返回捕获的 URL
+///
+/// 听写开始时若离捕获结束还远，会被取消并返回 `TypeFreeError::CaptureCancelled`
+pub async fn capture_asr_url_by_click() -> Result<String, TypeFreeError> {
+    let _gate = acquire_gate().await;
+
+    let cancel = Arc::new(Notify::new());
+    if let Ok(mut active) = ACTIVE_CAPTURE.lock() {
+        *active = Some(ActiveCapture {
+            started: Instant::now(),
+            cancel: cancel.clone(),
+        });
+    }
+
+    let result = capture_asr_url(Some(&cancel)).await;
+
+    if let Ok(mut active) = ACTIVE_CAPTURE.lock() {
+        *active = None;
+    }
+    result
+}
+
+/// 被动捕获 ASR URL：不点击语音按钮，只监听页面接下来建立的 ASR 连接
+///
+/// 用户在豆包中使用一次语音输入即可捕获。没有对话页时需要先导航过去，
+/// 这一步持有闸门；之后的监听不改变页面状态，不占用闸门
+pub async fn capture_asr_url_passively(timeout: Duration) -> Result<String, TypeFreeError> {
+    log::info!(
+        "[DoubaoCDP] Waiting up to {}s for Doubao to open an ASR WebSocket...",
+        timeout.as_secs()
+    );
+    let chat_page = {
+        let _gate = acquire_gate().await;
+        resolve_chat_page().await?
+    };
+    let ws_url = chat_page
+        .websocket_debugger_url
+        .as_ref()
+        .ok_or_else(|| unavailable("No WebSocket debugger URL"))?;
+    let mut session = CdpSession::connect(ws_url).await?;
+    session
+        .call("Network.enable", serde_json::json!({}))
+        .await
+        .map_err(|e| unavailable(format!("Failed to enable network: {}", e)))?;
+
+    match watch_asr_websocket(&mut session, timeout, None, true).await {
+        (Some(url), _) => {
+            log::info!("[DoubaoCDP] Passively captured ASR URL");
+            Ok(url)
+        }
+        (None, _) => Err(TypeFreeError::AsrCapture(
+            "No ASR WebSocket observed, please use voice input in Doubao once".to_string(),
+        )),
+    }
+}
+
+/// 是否为豆包 ASR 的 WebSocket 地址
+fn is_asr_websocket(url: &str) -> bool {
+    url.contains("samantha") && url.contains("asr")
+}
+
+/// 监听 Network.webSocketCreated 等待 ASR 连接：等满 duration，stop_on_capture 时捕获到即返回
+///
+/// 返回 (捕获的 URL, 是否被取消)
+async fn watch_asr_websocket(
+    session: &mut CdpSession,
+    duration: Duration,
+    cancel: Option<&Notify>,
+    stop_on_capture: bool,
+) -> (Option<String>, bool) {
+    let mut captured_url: Option<String> = None;
+    let wait_start = std::time::Instant::now();
+
+    while wait_start.elapsed() < duration {
+        let event = match cancel {
+            Some(cancel) => tokio::select! {
+                _ = cancel.notified() => return (captured_url, true),
+                event = session.next_event(Duration::from_millis(50)) => event,
+            },
+            None => session.next_event(Duration::from_millis(50)).await,
+        };
+        let Some(data) = event else {
+            continue;
+        };
+        let method = data.get("method").and_then(|m| m.as_str()).unwrap_or("");
+        if method == "Network.webSocketCreated" {
+            if let Some(params) = data.get("params") {
+                let url = params.get("url").and_then(|u| u.as_str()).unwrap_or("");
+                if is_asr_websocket(url) {
+                    log::info!("[DoubaoCDP] Captured ASR URL");
+                    captured_url = Some(url.to_string());
+                    if stop_on_capture {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    (captured_url, false)
+}
+
+// ============ 语音按钮 ============
+
+/// 点击语音按钮开始录音，返回点击前的 data-state（toggle 按钮：点一次开始，再点一次停止）
+const CLICK_START_JS: &str = r#"
+    (function() {
+        const btn = document.querySelector('[data-testid="asr_btn"]');
+        if (btn) {
+            const state = btn.getAttribute('data-state') || '';
+            console.log('[TypeFree] Clicking asr_btn to START, current state:', state);
+            btn.click();
+            return { result: 'clicked', state: state };
+        }
+        console.error('[TypeFree] asr_btn not found!');
+        return 'not_found';
+    })()
+"#;
+
+/// 模拟完整的鼠标点击停止录音
+const CLICK_STOP_JS: &str = r#"
+    (function() {
+        const btn = document.querySelector('[data-testid="asr_btn"]');
+        if (btn) {
+            const rect = btn.getBoundingClientRect();
+            const x = rect.left + rect.width / 2;
+            const y = rect.top + rect.height / 2;
+            const opts = { bubbles: true, cancelable: true, view: window, clientX: x, clientY: y, button: 0 };
+            btn.dispatchEvent(new MouseEvent('mousedown', opts));
+            btn.dispatchEvent(new MouseEvent('mouseup', opts));
+            btn.dispatchEvent(new MouseEvent('click', opts));
+            return 'stopped';
+        }
+        return 'not_found';
+    })()
+"#;
+
+/// 读取语音按钮当前的 data-state（找不到按钮时返回 null）
+const BUTTON_STATE_JS: &str = r#"
+    (function() {
+        const btn = document.querySelector('[data-testid="asr_btn"]');
+        return btn ? { button_state: btn.getAttribute('data-state') || '' } : null;
+    })()
+"#;
+
+/// 点击停止的最多次数
+const STOP_ATTEMPTS: usize = 3;
+
+/// 每次点击停止后检查按钮状态的次数和间隔（状态更新可能稍慢，确认没恢复才再次点击）
+const STOP_CHECKS: usize = 5;
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 点击开始的脚本返回的按钮原状态，旧页面没有返回时为 None
+fn initial_button_state(value: &serde_json::Value) -> Option<String> {
+    value.get("state")?.as_str().map(str::to_string)
+}
+
+/// 按钮状态脚本的结果，找不到按钮时为 None
+fn current_button_state(value: &serde_json::Value) -> Option<String> {
+    value.get("button_state")?.as_str().map(str::to_string)
+}
+
+/// 点击停止并确认按钮回到开始前的状态，没有恢复时重试
+///
+/// 不知道原状态（页面没有返回）时只点击一次
+async fn stop_voice_button(session: &mut CdpSession, initial_state: Option<&str>) {
+    for attempt in 1..=STOP_ATTEMPTS {
+        log::info!("[DoubaoCDP] Clicking to STOP (attempt {}/{})...", attempt, STOP_ATTEMPTS);
+        if let Err(e) = session.evaluate(CLICK_STOP_JS).await {
+            log::warn!("[DoubaoCDP] Stop command failed: {}", e);
+        }
+
+        let Some(initial) = initial_state else {
+            // 等待停止命令执行
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            log::info!("[DoubaoCDP] Stop command sent (button state unknown)");
+            return;
+        };
+
+        let mut state = None;
+        for _ in 0..STOP_CHECKS {
+            tokio::time::sleep(STOP_CHECK_INTERVAL).await;
+            state = match session.evaluate(BUTTON_STATE_JS).await {
+                Ok(value) => current_button_state(&value),
+                Err(e) => {
+                    log::warn!("[DoubaoCDP] Failed to read voice button state: {}", e);
+                    return;
+                }
+            };
+            match state.as_deref() {
+                None => {
+                    log::warn!("[DoubaoCDP] Voice button disappeared after stop");
+                    return;
+                }
+                Some(current) if current == initial => {
+                    log::info!("[DoubaoCDP] Voice button back to '{}'", initial);
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
+        log::warn!(
+            "[DoubaoCDP] Voice button still '{}' after stop (expected '{}')",
+            state.unwrap_or_default(),
+            initial
+        );
+    }
+    log::error!("[DoubaoCDP] Voice button did not return to idle, Doubao may still be recording");
+}
+
+/// 捕获 ASR URL（调用方需持有闸门）
+async fn capture_asr_url(cancel: Option<&Notify>) -> Result<String, TypeFreeError> {
+    log::info!("[DoubaoCDP] Capturing ASR URL by simulating click...");
+
+    // 找到 doubao.com/chat 页面（没有时自动打开）
+    let chat_page = resolve_chat_page().await?;
+
+    let ws_url = chat_page
+        .websocket_debugger_url
+        .as_ref()
+        .ok_or_else(|| unavailable("No WebSocket debugger URL"))?;
+
+    log::info!("[DoubaoCDP] Using chat page: {}", chat_page.url);
+    log::info!("[DoubaoCDP] Connecting to CDP: {}", ws_url);
+
+    // 连接 CDP WebSocket
+    let mut session = CdpSession::connect(ws_url).await?;
+
+    // 1. 启用网络监控
+    session
+        .call("Network.enable", serde_json::json!({}))
+        .await
+        .map_err(|e| unavailable(format!("Failed to enable network: {}", e)))?;
+
+    // 2. 点击语音按钮开始录音，记下原状态用于确认停止
+    log::info!("[DoubaoCDP] Clicking voice button to START...");
+    let initial_state = match session.evaluate(CLICK_START_JS).await {
+        Ok(value) => {
+            log::info!("[DoubaoCDP] Click response: {}", value);
+            initial_button_state(&value)
+        }
+        Err(e) => {
+            log::warn!("[DoubaoCDP] Click command failed: {}", e);
+            None
+        }
+    };
+
+    // 3. 固定等待 2 秒，同时监听 WebSocket 创建事件
+    log::info!("[DoubaoCDP] Waiting for ASR WebSocket (2s)...");
+    let (captured_url, cancelled) =
+        watch_asr_websocket(&mut session, Duration::from_secs(2), cancel, false).await;
+
+    // 4. 固定 2 秒后（或被取消时）点击停止，确认按钮恢复
+    stop_voice_button(&mut session, initial_state.as_deref()).await;
+
+    if cancelled {
+        log::info!("[DoubaoCDP] ASR URL capture cancelled");
+        return Err(TypeFreeError::CaptureCancelled);
+    }
+
+    match captured_url {
+        Some(url) => {
+            log::info!("[DoubaoCDP] Successfully captured ASR URL");
+            Ok(url)
+        }
+        None => {
+            Err(TypeFreeError::AsrCapture(
+                "Failed to capture ASR URL. Voice button may not be found or click failed.".to_string(),
+            ))
+        }
+    }
+}
+
+// ============ 多账号 ============
+
+/// 读取页面上登录账号名称的脚本（取不到时返回 null）
+const ACCOUNT_NAME_JS: &str = r#"
+    (function() {
+        const read = (el) => el && (el.getAttribute('alt') || el.getAttribute('title')
+            || el.textContent || '').trim();
+        const selectors = [
+            '[data-testid*="user-name"]',
+            '[data-testid*="username"]',
+            '[data-testid*="avatar"] img',
+            'img[class*="avatar"][alt]',
+        ];
+        for (const selector of selectors) {
+            const name = read(document.querySelector(selector));
+            if (name) return name;
+        }
+        return null;
+    })()
+"#;
+
+/// 一个豆包页面（可能对应不同账号）
+#[derive(Debug, Clone, Serialize)]
+pub struct DoubaoPage {
+    pub url: String,
+    pub title: String,
+    /// 页面上显示的账号名称，读取不到时为 None
+    pub account: Option<String>,
+    /// 是否为当前获取 Cookie 使用的页面
+    pub selected: bool,
+}
+
+/// 读取页面上的账号名称
+async fn read_account_name(page: &CdpPage) -> Option<String> {
+    let ws_url = page.websocket_debugger_url.as_ref()?;
+    let mut session = CdpSession::connect(ws_url).await.ok()?;
+    let value = session.evaluate(ACCOUNT_NAME_JS).await.ok()?;
+    value.as_str().map(str::to_string)
+}
+
+/// 列出所有 doubao.com 页面及其账号名称
+pub async fn list_doubao_pages() -> Result<Vec<DoubaoPage>, TypeFreeError> {
+    let _gate = acquire_gate().await;
+    let pages = fetch_pages().await?;
+    let selected = selected_page();
+    let current_url = find_chat_page(&pages, selected.as_deref()).map(|p| p.url.clone());
+
+    let mut result = Vec::new();
+    for page in pages.iter().filter(|p| p.url.contains("doubao.com")) {
+        result.push(DoubaoPage {
+            url: page.url.clone(),
+            title: page.title.clone(),
+            account: read_account_name(page).await,
+            selected: current_url.as_deref() == Some(page.url.as_str()),
+        });
+    }
+    log::info!("[DoubaoCDP] Found {} Doubao pages", result.len());
+    Ok(result)
+}
+
+/// 页面存活探测：/json/list 可访问且有 doubao.com 页面
+///
+/// 渲染进程崩溃时 /json/version 仍可能正常，只看它会误判为可用
+pub async fn probe_pages() -> Result<(), TypeFreeError> {
+    let pages = fetch_pages().await?;
+    if pages.iter().any(|p| p.url.contains("doubao.com")) {
+        Ok(())
+    } else {
+        Err(unavailable(format!("No doubao.com page among {} targets", pages.len())))
+    }
+}
+
+/// 检查豆包桌面端是否以调试模式运行
+///
+/// 只有 /json/version 确认是豆包时才返回 true，避免把 Chrome 等浏览器的调试端口当成豆包
+pub async fn is_doubao_debug_available() -> bool {
+    match fetch_cdp_version().await {
+        Ok(version) => version.is_doubao(),
+        Err(_) => false,
+    }
+}
+
+// ============ 登录检测 ============
+
+/// 登录状态的判断规则（豆包改版或切换界面语言时可在设置中调整）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoginDetection {
+    /// 已登录才有的元素（CSS 选择器），任一存在即视为已登录
+    pub logged_in_selectors: Vec<String>,
+    /// 未登录时显示的登录按钮文字（不区分大小写）
+    pub login_labels: Vec<String>,
+}
+
+impl Default for LoginDetection {
+    fn default() -> Self {
+        Self {
+            logged_in_selectors: vec![
+                r#"[data-testid*="avatar"]"#.to_string(),
+                r#"img[class*="avatar"]"#.to_string(),
+            ],
+            login_labels: ["登录", "登入", "Log in", "Login", "Sign in"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// 生成检测脚本：先找已登录元素，再找登录按钮，
+/// 返回 'logged_in' / 'logged_out' / 'unknown'
+fn login_check_js(rules: &LoginDetection) -> String {
+    let selectors = serde_json::to_string(&rules.logged_in_selectors).unwrap_or_default();
+    let labels: Vec<String> = rules.login_labels.iter().map(|l| l.trim().to_lowercase()).collect();
+    let labels = serde_json::to_string(&labels).unwrap_or_default();
+    format!(
+        r#"
+        (function() {{
+            const selectors = {selectors};
+            const labels = {labels};
+            const found = selectors.some(s => {{
+                // 设置中的选择器写错时忽略
+                try {{ return !!document.querySelector(s); }} catch (e) {{ return false; }}
+            }});
+            if (found) return 'logged_in';
+            const btns = [...document.querySelectorAll('button, a, [role="button"]')];
+            const loginBtn = btns.find(b => labels.includes(b.textContent.trim().toLowerCase()));
+            return loginBtn ? 'logged_out' : 'unknown';
+        }})()
+    "#
+    )
+}
+
+/// 检查用户是否已登录豆包
+///
+/// 通过 CDP 注入 JS 检测页面 DOM：有已登录元素视为已登录，有登录按钮视为未登录，
+/// 都没有时沿用以前的判断（没有登录按钮即已登录）
+pub async fn check_login_status() -> Result<bool, TypeFreeError> {
+    log::info!("[DoubaoCDP] Checking login status via DOM...");
+
+    let _gate = acquire_gate().await;
+
+    // 获取页面列表
+    let pages = fetch_pages().await?;
+
+    // 找到 doubao.com 页面（任何页面都能判断登录状态）
+    let doubao_page = pages
+        .iter()
+        .find(|p| p.url.contains("doubao.com"))
+        .ok_or(TypeFreeError::NoDoubaoPage)?;
+
+    let ws_url = doubao_page
+        .websocket_debugger_url
+        .as_ref()
+        .ok_or_else(|| unavailable("No WebSocket debugger URL"))?;
+
+    // 连接 CDP WebSocket
+    let mut session = CdpSession::connect(ws_url).await?;
+
+    let rules = crate::settings::get().login_detection;
+    let state = session.evaluate(&login_check_js(&rules)).await?;
+    let is_logged_in = match state.as_str() {
+        Some("logged_in") => true,
+        Some("logged_out") => false,
+        Some("unknown") => {
+            log::warn!(
+                "[DoubaoCDP] Neither a logged-in element nor a login button found, assuming logged in"
+            );
+            true
+        }
+        _ => return Err(TypeFreeError::Other(format!("Unexpected login check result: {}", state))),
+    };
+
+    log::info!("[DoubaoCDP] Login status (DOM check): {}", is_logged_in);
+
+    Ok(is_logged_in)
+}
+
+/// 自动获取完整的 ASR 请求信息
+///
+/// 通过 CDP 自动获取：
+/// 1. Cookie（用于认证）
+/// 2. User-Agent（用于解析版本号）
+/// 3. device_id, web_id（从 Cookie 中提取）
+/// 4. 构建完整的 ASR URL
+pub async fn fetch_asr_info_auto() -> Result<(String, AsrRequestInfo), TypeFreeError> {
+    log::info!("[DoubaoCDP] Auto fetching ASR info...");
+
+    let _gate = acquire_gate_for_dictation().await;
+    verify_cdp_endpoint().await?;
+
+    // 找到 doubao.com/chat 页面（没有时自动打开）
+    let chat_page = resolve_chat_page().await?;
+
+    let ws_url = chat_page
+        .websocket_debugger_url
+        .as_ref()
+        .ok_or_else(|| unavailable("No WebSocket debugger URL"))?;
+
+    log::info!("[DoubaoCDP] Connecting to: {}", ws_url);
+
+    // 连接 CDP WebSocket
+    let mut session = CdpSession::connect(ws_url).await?;
+
+    // 1. 获取 Cookie
+    let cookies = session.get_cookies().await?;
+
+    log::info!("[DoubaoCDP] Got {} cookies", cookies.len());
+
+    // 构建 Cookie 字符串
+    let cookie_str = build_cookie_header(&cookies);
+
+    if cookie_str.is_empty() {
+        return Err(TypeFreeError::NotLoggedIn);
+    }
+
+    // 提取 device_id 和 web_id（记录来源，只在没有参数模板时使用）
+    let (device_id, device_id_source) = resolve_id(
+        &cookies,
+        "device_id",
+        &DEVICE_ID_COOKIES,
+        normalize_id,
+        FALLBACK_DEVICE_ID,
+    );
+    let (web_id, web_id_source) = resolve_id(
+        &cookies,
+        "web_id",
+        &WEB_ID_COOKIES,
+        normalize_web_id,
+        FALLBACK_WEB_ID,
+    );
+    log::info!(
+        "[DoubaoCDP] device_id from {:?}, web_id from {:?}",
+        device_id_source,
+        web_id_source
+    );
+
+    // 2. 获取 User-Agent
+    let user_agent = match session.evaluate("navigator.userAgent").await {
+        Ok(value) => value
+            .as_str()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| AsrRequestInfo::default().user_agent),
+        Err(e) => {
+            log::warn!("[DoubaoCDP] Failed to get User-Agent: {}", e);
+            AsrRequestInfo::default().user_agent
+        }
+    };
+    drop(session);
+
+    log::info!("[DoubaoCDP] Got User-Agent: {}", user_agent);
+
+    // 解析版本号
+    let (pc_version, chromium_version) = parse_user_agent(&user_agent);
+    log::info!("[DoubaoCDP] Parsed pc_version: {}, chromium_version: {}", pc_version, chromium_version);
+
+    let endpoint = AsrEndpoint::current();
+    log::info!("[DoubaoCDP] ASR endpoint: {}", endpoint.describe());
+
+    // 3. 获取 URL 参数模板（优先使用缓存，否则通过模拟点击捕获）
+    let (url, param_source) = match get_cached_url_params() {
+        Some(template_params) => {
+            log::info!("[DoubaoCDP] Using cached URL params template");
+            (build_asr_url_from_template(&template_params, &endpoint), ParamSource::Cached)
+        }
+        None => {
+            log::info!("[DoubaoCDP] No cached URL params, trying to capture by click...");
+
+            // 尝试通过模拟点击捕获真实 URL（已持有闸门）
+            match capture_asr_url(None).await {
+                Ok(captured_url) => {
+                    log::info!("[DoubaoCDP] Captured real ASR URL, parsing params...");
+                    let params = parse_asr_url_params(&captured_url);
+                    log::info!("[DoubaoCDP] Parsed {} params from captured URL", params.len());
+
+                    // 缓存参数模板
+                    set_cached_url_params(params.clone());
+
+                    // 使用捕获的参数模板构建 URL 模板（去掉会话参数）
+                    (build_asr_url_from_template(&params, &endpoint), ParamSource::Captured)
+                }
+                Err(e) => {
+                    log::warn!("[DoubaoCDP] Failed to capture URL by click: {}, using fallback", e);
+                    // Fallback: 使用硬编码参数
+                    let fallback_ids: Vec<&str> = [
+                        ("device_id", device_id_source),
+                        ("web_id", web_id_source),
+                    ]
+                    .into_iter()
+                    .filter(|(_, source)| *source == IdSource::Fallback)
+                    .map(|(name, _)| name)
+                    .collect();
+                    if !fallback_ids.is_empty() {
+                        log::warn!(
+                            "[DoubaoCDP] No valid {} in cookies, using built-in values; auth will likely fail",
+                            fallback_ids.join("/")
+                        );
+                        if let Some(notify) = FALLBACK_ID_NOTIFIER.get() {
+                            notify(&fallback_ids.join("/"));
+                        }
+                    }
+                    let url =
+                        build_asr_url(&device_id, &web_id, &pc_version, &chromium_version, &endpoint);
+                    (url, ParamSource::Fallback)
+                }
+            }
+        }
+    };
+
+    // 高级设置中的 URL 参数覆盖最后合并
+    let overrides = crate::settings::get().asr_overrides;
+    let url = override_url_params(&url, &overrides.url_param_pairs());
+
+    log::info!("[DoubaoCDP] ASR URL template: {}", redact_url(&url));
+
+    let asr_info = AsrRequestInfo {
+        url_template: url,
+        user_agent,
+        origin: "https://www.doubao.com".to_string(),
+        host: endpoint.host().to_string(),
+        param_source,
+    };
+
+    // Cookie 和 ASR 信息一起缓存
+    update(|s| {
+        s.cookies = Some(cookie_str.clone());
+        s.asr_request = Some(asr_info.clone());
+    });
+
+    Ok((cookie_str, asr_info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cdp_mock::{MockCdp, MockScript};
+
+    fn page(url: &str) -> CdpPage {
+        CdpPage {
+            url: url.to_string(),
+            title: String::new(),
+            kind: "page".to_string(),
+            websocket_debugger_url: None,
+        }
+    }
+
+    #[test]
+    fn test_find_chat_page_prefers_selection() {
+        let pages = [
+            page("https://www.doubao.com/chat/111"),
+            page("https://www.doubao.com/chat/222"),
+            page("https://example.com/chat"),
+        ];
+
+        let found = |selected| find_chat_page(&pages, selected).map(|p| p.url.as_str());
+        let (first, second) = ("https://www.doubao.com/chat/111", "https://www.doubao.com/chat/222");
+        assert_eq!(found(None), Some(first));
+        assert_eq!(found(Some(second)), Some(second));
+        // 选择的页面已关闭或不是豆包页面时退回第一个
+        assert_eq!(found(Some("https://www.doubao.com/chat/333")), Some(first));
+        assert_eq!(found(Some("https://example.com/chat")), Some(first));
+    }
+
+    /// 接受连接但从不响应的本地监听器（模拟卡死的豆包）
+    fn unresponsive_listener() -> std::net::SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut held = Vec::new();
+            for stream in listener.incoming().flatten() {
+                held.push(stream);
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_http_get_bounded_when_unresponsive() {
+        let addr = unresponsive_listener();
+        let start = std::time::Instant::now();
+        let result = http_get_text(&format!("http://{}/json/version", addr)).await;
+        assert!(result.is_err());
+        assert!(start.elapsed() < CDP_HTTP_TIMEOUT + Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_ws_connect_bounded_when_unresponsive() {
+        let addr = unresponsive_listener();
+        let start = std::time::Instant::now();
+        let result = CdpSession::connect(&format!("ws://{}/devtools/page/1", addr)).await;
+        assert!(result.is_err());
+        assert!(start.elapsed() < CDP_WS_TIMEOUT + Duration::from_secs(1));
+    }
+
+    fn cookie(name: &str, value: &str, domain: &str) -> CdpCookie {
+        CdpCookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_id() {
+        let device_id = |cookies: &[CdpCookie]| {
+            resolve_id(cookies, "device_id", &DEVICE_ID_COOKIES, normalize_id, FALLBACK_DEVICE_ID)
+        };
+        let web_id = |cookies: &[CdpCookie]| {
+            resolve_id(cookies, "web_id", &WEB_ID_COOKIES, normalize_web_id, FALLBACK_WEB_ID)
+        };
+        let cookies = [
+            cookie("device_id", "not-a-number", ".doubao.com"),
+            cookie("tt_webid", "7412345678901234567", ".doubao.com"),
+            cookie("s_v_web_id", "verify_7589709632207275535", "www.doubao.com"),
+        ];
+        // 格式不对的 Cookie 跳过，记录实际来源
+        assert_eq!(
+            device_id(&cookies),
+            ("7412345678901234567".to_string(), IdSource::Cookie("tt_webid"))
+        );
+        assert_eq!(
+            web_id(&cookies),
+            ("7589709632207275535".to_string(), IdSource::Cookie("s_v_web_id"))
+        );
+        assert_eq!(web_id(&[]), (FALLBACK_WEB_ID.to_string(), IdSource::Fallback));
+
+        // 字母数字格式的 s_v_web_id 可作 web_id，但不能作 device_id
+        let cookies = [cookie("s_v_web_id", "verify_lx3k9a2b_AbCdEf", "www.doubao.com")];
+        assert_eq!(
+            web_id(&cookies),
+            ("lx3k9a2b_AbCdEf".to_string(), IdSource::Cookie("s_v_web_id"))
+        );
+        assert_eq!(device_id(&cookies), (FALLBACK_DEVICE_ID.to_string(), IdSource::Fallback));
+
+        assert_eq!(normalize_id(" 1707977353229076 ").as_deref(), Some("1707977353229076"));
+        assert_eq!(normalize_id("verify_lx3k9a2b_AbCdEf"), None);
+        assert_eq!(normalize_id("12345"), None);
+        assert_eq!(
+            normalize_web_id("verify_7589709632207275535").as_deref(),
+            Some("7589709632207275535")
+        );
+        assert_eq!(normalize_web_id("verify_abc"), None);
+        assert_eq!(normalize_web_id("verify_lx3k9a2b-AbCdEf"), None);
+        assert_eq!(normalize_web_id("lx3k9a2b_AbCdEf"), None);
+    }
+
+    #[test]
+    fn test_cookie_header_realistic_set() {
+        let cookies = vec![
+            cookie("sessionid", "abc123", ".doubao.com"),
+            cookie("sid_tt", "abc123", ".doubao.com"),
+            cookie("uid_tt", "u456", ".doubao.com"),
+            cookie("s_v_web_id", "verify_7589709632207275535", "www.doubao.com"),
+            cookie("tt_webid", "7589709632207275535", "doubao.com"),
+            cookie("ws_token", "wstok", "ws-samantha.doubao.com"),
+            cookie("passport_csrf_token", "csrf", ".doubao.com"),
+            // 同名 Cookie：ASR 子域优先
+            cookie("msToken", "www-token", "www.doubao.com"),
+            cookie("msToken", "ws-token", "ws-samantha.doubao.com"),
+            // 其他站点的 Cookie 必须排除
+            cookie("sessionid", "evil", "notdoubao.com"),
+            cookie("_ga", "GA1.1", ".google.com"),
+        ];
+
+        let header = build_cookie_header(&cookies);
+        let pairs: Vec<&str> = header.split("; ").collect();
+
+        for required in REQUIRED_AUTH_COOKIES {
+            assert!(pairs.iter().any(|p| p.starts_with(&format!("{}=", required))));
+        }
+        assert!(pairs.contains(&"sessionid=abc123"));
+        assert!(pairs.contains(&"tt_webid=7589709632207275535"));
+        assert!(pairs.contains(&"ws_token=wstok"));
+        assert!(pairs.contains(&"msToken=ws-token"));
+        assert!(!header.contains("evil"));
+        assert!(!header.contains("_ga"));
+        assert_eq!(pairs.iter().filter(|p| p.starts_with("sessionid=")).count(), 1);
+        assert_eq!(pairs.iter().filter(|p| p.starts_with("msToken=")).count(), 1);
+    }
+
+    #[test]
+    fn test_override_url_params() {
+        let url = "wss://ws-samantha.doubao.com/samantha/audio/asr?version_code=20800&language=zh";
+        let overrides = vec![
+            ("language".to_string(), "en".to_string()),
+            ("extra".to_string(), "1".to_string()),
+        ];
+        assert_eq!(
+            override_url_params(url, &overrides),
+            "wss://ws-samantha.doubao.com/samantha/audio/asr?version_code=20800&language=en&extra=1"
+        );
+        assert_eq!(override_url_params(url, &[]), url);
+    }
+
+    #[test]
+    fn test_asr_endpoint_validate() {
+        let endpoint = |host: Option<&str>, region: &str| AsrEndpoint {
+            host: host.map(str::to_string),
+            region: region.to_string(),
+        };
+        assert!(endpoint(None, "").validate().is_ok());
+        assert!(endpoint(Some("  "), "sg").validate().is_ok());
+        assert!(endpoint(Some("ws-samantha-sg.doubao.com"), "SG").validate().is_ok());
+        assert!(endpoint(Some("ws.doubao.com:8443"), "").validate().is_ok());
+
+        assert!(endpoint(Some("wss://ws.doubao.com/asr"), "").validate().is_err());
+        assert!(endpoint(Some("ws.doubao.com:99999"), "").validate().is_err());
+        assert!(endpoint(Some("-bad.doubao.com"), "").validate().is_err());
+        // Cookie 只对 doubao.com 有效
+        assert!(endpoint(Some("asr.example.com"), "").validate().is_err());
+        assert!(endpoint(None, "sg&x=1").validate().is_err());
+    }
+
+    #[test]
+    fn test_build_asr_url_with_endpoint() {
+        let template: HashMap<String, String> = [
+            ("region", ""),
+            ("sys_region", ""),
+            ("language", "zh"),
+            ("web_tab_id", "old"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        // 默认端点保留模板原值
+        let url = build_asr_url_from_template(&template, &AsrEndpoint::default());
+        assert!(url.starts_with("wss://ws-samantha.doubao.com/samantha/audio/asr?"));
+        let params = parse_asr_url_params(&url);
+        assert_eq!(params["region"], "");
+        // 会话参数不进入模板
+        assert!(!params.contains_key("web_tab_id"));
+
+        let endpoint = AsrEndpoint {
+            host: Some("ws-samantha-sg.doubao.com".to_string()),
+            region: " sg ".to_string(),
+        };
+        let url = build_asr_url_from_template(&template, &endpoint);
+        assert!(url.starts_with("wss://ws-samantha-sg.doubao.com/samantha/audio/asr?"));
+        let params = parse_asr_url_params(&url);
+        assert_eq!(params["region"], "sg");
+        assert_eq!(params["sys_region"], "sg");
+        assert_eq!(params["language"], "zh");
+
+        let url = build_asr_url("1", "2", "1.85.8", "135.0.0.0", &endpoint);
+        assert!(url.starts_with("wss://ws-samantha-sg.doubao.com/samantha/audio/asr?"));
+        assert!(url.contains("&region=sg&sys_region=sg&"));
+        assert!(!url.contains("web_tab_id"));
+    }
+
+    #[test]
+    fn test_state_snapshot_and_generation() {
+        let before = snapshot();
+        update(|s| s.login_status = Some(true));
+        let after = snapshot();
+        assert!(after.generation > before.generation);
+        assert_eq!(after.login_status, Some(true));
+
+        // 快照是独立的副本，之后的修改不影响它
+        update(|s| s.login_status = Some(false));
+        assert_eq!(after.login_status, Some(true));
+        assert_eq!(get_cached_login_status(), Some(false));
+        assert!(state_generation() > after.generation);
+    }
+
+    #[test]
+    fn test_session_url_fresh_per_session() {
+        let template: HashMap<String, String> =
+            [("device_id", "123"), ("language", "zh"), ("web_tab_id", "captured")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        let info = AsrRequestInfo {
+            url_template: build_asr_url_from_template(&template, &AsrEndpoint::default()),
+            ..Default::default()
+        };
+
+        // 两次会话：web_tab_id 不同，其余参数一致
+        let mut first = parse_asr_url_params(&build_session_url(&info));
+        let mut second = parse_asr_url_params(&build_session_url(&info));
+        let first_tab = first.remove("web_tab_id").unwrap();
+        let second_tab = second.remove("web_tab_id").unwrap();
+        assert_ne!(first_tab, second_tab);
+        assert_ne!(first_tab, "captured");
+        assert_eq!(first, second);
+        assert_eq!(first["device_id"], "123");
+
+        // 高级设置覆盖的 web_tab_id 保持不变
+        let info = AsrRequestInfo {
+            url_template: override_url_params(
+                &info.url_template,
+                &[("web_tab_id".to_string(), "fixed".to_string())],
+            ),
+            ..Default::default()
+        };
+        assert_eq!(parse_asr_url_params(&build_session_url(&info))["web_tab_id"], "fixed");
+    }
+
+    #[test]
+    fn test_redact_url() {
+        let url = "wss://ws-samantha.doubao.com/samantha/audio/asr?device_id=123&language=zh&fp=verify_abc&region=";
+        assert_eq!(
+            redact_url(url),
+            "wss://ws-samantha.doubao.com/samantha/audio/asr?device_id=***&language=zh&fp=***&region="
+        );
+        assert_eq!(redact_url("wss://example"), "wss://example");
+    }
+
+    #[test]
+    fn test_cookie_header_empty() {
+        assert_eq!(build_cookie_header(&[]), "");
+    }
+
+    #[test]
+    fn test_cdp_version_doubao() {
+        let body = r#"{
+            "Browser": "Chrome/135.0.7049.115",
+            "Protocol-Version": "1.3",
+            "User-Agent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/135.0.0.0 Safari/537.36 SamanthaDoubao/1.85.8",
+            "webSocketDebuggerUrl": "ws://127.0.0.1:9222/devtools/browser/abc"
+        }"#;
+        let version = parse_cdp_version(body).unwrap();
+        assert!(version.is_doubao());
+    }
+
+    #[test]
+    fn test_cdp_version_chrome() {
+        let body = r#"{
+            "Browser": "Chrome/138.0.7204.101",
+            "Protocol-Version": "1.3",
+            "User-Agent": "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36",
+            "V8-Version": "13.8.258.24",
+            "webSocketDebuggerUrl": "ws://127.0.0.1:9222/devtools/browser/def"
+        }"#;
+        let version = parse_cdp_version(body).unwrap();
+        assert!(!version.is_doubao());
+
+        // 只认 SamanthaDoubao/ 标识，UA 或 Browser 里别处出现 doubao 不算
+        let body = r#"{
+            "Browser": "DoubaoHelper/1.0",
+            "User-Agent": "Mozilla/5.0 Chrome/138.0.0.0 Safari/537.36 doubao-extension/2.1"
+        }"#;
+        let version = parse_cdp_version(body).unwrap();
+        assert!(!version.is_doubao());
+    }
+
+    #[test]
+    fn test_cdp_version_invalid() {
+        assert!(parse_cdp_version("not json").is_err());
+        // 缺少字段时不是豆包
+        let version = parse_cdp_version("{}").unwrap();
+        assert!(!version.is_doubao());
+    }
+
+    /// 模拟持有闸门的捕获，返回其取消通知
+    async fn hold_gate_as_capture(started: Instant, hold: Duration) -> Arc<Notify> {
+        let cancel = Arc::new(Notify::new());
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+        let cancel_clone = cancel.clone();
+        tokio::spawn(async move {
+            let _gate = acquire_gate().await;
+            *ACTIVE_CAPTURE.lock().unwrap() = Some(ActiveCapture {
+                started,
+                cancel: cancel_clone.clone(),
+            });
+            let _ = ready_tx.send(());
+            let _ = tokio::time::timeout(hold, cancel_clone.notified()).await;
+            *ACTIVE_CAPTURE.lock().unwrap() = None;
+        });
+        ready_rx.await.unwrap();
+        cancel
+    }
+
+    #[tokio::test]
+    async fn test_dictation_gate_cancels_or_waits_for_capture() {
+        // 刚开始的捕获被取消，听写立即拿到闸门
+        let _cancel = hold_gate_as_capture(Instant::now(), Duration::from_secs(10)).await;
+        let acquired = tokio::time::timeout(Duration::from_secs(1), acquire_gate_for_dictation()).await;
+        assert!(acquired.is_ok());
+        drop(acquired);
+
+        // 即将结束的捕获不被取消，听写等它完成
+        let almost_done = Instant::now() - Duration::from_millis(2500);
+        let cancel = hold_gate_as_capture(almost_done, Duration::from_millis(300)).await;
+        let start = Instant::now();
+        let _gate = acquire_gate_for_dictation().await;
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let notified = tokio::time::timeout(Duration::from_millis(20), cancel.notified()).await;
+        assert!(notified.is_err());
+    }
+
+    // ============ 模拟 CDP 服务 ============
+
+    /// CDP 地址是全局的，使用模拟服务的测试依次执行
+    static MOCK_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// 启动模拟服务并把 CDP 地址指向它
+    async fn start_mock(script: MockScript) -> (MockCdp, tokio::sync::MutexGuard<'static, ()>) {
+        let guard = MOCK_LOCK.lock().await;
+        let mock = MockCdp::start(script);
+        set_cdp_base_url(Some(mock.base_url()));
+        (mock, guard)
+    }
+
+    #[tokio::test]
+    async fn test_mock_fetch_cookies() {
+        let (mock, _guard) = start_mock(MockScript {
+            cookies: vec![
+                ("sessionid", "abc123", ".doubao.com"),
+                ("sid_tt", "abc123", ".doubao.com"),
+                ("uid_tt", "u456", ".doubao.com"),
+                ("_ga", "GA1.1", ".google.com"),
+            ],
+            ..Default::default()
+        })
+        .await;
+
+        let header = fetch_cookies().await.unwrap();
+        assert!(header.contains("sessionid=abc123"));
+        assert!(header.contains("uid_tt=u456"));
+        assert!(!header.contains("_ga"));
+        assert_eq!(mock.methods(), vec!["Network.getCookies"]);
+    }
+
+    #[tokio::test]
+    async fn test_mock_fetch_cookies_none_valid() {
+        let (_mock, _guard) = start_mock(MockScript {
+            cookies: vec![("_ga", "GA1.1", ".google.com")],
+            ..Default::default()
+        })
+        .await;
+
+        assert_eq!(fetch_cookies().await.unwrap_err(), TypeFreeError::NotLoggedIn);
+    }
+
+    #[tokio::test]
+    async fn test_mock_opens_chat_page() {
+        // 只有豆包首页（对话都关了）：导航到对话页后继续
+        let (mock, guard) = start_mock(MockScript {
+            pages: vec!["https://www.doubao.com/".to_string()],
+            cookies: vec![("sessionid", "abc123", ".doubao.com")],
+            ..Default::default()
+        })
+        .await;
+        assert!(fetch_cookies().await.unwrap().contains("sessionid=abc123"));
+        assert_eq!(
+            mock.methods(),
+            vec!["Page.enable", "Page.navigate", "Network.getCookies"]
+        );
+        drop(guard);
+
+        // 没有任何豆包页面可以导航
+        let (_mock, _guard) = start_mock(MockScript {
+            pages: vec!["https://example.com/".to_string()],
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(fetch_cookies().await.unwrap_err(), TypeFreeError::NoDoubaoPage);
+        assert_eq!(fetch_asr_info_auto().await.unwrap_err(), TypeFreeError::NoDoubaoPage);
+    }
+
+    #[tokio::test]
+    async fn test_mock_open_chat_page_logged_out() {
+        // 未登录：导航后跳到登录页
+        let (_mock, guard) = start_mock(MockScript {
+            pages: vec!["https://www.doubao.com/".to_string()],
+            redirect: Some("https://www.doubao.com/login?redirect=chat".to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(fetch_cookies().await.unwrap_err(), TypeFreeError::NotLoggedIn);
+        drop(guard);
+
+        // 未登录：停在首页，页面上有登录按钮
+        let (_mock, _guard) = start_mock(MockScript {
+            pages: vec!["https://www.doubao.com/".to_string()],
+            redirect: Some("https://www.doubao.com/".to_string()),
+            evaluate: vec![("const labels", "logged_out".into())],
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(fetch_cookies().await.unwrap_err(), TypeFreeError::NotLoggedIn);
+    }
+
+    #[test]
+    fn test_is_login_url() {
+        assert!(is_login_url("https://www.doubao.com/login"));
+        assert!(is_login_url("https://www.doubao.com/login?from=chat"));
+        assert!(is_login_url("https://passport.example.com/web/"));
+        assert!(!is_login_url("https://www.doubao.com/chat/123"));
+        assert!(!is_login_url("https://example.com/login"));
+    }
+
+    #[test]
+    fn test_login_check_js() {
+        let rules = LoginDetection {
+            logged_in_selectors: vec![r#"[data-testid="avatar"]"#.to_string()],
+            login_labels: vec!["  Log In ".to_string(), "登录".to_string()],
+        };
+        let js = login_check_js(&rules);
+        // 选择器和文字按 JSON 转义嵌入，文字统一小写
+        assert!(js.contains(r#"const selectors = ["[data-testid=\"avatar\"]"];"#));
+        assert!(js.contains(r#"const labels = ["log in","登录"];"#));
+
+        let empty = login_check_js(&LoginDetection {
+            logged_in_selectors: Vec::new(),
+            login_labels: Vec::new(),
+        });
+        assert!(empty.contains("const selectors = [];"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_login_detection() {
+        for (state, logged_in) in [
+            ("logged_in", Ok(true)),
+            ("logged_out", Ok(false)),
+            ("unknown", Ok(true)),
+        ] {
+            let (_mock, _guard) = start_mock(MockScript {
+                evaluate: vec![("const labels", state.into())],
+                ..Default::default()
+            })
+            .await;
+            assert_eq!(check_login_status().await, logged_in);
+        }
+
+        // 脚本没有返回有效结果
+        {
+            let (_mock, _guard) = start_mock(MockScript::default()).await;
+            assert!(check_login_status().await.is_err());
+        }
+
+        // 没有豆包页面
+        let (_mock, _guard) = start_mock(MockScript {
+            pages: vec!["https://example.com/".to_string()],
+            ..Default::default()
+        })
+        .await;
+        assert!(check_login_status().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_capture_asr_url() {
+        let asr_url =
+            "wss://ws-samantha.doubao.com/samantha/audio/asr?version_code=20800&format=pcm";
+        let (mock, guard) = start_mock(MockScript {
+            evaluate: vec![("'clicked'", "clicked".into()), ("'stopped'", "stopped".into())],
+            websocket_created: Some(asr_url.to_string()),
+            ..Default::default()
+        })
+        .await;
+
+        assert_eq!(capture_asr_url(None).await.unwrap(), asr_url);
+        // 启用网络监控 → 点击开始 → 点击停止
+        assert_eq!(
+            mock.methods(),
+            vec!["Network.enable", "Runtime.evaluate", "Runtime.evaluate"]
+        );
+        drop(guard);
+
+        // 页面没有建立 ASR 连接
+        let (_mock, _guard) = start_mock(MockScript::default()).await;
+        assert!(capture_asr_url(None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_capture_verifies_stop() {
+        let asr_url = "wss://ws-samantha.doubao.com/samantha/audio/asr?format=pcm";
+        let script = |button_state: &str| MockScript {
+            evaluate: vec![
+                ("'clicked'", serde_json::json!({ "result": "clicked", "state": "idle" })),
+                ("button_state", serde_json::json!({ "button_state": button_state })),
+                ("'stopped'", "stopped".into()),
+            ],
+            websocket_created: Some(asr_url.to_string()),
+            ..Default::default()
+        };
+
+        // 停止后按钮恢复：点击开始 → 点击停止 → 读取一次状态
+        {
+            let (mock, _guard) = start_mock(script("idle")).await;
+            assert_eq!(capture_asr_url(None).await.unwrap(), asr_url);
+            assert_eq!(mock.methods().len(), 4);
+        }
+
+        // 按钮一直处于录音状态：重试点击停止，仍然返回捕获的 URL
+        let (mock, _guard) = start_mock(script("recording")).await;
+        assert_eq!(capture_asr_url(None).await.unwrap(), asr_url);
+        let evaluates = mock.methods().len() - 2;
+        assert_eq!(evaluates, STOP_ATTEMPTS * (1 + STOP_CHECKS));
+    }
+
+    #[test]
+    fn test_button_state() {
+        let start = serde_json::json!({ "result": "clicked", "state": "idle" });
+        assert_eq!(initial_button_state(&start).as_deref(), Some("idle"));
+        // 旧页面只返回 'clicked'
+        assert_eq!(initial_button_state(&serde_json::json!("clicked")), None);
+        assert_eq!(
+            current_button_state(&serde_json::json!({ "button_state": "" })).as_deref(),
+            Some("")
+        );
+        assert_eq!(current_button_state(&serde_json::Value::Null), None);
+        assert!(is_asr_websocket("wss://ws-samantha.doubao.com/samantha/audio/asr"));
+        assert!(!is_asr_websocket("wss://ws-samantha.doubao.com/samantha/chat"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_debug_availability() {
+        let (_mock, guard) = start_mock(MockScript::default()).await;
+        assert!(is_doubao_debug_available().await);
+        assert!(verify_cdp_endpoint().await.is_ok());
+        drop(guard);
+
+        // 调试端口属于 Chrome
+        let (_mock, guard) = start_mock(MockScript {
+            user_agent: "Mozilla/5.0 (Macintosh) Chrome/138.0.0.0 Safari/537.36".to_string(),
+            ..Default::default()
+        })
+        .await;
+        assert!(!is_doubao_debug_available().await);
+        assert!(matches!(
+            verify_cdp_endpoint().await.unwrap_err(),
+            TypeFreeError::ForeignBrowser(_)
+        ));
+        drop(guard);
+
+        // 端口上没有服务
+        let _guard = MOCK_LOCK.lock().await;
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        set_cdp_base_url(Some(format!("http://{}", closed)));
+        assert!(!is_doubao_debug_available().await);
+    }
+}
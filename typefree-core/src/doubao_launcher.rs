@@ -2,10 +2,126 @@
 //!
 //! 管理豆包桌面端的启动（调试模式）
 //! 目前仅支持 macOS，Windows 支持待实现
+//! 用户选择自行管理豆包时只检测调试端口，从不启动、重启或关闭豆包
+
+use crate::error::TypeFreeError;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// 当前运行的豆包是否由 TypeFree 启动（退出时只关闭自己启动的实例）
+static OWNS_DOUBAO: AtomicBool = AtomicBool::new(false);
+
+/// 记录豆包实例归属
+pub fn set_owned(owned: bool) {
+    OWNS_DOUBAO.store(owned, Ordering::SeqCst);
+}
+
+/// 豆包是否由 TypeFree 启动
+pub fn is_owned() -> bool {
+    OWNS_DOUBAO.load(Ordering::SeqCst)
+}
+
+/// 退出时关闭由 TypeFree 启动的豆包（避免调试端口残留）
+pub fn shutdown_owned_doubao() {
+    if !OWNS_DOUBAO.swap(false, Ordering::SeqCst) {
+        log::info!("[DoubaoLauncher] Doubao not owned by TypeFree, leaving it running");
+        return;
+    }
+
+    log::info!("[DoubaoLauncher] Closing Doubao started by TypeFree...");
+    if let Err(e) = kill_doubao() {
+        log::warn!("[DoubaoLauncher] Failed to close owned Doubao: {}", e);
+    }
+}
+
+// ============ 手动管理模式 ============
+
+/// 手动管理模式下调试端口不可用时给用户的提示
+pub const MANUAL_MODE_REQUIREMENT: &str =
+    "已关闭自动管理豆包，请自行以 --remote-debugging-port=9222 启动豆包桌面端";
+
+/// 用户是否选择自行管理豆包
+pub fn is_managed_externally() -> bool {
+    crate::settings::get().manual_doubao
+}
+
+/// 手动管理模式：只检测调试端口是否可用
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+async fn check_external_doubao() -> Result<bool, TypeFreeError> {
+    if crate::doubao_cdp::is_doubao_debug_available().await {
+        log::info!("[DoubaoLauncher] Externally managed Doubao debug mode available");
+        Ok(false)
+    } else {
+        Err(TypeFreeError::ManualDoubao)
+    }
+}
+
+// ============ 僵死检测 ============
+
+/// 页面列表持续不可用多久判定为僵死
+const ZOMBIE_THRESHOLD: Duration = Duration::from_secs(6);
+
+/// 存活探测间隔
+const LIVENESS_PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 豆包调试实例的存活状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Liveness {
+    /// 页面可访问
+    Alive,
+    /// 进程不在运行
+    NotRunning,
+    /// 进程在运行，但页面列表持续不可用（渲染进程崩溃等）
+    Zombie,
+}
+
+/// 探测豆包是否僵死：进程在运行时，`ZOMBIE_THRESHOLD` 内页面列表一直不可用即判定僵死
+pub async fn probe_liveness() -> Liveness {
+    if !is_doubao_running() {
+        return Liveness::NotRunning;
+    }
+
+    let start = Instant::now();
+    loop {
+        match crate::doubao_cdp::probe_pages().await {
+            Ok(()) => return Liveness::Alive,
+            Err(e) => {
+                if start.elapsed() >= ZOMBIE_THRESHOLD {
+                    log::warn!(
+                        "[DoubaoLauncher] Pages unavailable for {}s ({}), treating as zombie",
+                        ZOMBIE_THRESHOLD.as_secs(),
+                        e
+                    );
+                    return Liveness::Zombie;
+                }
+                log::info!("[DoubaoLauncher] Liveness probe failed: {}", e);
+            }
+        }
+        tokio::time::sleep(LIVENESS_PROBE_INTERVAL).await;
+    }
+}
+
+/// 检测到僵死实例时强制重启，返回探测结果
+pub async fn recover_if_zombie() -> Result<Liveness, TypeFreeError> {
+    let liveness = probe_liveness().await;
+    if liveness == Liveness::Zombie && is_managed_externally() {
+        log::warn!("[DoubaoLauncher] Doubao is unresponsive, but it is managed externally");
+        return Ok(liveness);
+    }
+    if liveness == Liveness::Zombie {
+        log::warn!("[DoubaoLauncher] Restarting zombie Doubao instance...");
+        restart_doubao_debug_mode().await?;
+        log::info!("[DoubaoLauncher] Zombie Doubao recovered");
+    }
+    Ok(liveness)
+}
 
 // ============ macOS 实现 ============
 #[cfg(target_os = "macos")]
 mod macos {
+    use crate::error::TypeFreeError;
     use std::process::Command;
 
     const DOUBAO_APP_PATH: &str = "/Applications/Doubao.app/Contents/MacOS/Doubao";
@@ -41,7 +157,7 @@ mod macos {
     }
 
     /// 关闭豆包（多种方法确保杀死）
-    pub fn kill_doubao() -> Result<(), String> {
+    pub fn kill_doubao() -> Result<(), TypeFreeError> {
         log::info!("[DoubaoLauncher] Killing Doubao...");
 
         // 方法1: 使用 pkill -f 匹配命令行
@@ -74,7 +190,7 @@ mod macos {
 
         if is_doubao_running() {
             log::error!("[DoubaoLauncher] Failed to kill Doubao");
-            return Err("无法关闭豆包，请手动关闭后重试".to_string());
+            return Err(TypeFreeError::Other("无法关闭豆包，请手动关闭后重试".to_string()));
         }
 
         log::info!("[DoubaoLauncher] Doubao killed successfully");
@@ -82,12 +198,12 @@ mod macos {
     }
 
     /// 以调试模式启动豆包（后台隐藏启动）
-    pub fn launch_doubao_debug() -> Result<(), String> {
+    pub fn launch_doubao_debug() -> Result<(), TypeFreeError> {
         log::info!("[DoubaoLauncher] Launching Doubao in debug mode (background)...");
 
         // 检查豆包是否存在
         if !std::path::Path::new(DOUBAO_APP_PATH).exists() {
-            return Err("Doubao.app not found in /Applications".to_string());
+            return Err(TypeFreeError::DoubaoNotInstalled);
         }
 
         // 使用 open -g -j 后台隐藏启动
@@ -102,7 +218,7 @@ mod macos {
                 &format!("--remote-debugging-port={}", CDP_PORT),
             ])
             .spawn()
-            .map_err(|e| format!("Failed to launch Doubao: {}", e))?;
+            .map_err(|e| TypeFreeError::DoubaoLaunch(format!("Failed to launch Doubao: {}", e)))?;
 
         log::info!("[DoubaoLauncher] Doubao launched in background with --remote-debugging-port={}", CDP_PORT);
         Ok(())
@@ -112,9 +228,17 @@ mod macos {
     ///
     /// 返回 Ok(true) 表示是我们启动/重启的（可以关闭）
     /// 返回 Ok(false) 表示用户已经在以调试模式运行（不应关闭）
-    pub async fn ensure_doubao_debug_mode() -> Result<bool, String> {
+    pub async fn ensure_doubao_debug_mode() -> Result<bool, TypeFreeError> {
+        if super::is_managed_externally() {
+            return super::check_external_doubao().await;
+        }
+
         // 先检查 CDP 是否已经可用
         if crate::doubao_cdp::is_doubao_debug_available().await {
+            // 调试端口在但页面不响应时重启
+            if super::recover_if_zombie().await? == super::Liveness::Zombie {
+                return Ok(true);
+            }
             log::info!("[DoubaoLauncher] Doubao debug mode already available");
             return Ok(false); // 已经是调试模式，不需要重启
         }
@@ -135,15 +259,20 @@ mod macos {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
             if crate::doubao_cdp::is_doubao_debug_available().await {
                 log::info!("[DoubaoLauncher] CDP available after {}ms", (i + 1) * 500);
+                super::set_owned(true);
                 return Ok(true); // 我们启动的，可以关闭
             }
         }
 
-        Err("豆包启动超时，请手动检查".to_string())
+        Err(TypeFreeError::DoubaoLaunch("Timed out waiting for the debug port".to_string()))
     }
 
     /// 强制以调试模式重启豆包
-    pub async fn restart_doubao_debug_mode() -> Result<(), String> {
+    pub async fn restart_doubao_debug_mode() -> Result<(), TypeFreeError> {
+        if super::is_managed_externally() {
+            return Err(TypeFreeError::ManualDoubao);
+        }
+
         // 先关闭
         kill_doubao()?;
 
@@ -158,11 +287,12 @@ mod macos {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
             if crate::doubao_cdp::is_doubao_debug_available().await {
                 log::info!("[DoubaoLauncher] CDP available after restart, took {}ms", (i + 1) * 500);
+                super::set_owned(true);
                 return Ok(());
             }
         }
 
-        Err("豆包重启后 CDP 不可用".to_string())
+        Err(TypeFreeError::DoubaoLaunch("Debug port unavailable after restart".to_string()))
     }
 
     /// 检查豆包桌面端是否已安装
@@ -174,6 +304,7 @@ mod macos {
 // ============ Windows 实现（待完善） ============
 #[cfg(target_os = "windows")]
 mod windows {
+    use crate::error::TypeFreeError;
     use std::process::Command;
 
     // Windows 上豆包的可能安装路径
@@ -219,7 +350,7 @@ mod windows {
     }
 
     /// 关闭豆包
-    pub fn kill_doubao() -> Result<(), String> {
+    pub fn kill_doubao() -> Result<(), TypeFreeError> {
         log::info!("[DoubaoLauncher] Killing Doubao...");
 
         let _ = Command::new("taskkill")
@@ -230,7 +361,7 @@ mod windows {
 
         if is_doubao_running() {
             log::error!("[DoubaoLauncher] Failed to kill Doubao");
-            return Err("无法关闭豆包，请手动关闭后重试".to_string());
+            return Err(TypeFreeError::Other("无法关闭豆包，请手动关闭后重试".to_string()));
         }
 
         log::info!("[DoubaoLauncher] Doubao killed successfully");
@@ -238,24 +369,31 @@ mod windows {
     }
 
     /// 以调试模式启动豆包
-    pub fn launch_doubao_debug() -> Result<(), String> {
+    pub fn launch_doubao_debug() -> Result<(), TypeFreeError> {
         log::info!("[DoubaoLauncher] Launching Doubao in debug mode...");
 
         let doubao_path = find_doubao_path()
-            .ok_or_else(|| "Doubao not found. Please install Doubao first.".to_string())?;
+            .ok_or(TypeFreeError::DoubaoNotInstalled)?;
 
         Command::new(&doubao_path)
             .arg("--remote-debugging-port=9222")
             .spawn()
-            .map_err(|e| format!("Failed to launch Doubao: {}", e))?;
+            .map_err(|e| TypeFreeError::DoubaoLaunch(format!("Failed to launch Doubao: {}", e)))?;
 
         log::info!("[DoubaoLauncher] Doubao launched with --remote-debugging-port=9222");
         Ok(())
     }
 
     /// 确保豆包以调试模式运行
-    pub async fn ensure_doubao_debug_mode() -> Result<bool, String> {
+    pub async fn ensure_doubao_debug_mode() -> Result<bool, TypeFreeError> {
+        if super::is_managed_externally() {
+            return super::check_external_doubao().await;
+        }
+
         if crate::doubao_cdp::is_doubao_debug_available().await {
+            if super::recover_if_zombie().await? == super::Liveness::Zombie {
+                return Ok(true);
+            }
             log::info!("[DoubaoLauncher] Doubao debug mode already available");
             return Ok(false);
         }
@@ -272,15 +410,20 @@ mod windows {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
             if crate::doubao_cdp::is_doubao_debug_available().await {
                 log::info!("[DoubaoLauncher] CDP available after {}ms", (i + 1) * 500);
+                super::set_owned(true);
                 return Ok(true);
             }
         }
 
-        Err("豆包启动超时，请手动检查".to_string())
+        Err(TypeFreeError::DoubaoLaunch("Timed out waiting for the debug port".to_string()))
     }
 
     /// 强制以调试模式重启豆包
-    pub async fn restart_doubao_debug_mode() -> Result<(), String> {
+    pub async fn restart_doubao_debug_mode() -> Result<(), TypeFreeError> {
+        if super::is_managed_externally() {
+            return Err(TypeFreeError::ManualDoubao);
+        }
+
         kill_doubao()?;
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         launch_doubao_debug()?;
@@ -289,11 +432,12 @@ mod windows {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
             if crate::doubao_cdp::is_doubao_debug_available().await {
                 log::info!("[DoubaoLauncher] CDP available after restart, took {}ms", (i + 1) * 500);
+                super::set_owned(true);
                 return Ok(());
             }
         }
 
-        Err("豆包重启后 CDP 不可用".to_string())
+        Err(TypeFreeError::DoubaoLaunch("Debug port unavailable after restart".to_string()))
     }
 
     /// 检查豆包桌面端是否已安装
@@ -305,25 +449,27 @@ mod windows {
 // ============ 其他平台（不支持） ============
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
 mod unsupported {
+    use crate::error::TypeFreeError;
+
     pub fn is_doubao_running() -> bool {
         log::warn!("[DoubaoLauncher] Platform not supported");
         false
     }
 
-    pub fn kill_doubao() -> Result<(), String> {
-        Err("Platform not supported".to_string())
+    pub fn kill_doubao() -> Result<(), TypeFreeError> {
+        Err(TypeFreeError::Other("Platform not supported".to_string()))
     }
 
-    pub fn launch_doubao_debug() -> Result<(), String> {
-        Err("Platform not supported".to_string())
+    pub fn launch_doubao_debug() -> Result<(), TypeFreeError> {
+        Err(TypeFreeError::Other("Platform not supported".to_string()))
     }
 
-    pub async fn ensure_doubao_debug_mode() -> Result<bool, String> {
-        Err("Platform not supported".to_string())
+    pub async fn ensure_doubao_debug_mode() -> Result<bool, TypeFreeError> {
+        Err(TypeFreeError::Other("Platform not supported".to_string()))
     }
 
-    pub async fn restart_doubao_debug_mode() -> Result<(), String> {
-        Err("Platform not supported".to_string())
+    pub async fn restart_doubao_debug_mode() -> Result<(), TypeFreeError> {
+        Err(TypeFreeError::Other("Platform not supported".to_string()))
     }
 
     pub fn is_doubao_installed() -> bool {
@@ -0,0 +1,434 @@
+//! 错误类型和展示
+//!
+//! `TypeFreeError` 按原因区分错误，前端命令直接返回它（序列化为 kind、message、detail），
+//! 调用方按 kind 处理，不用匹配错误文字。
+//! 浮层上：原始错误（英文、可能很长）只放在第二行的详情里并截断；
+//! 短时间内重复出现的同一错误合并显示为"仍然失败 (N)"
+
+use crate::audio::AudioError;
+use crate::doubao_asr::{self, AsrError, ServerError};
+use crate::doubao_launcher;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 同一错误在该时间内再次出现时合并计数
+const REPEAT_WINDOW: Duration = Duration::from_secs(30);
+
+/// 主提示和详情的最大显示宽度（中文字符算 2）
+const MESSAGE_MAX_WIDTH: usize = 56;
+const DETAIL_MAX_WIDTH: usize = 80;
+
+/// TypeFree 的错误（字符串载荷是给排查用的原始详情）
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeFreeError {
+    /// 豆包未以调试模式运行
+    DoubaoNotRunning,
+    /// 已关闭自动管理豆包，需要用户自行以调试模式启动
+    ManualDoubao,
+    /// 没有安装豆包桌面端
+    DoubaoNotInstalled,
+    /// 启动、关闭或重启豆包失败
+    DoubaoLaunch(String),
+    /// 连不上豆包的调试端口，或 CDP 调用失败、超时
+    CdpUnavailable(String),
+    /// 调试端口被其他浏览器占用（占用者的 Browser 标识）
+    ForeignBrowser(String),
+    /// 豆包没有打开任何 doubao.com 页面
+    NoDoubaoPage,
+    /// 没有对话页，且打开对话页失败
+    NoChatPage(String),
+    /// 没有有效的登录 Cookie
+    NotLoggedIn,
+    /// ASR 连接被拒绝（401/403），登录已失效
+    LoginExpired(String),
+    /// 连接 ASR WebSocket 失败
+    WsConnect(String),
+    /// 高级设置中的参数覆盖无效，或带覆盖时连接失败
+    InvalidOverride(String),
+    /// 没能从豆包捕获 ASR 地址
+    AsrCapture(String),
+    /// 捕获 ASR 地址时被听写取消
+    CaptureCancelled,
+    /// 连续多次识别失败，暂停自动重试（失败次数）
+    CoolingDown(usize),
+    /// 服务端拒绝识别，没有任何结果
+    AsrBlocked { code: i64, message: String },
+    /// 服务端报错，报错前的结果已交付
+    Interrupted { code: i64, message: String },
+    /// 缺少系统权限（权限名称）
+    MissingPermissions(Vec<&'static str>),
+    /// 麦克风打开失败
+    Audio(AudioError),
+    /// 正在进行其他操作（如听写、诊断），稍后再试
+    Busy(String),
+    /// 其他错误（文件、系统调用、网络等）
+    Other(String),
+}
+
+impl From<&AsrError> for TypeFreeError {
+    fn from(e: &AsrError) -> Self {
+        match e {
+            AsrError::Connect(e) => e.clone(),
+            AsrError::Server { error, .. } => TypeFreeError::blocked(error),
+        }
+    }
+}
+
+impl From<AudioError> for TypeFreeError {
+    fn from(e: AudioError) -> Self {
+        TypeFreeError::Audio(e)
+    }
+}
+
+impl TypeFreeError {
+    /// 服务端拒绝识别
+    pub fn blocked(error: &ServerError) -> Self {
+        TypeFreeError::AsrBlocked {
+            code: error.code,
+            message: error.message.clone(),
+        }
+    }
+
+    /// 服务端报错，已有结果交付
+    pub fn interrupted(error: &ServerError) -> Self {
+        TypeFreeError::Interrupted {
+            code: error.code,
+            message: error.message.clone(),
+        }
+    }
+
+    /// 错误类别（序列化后的 kind 字段，前端据此处理）
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TypeFreeError::DoubaoNotRunning => "doubao_not_running",
+            TypeFreeError::ManualDoubao => "manual_doubao",
+            TypeFreeError::DoubaoNotInstalled => "doubao_not_installed",
+            TypeFreeError::DoubaoLaunch(_) => "doubao_launch",
+            TypeFreeError::CdpUnavailable(_) => "cdp_unavailable",
+            TypeFreeError::ForeignBrowser(_) => "foreign_browser",
+            TypeFreeError::NoDoubaoPage => "no_doubao_page",
+            TypeFreeError::NoChatPage(_) => "no_chat_page",
+            TypeFreeError::NotLoggedIn => "not_logged_in",
+            TypeFreeError::LoginExpired(_) => "login_expired",
+            TypeFreeError::WsConnect(_) => "ws_connect",
+            TypeFreeError::InvalidOverride(_) => "invalid_override",
+            TypeFreeError::AsrCapture(_) => "asr_capture",
+            TypeFreeError::CaptureCancelled => "capture_cancelled",
+            TypeFreeError::CoolingDown(_) => "cooling_down",
+            TypeFreeError::AsrBlocked { .. } => "asr_blocked",
+            TypeFreeError::Interrupted { .. } => "interrupted",
+            TypeFreeError::MissingPermissions(_) => "missing_permissions",
+            TypeFreeError::Audio(AudioError::NoDevice) => "no_device",
+            TypeFreeError::Audio(AudioError::PermissionDenied) => "microphone_denied",
+            TypeFreeError::Audio(AudioError::UnsupportedFormat(_)) => "unsupported_format",
+            TypeFreeError::Audio(AudioError::ChannelOutOfRange { .. }) => "channel_out_of_range",
+            TypeFreeError::Audio(AudioError::Device(_)) => "audio_device",
+            TypeFreeError::Busy(_) => "busy",
+            TypeFreeError::Other(_) => "other",
+        }
+    }
+
+    /// 服务端错误码（只有服务端报错时有）
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            TypeFreeError::AsrBlocked { code, .. } | TypeFreeError::Interrupted { code, .. } => {
+                Some(*code)
+            }
+            _ => None,
+        }
+    }
+
+    /// 给用户看的简短提示和可选的原始详情
+    pub fn describe(&self) -> (String, Option<String>) {
+        let (message, detail): (&str, Option<String>) = match self {
+            TypeFreeError::DoubaoNotRunning => ("请先启动豆包桌面端", None),
+            TypeFreeError::ManualDoubao => (doubao_launcher::MANUAL_MODE_REQUIREMENT, None),
+            TypeFreeError::DoubaoNotInstalled => ("未安装豆包桌面端", None),
+            TypeFreeError::DoubaoLaunch(e) => ("豆包启动失败", Some(e.clone())),
+            TypeFreeError::CdpUnavailable(e) => ("无法连接豆包桌面端", Some(e.clone())),
+            TypeFreeError::ForeignBrowser(browser) => {
+                ("调试端口被其他浏览器占用", Some(browser.clone()))
+            }
+            TypeFreeError::NoDoubaoPage => ("豆包窗口已关闭，请重新打开豆包", None),
+            TypeFreeError::NoChatPage(e) => ("请在豆包中打开一个对话", Some(e.clone())),
+            TypeFreeError::NotLoggedIn => ("未获取到登录信息，请在豆包中登录", None),
+            TypeFreeError::LoginExpired(e) => ("登录已失效，请在豆包中重新登录", Some(e.clone())),
+            TypeFreeError::WsConnect(e) => ("无法连接语音识别服务", Some(e.clone())),
+            TypeFreeError::InvalidOverride(e) => {
+                ("连接失败，请检查高级设置中的参数覆盖", Some(e.clone()))
+            }
+            TypeFreeError::AsrCapture(e) => (
+                "未能获取识别参数，请在豆包中使用一次语音输入",
+                Some(e.clone()),
+            ),
+            TypeFreeError::CaptureCancelled => ("识别参数捕获已让位于听写", None),
+            TypeFreeError::CoolingDown(failures) => {
+                return (
+                    "暂时无法连接，请稍后重试或重新登录".to_string(),
+                    Some(format!("连续 {} 次识别失败", failures)),
+                )
+            }
+            TypeFreeError::AsrBlocked { code, .. } => {
+                return (
+                    doubao_asr::server_message(*code).to_string(),
+                    Some(format!("错误码 {}", code)),
+                )
+            }
+            TypeFreeError::Interrupted { code, .. } => {
+                return (
+                    format!("识别中断：{}", doubao_asr::server_message(*code)),
+                    Some(format!("错误码 {}", code)),
+                )
+            }
+            TypeFreeError::MissingPermissions(names) => {
+                return (
+                    format!("缺少权限：{}", names.join("、")),
+                    Some("可在托盘「权限设置」中打开系统设置".to_string()),
+                )
+            }
+            TypeFreeError::Audio(AudioError::Device(e)) => ("麦克风打开失败", Some(e.clone())),
+            TypeFreeError::Audio(e) => return (e.user_message(), None),
+            TypeFreeError::Busy(e) | TypeFreeError::Other(e) => return (e.clone(), None),
+        };
+        (message.to_string(), detail)
+    }
+}
+
+impl std::fmt::Display for TypeFreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.describe() {
+            (message, Some(detail)) => write!(f, "{}（{}）", message, detail),
+            (message, None) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for TypeFreeError {}
+
+/// 序列化为 `{ kind, message, detail, code }`，前端按 kind 处理，直接显示 message
+impl Serialize for TypeFreeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (message, detail) = self.describe();
+        let mut state = serializer.serialize_struct("TypeFreeError", 4)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &message)?;
+        state.serialize_field("detail", &detail)?;
+        state.serialize_field("code", &self.code())?;
+        state.end()
+    }
+}
+
+/// 浮层上显示的错误（overlay-error 事件的载荷）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorDisplay {
+    /// 主提示
+    pub message: String,
+    /// 第二行的原始详情
+    pub detail: Option<String>,
+}
+
+/// 显示宽度：ASCII 算 1，其他字符算 2
+fn display_width(c: char) -> usize {
+    if c.is_ascii() {
+        1
+    } else {
+        2
+    }
+}
+
+/// 超出宽度时截断并加省略号
+fn ellipsize(text: &str, max_width: usize) -> String {
+    let text = text.trim();
+    if text.chars().map(display_width).sum::<usize>() <= max_width {
+        return text.to_string();
+    }
+    let mut width = 0;
+    let mut result = String::new();
+    for c in text.chars() {
+        width += display_width(c);
+        if width + 1 > max_width {
+            break;
+        }
+        result.push(c);
+    }
+    result.push('…');
+    result
+}
+
+/// 合并短时间内重复的错误
+#[derive(Debug, Default)]
+struct RepeatTracker {
+    last: Option<(String, Instant, u32)>,
+}
+
+impl RepeatTracker {
+    /// 记录一次错误，返回时间窗口内连续出现的次数（首次为 1）
+    fn record(&mut self, key: &str, now: Instant) -> u32 {
+        let count = match &self.last {
+            Some((last_key, at, count))
+                if last_key == key && now.duration_since(*at) <= REPEAT_WINDOW =>
+            {
+                count + 1
+            }
+            _ => 1,
+        };
+        self.last = Some((key.to_string(), now, count));
+        count
+    }
+}
+
+static REPEATS: Mutex<RepeatTracker> = Mutex::new(RepeatTracker { last: None });
+
+/// 格式化浮层上的错误提示
+fn format_display(message: &str, detail: Option<&str>, repeats: u32) -> ErrorDisplay {
+    let message = if repeats > 1 {
+        format!("仍然失败 ({})：{}", repeats, message)
+    } else {
+        message.to_string()
+    };
+    ErrorDisplay {
+        message: ellipsize(&message, MESSAGE_MAX_WIDTH),
+        detail: detail
+            .filter(|d| !d.trim().is_empty())
+            .map(|d| ellipsize(d, DETAIL_MAX_WIDTH)),
+    }
+}
+
+/// 整理要显示的错误（同时记录重复次数）
+pub fn present(error: &TypeFreeError) -> ErrorDisplay {
+    let (message, detail) = error.describe();
+    let repeats = REPEATS
+        .lock()
+        .map(|mut tracker| tracker.record(&message, Instant::now()))
+        .unwrap_or(1);
+    format_display(&message, detail.as_deref(), repeats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(error: TypeFreeError) -> ErrorDisplay {
+        let (message, detail) = error.describe();
+        format_display(&message, detail.as_deref(), 1)
+    }
+
+    fn server(code: i64) -> ServerError {
+        ServerError {
+            code,
+            message: "service busy".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let cases = [
+            (TypeFreeError::DoubaoNotRunning, "请先启动豆包桌面端", None),
+            (
+                TypeFreeError::MissingPermissions(vec!["辅助功能", "麦克风"]),
+                "缺少权限：辅助功能、麦克风",
+                Some("可在托盘「权限设置」中打开系统设置"),
+            ),
+            (
+                TypeFreeError::Audio(AudioError::NoDevice),
+                "未找到麦克风设备",
+                None,
+            ),
+            (
+                TypeFreeError::NotLoggedIn,
+                "未获取到登录信息，请在豆包中登录",
+                None,
+            ),
+            (
+                TypeFreeError::LoginExpired("HTTP error: 401 Unauthorized".to_string()),
+                "登录已失效，请在豆包中重新登录",
+                Some("HTTP error: 401 Unauthorized"),
+            ),
+            (
+                TypeFreeError::CdpUnavailable(
+                    "CDP request timed out: http://127.0.0.1:9222/json/list".to_string(),
+                ),
+                "无法连接豆包桌面端",
+                Some("CDP request timed out: http://127.0.0.1:9222/json/list"),
+            ),
+            (
+                TypeFreeError::CoolingDown(3),
+                "暂时无法连接，请稍后重试或重新登录",
+                Some("连续 3 次识别失败"),
+            ),
+            (
+                TypeFreeError::NoDoubaoPage,
+                "豆包窗口已关闭，请重新打开豆包",
+                None,
+            ),
+            (
+                TypeFreeError::blocked(&server(710022002)),
+                "服务暂时不可用，请稍后再试",
+                Some("错误码 710022002"),
+            ),
+            (
+                TypeFreeError::interrupted(&server(1001)),
+                "识别中断：语音识别出错，请重试",
+                Some("错误码 1001"),
+            ),
+        ];
+
+        for (error, message, detail) in cases {
+            let shown = display(error);
+            assert_eq!(shown.message, message);
+            assert_eq!(shown.detail.as_deref(), detail);
+        }
+    }
+
+    #[test]
+    fn test_serialize_with_kind() {
+        let value = serde_json::to_value(TypeFreeError::blocked(&server(710022002))).unwrap();
+        assert_eq!(value["kind"], "asr_blocked");
+        assert_eq!(value["message"], "服务暂时不可用，请稍后再试");
+        assert_eq!(value["code"], 710022002);
+
+        let value = serde_json::to_value(TypeFreeError::NotLoggedIn).unwrap();
+        assert_eq!(value["kind"], "not_logged_in");
+        assert!(value["detail"].is_null());
+        assert!(value["code"].is_null());
+
+        let error = TypeFreeError::WsConnect("timeout".to_string());
+        assert_eq!(error.to_string(), "无法连接语音识别服务（timeout）");
+    }
+
+    #[test]
+    fn test_long_detail_is_ellipsized() {
+        let raw = format!("Failed to connect ASR WebSocket: {}", "x".repeat(200));
+        let shown = display(TypeFreeError::WsConnect(raw));
+        assert_eq!(
+            shown.detail.as_deref(),
+            Some(
+                "Failed to connect ASR WebSocket: xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx…"
+            )
+        );
+
+        // 中文按双倍宽度计算
+        assert_eq!(ellipsize("一二三四五", 6), "一二…");
+        assert_eq!(ellipsize("一二三", 6), "一二三");
+    }
+
+    #[test]
+    fn test_repeats_within_window() {
+        let mut tracker = RepeatTracker::default();
+        let start = Instant::now();
+        assert_eq!(tracker.record("A", start), 1);
+        assert_eq!(tracker.record("A", start + Duration::from_secs(5)), 2);
+        assert_eq!(tracker.record("A", start + Duration::from_secs(20)), 3);
+        // 换了错误或超过时间窗口重新计数
+        assert_eq!(tracker.record("B", start + Duration::from_secs(21)), 1);
+        assert_eq!(tracker.record("B", start + Duration::from_secs(60)), 1);
+
+        let shown = format_display("登录已失效，请在豆包中重新登录", None, 3);
+        assert_eq!(
+            shown.message,
+            "仍然失败 (3)：登录已失效，请在豆包中重新登录"
+        );
+    }
+}
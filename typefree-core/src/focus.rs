@@ -0,0 +1,441 @@
+//! 前台应用检测
+//!
+//! 用于按应用区分的规则（粘贴时的目标应用），以及确认粘贴时前台窗口没有变化；
+//! 也可以事先记录粘贴目标，之后的听写都插入到该窗口（macOS 上为该输入框）
+
+use serde::Serialize;
+use std::sync::Mutex;
+
+/// 前台应用信息
+#[derive(Debug, Clone, Serialize)]
+pub struct AppInfo {
+    /// 显示名称（macOS 本地化名称，Windows 可执行文件名去掉扩展名）
+    pub name: String,
+    /// 标识（macOS bundle id，Windows 可执行文件名）
+    pub id: String,
+}
+
+impl AppInfo {
+    /// 规则中的应用是否匹配（名称或标识，不区分大小写）
+    pub fn matches(&self, app: &str) -> bool {
+        let app = app.trim();
+        !app.is_empty() && (self.name.eq_ignore_ascii_case(app) || self.id.eq_ignore_ascii_case(app))
+    }
+}
+
+/// 某一时刻的前台窗口（按下触发键时记录，粘贴前比较）
+#[derive(Debug, Clone)]
+pub struct FocusTarget {
+    pub app: AppInfo,
+    /// macOS 为进程 pid，Windows 为窗口句柄 HWND
+    handle: isize,
+}
+
+impl FocusTarget {
+    /// 是否为同一个应用（macOS）或窗口（Windows）
+    pub fn same_as(&self, other: &FocusTarget) -> bool {
+        self.handle == other.handle && self.app.id == other.app.id
+    }
+}
+
+/// 记录的粘贴目标：窗口，以及 macOS 上当时聚焦的输入框
+#[derive(Debug, Clone)]
+pub struct PinnedTarget {
+    pub target: FocusTarget,
+    #[cfg(target_os = "macos")]
+    element: Option<macos::AxElement>,
+}
+
+#[cfg(target_os = "macos")]
+#[allow(deprecated)]
+mod macos {
+    use super::{AppInfo, FocusTarget};
+    use cocoa::base::{id, nil, NO};
+    use core_foundation::base::TCFType;
+    use core_foundation::string::{CFString, CFStringRef};
+    use core_foundation_sys::base::{CFGetTypeID, CFRelease, CFRetain, CFTypeRef};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXUIElementCreateApplication(pid: i32) -> CFTypeRef;
+        fn AXUIElementCopyAttributeValue(
+            element: CFTypeRef,
+            attribute: CFStringRef,
+            value: *mut CFTypeRef,
+        ) -> i32;
+        fn AXUIElementSetAttributeValue(
+            element: CFTypeRef,
+            attribute: CFStringRef,
+            value: CFTypeRef,
+        ) -> i32;
+        fn AXUIElementIsAttributeSettable(
+            element: CFTypeRef,
+            attribute: CFStringRef,
+            settable: *mut u8,
+        ) -> i32;
+    }
+
+    /// kAXErrorSuccess
+    const AX_SUCCESS: i32 = 0;
+
+    /// 辅助功能元素（持有一次引用）
+    #[derive(Debug)]
+    pub struct AxElement(CFTypeRef);
+
+    // AXUIElement 可以在任意线程使用
+    unsafe impl Send for AxElement {}
+    unsafe impl Sync for AxElement {}
+
+    impl Clone for AxElement {
+        fn clone(&self) -> Self {
+            unsafe { AxElement(CFRetain(self.0)) }
+        }
+    }
+
+    impl Drop for AxElement {
+        fn drop(&mut self) {
+            unsafe { CFRelease(self.0) }
+        }
+    }
+
+    impl AxElement {
+        fn attribute(&self, name: &'static str) -> Option<CFTypeRef> {
+            let name = CFString::from_static_string(name);
+            let mut value: CFTypeRef = std::ptr::null();
+            let err = unsafe {
+                AXUIElementCopyAttributeValue(self.0, name.as_concrete_TypeRef(), &mut value)
+            };
+            (err == AX_SUCCESS && !value.is_null()).then_some(value)
+        }
+
+        /// 字符串属性的值
+        fn string_attribute(&self, name: &'static str) -> Option<String> {
+            let value = self.attribute(name)?;
+            unsafe {
+                if CFGetTypeID(value) != CFString::type_id() {
+                    CFRelease(value);
+                    return None;
+                }
+                Some(CFString::wrap_under_create_rule(value as CFStringRef).to_string())
+            }
+        }
+    }
+
+    /// 应用当前聚焦的输入框
+    pub fn focused_element(pid: i32) -> Option<AxElement> {
+        let app = unsafe { AXUIElementCreateApplication(pid) };
+        if app.is_null() {
+            return None;
+        }
+        let app = AxElement(app);
+        app.attribute("AXFocusedUIElement").map(AxElement)
+    }
+
+    /// 在输入框的光标处插入文本（替换选中内容），不需要切换前台窗口
+    ///
+    /// 部分应用（如 Electron）接受写入但不生效，写入后读回内容确认
+    pub fn insert_text(element: &AxElement, text: &str) -> bool {
+        let selected = CFString::from_static_string("AXSelectedText");
+        let mut settable = 0u8;
+        let err = unsafe {
+            AXUIElementIsAttributeSettable(element.0, selected.as_concrete_TypeRef(), &mut settable)
+        };
+        if err != AX_SUCCESS || settable == 0 {
+            return false;
+        }
+
+        let value = CFString::new(text);
+        let err = unsafe {
+            AXUIElementSetAttributeValue(
+                element.0,
+                selected.as_concrete_TypeRef(),
+                value.as_CFTypeRef(),
+            )
+        };
+        if err != AX_SUCCESS {
+            log::info!("[Focus] AXSelectedText rejected (error {})", err);
+            return false;
+        }
+        // 读不到内容的输入框只能相信写入结果
+        element
+            .string_attribute("AXValue")
+            .is_none_or(|current| current.contains(text))
+    }
+
+    unsafe fn ns_string(s: id) -> Option<String> {
+        if s == nil {
+            return None;
+        }
+        let ptr: *const c_char = msg_send![s, UTF8String];
+        if ptr.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+
+    /// NSApplicationActivateIgnoringOtherApps
+    const ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+
+    unsafe fn frontmost_application() -> Option<id> {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        (app != nil).then_some(app)
+    }
+
+    unsafe fn app_info(app: id) -> AppInfo {
+        let name = ns_string(msg_send![app, localizedName]).unwrap_or_default();
+        let id = ns_string(msg_send![app, bundleIdentifier]).unwrap_or_default();
+        AppInfo { name, id }
+    }
+
+    pub fn frontmost_app() -> Option<AppInfo> {
+        unsafe { frontmost_application().map(|app| app_info(app)) }
+    }
+
+    pub fn capture_target() -> Option<FocusTarget> {
+        unsafe {
+            let app = frontmost_application()?;
+            let pid: i32 = msg_send![app, processIdentifier];
+            Some(FocusTarget {
+                app: app_info(app),
+                handle: pid as isize,
+            })
+        }
+    }
+
+    /// 指定 bundle id 的运行中的应用（有多个实例时取第一个）
+    pub fn running_app(bundle_id: &str) -> Option<FocusTarget> {
+        let bundle = CFString::new(bundle_id);
+        unsafe {
+            let apps: id = msg_send![
+                class!(NSRunningApplication),
+                runningApplicationsWithBundleIdentifier: bundle.as_concrete_TypeRef() as id
+            ];
+            let count: usize = msg_send![apps, count];
+            if count == 0 {
+                return None;
+            }
+            let app: id = msg_send![apps, objectAtIndex: 0usize];
+            let pid: i32 = msg_send![app, processIdentifier];
+            Some(FocusTarget {
+                app: app_info(app),
+                handle: pid as isize,
+            })
+        }
+    }
+
+    pub fn activate(target: &FocusTarget) -> bool {
+        unsafe {
+            let pid = target.handle as i32;
+            let app: id = msg_send![
+                class!(NSRunningApplication),
+                runningApplicationWithProcessIdentifier: pid
+            ];
+            if app == nil {
+                return false;
+            }
+            let ok: cocoa::base::BOOL =
+                msg_send![app, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+            ok != NO
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::{AppInfo, FocusTarget};
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::windef::HWND;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winbase::QueryFullProcessImageNameW;
+    use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+    use winapi::um::winuser::{
+        GetForegroundWindow, GetWindowThreadProcessId, IsWindow, SetForegroundWindow,
+    };
+
+    pub fn frontmost_app() -> Option<AppInfo> {
+        app_for_window(foreground_window()?)
+    }
+
+    pub fn capture_target() -> Option<FocusTarget> {
+        let hwnd = foreground_window()?;
+        Some(FocusTarget {
+            app: app_for_window(hwnd)?,
+            handle: hwnd as isize,
+        })
+    }
+
+    pub fn activate(target: &FocusTarget) -> bool {
+        let hwnd = target.handle as HWND;
+        unsafe { IsWindow(hwnd) != 0 && SetForegroundWindow(hwnd) != 0 }
+    }
+
+    fn foreground_window() -> Option<HWND> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        (!hwnd.is_null()).then_some(hwnd)
+    }
+
+    fn app_for_window(hwnd: HWND) -> Option<AppInfo> {
+        unsafe {
+            let mut pid: DWORD = 0;
+            GetWindowThreadProcessId(hwnd, &mut pid);
+            if pid == 0 {
+                return None;
+            }
+
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+
+            let mut buf = [0u16; 1024];
+            let mut len = buf.len() as DWORD;
+            let ok = QueryFullProcessImageNameW(process, 0, buf.as_mut_ptr(), &mut len);
+            CloseHandle(process);
+            if ok == 0 {
+                return None;
+            }
+
+            let path = String::from_utf16_lossy(&buf[..len as usize]);
+            let file = std::path::Path::new(&path);
+            let id = file.file_name()?.to_string_lossy().into_owned();
+            let name = file
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| id.clone());
+            Some(AppInfo { name, id })
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use macos::{activate, capture_target, frontmost_app};
+
+#[cfg(target_os = "windows")]
+pub use windows::{activate, capture_target, frontmost_app};
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn frontmost_app() -> Option<AppInfo> {
+    None
+}
+
+/// 记录当前前台窗口
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn capture_target() -> Option<FocusTarget> {
+    None
+}
+
+/// 把目标窗口切回前台，成功返回 true
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn activate(_target: &FocusTarget) -> bool {
+    false
+}
+
+// ============ 记录的粘贴目标 ============
+
+static PINNED: Mutex<Option<PinnedTarget>> = Mutex::new(None);
+
+/// 记录当前前台窗口（macOS 上连同聚焦的输入框）为粘贴目标
+pub fn pin_target() -> Option<AppInfo> {
+    let target = capture_target()?;
+    #[cfg(target_os = "macos")]
+    let element = macos::focused_element(target.handle as i32);
+    log::info!("[Focus] Pinned paste target: {}", target.app.id);
+
+    let app = target.app.clone();
+    *PINNED.lock().unwrap() = Some(PinnedTarget {
+        target,
+        #[cfg(target_os = "macos")]
+        element,
+    });
+    Some(app)
+}
+
+/// 清除记录的粘贴目标，返回之前是否有记录
+pub fn clear_pinned_target() -> bool {
+    PINNED.lock().unwrap().take().is_some()
+}
+
+pub fn pinned_target() -> Option<PinnedTarget> {
+    PINNED.lock().unwrap().clone()
+}
+
+impl PinnedTarget {
+    /// 直接写入记录的输入框（仅 macOS），成功时不需要再粘贴
+    pub fn insert_text(&self, text: &str) -> bool {
+        #[cfg(target_os = "macos")]
+        if let Some(element) = &self.element {
+            return macos::insert_text(element, text);
+        }
+        let _ = text;
+        false
+    }
+}
+
+// ============ 设置中指定的插入应用 ============
+
+/// 启动应用后等待其运行的最长时间
+#[cfg(target_os = "macos")]
+const LAUNCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+/// 刚启动的应用还没有窗口，检测到运行后再等一会儿
+#[cfg(target_os = "macos")]
+const LAUNCH_SETTLE: std::time::Duration = std::time::Duration::from_millis(800);
+
+/// 确保指定应用（macOS bundle id）在运行：没有运行时启动并等它就绪，返回是否在运行
+///
+/// 会阻塞数秒，在按下触发键时放到后台线程执行，识别结束时应用已经启动好
+pub fn ensure_app_running(bundle_id: &str) -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::running_app(bundle_id).is_some() || launch_app(bundle_id)
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        log::warn!("[Focus] Target app is only supported on macOS: {}", bundle_id);
+        false
+    }
+}
+
+/// 指定应用作为粘贴目标，连同其聚焦的输入框返回，用法同记录的粘贴目标。
+/// 不启动应用（见 [`ensure_app_running`]），没有运行或非 macOS 时返回 None
+pub fn app_target(bundle_id: &str) -> Option<PinnedTarget> {
+    #[cfg(target_os = "macos")]
+    {
+        let target = macos::running_app(bundle_id)?;
+        let element = macos::focused_element(target.handle as i32);
+        Some(PinnedTarget { target, element })
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = bundle_id;
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn launch_app(bundle_id: &str) -> bool {
+    log::info!("[Focus] Target app {} is not running, launching", bundle_id);
+    let launched = std::process::Command::new("open")
+        .args(["-b", bundle_id])
+        .status()
+        .is_ok_and(|status| status.success());
+    if !launched {
+        log::warn!("[Focus] Failed to launch {}", bundle_id);
+        return false;
+    }
+
+    let deadline = std::time::Instant::now() + LAUNCH_TIMEOUT;
+    while std::time::Instant::now() < deadline {
+        if macos::running_app(bundle_id).is_some() {
+            std::thread::sleep(LAUNCH_SETTLE);
+            return true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    log::warn!("[Focus] {} did not start within {:?}", bundle_id, LAUNCH_TIMEOUT);
+    false
+}
@@ -0,0 +1,116 @@
+//! 系统专注状态 - 勿扰模式、演示或镜像屏幕时减少打扰
+//!
+//! 不检测屏幕共享（会议软件共享屏幕时系统不提供可靠的状态），需要时用听写总开关临时停用
+//!
+//! 只在会话开始时检测一次并缓存，不持续轮询；提示音在该状态下不播放，
+//! 开启「演示时隐藏浮层」时浮层也不显示
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// 重新检测（会话开始时调用），返回是否处于勿扰或镜像屏幕状态
+pub fn refresh() -> bool {
+    let quiet = do_not_disturb() || presenting();
+    if quiet != QUIET.swap(quiet, Ordering::SeqCst) {
+        log::info!(
+            "[FocusState] Quiet mode {}",
+            if quiet { "on" } else { "off" }
+        );
+    }
+    quiet
+}
+
+/// 最近一次检测的结果
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::SeqCst)
+}
+
+/// 是否应隐藏浮层（设置开启且处于勿扰或演示状态）
+pub fn overlay_suppressed() -> bool {
+    is_quiet() && crate::settings::get().hide_overlay_when_presenting
+}
+
+// ============ 勿扰模式 ============
+
+/// 系统是否处于勿扰模式（检测不到时视为关闭）
+#[cfg(target_os = "macos")]
+fn do_not_disturb() -> bool {
+    // macOS 12+ 的专注模式状态（读取失败时通常是没有完全磁盘访问权限）
+    let Some(home) = std::env::var_os("HOME") else {
+        return false;
+    };
+    let path = std::path::Path::new(&home).join("Library/DoNotDisturb/DB/Assertions.json");
+    std::fs::read_to_string(path)
+        .map(|json| focus_assertions_active(&json))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn do_not_disturb() -> bool {
+    use winapi::um::shellapi::{SHQueryUserNotificationState, QUNS_ACCEPTS_NOTIFICATIONS};
+
+    let mut state = 0;
+    // 专注助手、全屏和演示模式都不接受通知
+    let result = unsafe { SHQueryUserNotificationState(&mut state) };
+    result == 0 && state != QUNS_ACCEPTS_NOTIFICATIONS
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn do_not_disturb() -> bool {
+    false
+}
+
+/// Assertions.json 中有生效的专注模式记录
+#[cfg(any(target_os = "macos", test))]
+fn focus_assertions_active(json: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(json)
+        .ok()
+        .and_then(|value| {
+            value["data"].as_array().map(|data| {
+                data.iter().any(|entry| {
+                    entry["storeAssertionRecords"]
+                        .as_array()
+                        .is_some_and(|records| !records.is_empty())
+                })
+            })
+        })
+        .unwrap_or(false)
+}
+
+// ============ 演示 ============
+
+/// 是否正在镜像屏幕（接投影仪或投屏演示时）
+#[cfg(target_os = "macos")]
+fn presenting() -> bool {
+    use core_graphics::display::CGDisplay;
+
+    CGDisplay::active_displays()
+        .map(|ids| {
+            ids.into_iter()
+                .any(|id| CGDisplay::new(id).is_in_mirror_set())
+        })
+        .unwrap_or(false)
+}
+
+/// Windows 的演示模式已包含在通知状态中
+#[cfg(not(target_os = "macos"))]
+fn presenting() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_assertions() {
+        let active = r#"{"data":[{"storeAssertionRecords":[{"assertionDetails":{"assertionDetailsModeIdentifier":"com.apple.donotdisturb.mode.default"}}]}]}"#;
+        assert!(focus_assertions_active(active));
+        assert!(!focus_assertions_active(
+            r#"{"data":[{"storeAssertionRecords":[]}]}"#
+        ));
+        assert!(!focus_assertions_active(r#"{"data":[{}]}"#));
+        assert!(!focus_assertions_active("not json"));
+    }
+}
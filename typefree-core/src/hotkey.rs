@@ -0,0 +1,58 @@
+//! 触发键定义
+//!
+//! 设置中保存的触发键和修饰键；按键监听本身由外壳实现
+
+use serde::{Deserialize, Serialize};
+
+/// 触发键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Trigger {
+    /// macOS Fn 键
+    Fn,
+    /// macOS HID 键盘 usage（usage page 0x07，如右 Option = 0xE6、F13 = 0x68）
+    HidUsage { usage: u32 },
+    /// Windows 虚拟键码，长按触发（如右 Alt = 0xA5）
+    VirtualKey { vk: u32 },
+}
+
+/// 当前平台的默认触发键
+pub fn default_triggers() -> Vec<Trigger> {
+    if cfg!(target_os = "windows") {
+        vec![Trigger::VirtualKey { vk: 0xA5 }]
+    } else {
+        vec![Trigger::Fn]
+    }
+}
+
+/// 修饰键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    Shift,
+    Control,
+    /// macOS Option / Windows Alt
+    Alt,
+    /// macOS Command / Windows Win
+    Command,
+}
+
+/// 触发键按下时按住的修饰键
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub command: bool,
+}
+
+impl Modifiers {
+    pub fn contains(self, modifier: Modifier) -> bool {
+        match modifier {
+            Modifier::Shift => self.shift,
+            Modifier::Control => self.control,
+            Modifier::Alt => self.alt,
+            Modifier::Command => self.command,
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! 键盘操作 - 粘贴，以及撤销上一次粘贴
+
+use crate::focus::{self, FocusTarget};
+use arboard::Clipboard;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static SAVED_CLIPBOARD: Mutex<Option<String>> = Mutex::new(None);
+
+/// 剪贴板校验的最多写入次数（首次 + 重试一次）
+const CLIPBOARD_WRITE_ATTEMPTS: usize = 2;
+
+/// 保存当前剪贴板内容
+pub fn save_clipboard() {
+    log::info!("[Keyboard] Saving clipboard...");
+
+    match Clipboard::new() {
+        Ok(mut clip) => match clip.get_text() {
+            Ok(text) => {
+                // 按字符截取，避免在中文字符中间切开
+                let preview: String = text.chars().take(50).collect();
+                let preview = if text.chars().count() > 50 {
+                    format!("{}...", preview)
+                } else {
+                    preview
+                };
+                log::info!(
+                    "[Keyboard] Clipboard saved ({} chars): {}",
+                    text.chars().count(),
+                    preview
+                );
+                *SAVED_CLIPBOARD.lock().unwrap() = Some(text);
+            }
+            Err(e) => {
+                log::warn!("[Keyboard] No text in clipboard: {}", e);
+            }
+        },
+        Err(e) => {
+            log::error!("[Keyboard] Failed to open clipboard: {}", e);
+        }
+    }
+}
+
+/// 恢复剪贴板内容
+pub fn restore_clipboard() {
+    log::info!("[Keyboard] Restoring clipboard...");
+
+    if let Some(text) = SAVED_CLIPBOARD.lock().unwrap().take() {
+        match Clipboard::new() {
+            Ok(mut clip) => {
+                if let Err(e) = clip.set_text(&text) {
+                    log::error!("[Keyboard] Failed to restore clipboard: {}", e);
+                } else {
+                    log::info!("[Keyboard] Clipboard restored ({} chars)", text.len());
+                }
+            }
+            Err(e) => {
+                log::error!("[Keyboard] Failed to open clipboard: {}", e);
+            }
+        }
+    } else {
+        log::info!("[Keyboard] No saved clipboard to restore");
+    }
+}
+
+/// 是否开启了安全输入（密码框等获得焦点时），此时模拟的粘贴键会被系统拦截
+#[cfg(target_os = "macos")]
+pub fn secure_input_enabled() -> bool {
+    #[link(name = "Carbon", kind = "framework")]
+    extern "C" {
+        fn IsSecureEventInputEnabled() -> u8;
+    }
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn secure_input_enabled() -> bool {
+    false
+}
+
+/// 只把文本复制到剪贴板（不粘贴），成功返回 true
+pub fn copy_text(text: &str) -> bool {
+    match Clipboard::new().and_then(|mut clip| clip.set_text(text)) {
+        Ok(()) => {
+            log::info!("[Keyboard] Copied text ({} chars)", text.chars().count());
+            true
+        }
+        Err(e) => {
+            log::error!("[Keyboard] Failed to set clipboard: {}", e);
+            false
+        }
+    }
+}
+
+/// 粘贴最终文本到光标位置，`suffix` 追加在末尾（已以其结尾时不重复）
+pub fn paste_final(text: &str, suffix: &str) {
+    if text.is_empty() {
+        log::warn!("[Keyboard] Empty text, skip paste");
+        return;
+    }
+
+    // 后缀随文本一起写入剪贴板
+    let text = &crate::postprocess::append_suffix(text, suffix);
+    log::info!("[Keyboard] Pasting text ({} chars): {}", text.len(), text);
+
+    // 设置剪贴板
+    let mut clip = match Clipboard::new() {
+        Ok(c) => c,
+        Err(e) => {
+            log::error!("[Keyboard] Failed to open clipboard: {}", e);
+            return;
+        }
+    };
+
+    let settings = crate::settings::get();
+    let delay = Duration::from_millis(settings.paste_delay_ms);
+    if let Err(e) = write_clipboard(&mut clip, text, delay, settings.verify_clipboard) {
+        log::error!("[Keyboard] {}", e);
+        return;
+    }
+
+    log::info!("[Keyboard] Text set to clipboard");
+
+    if send_paste_keys() {
+        *LAST_PASTE.lock().unwrap() = Some(LastPaste {
+            chars: text.chars().count(),
+            target: focus::capture_target(),
+            at: Instant::now(),
+        });
+    }
+}
+
+/// 写入剪贴板并等待生效；verify 时读回校验，不一致则重写一次
+///
+/// 系统负载高时写入可能还没生效就发出了粘贴键，结果什么也没粘贴
+fn write_clipboard(
+    clip: &mut Clipboard,
+    text: &str,
+    delay: Duration,
+    verify: bool,
+) -> Result<(), String> {
+    for attempt in 1..=CLIPBOARD_WRITE_ATTEMPTS {
+        clip.set_text(text)
+            .map_err(|e| format!("Failed to set clipboard: {}", e))?;
+        std::thread::sleep(delay);
+        if !verify {
+            return Ok(());
+        }
+        match clip.get_text() {
+            Ok(current) if current == text => return Ok(()),
+            Ok(_) => log::warn!(
+                "[Keyboard] Clipboard mismatch after write (attempt {})",
+                attempt
+            ),
+            // 读不出来无法校验，照常粘贴
+            Err(e) => {
+                log::warn!("[Keyboard] Failed to read back clipboard: {}", e);
+                return Ok(());
+            }
+        }
+    }
+    Err("Clipboard still differs after retry, skip paste".to_string())
+}
+
+// ============ 撤销粘贴 ============
+
+/// 超过该时间的粘贴不再撤销（用户多半已继续编辑）
+const UNDO_MAX_AGE: Duration = Duration::from_secs(120);
+
+/// 切回原窗口后等待生效
+const UNDO_REACTIVATE_SETTLE: Duration = Duration::from_millis(150);
+
+/// 最近一次成功的粘贴
+struct LastPaste {
+    /// 粘贴的字符数（含后缀）
+    chars: usize,
+    /// 粘贴时的前台窗口
+    target: Option<FocusTarget>,
+    at: Instant,
+}
+
+static LAST_PASTE: Mutex<Option<LastPaste>> = Mutex::new(None);
+
+/// 撤销上一次粘贴：在原窗口发送与粘贴字符数相同的退格键
+///
+/// 只能确认窗口和时间，无法知道粘贴后光标是否移动过；移动过时会删掉错误的内容。
+/// 没有可撤销的粘贴时返回 Ok(None)，拒绝撤销时返回原因
+pub fn undo_last_paste() -> Result<Option<usize>, String> {
+    let Some(last) = LAST_PASTE.lock().unwrap().take() else {
+        return Ok(None);
+    };
+    if last.at.elapsed() > UNDO_MAX_AGE {
+        log::info!("[Keyboard] Last paste is too old to undo");
+        return Err("上次粘贴已过去太久，为避免删错不再撤销".to_string());
+    }
+
+    if let Some(target) = &last.target {
+        let current = focus::capture_target();
+        if !current.is_some_and(|current| current.same_as(target)) {
+            if !focus::activate(target) {
+                log::warn!("[Keyboard] Failed to reactivate {} for undo", target.app.id);
+                return Err("粘贴的窗口已关闭，无法撤销".to_string());
+            }
+            std::thread::sleep(UNDO_REACTIVATE_SETTLE);
+        }
+    }
+
+    log::info!("[Keyboard] Undoing last paste ({} chars)", last.chars);
+    if send_backspaces(last.chars) {
+        Ok(Some(last.chars))
+    } else {
+        Err("发送退格键失败".to_string())
+    }
+}
+
+// ============ 模拟按键 ============
+
+/// 模拟 Cmd+V / Ctrl+V，成功返回 true
+fn send_paste_keys() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        log::info!("[Keyboard] Executing Cmd+V via AppleScript");
+
+        // AppleScript 模拟 Cmd+V
+        let script = r#"
+            tell application "System Events"
+                keystroke "v" using command down
+            end tell
+        "#;
+        run_osascript(script)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winapi::um::winuser::VK_CONTROL;
+
+        log::info!("[Keyboard] Executing Ctrl+V via Windows SendInput API");
+
+        const VK_V: u16 = 0x56;
+
+        // Ctrl按下 -> V按下 -> V释放 -> Ctrl释放
+        send_key_inputs(&[
+            (VK_CONTROL as u16, false),
+            (VK_V, false),
+            (VK_V, true),
+            (VK_CONTROL as u16, true),
+        ])
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        log::warn!("[Keyboard] Paste not supported on this platform");
+        false
+    }
+}
+
+/// 模拟按下 count 次退格键，成功返回 true
+fn send_backspaces(count: usize) -> bool {
+    if count == 0 {
+        return true;
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // key code 51 = Delete（退格）
+        let script = format!(
+            r#"
+            tell application "System Events"
+                repeat {} times
+                    key code 51
+                end repeat
+            end tell
+        "#,
+            count
+        );
+        run_osascript(&script)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use winapi::um::winuser::VK_BACK;
+
+        let keys: Vec<(u16, bool)> = (0..count)
+            .flat_map(|_| [(VK_BACK as u16, false), (VK_BACK as u16, true)])
+            .collect();
+        send_key_inputs(&keys)
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        log::warn!("[Keyboard] Backspace not supported on this platform");
+        false
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn run_osascript(script: &str) -> bool {
+    use std::process::Command;
+
+    match Command::new("osascript").arg("-e").arg(script).output() {
+        Ok(output) if output.status.success() => {
+            log::info!("[Keyboard] Key command executed successfully");
+            true
+        }
+        Ok(output) => {
+            log::error!(
+                "[Keyboard] Key command failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            false
+        }
+        Err(e) => {
+            log::error!("[Keyboard] Failed to run osascript: {}", e);
+            false
+        }
+    }
+}
+
+/// 按顺序发送按键（虚拟键码, 是否为释放）
+#[cfg(target_os = "windows")]
+fn send_key_inputs(keys: &[(u16, bool)]) -> bool {
+    use winapi::um::winuser::{SendInput, INPUT, INPUT_KEYBOARD, KEYEVENTF_KEYUP};
+
+    unsafe {
+        let mut inputs: Vec<INPUT> = keys
+            .iter()
+            .map(|&(vk, up)| {
+                let mut input: INPUT = std::mem::zeroed();
+                input.type_ = INPUT_KEYBOARD;
+                input.u.ki_mut().wVk = vk;
+                input.u.ki_mut().dwFlags = if up { KEYEVENTF_KEYUP } else { 0 };
+                input
+            })
+            .collect();
+
+        let sent = SendInput(
+            inputs.len() as u32,
+            inputs.as_mut_ptr(),
+            std::mem::size_of::<INPUT>() as i32,
+        );
+
+        if sent == inputs.len() as u32 {
+            log::info!("[Keyboard] Key command executed successfully ({} inputs sent)", sent);
+            true
+        } else {
+            let error = std::io::Error::last_os_error();
+            log::error!(
+                "[Keyboard] SendInput failed: only {} of {} inputs sent, error: {}",
+                sent,
+                inputs.len(),
+                error
+            );
+            false
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! 延迟分解 - 记录一次听写各阶段的时间点，会话结束后输出一行耗时分解
+//!
+//! 时间线在按下触发键时创建，随会话选项传给录音和识别；各阶段只记录第一次到达的时间
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 会话阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// 收到按键
+    KeyPressed,
+    /// 浮层显示
+    OverlayShown,
+    /// 录音线程采集到第一段音频
+    FirstAudio,
+    /// ASR WebSocket 连接完成
+    WsConnected,
+    /// 第一个识别结果
+    FirstPartial,
+    /// 松开按键（或到达最长时长）
+    StopRequested,
+    /// 发出 finish
+    FinishSent,
+    /// 收到 finish 或等待超时，最终结果确定
+    Finished,
+    /// 粘贴完成
+    Pasted,
+}
+
+const STAGE_COUNT: usize = 9;
+
+impl Stage {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Stage::KeyPressed => "key",
+            Stage::OverlayShown => "overlay",
+            Stage::FirstAudio => "audio",
+            Stage::WsConnected => "ws",
+            Stage::FirstPartial => "partial",
+            Stage::StopRequested => "stop",
+            Stage::FinishSent => "finish",
+            Stage::Finished => "final",
+            Stage::Pasted => "paste",
+        }
+    }
+
+    const ALL: [Stage; STAGE_COUNT] = [
+        Stage::KeyPressed,
+        Stage::OverlayShown,
+        Stage::FirstAudio,
+        Stage::WsConnected,
+        Stage::FirstPartial,
+        Stage::StopRequested,
+        Stage::FinishSent,
+        Stage::Finished,
+        Stage::Pasted,
+    ];
+}
+
+/// 一次会话的时间线（克隆后共享同一份记录）
+#[derive(Debug, Clone)]
+pub struct SessionTimeline(Arc<Timeline>);
+
+#[derive(Debug)]
+struct Timeline {
+    start: Instant,
+    marks: Mutex<[Option<Duration>; STAGE_COUNT]>,
+}
+
+impl Default for SessionTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionTimeline {
+    /// 从现在开始计时（即收到按键的时间）
+    pub fn new() -> Self {
+        let mut marks = [None; STAGE_COUNT];
+        marks[Stage::KeyPressed.index()] = Some(Duration::ZERO);
+        Self(Arc::new(Timeline {
+            start: Instant::now(),
+            marks: Mutex::new(marks),
+        }))
+    }
+
+    /// 记录到达某阶段，已记录过的阶段不覆盖
+    pub fn mark(&self, stage: Stage) {
+        let elapsed = self.0.start.elapsed();
+        self.0.marks.lock().unwrap()[stage.index()].get_or_insert(elapsed);
+    }
+
+    pub fn breakdown(&self) -> LatencyBreakdown {
+        let marks = *self.0.marks.lock().unwrap();
+        breakdown_from(&marks)
+    }
+}
+
+/// 一个阶段的时间
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageTime {
+    pub stage: Stage,
+    /// 距按键的毫秒数
+    pub at_ms: u64,
+    /// 距上一个阶段的毫秒数
+    pub delta_ms: u64,
+}
+
+/// 一次会话的耗时分解（按发生先后排列，没到达的阶段不列出）
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyBreakdown {
+    pub stages: Vec<StageTime>,
+    /// 松开按键到粘贴完成的毫秒数
+    pub release_to_paste_ms: Option<u64>,
+}
+
+fn breakdown_from(marks: &[Option<Duration>; STAGE_COUNT]) -> LatencyBreakdown {
+    let mut reached: Vec<(Stage, u64)> = Stage::ALL
+        .iter()
+        .filter_map(|&stage| marks[stage.index()].map(|at| (stage, at.as_millis() as u64)))
+        .collect();
+    // 识别结果可能早于松开按键，按实际先后排列
+    reached.sort_by_key(|&(_, at_ms)| at_ms);
+
+    let mut previous = 0;
+    let stages = reached
+        .into_iter()
+        .map(|(stage, at_ms)| {
+            let delta_ms = at_ms - previous;
+            previous = at_ms;
+            StageTime {
+                stage,
+                at_ms,
+                delta_ms,
+            }
+        })
+        .collect();
+
+    let at = |stage: Stage| marks[stage.index()].map(|d| d.as_millis() as u64);
+    let release_to_paste_ms = match (at(Stage::StopRequested), at(Stage::Pasted)) {
+        (Some(stop), Some(paste)) => Some(paste.saturating_sub(stop)),
+        _ => None,
+    };
+    LatencyBreakdown {
+        stages,
+        release_to_paste_ms,
+    }
+}
+
+impl LatencyBreakdown {
+    /// 一行摘要，如 `overlay 12ms | ws +240ms | ... | release→paste 480ms`
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = self
+            .stages
+            .iter()
+            .filter(|time| time.stage != Stage::KeyPressed)
+            .map(|time| format!("{} +{}ms", time.stage.label(), time.delta_ms))
+            .collect();
+        if let Some(ms) = self.release_to_paste_ms {
+            parts.push(format!("release→paste {}ms", ms));
+        }
+        parts.join(" | ")
+    }
+}
+
+// 最近一次会话的耗时分解（诊断面板读取）
+static LAST: Mutex<Option<LatencyBreakdown>> = Mutex::new(None);
+
+/// 会话结束：输出耗时分解并保存为最近一次
+pub fn finish(generation: u64, timeline: &SessionTimeline) {
+    let breakdown = timeline.breakdown();
+    log::info!("[Latency] Session #{}: {}", generation, breakdown.summary());
+    *LAST.lock().unwrap() = Some(breakdown);
+}
+
+pub fn last() -> Option<LatencyBreakdown> {
+    LAST.lock().unwrap().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakdown() {
+        let ms = |ms: u64| Some(Duration::from_millis(ms));
+        let mut marks = [None; STAGE_COUNT];
+        marks[Stage::KeyPressed.index()] = ms(0);
+        marks[Stage::OverlayShown.index()] = ms(10);
+        marks[Stage::WsConnected.index()] = ms(300);
+        marks[Stage::FirstPartial.index()] = ms(700);
+        marks[Stage::StopRequested.index()] = ms(500);
+        marks[Stage::Pasted.index()] = ms(1400);
+
+        let breakdown = breakdown_from(&marks);
+        // 按先后排列，没到达的阶段不列出
+        let order: Vec<Stage> = breakdown.stages.iter().map(|t| t.stage).collect();
+        assert_eq!(
+            order,
+            [
+                Stage::KeyPressed,
+                Stage::OverlayShown,
+                Stage::WsConnected,
+                Stage::StopRequested,
+                Stage::FirstPartial,
+                Stage::Pasted,
+            ]
+        );
+        assert_eq!(breakdown.release_to_paste_ms, Some(900));
+        assert_eq!(
+            breakdown.summary(),
+            "overlay +10ms | ws +290ms | stop +200ms | partial +200ms | paste +700ms | release→paste 900ms"
+        );
+    }
+
+    #[test]
+    fn test_mark_keeps_first() {
+        let timeline = SessionTimeline::new();
+        timeline.mark(Stage::StopRequested);
+        let first = timeline.breakdown().stages[1].at_ms;
+        std::thread::sleep(Duration::from_millis(5));
+        timeline.clone().mark(Stage::StopRequested);
+        let breakdown = timeline.breakdown();
+        assert_eq!(breakdown.stages.len(), 2);
+        assert_eq!(breakdown.stages[1].at_ms, first);
+        // 没有粘贴时没有松开到粘贴的耗时
+        assert_eq!(breakdown.release_to_paste_ms, None);
+    }
+}
@@ -0,0 +1,36 @@
+//! TypeFree 核心 - 录音 → 豆包 ASR → 文本 → 粘贴，不依赖 Tauri
+//!
+//! 桌面应用（src-tauri）只是外壳，负责浮层、托盘、触发键监听和前端命令；
+//! 其他程序（命令行、本地服务等）可以直接使用这里的流程：
+//!
+//! 1. [`settings::init`] 加载设置（不调用时使用默认值）
+//! 2. [`doubao_launcher`] 确保豆包以调试模式运行，[`doubao_cdp`] 从中获取 Cookie 和 ASR 地址
+//! 3. [`audio::start_recording`] 把 16kHz PCM 帧写入 channel，设置停止标志时结束
+//! 4. [`doubao_asr::run_asr_session`] 读取该 channel，中间结果和最终结果通过回调交付
+//! 5. [`postprocess::process`] 后处理，[`keyboard::paste_final`] 粘贴到光标
+//!
+//! 核心不发送任何 UI 事件：状态变化通过回调（如 [`doubao_cdp::set_gate_wait_notifier`]）
+//! 或 channel 交给调用方，错误统一为 [`error::TypeFreeError`]
+
+pub mod audio;
+pub mod audio_dump;
+#[cfg(test)]
+mod cdp_mock;
+pub mod codec;
+pub mod cue;
+pub mod doubao_asr;
+pub mod doubao_cdp;
+pub mod doubao_launcher;
+pub mod error;
+pub mod focus;
+pub mod focus_state;
+pub mod hotkey;
+pub mod keyboard;
+pub mod latency;
+pub mod limiter;
+pub mod permissions;
+pub mod postprocess;
+pub mod resample;
+pub mod ring_buffer;
+pub mod settings;
+pub mod silence;
@@ -0,0 +1,138 @@
+//! 音量限制 - 大音量 f32 采样转换为 i16 时避免硬削波
+//!
+//! 靠近麦克风说话时采样常超出 [-1, 1]，直接截断会产生谐波失真，识别率下降。
+//! 限制器在峰值超过上限时立即降低增益，之后缓慢恢复，波形形状基本不变。
+//! 由设置 `clip_mode` 选择，每次录音时确定
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// 超出范围的采样如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipMode {
+    /// 直接截断到 [-1, 1]
+    Hard,
+    /// 限制器：降低增益使峰值不超过上限
+    #[default]
+    Limiter,
+}
+
+/// 限制器输出的峰值上限
+const CEILING: f32 = 0.98;
+
+/// 增益恢复的时间常数
+const RELEASE: Duration = Duration::from_millis(200);
+
+/// f32 → i16 转换器（限制器的增益在一次录音内保持）
+#[derive(Debug, Clone)]
+pub struct SampleConverter {
+    mode: ClipMode,
+    gain: f32,
+    /// 每个采样增益向 1 恢复的比例
+    release: f32,
+}
+
+impl SampleConverter {
+    pub fn new(mode: ClipMode, sample_rate: u32) -> Self {
+        let release_samples = RELEASE.as_secs_f32() * sample_rate.max(1) as f32;
+        Self {
+            mode,
+            gain: 1.0,
+            release: 1.0 - (-1.0 / release_samples).exp(),
+        }
+    }
+
+    /// 转换一个采样（四舍五入）
+    pub fn convert(&mut self, sample: f32) -> i16 {
+        let sample = match self.mode {
+            ClipMode::Hard => sample.clamp(-1.0, 1.0),
+            ClipMode::Limiter => {
+                self.gain += (1.0 - self.gain) * self.release;
+                let peak = sample.abs();
+                if peak * self.gain > CEILING {
+                    // 立即压低增益，不让这个采样超出上限
+                    self.gain = CEILING / peak;
+                }
+                sample * self.gain
+            }
+        };
+        (sample * 32767.0).round().clamp(-32768.0, 32767.0) as i16
+    }
+}
+
+/// 多通道 i16 采样取平均（四舍五入）
+pub fn mix_i16(frame: &[i16]) -> i16 {
+    if frame.is_empty() {
+        return 0;
+    }
+    let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+    (sum as f32 / frame.len() as f32).round() as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    /// 整数个周期内的总谐波失真（2 ~ 10 次谐波与基波的幅度比）
+    fn thd(samples: &[i16], freq: f64) -> f64 {
+        let amplitude = |harmonic: f64| {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (i, &s) in samples.iter().enumerate() {
+                let phase = 2.0 * std::f64::consts::PI * freq * harmonic * i as f64 / RATE as f64;
+                re += s as f64 * phase.cos();
+                im += s as f64 * phase.sin();
+            }
+            (re * re + im * im).sqrt()
+        };
+        let harmonics: f64 = (2..=10).map(|h| amplitude(h as f64).powi(2)).sum();
+        harmonics.sqrt() / amplitude(1.0)
+    }
+
+    fn loud_sine(mode: ClipMode) -> Vec<i16> {
+        let mut converter = SampleConverter::new(mode, RATE);
+        let samples: Vec<i16> = (0..RATE)
+            .map(|i| {
+                let t = i as f32 / RATE as f32;
+                converter.convert(1.4 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin())
+            })
+            .collect();
+        // 跳过开头增益建立的 100ms，剩下整数个周期
+        samples[(RATE / 10) as usize..].to_vec()
+    }
+
+    #[test]
+    fn test_limiter_reduces_distortion_on_loud_sine() {
+        let hard = thd(&loud_sine(ClipMode::Hard), 1000.0);
+        let limited = thd(&loud_sine(ClipMode::Limiter), 1000.0);
+        assert!(hard > 0.05, "hard clip THD {}", hard);
+        assert!(limited < 0.01, "limiter THD {}", limited);
+
+        let peak = loud_sine(ClipMode::Limiter)
+            .iter()
+            .map(|s| s.unsigned_abs())
+            .max()
+            .unwrap();
+        assert!(peak <= (CEILING * 32767.0).round() as u16);
+    }
+
+    #[test]
+    fn test_quiet_input_passes_through_with_rounding() {
+        let mut converter = SampleConverter::new(ClipMode::Limiter, RATE);
+        assert_eq!(converter.convert(0.5), 16384);
+        assert_eq!(converter.convert(-0.25), -8192);
+        let mut hard = SampleConverter::new(ClipMode::Hard, RATE);
+        assert_eq!(hard.convert(2.0), 32767);
+        assert_eq!(hard.convert(-2.0), -32767);
+    }
+
+    #[test]
+    fn test_mix_rounds_instead_of_truncating() {
+        assert_eq!(mix_i16(&[1, 2]), 2);
+        assert_eq!(mix_i16(&[-1, -2]), -2);
+        assert_eq!(mix_i16(&[32767, 32767]), 32767);
+        assert_eq!(mix_i16(&[]), 0);
+    }
+}
@@ -0,0 +1,472 @@
+//! macOS 权限检测模块
+//!
+//! 权限状态按功能给出（能否监听按键、粘贴、直接写入、录音），每项由实际依赖的系统权限算出。
+//! 后台定时检查权限，变化时回调（用户在系统设置中授权后界面自动更新）
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use core_foundation::base::*;
+    use core_foundation::dictionary::*;
+    use core_foundation::number::*;
+    use core_foundation::runloop::*;
+    use core_foundation::string::*;
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    const K_IO_HID_DEVICE_USAGE_PAGE_KEY: &str = "DeviceUsagePage";
+    const K_IO_HID_DEVICE_USAGE_KEY: &str = "DeviceUsage";
+    const K_HID_PAGE_GENERIC_DESKTOP: i32 = 0x01;
+    const K_HID_USAGE_KEYBOARD: i32 = 0x06;
+
+    #[repr(C)]
+    struct __IOHIDManager {
+        _private: [u8; 0],
+    }
+    type IOHIDManagerRef = *mut __IOHIDManager;
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOHIDManagerCreate(allocator: CFAllocatorRef, options: u32) -> IOHIDManagerRef;
+        fn IOHIDManagerSetDeviceMatching(manager: IOHIDManagerRef, matching: CFDictionaryRef);
+        fn IOHIDManagerOpen(manager: IOHIDManagerRef, options: u32) -> i32;
+        fn IOHIDManagerClose(manager: IOHIDManagerRef, options: u32) -> i32;
+    }
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+    }
+
+    /// Apple Event 地址描述
+    #[repr(C)]
+    struct AEDesc {
+        descriptor_type: u32,
+        data_handle: *mut std::ffi::c_void,
+    }
+
+    #[link(name = "CoreServices", kind = "framework")]
+    extern "C" {
+        fn AECreateDesc(
+            type_code: u32,
+            data: *const std::ffi::c_void,
+            size: isize,
+            result: *mut AEDesc,
+        ) -> i16;
+        fn AEDisposeDesc(desc: *mut AEDesc) -> i16;
+        fn AEDeterminePermissionToAutomateTarget(
+            target: *const AEDesc,
+            event_class: u32,
+            event_id: u32,
+            ask_user_if_needed: u8,
+        ) -> i32;
+    }
+
+    const SYSTEM_EVENTS_BUNDLE_ID: &str = "com.apple.systemevents";
+    const TYPE_APPLICATION_BUNDLE_ID: u32 = u32::from_be_bytes(*b"bund");
+    const TYPE_WILD_CARD: u32 = u32::from_be_bytes(*b"****");
+    /// 用户拒绝了自动化权限
+    const ERR_AE_EVENT_NOT_PERMITTED: i32 = -1743;
+    /// 还没询问过用户
+    const ERR_AE_EVENT_WOULD_REQUIRE_USER_CONSENT: i32 = -1744;
+    /// 目标应用没有运行
+    const PROC_NOT_FOUND: i32 = -600;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    extern "C" {
+        static AVMediaTypeAudio: *mut Object;
+    }
+
+    /// AVAuthorizationStatus：0 未决定，1 受限，2 拒绝，3 已授权
+    const AV_AUTHORIZATION_STATUS_AUTHORIZED: isize = 3;
+
+    /// 检测 Input Monitoring 权限
+    /// 通过尝试打开 IOHIDManager 来检测
+    pub fn check_input_monitoring() -> bool {
+        unsafe {
+            let manager = IOHIDManagerCreate(kCFAllocatorDefault, 0);
+            if manager.is_null() {
+                return false;
+            }
+
+            let page_key = CFString::new(K_IO_HID_DEVICE_USAGE_PAGE_KEY);
+            let usage_key = CFString::new(K_IO_HID_DEVICE_USAGE_KEY);
+            let page_num = CFNumber::from(K_HID_PAGE_GENERIC_DESKTOP);
+            let usage_num = CFNumber::from(K_HID_USAGE_KEYBOARD);
+
+            let matching = CFDictionary::from_CFType_pairs(&[
+                (page_key.as_CFType(), page_num.as_CFType()),
+                (usage_key.as_CFType(), usage_num.as_CFType()),
+            ]);
+
+            IOHIDManagerSetDeviceMatching(manager, matching.as_concrete_TypeRef());
+
+            let result = IOHIDManagerOpen(manager, 0);
+            if result == 0 {
+                IOHIDManagerClose(manager, 0);
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    /// 检测 Accessibility 权限
+    pub fn check_accessibility() -> bool {
+        unsafe { AXIsProcessTrusted() }
+    }
+
+    /// 检测控制 System Events 的自动化权限（AppleScript 模拟粘贴键需要）
+    pub fn check_automation() -> bool {
+        automation_permission(false)
+    }
+
+    /// 请求自动化权限：还没询问过时弹出系统提示，阻塞到用户选择，不要在主线程调用
+    pub fn request_automation() -> bool {
+        automation_permission(true)
+    }
+
+    fn automation_permission(ask: bool) -> bool {
+        unsafe {
+            let mut target = AEDesc {
+                descriptor_type: 0,
+                data_handle: std::ptr::null_mut(),
+            };
+            let err = AECreateDesc(
+                TYPE_APPLICATION_BUNDLE_ID,
+                SYSTEM_EVENTS_BUNDLE_ID.as_ptr().cast(),
+                SYSTEM_EVENTS_BUNDLE_ID.len() as isize,
+                &mut target,
+            );
+            if err != 0 {
+                log::warn!("[Permissions] AECreateDesc failed: {}", err);
+                return false;
+            }
+            let status = AEDeterminePermissionToAutomateTarget(
+                &target,
+                TYPE_WILD_CARD,
+                TYPE_WILD_CARD,
+                ask as u8,
+            );
+            AEDisposeDesc(&mut target);
+
+            log::debug!("[Permissions] System Events automation status: {}", status);
+            match status {
+                0 => true,
+                // System Events 没运行时无法判断，首次粘贴会启动它，需要时系统会弹出授权提示
+                PROC_NOT_FOUND => true,
+                ERR_AE_EVENT_NOT_PERMITTED | ERR_AE_EVENT_WOULD_REQUIRE_USER_CONSENT => false,
+                other => {
+                    log::warn!("[Permissions] Unexpected automation status: {}", other);
+                    false
+                }
+            }
+        }
+    }
+
+    /// 检测麦克风权限
+    /// 直接读取 AVCaptureDevice 的授权状态（不触发授权弹窗，可以频繁调用）
+    pub fn check_microphone() -> bool {
+        let status: isize = unsafe {
+            msg_send![
+                class!(AVCaptureDevice),
+                authorizationStatusForMediaType: AVMediaTypeAudio
+            ]
+        };
+        log::debug!("[Permissions] Microphone authorization status: {}", status);
+        status == AV_AUTHORIZATION_STATUS_AUTHORIZED
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use macos::*;
+
+// ============ Windows 权限检查 ============
+#[cfg(target_os = "windows")]
+mod windows {
+    /// 检测 Input Monitoring 权限
+    /// Windows 不需要显式的 Input Monitoring 权限，全局键盘钩子通常可以直接使用
+    /// 但某些安全软件可能会阻止，这里返回 true 表示默认允许
+    pub fn check_input_monitoring() -> bool {
+        // Windows 上键盘钩子不需要特殊权限（除非被安全软件阻止）
+        // 实际检测在 fn_key.rs 中设置钩子时会失败
+        true
+    }
+
+    /// 检测 Accessibility 权限
+    /// Windows 上使用 UI Automation 不需要特殊权限
+    pub fn check_accessibility() -> bool {
+        true
+    }
+
+    /// Windows 用 SendInput 模拟粘贴键，不需要自动化权限
+    pub fn check_automation() -> bool {
+        true
+    }
+
+    pub fn request_automation() -> bool {
+        true
+    }
+
+    /// 检测麦克风权限
+    /// Windows 10/11 有麦克风隐私设置，通过尝试枚举音频设备来检测
+    pub fn check_microphone() -> bool {
+        // 尝试通过 cpal 检测是否有可用的输入设备
+        // 如果麦克风权限被拒绝，通常会返回空列表或报错
+        use cpal::traits::{DeviceTrait, HostTrait};
+
+        let host = cpal::default_host();
+
+        // 检查是否有默认输入设备
+        match host.default_input_device() {
+            Some(device) => {
+                // 尝试获取设备名称，如果权限被拒绝可能会失败
+                match device.name() {
+                    Ok(name) => {
+                        log::debug!("[Permissions] Windows microphone available: {}", name);
+                        true
+                    }
+                    Err(e) => {
+                        log::debug!("[Permissions] Windows microphone access error: {}", e);
+                        false
+                    }
+                }
+            }
+            None => {
+                log::debug!("[Permissions] No default input device found on Windows");
+                false
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use windows::*;
+
+// ============ 其他平台（Linux 等） ============
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn check_input_monitoring() -> bool {
+    true
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn check_accessibility() -> bool {
+    true
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn check_microphone() -> bool {
+    true
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn check_automation() -> bool {
+    true
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn request_automation() -> bool {
+    true
+}
+
+/// 权限状态（按功能）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionStatus {
+    /// 能监听触发键（输入监控）
+    pub can_monitor_keys: bool,
+    /// 能模拟粘贴键（辅助功能 + 控制 System Events 的自动化权限）
+    pub can_paste: bool,
+    /// 能直接写入输入框或模拟键入（辅助功能）
+    pub can_type_direct: bool,
+    /// 能录音（麦克风）
+    pub can_record_audio: bool,
+}
+
+/// 序列化格式：按功能的字段，加上旧版的 input_monitoring / accessibility / microphone
+#[derive(serde::Serialize)]
+struct PermissionStatusWire {
+    can_monitor_keys: bool,
+    can_paste: bool,
+    can_type_direct: bool,
+    can_record_audio: bool,
+    input_monitoring: bool,
+    accessibility: bool,
+    microphone: bool,
+}
+
+impl serde::Serialize for PermissionStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PermissionStatusWire {
+            can_monitor_keys: self.can_monitor_keys,
+            can_paste: self.can_paste,
+            can_type_direct: self.can_type_direct,
+            can_record_audio: self.can_record_audio,
+            input_monitoring: self.can_monitor_keys,
+            accessibility: self.can_type_direct,
+            microphone: self.can_record_audio,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl PermissionStatus {
+    pub fn check() -> Self {
+        Self::from_grants(
+            check_input_monitoring(),
+            check_accessibility(),
+            check_automation(),
+            check_microphone(),
+        )
+    }
+
+    /// 由各项系统权限算出功能
+    fn from_grants(
+        input_monitoring: bool,
+        accessibility: bool,
+        automation: bool,
+        microphone: bool,
+    ) -> Self {
+        Self {
+            can_monitor_keys: input_monitoring,
+            can_paste: accessibility && automation,
+            can_type_direct: accessibility,
+            can_record_audio: microphone,
+        }
+    }
+
+    /// 需要用户去授予的系统权限名称
+    pub fn missing(&self) -> Vec<&'static str> {
+        [
+            (self.can_monitor_keys, "输入监控"),
+            (self.can_type_direct, "辅助功能"),
+            // 缺辅助功能时已经列出，不再重复提示自动化
+            (self.can_paste || !self.can_type_direct, "自动化（System Events）"),
+            (self.can_record_audio, "麦克风"),
+        ]
+        .into_iter()
+        .filter(|(granted, _)| !granted)
+        .map(|(_, name)| name)
+        .collect()
+    }
+
+    pub fn all_granted(&self) -> bool {
+        self.can_monitor_keys && self.can_paste && self.can_type_direct && self.can_record_audio
+    }
+}
+
+// ============ 权限变化监控 ============
+
+/// 有权限未授予时的检查间隔（等用户去系统设置授权）
+const POLL_INTERVAL_MISSING: Duration = Duration::from_secs(2);
+
+/// 权限都已授予时的检查间隔（只为发现被撤销）
+const POLL_INTERVAL_GRANTED: Duration = Duration::from_secs(30);
+
+/// 权限变化（事件载荷）
+#[derive(Debug, serde::Serialize, Clone, PartialEq)]
+pub struct PermissionChange {
+    pub before: PermissionStatus,
+    pub after: PermissionStatus,
+}
+
+/// 请求立即重新检查（主窗口获得焦点时）
+static RECHECK: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+fn poll_interval(status: &PermissionStatus) -> Duration {
+    if status.all_granted() {
+        POLL_INTERVAL_GRANTED
+    } else {
+        POLL_INTERVAL_MISSING
+    }
+}
+
+/// 和上次的状态比较，有变化时返回变化
+fn diff(before: &PermissionStatus, after: &PermissionStatus) -> Option<PermissionChange> {
+    (before != after).then(|| PermissionChange {
+        before: before.clone(),
+        after: after.clone(),
+    })
+}
+
+/// 唤醒监控线程立即检查一次
+pub fn request_recheck() {
+    let (pending, wake) = &RECHECK;
+    *pending.lock().unwrap() = true;
+    wake.notify_one();
+}
+
+/// 启动监控线程：从 `initial` 开始定时检查，权限变化时回调
+pub fn spawn_watcher(
+    initial: PermissionStatus,
+    on_change: impl Fn(PermissionChange) + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        let mut last = initial;
+        loop {
+            {
+                let (pending, wake) = &RECHECK;
+                let guard = pending.lock().unwrap();
+                let (mut guard, _) = wake
+                    .wait_timeout_while(guard, poll_interval(&last), |pending| !*pending)
+                    .unwrap();
+                *guard = false;
+            }
+
+            let current = PermissionStatus::check();
+            if let Some(change) = diff(&last, &current) {
+                log::info!("[Permissions] Changed: {:?} -> {:?}", change.before, change.after);
+                on_change(change);
+            }
+            last = current;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_from_grants() {
+        let granted = PermissionStatus::from_grants(true, true, true, true);
+        assert!(granted.all_granted());
+        assert!(granted.missing().is_empty());
+
+        // 辅助功能已授权但不能控制 System Events：能直接写入，不能粘贴
+        let no_automation = PermissionStatus::from_grants(true, true, false, true);
+        assert!(no_automation.can_type_direct);
+        assert!(!no_automation.can_paste);
+        assert_eq!(no_automation.missing(), vec!["自动化（System Events）"]);
+
+        let no_accessibility = PermissionStatus::from_grants(true, false, true, false);
+        assert!(!no_accessibility.can_paste);
+        assert_eq!(no_accessibility.missing(), vec!["辅助功能", "麦克风"]);
+    }
+
+    #[test]
+    fn test_serialized_with_legacy_fields() {
+        let status = PermissionStatus::from_grants(true, true, false, false);
+        let value = serde_json::to_value(&status).unwrap();
+        assert_eq!(value["can_paste"], false);
+        assert_eq!(value["can_type_direct"], true);
+        assert_eq!(value["input_monitoring"], true);
+        assert_eq!(value["accessibility"], true);
+        assert_eq!(value["microphone"], false);
+    }
+
+    #[test]
+    fn test_diff_and_interval() {
+        let missing = PermissionStatus::from_grants(true, false, true, true);
+        let granted = PermissionStatus::from_grants(true, true, true, true);
+
+        assert_eq!(diff(&missing, &missing.clone()), None);
+        let change = diff(&missing, &granted).unwrap();
+        assert!(!change.before.can_paste);
+        assert!(change.after.can_paste);
+
+        assert_eq!(poll_interval(&missing), POLL_INTERVAL_MISSING);
+        assert_eq!(poll_interval(&granted), POLL_INTERVAL_GRANTED);
+    }
+}
@@ -0,0 +1,280 @@
+//! 最终文本后处理
+//!
+//! 粘贴前按设置对识别结果做大小写转换（应用规则优先于全局设置）。
+//!
+//! 大小写转换只影响拉丁字母，中文中夹杂的英文单词同样处理。
+//! 粘贴后缀（如空格）不属于识别结果，在粘贴时才追加。
+//! 只有标点、空白或语气词的结果（误按时常见）在粘贴前过滤掉。
+
+use crate::focus::AppInfo;
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+/// 大小写转换方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseMode {
+    /// 不转换
+    #[default]
+    None,
+    /// 每句首字母大写
+    Sentence,
+    /// 全部小写
+    Lower,
+    /// 全部大写
+    Upper,
+    /// 每个单词首字母大写
+    Title,
+}
+
+/// 处理最终文本，`app` 为粘贴目标应用（用于按应用的规则）
+pub fn process(text: &str, settings: &Settings, app: Option<&AppInfo>) -> String {
+    let case_mode = case_mode_for(settings, app);
+    apply_case(text, case_mode)
+}
+
+/// 目标应用的大小写转换方式（应用规则优先于全局设置）
+fn case_mode_for(settings: &Settings, app: Option<&AppInfo>) -> CaseMode {
+    app.and_then(|app| {
+        settings
+            .app_rules
+            .iter()
+            .find(|rule| app.matches(&rule.app))
+            .and_then(|rule| rule.case_mode)
+    })
+    .unwrap_or(settings.case_mode)
+}
+
+/// 目标应用的粘贴后缀（应用规则优先于全局设置）
+pub fn paste_suffix_for<'a>(settings: &'a Settings, app: Option<&AppInfo>) -> &'a str {
+    app.and_then(|app| {
+        settings
+            .app_rules
+            .iter()
+            .find(|rule| app.matches(&rule.app))
+            .and_then(|rule| rule.paste_suffix.as_deref())
+    })
+    .unwrap_or(&settings.paste_suffix)
+}
+
+/// 追加粘贴后缀，文本已以后缀结尾时不重复追加（空白后缀遇到已有的结尾空白同样跳过）
+pub fn append_suffix(text: &str, suffix: &str) -> String {
+    let already_ends = text.ends_with(suffix)
+        || (suffix.chars().all(char::is_whitespace) && text.ends_with(char::is_whitespace));
+    if text.is_empty() || suffix.is_empty() || already_ends {
+        return text.to_string();
+    }
+    format!("{}{}", text, suffix)
+}
+
+// ============ 内容过滤 ============
+
+const CJK_PUNCTUATION: &str = "。，、！？；：…～—“”‘’（）《》";
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || CJK_PUNCTUATION.contains(c)
+}
+
+/// 由语气词重复组成（如「嗯嗯」「uh」），不区分大小写
+fn is_filler(token: &str, fillers: &[String]) -> bool {
+    let token = token.to_lowercase();
+    let mut rest = token.as_str();
+    while !rest.is_empty() {
+        let Some(filler) = fillers
+            .iter()
+            .map(|f| f.trim().to_lowercase())
+            .find(|f| !f.is_empty() && rest.starts_with(f.as_str()))
+        else {
+            return false;
+        };
+        rest = &rest[filler.len()..];
+    }
+    true
+}
+
+/// 去掉空白、标点和纯语气词后剩余的字数
+fn content_chars(text: &str, fillers: &[String]) -> usize {
+    text.split(|c: char| c.is_whitespace() || is_punctuation(c))
+        .filter(|token| !token.is_empty() && !is_filler(token, fillers))
+        .map(|token| token.chars().count())
+        .sum()
+}
+
+/// 有效内容少于设置的字数（设为 0 时不过滤）
+pub fn is_too_short(text: &str, settings: &Settings) -> bool {
+    settings.min_content_chars > 0
+        && content_chars(text, &settings.filler_words) < settings.min_content_chars
+}
+
+/// 中日韩文字（不属于拉丁单词）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}'
+    )
+}
+
+/// 拉丁单词中的字符
+fn is_word_char(c: char) -> bool {
+    (c.is_alphanumeric() && !is_cjk(c)) || c == '\''
+}
+
+/// 句末标点
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '。' | '！' | '？' | '\n')
+}
+
+fn push_upper(out: &mut String, c: char) {
+    out.extend(c.to_uppercase());
+}
+
+/// 转换大小写
+pub fn apply_case(text: &str, mode: CaseMode) -> String {
+    match mode {
+        CaseMode::None => text.to_string(),
+        CaseMode::Lower => text.to_lowercase(),
+        CaseMode::Upper => text.to_uppercase(),
+        CaseMode::Sentence => {
+            let mut out = String::with_capacity(text.len());
+            let mut at_sentence_start = true;
+            for c in text.chars() {
+                if is_sentence_end(c) {
+                    at_sentence_start = true;
+                    out.push(c);
+                } else if c.is_whitespace() {
+                    out.push(c);
+                } else if at_sentence_start && c.is_alphabetic() && !is_cjk(c) {
+                    at_sentence_start = false;
+                    push_upper(&mut out, c);
+                } else {
+                    at_sentence_start = false;
+                    out.push(c);
+                }
+            }
+            out
+        }
+        CaseMode::Title => {
+            let mut out = String::with_capacity(text.len());
+            let mut prev_is_word = false;
+            for c in text.chars() {
+                if !prev_is_word && c.is_alphabetic() && !is_cjk(c) {
+                    push_upper(&mut out, c);
+                } else {
+                    out.push(c);
+                }
+                prev_is_word = is_word_char(c);
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_modes_on_english() {
+        let text = "hello world. this is an API test! ok";
+        assert_eq!(apply_case(text, CaseMode::None), text);
+        assert_eq!(apply_case(text, CaseMode::Lower), "hello world. this is an api test! ok");
+        assert_eq!(apply_case(text, CaseMode::Upper), "HELLO WORLD. THIS IS AN API TEST! OK");
+        assert_eq!(apply_case(text, CaseMode::Sentence), "Hello world. This is an API test! Ok");
+        assert_eq!(apply_case(text, CaseMode::Title), "Hello World. This Is An API Test! Ok");
+    }
+
+    #[test]
+    fn test_case_modes_on_mixed_chinese() {
+        let text = "我在用python写rust代码。github上见";
+        assert_eq!(apply_case(text, CaseMode::Upper), "我在用PYTHON写RUST代码。GITHUB上见");
+        assert_eq!(apply_case(text, CaseMode::Title), "我在用Python写Rust代码。Github上见");
+        // 句首是中文时不改动后面的英文
+        assert_eq!(apply_case(text, CaseMode::Sentence), "我在用python写rust代码。Github上见");
+    }
+
+    #[test]
+    fn test_title_keeps_apostrophes_and_digits() {
+        assert_eq!(apply_case("don't use 3d models", CaseMode::Title), "Don't Use 3d Models");
+    }
+
+    #[test]
+    fn test_app_rule_overrides_global() {
+        let mut settings = Settings {
+            case_mode: CaseMode::Sentence,
+            ..Default::default()
+        };
+        settings.app_rules.push(crate::settings::AppRule {
+            app: "com.microsoft.VSCode".to_string(),
+            case_mode: Some(CaseMode::Lower),
+            paste_suffix: None,
+        });
+
+        let vscode = AppInfo {
+            name: "Code".to_string(),
+            id: "com.microsoft.VSCode".to_string(),
+        };
+        let notes = AppInfo {
+            name: "Notes".to_string(),
+            id: "com.apple.Notes".to_string(),
+        };
+
+        assert_eq!(process("Hello World", &settings, Some(&vscode)), "hello world");
+        assert_eq!(process("hello world", &settings, Some(&notes)), "Hello world");
+        assert_eq!(process("hello world", &settings, None), "Hello world");
+    }
+
+    #[test]
+    fn test_paste_suffix_per_app_without_doubling() {
+        let mut settings = Settings {
+            paste_suffix: " ".to_string(),
+            ..Default::default()
+        };
+        settings.app_rules.push(crate::settings::AppRule {
+            app: "code.exe".to_string(),
+            case_mode: None,
+            paste_suffix: Some(String::new()),
+        });
+        let editor = AppInfo {
+            name: "Code".to_string(),
+            id: "Code.exe".to_string(),
+        };
+
+        assert_eq!(paste_suffix_for(&settings, Some(&editor)), "");
+        assert_eq!(paste_suffix_for(&settings, None), " ");
+
+        assert_eq!(append_suffix("你好", " "), "你好 ");
+        assert_eq!(append_suffix("你好 ", " "), "你好 ");
+        assert_eq!(append_suffix("你好\n", " "), "你好\n");
+        assert_eq!(append_suffix("done", ", "), "done, ");
+        assert_eq!(append_suffix("done, ", ", "), "done, ");
+        assert_eq!(append_suffix("", " "), "");
+    }
+
+    #[test]
+    fn test_too_short() {
+        let settings = Settings::default();
+        for junk in ["嗯", "。", " ", "嗯嗯。", "啊，嗯", "Uh...", "um uh"] {
+            assert!(is_too_short(junk, &settings), "{:?}", junk);
+        }
+        // 一个字的回答不算语气词
+        for text in ["好", "1", "嗯好", "OK", "uhh"] {
+            assert!(!is_too_short(text, &settings), "{:?}", text);
+        }
+
+        let settings = Settings {
+            min_content_chars: 0,
+            ..Default::default()
+        };
+        assert!(!is_too_short("嗯", &settings));
+
+        let settings = Settings {
+            min_content_chars: 2,
+            filler_words: Vec::new(),
+            ..Default::default()
+        };
+        assert!(is_too_short("好。", &settings));
+        assert!(!is_too_short("嗯嗯", &settings));
+    }
+}
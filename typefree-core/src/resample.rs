@@ -0,0 +1,619 @@
+//! 重采样模块 - 支持线性插值、Sinc 和多相滤波三种算法
+//!
+//! 算法由设置 `resample_method` 选择，每次录音时确定:
+//! - `linear`: 线性插值，低延迟，质量一般
+//! - `sinc`: Sinc 插值 + 抗混叠，高质量，略高延迟
+//! - `polyphase`: 有理数倍率的多相 FIR，44.1kHz/22.05kHz 等设备专用，每次会话独立保存状态
+//! - `auto` (默认): 整数倍降采样用线性，其他有理数倍率用多相，倍率过于复杂时用 Sinc
+//!
+//! 环境变量 `TYPEFREE_RESAMPLE` 仅作为设置的初始默认值
+
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 重采样算法类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResampleMethod {
+    Linear,
+    Sinc,
+    Polyphase,
+    #[default]
+    Auto,
+}
+
+impl ResampleMethod {
+    pub fn from_env() -> Self {
+        match std::env::var("TYPEFREE_RESAMPLE").as_deref() {
+            Ok("sinc") => Self::Sinc,
+            Ok("linear") => Self::Linear,
+            Ok("polyphase") => Self::Polyphase,
+            _ => Self::Auto,
+        }
+    }
+
+    /// 按采样率确定实际算法（Auto 在非整数倍时用多相滤波，避免 44.1kHz 等混叠）
+    pub fn resolve(self, from_rate: u32, to_rate: u32) -> Self {
+        let polyphase = rational_ratio(from_rate, to_rate).is_some();
+        match self {
+            Self::Auto if from_rate.is_multiple_of(to_rate) => Self::Linear,
+            Self::Auto | Self::Polyphase if polyphase => Self::Polyphase,
+            Self::Auto | Self::Polyphase => Self::Sinc,
+            method => method,
+        }
+    }
+}
+
+/// 全局重采样器（Sinc 需要状态）
+static SINC_RESAMPLER: OnceLock<std::sync::Mutex<Option<SincResampler>>> = OnceLock::new();
+
+/// Sinc 重采样器封装
+struct SincResampler {
+    resampler: rubato::SincFixedIn<f32>,
+    from_rate: u32,
+    to_rate: u32,
+}
+
+impl SincResampler {
+    fn new(from_rate: u32, to_rate: u32, chunk_size: usize) -> Result<Self, String> {
+        use rubato::{SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
+
+        let params = SincInterpolationParameters {
+            sinc_len: 64,           // 平衡质量和性能
+            f_cutoff: 0.95,         // 截止频率
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 128,
+            window: WindowFunction::Blackman,
+        };
+
+        let ratio = to_rate as f64 / from_rate as f64;
+
+        let resampler = SincFixedIn::new(
+            ratio,
+            2.0,        // max relative ratio
+            params,
+            chunk_size,
+            1,          // mono
+        ).map_err(|e| format!("Failed to create Sinc resampler: {}", e))?;
+
+        Ok(Self {
+            resampler,
+            from_rate,
+            to_rate,
+        })
+    }
+
+    fn process(&mut self, input: &[f32]) -> Result<Vec<f32>, String> {
+        use rubato::Resampler;
+
+        if input.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let input_frames = vec![input.to_vec()];
+
+        match self.resampler.process(&input_frames, None) {
+            Ok(output) => Ok(output.into_iter().next().unwrap_or_default()),
+            Err(e) => Err(format!("Sinc resample error: {}", e)),
+        }
+    }
+}
+
+/// 重采样入口函数
+///
+/// `method` 为 Auto 时按采样率现场确定，调用方最好每次会话先 `resolve` 一次
+pub fn resample(input: &[i16], from_rate: u32, to_rate: u32, method: ResampleMethod) -> Vec<i16> {
+    if from_rate == to_rate {
+        return input.to_vec();
+    }
+
+    match method.resolve(from_rate, to_rate) {
+        ResampleMethod::Sinc => resample_sinc(input, from_rate, to_rate),
+        // 单次调用不保留状态，连续音频请用 StreamResampler
+        ResampleMethod::Polyphase => match Polyphase::new(from_rate, to_rate) {
+            Some(mut polyphase) => polyphase.process(input),
+            None => resample_sinc(input, from_rate, to_rate),
+        },
+        _ => resample_linear(input, from_rate, to_rate),
+    }
+}
+
+/// 一次录音会话的重采样器（多相滤波的历史在会话内跨帧保留，会话之间互不影响）
+pub struct StreamResampler {
+    from_rate: u32,
+    to_rate: u32,
+    method: ResampleMethod,
+    polyphase: Option<Polyphase>,
+}
+
+impl StreamResampler {
+    /// `method` 为 Auto 时按采样率确定，实际使用的算法记录到日志
+    pub fn new(method: ResampleMethod, from_rate: u32, to_rate: u32) -> Self {
+        let method = method.resolve(from_rate, to_rate);
+        let polyphase = match method {
+            ResampleMethod::Polyphase => Polyphase::new(from_rate, to_rate),
+            _ => None,
+        };
+        match &polyphase {
+            Some(p) => log::info!(
+                "[Resample] Session path: Polyphase {}Hz -> {}Hz (L={}, M={}, {} taps/phase)",
+                from_rate,
+                to_rate,
+                p.up,
+                p.down,
+                POLYPHASE_TAPS
+            ),
+            None => log::info!(
+                "[Resample] Session path: {:?} {}Hz -> {}Hz",
+                method,
+                from_rate,
+                to_rate
+            ),
+        }
+        Self {
+            from_rate,
+            to_rate,
+            method,
+            polyphase,
+        }
+    }
+
+    /// 实际使用的算法
+    pub fn method(&self) -> ResampleMethod {
+        self.method
+    }
+
+    pub fn process(&mut self, input: &[i16]) -> Vec<i16> {
+        match &mut self.polyphase {
+            Some(polyphase) => polyphase.process(input),
+            None => resample(input, self.from_rate, self.to_rate, self.method),
+        }
+    }
+
+    /// CPU 跟不上时改用线性插值（本次会话内不再恢复）
+    pub fn fall_back_to_linear(&mut self) {
+        log::warn!(
+            "[Resample] {:?} can't keep up, session path: Linear {}Hz -> {}Hz",
+            self.method,
+            self.from_rate,
+            self.to_rate
+        );
+        self.method = ResampleMethod::Linear;
+        self.polyphase = None;
+    }
+}
+
+// ============ CPU 压力降级 ============
+
+/// 单次处理耗时超过音频时长的该比例视为超时
+const SINC_BUDGET_FRACTION: f64 = 0.5;
+/// 连续超时多少次后降级为线性
+const SINC_OVERRUN_LIMIT: u32 = 3;
+
+/// Sinc 处理耗时统计：持续跟不上实时就降级，避免录音回调卡顿
+#[derive(Debug, Default)]
+pub struct SincBudget {
+    overruns: u32,
+}
+
+impl SincBudget {
+    /// 记录一次处理耗时，返回是否应降级为线性
+    pub fn record(&mut self, elapsed: Duration, audio: Duration) -> bool {
+        if elapsed.as_secs_f64() > audio.as_secs_f64() * SINC_BUDGET_FRACTION {
+            self.overruns += 1;
+        } else {
+            self.overruns = 0;
+        }
+        self.overruns >= SINC_OVERRUN_LIMIT
+    }
+}
+
+// ============ 多相滤波 ============
+
+/// 支持的最大插值倍数 L（44.1kHz→16kHz 为 160，22.05kHz→16kHz 为 320）
+const MAX_POLYPHASE_UP: u32 = 512;
+/// 每个相位的滤波器长度
+const POLYPHASE_TAPS: usize = 128;
+/// Kaiser 窗参数（阻带约 -80dB）
+const KAISER_BETA: f64 = 8.0;
+/// 截止频率占较低一侧采样率的比例（输出 16kHz 时为 7.6kHz，留出过渡带）
+const CUTOFF_RATIO: f64 = 0.475;
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// 有理数倍率（插值 L, 抽取 M），L 太大时返回 None
+fn rational_ratio(from_rate: u32, to_rate: u32) -> Option<(usize, usize)> {
+    if from_rate == 0 || to_rate == 0 || from_rate == to_rate {
+        return None;
+    }
+    let g = gcd(from_rate, to_rate);
+    let (up, down) = (to_rate / g, from_rate / g);
+    (up <= MAX_POLYPHASE_UP).then_some((up as usize, down as usize))
+}
+
+/// 零阶修正贝塞尔函数（Kaiser 窗用）
+fn bessel_i0(x: f64) -> f64 {
+    let half = x / 2.0;
+    let (mut sum, mut term) = (1.0, 1.0);
+    for k in 1..64 {
+        term *= (half / k as f64).powi(2);
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+/// 多相 FIR 重采样器：等效于插值 L 倍、低通、再抽取 M 倍，但只计算需要的输出点
+///
+/// 输入历史跨调用保留，同一会话内分批调用 `process` 没有接缝
+pub struct Polyphase {
+    up: usize,
+    down: usize,
+    /// phases[p][k] = h[p + k * L]
+    phases: Vec<Vec<f32>>,
+    /// 尚需使用的输入，history[0] 对应输入序号 base（开头补了 taps - 1 个 0）
+    history: Vec<f32>,
+    base: i64,
+    /// 下一个输出点在插值后序列中的位置
+    next: u64,
+}
+
+impl Polyphase {
+    pub fn new(from_rate: u32, to_rate: u32) -> Option<Self> {
+        let (up, down) = rational_ratio(from_rate, to_rate)?;
+        let len = POLYPHASE_TAPS * up;
+        // 截止频率按插值后的采样率归一化（周期/采样）
+        let cutoff = CUTOFF_RATIO * from_rate.min(to_rate) as f64 / (from_rate as f64 * up as f64);
+        let center = (len - 1) as f64 / 2.0;
+        let i0_beta = bessel_i0(KAISER_BETA);
+
+        let mut phases = vec![Vec::with_capacity(POLYPHASE_TAPS); up];
+        for n in 0..len {
+            let x = n as f64 - center;
+            let sinc = if x == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * PI * cutoff * x).sin() / (PI * x)
+            };
+            let r = 2.0 * n as f64 / (len - 1) as f64 - 1.0;
+            let window = bessel_i0(KAISER_BETA * (1.0 - r * r).max(0.0).sqrt()) / i0_beta;
+            // 插零后幅度只剩 1/L，在系数里补回
+            phases[n % up].push((sinc * window * up as f64) as f32);
+        }
+
+        Some(Self {
+            up,
+            down,
+            phases,
+            history: vec![0.0; POLYPHASE_TAPS - 1],
+            base: -(POLYPHASE_TAPS as i64 - 1),
+            next: 0,
+        })
+    }
+
+    pub fn process(&mut self, input: &[i16]) -> Vec<i16> {
+        self.history.extend(input.iter().map(|&s| s as f32));
+        let end = self.base + self.history.len() as i64;
+
+        let mut output = Vec::with_capacity(input.len() * self.up / self.down + 1);
+        loop {
+            let newest = (self.next / self.up as u64) as i64;
+            if newest >= end {
+                break;
+            }
+            let phase = &self.phases[(self.next % self.up as u64) as usize];
+            let newest = (newest - self.base) as usize;
+            let window = &self.history[newest + 1 - POLYPHASE_TAPS..=newest];
+            let acc: f32 = phase
+                .iter()
+                .zip(window.iter().rev())
+                .map(|(h, x)| h * x)
+                .sum();
+            output.push(acc.round().clamp(-32768.0, 32767.0) as i16);
+            self.next += self.down as u64;
+        }
+
+        // 只保留下一个输出点还要用到的输入
+        let newest = (self.next / self.up as u64) as i64;
+        let keep_from = (newest - (POLYPHASE_TAPS as i64 - 1)).clamp(self.base, end);
+        self.history.drain(..(keep_from - self.base) as usize);
+        self.base = keep_from;
+        output
+    }
+}
+
+/// 线性插值重采样（原实现）
+fn resample_linear(input: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if input.is_empty() {
+        return Vec::new();
+    }
+    if input.len() == 1 {
+        return input.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let new_len = (input.len() as f64 / ratio) as usize;
+
+    if new_len == 0 {
+        return Vec::new();
+    }
+
+    let mut output = Vec::with_capacity(new_len);
+
+    for i in 0..new_len {
+        let src_pos = i as f64 * ratio;
+        let src_idx = src_pos as usize;
+        let frac = src_pos - src_idx as f64;
+
+        let sample = if src_idx + 1 < input.len() {
+            let y0 = input[src_idx] as f64;
+            let y1 = input[src_idx + 1] as f64;
+            (y0 + (y1 - y0) * frac) as i16
+        } else {
+            input[input.len() - 1]
+        };
+
+        output.push(sample);
+    }
+
+    output
+}
+
+/// Sinc 重采样（高质量）
+fn resample_sinc(input: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if input.is_empty() {
+        return Vec::new();
+    }
+
+    // i16 -> f32
+    let input_f32: Vec<f32> = input.iter().map(|&s| s as f32 / 32768.0).collect();
+
+    // 获取或创建重采样器
+    let mutex = SINC_RESAMPLER.get_or_init(|| std::sync::Mutex::new(None));
+    let mut guard = mutex.lock().unwrap();
+
+    // 检查是否需要重新创建（采样率变化或首次使用）
+    let need_recreate = match guard.as_ref() {
+        Some(r) => r.from_rate != from_rate || r.to_rate != to_rate,
+        None => true,
+    };
+
+    if need_recreate {
+        match SincResampler::new(from_rate, to_rate, input.len().max(1024)) {
+            Ok(r) => {
+                log::info!(
+                    "[Resample] Created Sinc resampler: {}Hz -> {}Hz",
+                    from_rate, to_rate
+                );
+                *guard = Some(r);
+            }
+            Err(e) => {
+                log::error!("[Resample] {}, falling back to linear", e);
+                return resample_linear(input, from_rate, to_rate);
+            }
+        }
+    }
+
+    // 执行重采样
+    let result = match guard.as_mut() {
+        Some(resampler) => resampler.process(&input_f32),
+        None => return resample_linear(input, from_rate, to_rate),
+    };
+
+    match result {
+        Ok(output_f32) => {
+            // f32 -> i16
+            output_f32
+                .iter()
+                .map(|&s| (s * 32767.0).clamp(-32768.0, 32767.0) as i16)
+                .collect()
+        }
+        Err(e) => {
+            log::error!("[Resample] {}, falling back to linear", e);
+            resample_linear(input, from_rate, to_rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_downsample() {
+        let input: Vec<i16> = (0..4800).map(|i| (i % 1000) as i16).collect();
+        let output = resample_linear(&input, 48000, 16000);
+        assert_eq!(output.len(), 1600);
+    }
+
+    #[test]
+    fn test_sinc_downsample() {
+        let input: Vec<i16> = (0..4800).map(|i| ((i as f32 * 0.1).sin() * 10000.0) as i16).collect();
+        let output = resample_sinc(&input, 48000, 16000);
+        // Sinc 输出长度可能略有差异
+        assert!(output.len() >= 1500 && output.len() <= 1700);
+    }
+
+    #[test]
+    fn test_sinc_budget_needs_repeated_overruns() {
+        let audio = Duration::from_millis(10);
+        let slow = Duration::from_millis(8);
+        let fast = Duration::from_millis(1);
+
+        let mut budget = SincBudget::default();
+        assert!(!budget.record(slow, audio));
+        assert!(!budget.record(slow, audio));
+        // 中间一次正常就重新计数
+        assert!(!budget.record(fast, audio));
+        assert!(!budget.record(slow, audio));
+        assert!(!budget.record(slow, audio));
+        assert!(budget.record(slow, audio));
+    }
+
+    #[test]
+    fn test_auto_resolve() {
+        assert_eq!(ResampleMethod::Auto.resolve(48000, 16000), ResampleMethod::Linear);
+        assert_eq!(ResampleMethod::Auto.resolve(16000, 16000), ResampleMethod::Linear);
+        assert_eq!(ResampleMethod::Auto.resolve(44100, 16000), ResampleMethod::Polyphase);
+        assert_eq!(ResampleMethod::Auto.resolve(22050, 16000), ResampleMethod::Polyphase);
+        assert_eq!(ResampleMethod::Auto.resolve(8000, 16000), ResampleMethod::Polyphase);
+        // 倍率太复杂时用 Sinc
+        assert_eq!(ResampleMethod::Auto.resolve(44099, 16000), ResampleMethod::Sinc);
+        assert_eq!(ResampleMethod::Polyphase.resolve(44099, 16000), ResampleMethod::Sinc);
+        assert_eq!(ResampleMethod::Linear.resolve(44100, 16000), ResampleMethod::Linear);
+        assert_eq!(ResampleMethod::Sinc.resolve(48000, 16000), ResampleMethod::Sinc);
+    }
+
+    #[test]
+    fn test_rational_ratio() {
+        assert_eq!(rational_ratio(44100, 16000), Some((160, 441)));
+        assert_eq!(rational_ratio(22050, 16000), Some((320, 441)));
+        assert_eq!(rational_ratio(48000, 16000), Some((1, 3)));
+        assert_eq!(rational_ratio(44099, 16000), None);
+        assert_eq!(rational_ratio(16000, 16000), None);
+    }
+
+    /// 对数扫频正弦（100Hz → 6kHz，1 秒）
+    fn sweep(t: f64) -> f64 {
+        let (f0, f1) = (100.0_f64, 6000.0);
+        let k = (f1 / f0).ln();
+        (2.0 * PI * f0 * ((k * t).exp() - 1.0) / k).sin() * 8000.0
+    }
+
+    fn sweep_input(rate: u32) -> Vec<i16> {
+        (0..rate).map(|i| sweep(i as f64 / rate as f64).round() as i16).collect()
+    }
+
+    /// 与理想 16kHz 扫频对比的信噪比（dB），`delay` 为滤波器延迟（秒），两端各跳过 20ms
+    fn sweep_snr_db(output: &[i16], delay: f64) -> f64 {
+        let (mut signal, mut noise) = (0.0, 0.0);
+        for (i, &s) in output.iter().enumerate().take(output.len() - 320).skip(320) {
+            let ideal = sweep(i as f64 / 16000.0 - delay);
+            signal += ideal * ideal;
+            noise += (s as f64 - ideal).powi(2);
+        }
+        10.0 * (signal / noise).log10()
+    }
+
+    /// 多相滤波的群延迟（秒）
+    fn polyphase_delay(from_rate: u32) -> f64 {
+        let (up, _) = rational_ratio(from_rate, 16000).unwrap();
+        (POLYPHASE_TAPS * up - 1) as f64 / 2.0 / up as f64 / from_rate as f64
+    }
+
+    #[test]
+    fn test_polyphase_sweep_snr_beats_linear() {
+        for rate in [44100, 22050] {
+            let input = sweep_input(rate);
+            let polyphase = Polyphase::new(rate, 16000).unwrap().process(&input);
+            let linear = resample_linear(&input, rate, 16000);
+            assert!(polyphase.len().abs_diff(16000) <= 1, "{}", polyphase.len());
+
+            let polyphase_snr = sweep_snr_db(&polyphase, polyphase_delay(rate));
+            let linear_snr = sweep_snr_db(&linear, 0.0);
+            assert!(polyphase_snr > 50.0, "{}Hz polyphase SNR {:.1}dB", rate, polyphase_snr);
+            assert!(
+                polyphase_snr > linear_snr + 10.0,
+                "{}Hz polyphase {:.1}dB vs linear {:.1}dB",
+                rate,
+                polyphase_snr,
+                linear_snr
+            );
+        }
+    }
+
+    #[test]
+    fn test_polyphase_streaming_matches_one_shot() {
+        let input = sweep_input(44100);
+        let one_shot = Polyphase::new(44100, 16000).unwrap().process(&input);
+
+        // 回调大小不固定时输出与一次性处理完全一致
+        let mut polyphase = Polyphase::new(44100, 16000).unwrap();
+        let mut streamed = Vec::new();
+        let (mut start, mut len) = (0, 0);
+        while start < input.len() {
+            len = len % 997 + 1;
+            let end = (start + len).min(input.len());
+            streamed.extend(polyphase.process(&input[start..end]));
+            start = end;
+        }
+        assert_eq!(streamed, one_shot);
+    }
+
+    #[test]
+    fn test_polyphase_distortion_beats_linear() {
+        for rate in [44100u32, 22050] {
+            // 1kHz 正弦 + 10kHz 带外分量（线性插值会折叠到 6kHz）
+            let input: Vec<i16> = (0..rate)
+                .map(|i| {
+                    let t = i as f64 / rate as f64;
+                    let v = (2.0 * PI * 1000.0 * t).sin() + 0.5 * (2.0 * PI * 10000.0 * t).sin();
+                    (v * 8000.0).round() as i16
+                })
+                .collect();
+            let polyphase = Polyphase::new(rate, 16000).unwrap().process(&input);
+            let linear = resample_linear(&input, rate, 16000);
+
+            // THD+N：去掉 1kHz 基波后剩余能量与基波之比（两端各跳过 20ms）
+            let thd_n = |output: &[i16]| {
+                let output = &output[320..output.len() - 320];
+                let fundamental = tone_magnitude(output, 16000.0, 1000.0);
+                let total = output.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / output.len() as f64;
+                let rest = (total - fundamental * fundamental * 2.0).max(0.0);
+                10.0 * (rest / (fundamental * fundamental * 2.0)).log10()
+            };
+            let polyphase_thd = thd_n(&polyphase);
+            let linear_thd = thd_n(&linear);
+            assert!(polyphase_thd < -50.0, "{}Hz polyphase THD+N {:.1}dB", rate, polyphase_thd);
+            assert!(
+                polyphase_thd < linear_thd - 20.0,
+                "{}Hz polyphase {:.1}dB vs linear {:.1}dB",
+                rate,
+                polyphase_thd,
+                linear_thd
+            );
+        }
+    }
+
+    /// 单频点幅度（Goertzel 式 DFT）
+    fn tone_magnitude(samples: &[i16], rate: f64, freq: f64) -> f64 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for (i, &s) in samples.iter().enumerate() {
+            let phase = 2.0 * std::f64::consts::PI * freq * i as f64 / rate;
+            re += s as f64 * phase.cos();
+            im += s as f64 * phase.sin();
+        }
+        (re * re + im * im).sqrt() / samples.len() as f64
+    }
+
+    #[test]
+    fn test_auto_44k_no_gross_aliasing() {
+        // 1kHz（保留）+ 10kHz（超出 8kHz 奈奎斯特，线性插值会折叠到 6kHz）
+        let input: Vec<i16> = (0..44100)
+            .map(|i| {
+                let t = i as f64 / 44100.0;
+                let v = (2.0 * std::f64::consts::PI * 1000.0 * t).sin()
+                    + (2.0 * std::f64::consts::PI * 10000.0 * t).sin();
+                (v * 8000.0) as i16
+            })
+            .collect();
+
+        let output = resample(&input, 44100, 16000, ResampleMethod::Auto);
+        assert!(output.len() >= 15000 && output.len() <= 16500);
+
+        let wanted = tone_magnitude(&output, 16000.0, 1000.0);
+        let alias = tone_magnitude(&output, 16000.0, 6000.0);
+        assert!(wanted > 2000.0, "1kHz tone lost: {}", wanted);
+        assert!(alias < wanted * 0.05, "aliasing at 6kHz: {} vs {}", alias, wanted);
+    }
+}
@@ -0,0 +1,211 @@
+//! 单生产者单消费者环形缓冲 - 音频回调只把原始采样拷进来，不加锁、不分配内存
+//!
+//! 回调线程写入（Producer），录音线程取出后再做混音和重采样（Consumer）
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    /// 累计写入的采样数（只由生产者修改）
+    written: AtomicUsize,
+    /// 累计取出的采样数（只由消费者修改）
+    read: AtomicUsize,
+}
+
+// 生产者只写已被取走的位置，消费者只读已发布（written 之前）的位置，两端不会同时访问同一位置
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// 写入端（音频回调持有）
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 读取端（录音线程持有）
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 创建容量为 capacity 个采样的缓冲
+pub fn channel<T: Copy + Default>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(Shared {
+        slots: (0..capacity)
+            .map(|_| UnsafeCell::new(T::default()))
+            .collect(),
+        written: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+    });
+    (
+        Producer {
+            shared: shared.clone(),
+        },
+        Consumer { shared },
+    )
+}
+
+impl<T: Copy> Producer<T> {
+    /// 写入整段采样；空间不够时整段丢弃并返回 false（不拆开，保证取出的总是完整的帧）
+    pub fn push_slice(&mut self, data: &[T]) -> bool {
+        let shared = &*self.shared;
+        let capacity = shared.slots.len();
+        let written = shared.written.load(Ordering::Relaxed);
+        let read = shared.read.load(Ordering::Acquire);
+        if capacity - written.wrapping_sub(read) < data.len() {
+            return false;
+        }
+        for (i, &sample) in data.iter().enumerate() {
+            let slot = &shared.slots[written.wrapping_add(i) % capacity];
+            unsafe { *slot.get() = sample };
+        }
+        shared
+            .written
+            .store(written.wrapping_add(data.len()), Ordering::Release);
+        true
+    }
+}
+
+impl<T: Copy> Consumer<T> {
+    /// 取出已写入的全部采样追加到 out，返回取出的数量
+    pub fn pop_into(&mut self, out: &mut Vec<T>) -> usize {
+        let shared = &*self.shared;
+        let capacity = shared.slots.len();
+        let read = shared.read.load(Ordering::Relaxed);
+        let written = shared.written.load(Ordering::Acquire);
+        let available = written.wrapping_sub(read);
+        out.reserve(available);
+        for i in 0..available {
+            let slot = &shared.slots[read.wrapping_add(i) % capacity];
+            out.push(unsafe { *slot.get() });
+        }
+        shared
+            .read
+            .store(read.wrapping_add(available), Ordering::Release);
+        available
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop_wraps_around() {
+        let (mut producer, mut consumer) = channel::<i16>(5);
+        let mut out = Vec::new();
+        assert!(producer.push_slice(&[1, 2, 3]));
+        assert_eq!(consumer.pop_into(&mut out), 3);
+        // 写入跨过缓冲末尾
+        assert!(producer.push_slice(&[4, 5, 6, 7]));
+        assert_eq!(consumer.pop_into(&mut out), 4);
+        assert_eq!(out, [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(consumer.pop_into(&mut out), 0);
+    }
+
+    #[test]
+    fn test_full_drops_whole_slice() {
+        let (mut producer, mut consumer) = channel::<f32>(4);
+        assert!(producer.push_slice(&[0.1, 0.2, 0.3]));
+        // 剩余空间不够时整段丢弃
+        assert!(!producer.push_slice(&[0.4, 0.5]));
+        assert!(producer.push_slice(&[0.4]));
+        let mut out = Vec::new();
+        consumer.pop_into(&mut out);
+        assert_eq!(out, [0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_across_threads() {
+        let (mut producer, mut consumer) = channel::<i16>(64);
+        let writer = std::thread::spawn(move || {
+            let mut next = 0i16;
+            while next < 1000 {
+                let chunk: Vec<i16> = (next..next + 10).collect();
+                if producer.push_slice(&chunk) {
+                    next += 10;
+                } else {
+                    std::thread::yield_now();
+                }
+            }
+        });
+        let mut out = Vec::new();
+        while out.len() < 1000 {
+            if consumer.pop_into(&mut out) == 0 {
+                std::thread::yield_now();
+            }
+        }
+        writer.join().unwrap();
+        // 顺序完整，没有丢失或重复
+        assert!(out.iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn test_stress_no_loss_below_capacity() {
+        const TOTAL: u32 = 500_000;
+        let (mut producer, mut consumer) = channel::<u32>(4800);
+        let writer = std::thread::spawn(move || {
+            let mut next = 0u32;
+            let mut len = 0u32;
+            while next < TOTAL {
+                // 回调大小不固定
+                len = len % 480 + 1;
+                let end = (next + len).min(TOTAL);
+                let chunk: Vec<u32> = (next..end).collect();
+                if producer.push_slice(&chunk) {
+                    next = end;
+                } else {
+                    // 缓冲满时等消费者取走，不丢数据
+                    std::thread::yield_now();
+                }
+            }
+        });
+        let mut out = Vec::with_capacity(TOTAL as usize);
+        while out.len() < TOTAL as usize {
+            if consumer.pop_into(&mut out) == 0 {
+                std::thread::yield_now();
+            }
+        }
+        writer.join().unwrap();
+        assert!(out.iter().copied().eq(0..TOTAL));
+    }
+
+    #[test]
+    fn test_stress_overflow_drops_whole_chunks() {
+        const CHUNKS: u32 = 20_000;
+        const CHUNK_LEN: u32 = 64;
+        let (mut producer, mut consumer) = channel::<u32>(1024);
+        // 像音频回调一样写入失败不重试；每个采样记录 (块序号, 块内位置)
+        let writer = std::thread::spawn(move || {
+            let mut accepted = 0u32;
+            for chunk in 0..CHUNKS {
+                let samples: Vec<u32> = (0..CHUNK_LEN).map(|i| chunk * CHUNK_LEN + i).collect();
+                if producer.push_slice(&samples) {
+                    accepted += 1;
+                }
+            }
+            accepted
+        });
+        // 消费者偶尔停顿，制造溢出
+        let mut out = Vec::new();
+        while !writer.is_finished() {
+            consumer.pop_into(&mut out);
+            std::thread::sleep(std::time::Duration::from_micros(200));
+        }
+        let accepted = writer.join().unwrap();
+        consumer.pop_into(&mut out);
+
+        // 收到的都是完整的块，块序号递增，数量与写入成功的一致
+        assert_eq!(out.len(), (accepted * CHUNK_LEN) as usize);
+        let mut last_chunk = None;
+        for block in out.chunks(CHUNK_LEN as usize) {
+            let chunk = block[0] / CHUNK_LEN;
+            assert!(block
+                .iter()
+                .copied()
+                .eq(chunk * CHUNK_LEN..(chunk + 1) * CHUNK_LEN));
+            assert!(last_chunk.is_none_or(|last| chunk > last));
+            last_chunk = Some(chunk);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! 用户设置
+//!
+//! 持久化到应用配置目录下的 settings.json，缺失字段使用默认值
+
+use crate::codec::AudioFormat;
+use crate::doubao_asr::AsrOverrides;
+use crate::doubao_cdp::LoginDetection;
+use crate::hotkey::{self, Modifier, Trigger};
+use crate::limiter::ClipMode;
+use crate::postprocess::CaseMode;
+use crate::resample::ResampleMethod;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{LazyLock, OnceLock, RwLock};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// 设置文件路径（启动时由 init 设置）
+static SETTINGS_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// 当前设置
+static SETTINGS: LazyLock<RwLock<Settings>> = LazyLock::new(|| RwLock::new(Settings::default()));
+
+/// 用户设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// 识别完成后先在浮层确认（Enter 粘贴 / Esc 取消），默认关闭
+    pub review_before_paste: bool,
+    /// 退出 TypeFree 时关闭由它启动的豆包
+    pub quit_doubao_on_exit: bool,
+    /// 获取 Cookie 使用的豆包页面（按 URL），None 或页面已关闭时用第一个对话页
+    pub doubao_page: Option<String>,
+    /// 不自动管理豆包：只检测调试端口，不启动、重启或关闭豆包
+    pub manual_doubao: bool,
+    /// 被动捕获 ASR URL：不点击豆包的语音按钮，等用户在豆包中使用一次语音输入
+    pub passive_url_capture: bool,
+    /// 上次识别结果之后累计说话多久仍没有新结果就提示（毫秒，不计停顿，0 表示关闭）
+    pub no_result_timeout_ms: u64,
+    /// 无识别结果超时后直接结束本次会话
+    pub end_session_on_no_result: bool,
+    /// 按住触发键短于该时长（毫秒）时丢弃结果，0 表示不检查
+    pub min_session_ms: u64,
+    /// 松开触发键后这段时间内（毫秒）再次按下，继续同一次会话而不是重新开始，0 表示关闭
+    ///
+    /// 窗口内麦克风仍在录音、录音指示保持；这段声音只在续录时送去识别，否则丢弃
+    pub resume_window_ms: u64,
+    /// 单次录音最长时间（秒），到达后自动结束录音；超出宽限仍未结束时强制复位
+    pub max_session_secs: u64,
+    /// 录音触发键，可同时绑定多个
+    pub hotkeys: Vec<Trigger>,
+    /// Windows 兼容模式：不使用低级键盘钩子，改用 RegisterHotKey 和按键轮询
+    /// （安全软件会摘除钩子时使用，重启后生效）
+    pub hotkey_compat_mode: bool,
+    /// 每帧音频采样数（16kHz，1600 = 100ms）
+    pub audio_chunk_samples: usize,
+    /// 只录制输入设备的某个通道（从 0 开始），None 表示混合所有通道
+    pub input_channel: Option<u16>,
+    /// 输入设备名称，None 使用系统默认；设备不在时也回退到系统默认
+    pub input_device: Option<String>,
+    /// 重采样算法（auto 按设备采样率选择）
+    pub resample_method: ResampleMethod,
+    /// 大音量输入超出范围时的处理（limiter 降低增益，hard 直接截断）
+    pub clip_mode: ClipMode,
+    /// 上传音频的格式，不支持 Opus 的构建会退回 PCM
+    pub audio_format: AudioFormat,
+    /// 裁剪开头和结尾的静音，默认关闭
+    pub trim_silence: bool,
+    /// 静音裁剪的语音 RMS 阈值（16-bit PCM）
+    pub silence_rms_threshold: f64,
+    /// 语音开始前保留的帧数
+    pub pre_speech_chunks: usize,
+    /// 录音短于该时长（毫秒）时不粘贴结果，避免误触产生杂字，0 表示不检查
+    pub min_audio_ms: u64,
+    /// 录音能量始终低于该值（RMS）时不粘贴结果，0 表示不检查
+    pub min_audio_rms: f64,
+    /// 最终文本的大小写转换
+    pub case_mode: CaseMode,
+    /// 识别期间前台窗口变了怎么处理
+    pub focus_change: FocusChangeAction,
+    /// 去掉空白、标点和语气词后少于该字数的结果不粘贴（0 表示不过滤）
+    pub min_content_chars: usize,
+    /// 过滤时视为无内容的语气词（不区分大小写）
+    pub filler_words: Vec<String>,
+    /// 粘贴时追加在文本后的后缀（如空格），空字符串表示不追加
+    pub paste_suffix: String,
+    /// 写入剪贴板后等待多久再发送粘贴键（毫秒）
+    pub paste_delay_ms: u64,
+    /// 发送粘贴键前读回剪贴板校验，不一致时重写一次
+    pub verify_clipboard: bool,
+    /// 草稿模式：识别结果先加入草稿，手动插入全部
+    pub draft_mode: bool,
+    /// 按应用覆盖的规则
+    pub app_rules: Vec<AppRule>,
+    /// 快捷短语：按下对应按键直接粘贴预设文本
+    pub snippets: Vec<Snippet>,
+    /// 撤销上一次粘贴的按键（粘贴后移动过光标会删错），None 表示不绑定
+    pub undo_paste_key: Option<Trigger>,
+    /// 重新听写的按键：撤销上一次粘贴并开始录音，再按一次结束，None 表示不绑定
+    pub redictate_key: Option<Trigger>,
+    /// 记录粘贴目标的按键：之后的听写都插入到当时的窗口和输入框，None 表示不绑定
+    pub pin_target_key: Option<Trigger>,
+    /// 插入全部草稿的按键（草稿模式下使用），None 表示不绑定
+    pub commit_draft_key: Option<Trigger>,
+    /// 固定插入到该应用（macOS bundle id，如 com.apple.Notes），没有运行时先启动；
+    /// 记录了粘贴目标时以记录的为准，None 表示插入到前台应用
+    pub target_app: Option<String>,
+    /// 听写总开关：关闭时忽略触发键（游戏、共享屏幕时临时停用），重启后保持
+    pub dictation_enabled: bool,
+    /// 暂停/恢复听写的按键，None 表示不绑定
+    pub toggle_dictation_key: Option<Trigger>,
+    /// 开机自动启动时隐藏主窗口（完成引导后生效）
+    pub start_hidden: bool,
+    /// 仅菜单栏模式：从不创建主窗口，设置通过托盘子菜单或配置文件修改（重启后生效）
+    pub menu_bar_only: bool,
+    /// 字幕模式（演示、展台用）：启动后不等触发键，循环录音并在浮层显示识别结果，不粘贴；
+    /// 该模式下触发键不起作用（重启后生效）
+    pub caption_mode: bool,
+    /// 字幕模式下说话后静音多久（毫秒）算一句结束，随即开始下一句
+    pub caption_silence_ms: u64,
+    /// 已完成引导（权限全部授予过）
+    pub onboarding_completed: bool,
+    /// 开机自动启动后延迟多久再初始化豆包（秒），避免拖慢登录
+    pub autostart_grace_secs: u64,
+    /// 浮层显示在哪块屏幕上
+    pub overlay_screen: OverlayScreen,
+    /// 浮层显示整次会话的识别文字，还是只显示当前这句
+    pub overlay_text: OverlayText,
+    /// 中间结果刷新浮层的最短间隔（毫秒），间隔内只显示最新的文字，0 表示不限制
+    pub partial_throttle_ms: u64,
+    /// 按下触发键时按住该修饰键，本次会话使用备用语言，None 表示关闭
+    pub alternate_language_modifier: Option<Modifier>,
+    /// 备用识别语言（ASR URL 的 language 参数）
+    pub alternate_language: String,
+    /// 自动识别语言：开头没有结果或文字不符时，用已录的音频换备用语言重新识别
+    pub auto_language: bool,
+    /// ASR 区域（URL 的 region / sys_region 参数），为空时使用捕获的原值
+    pub asr_region: String,
+    /// 备用 ASR WebSocket 主机（必须是 doubao.com 子域名），None 使用默认主机
+    pub asr_host: Option<String>,
+    /// 识别结果在浮层上停留的时间（毫秒）
+    pub final_display_ms: u64,
+    /// 不自动隐藏最终结果，浮层保持到下次录音或点击关闭
+    pub keep_final_result: bool,
+    /// 错误提示停留的时间（毫秒）
+    pub error_display_ms: u64,
+    /// 撤销、重新粘贴等操作的短提示停留的时间（毫秒）
+    pub status_display_ms: u64,
+    /// 系统处于勿扰模式、演示或镜像屏幕时不显示浮层（录音照常，托盘图标旁显示录音中）
+    pub hide_overlay_when_presenting: bool,
+    /// macOS 菜单栏图标旁显示录音秒数，结束后短暂显示字数
+    pub menu_bar_status: bool,
+    /// 录音时在屏幕右上角显示一个小红点（浮层隐藏时也能看到）
+    pub recording_dot: bool,
+    /// 开始采集和粘贴结果时播放提示音（勿扰模式或演示时不播放）
+    pub sound_cues: bool,
+    /// 提示音音量（0 ~ 1）
+    pub sound_cue_volume: f32,
+    /// 调试录音：把发给 ASR 的音频按会话保存为 WAV（日志目录下的 audio 目录）
+    pub debug_audio_dump: bool,
+    /// 调试录音保留的文件数
+    pub debug_audio_keep: usize,
+    /// 高级：ASR URL 参数和请求头覆盖
+    pub asr_overrides: AsrOverrides,
+    /// 高级：登录状态检测规则
+    pub login_detection: LoginDetection,
+    /// 识别结果的文字与识别语言不符时的处理
+    pub script_mismatch: ScriptMismatchAction,
+    /// 其他文字超过该比例视为不符（0 ~ 1）
+    pub script_mismatch_ratio: f64,
+    /// 每天在后台检查一次新版本（默认关闭，开启后会访问 GitHub）
+    pub auto_check_updates: bool,
+    /// 检查更新的发布通道
+    pub update_channel: UpdateChannel,
+}
+
+/// 更新的发布通道
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    /// 只提示正式版
+    #[default]
+    Stable,
+    /// 也提示预发布版
+    Beta,
+}
+
+/// 浮层所在屏幕的选择方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayScreen {
+    /// 鼠标所在屏幕（最快）
+    #[default]
+    Mouse,
+    /// 前台窗口所在屏幕
+    FocusedWindow,
+}
+
+/// 浮层上识别文字的显示方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayText {
+    /// 累积显示整次会话的文字（太长时只显示末尾）
+    #[default]
+    Session,
+    /// 只显示当前这句，新的一句开始时清空
+    Utterance,
+}
+
+/// 识别结果的文字与识别语言不符时的处理方式（都不会阻止粘贴）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptMismatchAction {
+    /// 不检测
+    Off,
+    /// 在浮层提示
+    #[default]
+    Hint,
+    /// 用另一种语言（主语言 / 备用语言）重新识别一次，仍不符时提示
+    Retry,
+}
+
+/// 识别期间前台窗口发生变化时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusChangeAction {
+    /// 切回按下触发键时的窗口再粘贴
+    #[default]
+    Reactivate,
+    /// 不粘贴，只复制到剪贴板
+    CopyOnly,
+    /// 粘贴到当前窗口（旧行为）
+    PasteAnyway,
+}
+
+/// 按应用覆盖的规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppRule {
+    /// 应用名称或标识（macOS bundle id / Windows 可执行文件名），不区分大小写
+    pub app: String,
+    /// 大小写转换，未设置时使用全局设置
+    #[serde(default)]
+    pub case_mode: Option<CaseMode>,
+    /// 粘贴后缀，未设置时使用全局设置（代码编辑器可设为空字符串关闭）
+    #[serde(default)]
+    pub paste_suffix: Option<String>,
+}
+
+/// 快捷短语
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    /// 触发按键
+    pub key: Trigger,
+    /// 粘贴的文本
+    pub text: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            review_before_paste: false,
+            quit_doubao_on_exit: true,
+            manual_doubao: false,
+            passive_url_capture: false,
+            doubao_page: None,
+            no_result_timeout_ms: 4000,
+            end_session_on_no_result: false,
+            min_session_ms: 0,
+            resume_window_ms: 500,
+            max_session_secs: 600,
+            hotkeys: hotkey::default_triggers(),
+            hotkey_compat_mode: false,
+            audio_chunk_samples: 1600,
+            input_channel: None,
+            input_device: None,
+            resample_method: ResampleMethod::from_env(),
+            clip_mode: ClipMode::default(),
+            audio_format: AudioFormat::Pcm,
+            trim_silence: false,
+            silence_rms_threshold: 500.0,
+            pre_speech_chunks: 1,
+            min_audio_ms: 300,
+            min_audio_rms: 200.0,
+            case_mode: CaseMode::None,
+            focus_change: FocusChangeAction::Reactivate,
+            min_content_chars: 1,
+            filler_words: ["嗯", "啊", "呃", "额", "uh", "um"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            paste_suffix: String::new(),
+            paste_delay_ms: 50,
+            verify_clipboard: true,
+            draft_mode: false,
+            app_rules: Vec::new(),
+            snippets: Vec::new(),
+            undo_paste_key: None,
+            redictate_key: None,
+            pin_target_key: None,
+            commit_draft_key: None,
+            target_app: None,
+            dictation_enabled: true,
+            toggle_dictation_key: None,
+            start_hidden: true,
+            menu_bar_only: false,
+            caption_mode: false,
+            caption_silence_ms: 800,
+            onboarding_completed: false,
+            autostart_grace_secs: 20,
+            overlay_screen: OverlayScreen::Mouse,
+            overlay_text: OverlayText::Session,
+            partial_throttle_ms: 80,
+            alternate_language_modifier: Some(Modifier::Shift),
+            alternate_language: "en".to_string(),
+            auto_language: false,
+            asr_region: String::new(),
+            asr_host: None,
+            final_display_ms: 1000,
+            keep_final_result: false,
+            error_display_ms: 3000,
+            status_display_ms: 1500,
+            hide_overlay_when_presenting: true,
+            menu_bar_status: true,
+            recording_dot: false,
+            sound_cues: false,
+            sound_cue_volume: 0.4,
+            debug_audio_dump: false,
+            debug_audio_keep: 10,
+            asr_overrides: AsrOverrides::default(),
+            login_detection: LoginDetection::default(),
+            script_mismatch: ScriptMismatchAction::Hint,
+            script_mismatch_ratio: 0.8,
+            auto_check_updates: false,
+            update_channel: UpdateChannel::Stable,
+        }
+    }
+}
+
+/// 加载设置（启动时调用一次）
+pub fn init(config_dir: PathBuf) {
+    let path = config_dir.join(SETTINGS_FILE);
+
+    let loaded = match std::fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str::<Settings>(&content) {
+            Ok(s) => {
+                log::info!("[Settings] Loaded from {}", path.display());
+                s
+            }
+            Err(e) => {
+                log::warn!("[Settings] Failed to parse {}: {}, using defaults", path.display(), e);
+                Settings::default()
+            }
+        },
+        Err(_) => {
+            log::info!("[Settings] No settings file, using defaults");
+            Settings::default()
+        }
+    };
+
+    if let Ok(mut s) = SETTINGS.write() {
+        *s = loaded;
+    }
+    let _ = SETTINGS_PATH.set(path);
+}
+
+/// 配置目录（settings.json 所在目录）
+pub fn config_dir() -> Option<PathBuf> {
+    SETTINGS_PATH.get()?.parent().map(|dir| dir.to_path_buf())
+}
+
+/// 设置文件路径
+pub fn file_path() -> Option<PathBuf> {
+    SETTINGS_PATH.get().cloned()
+}
+
+/// 获取当前设置
+pub fn get() -> Settings {
+    SETTINGS.read().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// 替换并保存设置
+pub fn set(new_settings: Settings) -> Result<(), String> {
+    save(&new_settings)?;
+    if let Ok(mut s) = SETTINGS.write() {
+        *s = new_settings;
+    }
+    Ok(())
+}
+
+/// 修改并保存设置
+pub fn update(f: impl FnOnce(&mut Settings)) -> Result<Settings, String> {
+    let mut new_settings = get();
+    f(&mut new_settings);
+    set(new_settings.clone())?;
+    Ok(new_settings)
+}
+
+/// 写入设置文件
+fn save(settings: &Settings) -> Result<(), String> {
+    let path = SETTINGS_PATH.get().ok_or("Settings not initialized")?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    std::fs::write(path, content).map_err(|e| format!("Failed to write settings: {}", e))?;
+
+    log::info!("[Settings] Saved to {}", path.display());
+    Ok(())
+}
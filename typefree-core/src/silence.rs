@@ -0,0 +1,203 @@
+//! 静音裁剪 - 去掉按键后开口前、说完后松键前的静音
+//!
+//! 开头：第一个能量超过阈值的帧之前的静音不发送（保留若干帧作为前置上下文）
+//! 结尾：说话后的静音帧先暂存，再次出现语音时补发，录音结束时丢弃；
+//! 暂存超过 `MAX_TRAILING_MS` 时照常发送，结尾最多裁掉这么长
+
+use std::collections::VecDeque;
+
+/// 16kHz 采样率下每毫秒的采样数
+const SAMPLES_PER_MS: usize = 16;
+
+/// 最多暂存的静音时长，超过时发送（说话中途长时间停顿不会一直占用内存）
+const MAX_TRAILING_MS: usize = 2000;
+
+/// 计算 RMS（16-bit PCM）
+pub fn rms(samples: &[i16]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / samples.len() as f64).sqrt()
+}
+
+/// 误触判断：录音太短，或能量从未超过下限（阈值为 0 时不检查该项）
+pub fn is_accidental_tap(captured_ms: u64, peak_rms: f64, min_ms: u64, min_rms: f64) -> bool {
+    captured_ms < min_ms || (min_rms > 0.0 && peak_rms < min_rms)
+}
+
+/// 一句话是否已说完：出现过语音，且之后的静音达到 `silence_ms`
+///
+/// `speech_end_ms` 是最后一段语音结束时已采集的时长
+pub fn utterance_ended(speech_end_ms: Option<u64>, captured_ms: u64, silence_ms: u64) -> bool {
+    speech_end_ms.is_some_and(|end| captured_ms.saturating_sub(end) >= silence_ms)
+}
+
+/// 小端 16-bit PCM 字节的 RMS
+fn rms_le_bytes(data: &[u8]) -> f64 {
+    let samples: Vec<i16> = data
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    rms(&samples)
+}
+
+/// 静音裁剪器（每次会话一个）
+#[derive(Debug)]
+pub struct SilenceTrimmer {
+    /// 判定为语音的 RMS 阈值
+    rms_threshold: f64,
+    /// 语音开始前保留的帧数
+    pre_speech_chunks: usize,
+    speech_started: bool,
+    /// 语音开始前的最近几帧
+    leading: VecDeque<Vec<u8>>,
+    /// 语音之后暂存的静音帧
+    trailing: Vec<Vec<u8>>,
+    trimmed_leading_bytes: usize,
+    trimmed_trailing_bytes: usize,
+}
+
+impl SilenceTrimmer {
+    pub fn new(rms_threshold: f64, pre_speech_chunks: usize) -> Self {
+        Self {
+            rms_threshold,
+            pre_speech_chunks,
+            speech_started: false,
+            leading: VecDeque::new(),
+            trailing: Vec::new(),
+            trimmed_leading_bytes: 0,
+            trimmed_trailing_bytes: 0,
+        }
+    }
+
+    /// 送入一帧，返回现在可以发送的帧（按顺序）
+    pub fn push(&mut self, chunk: Vec<u8>) -> Vec<Vec<u8>> {
+        let is_speech = rms_le_bytes(&chunk) >= self.rms_threshold;
+
+        if !self.speech_started {
+            if !is_speech {
+                self.leading.push_back(chunk);
+                if self.leading.len() > self.pre_speech_chunks {
+                    if let Some(dropped) = self.leading.pop_front() {
+                        self.trimmed_leading_bytes += dropped.len();
+                    }
+                }
+                return Vec::new();
+            }
+            self.speech_started = true;
+            let mut out: Vec<Vec<u8>> = self.leading.drain(..).collect();
+            out.push(chunk);
+            return out;
+        }
+
+        if is_speech {
+            let mut out = std::mem::take(&mut self.trailing);
+            out.push(chunk);
+            out
+        } else {
+            self.trailing.push(chunk);
+            let held: usize = self.trailing.iter().map(|c| c.len()).sum();
+            if held / 2 / SAMPLES_PER_MS > MAX_TRAILING_MS {
+                return std::mem::take(&mut self.trailing);
+            }
+            Vec::new()
+        }
+    }
+
+    /// 录音结束：丢弃结尾的静音（从未检测到语音时全部丢弃）
+    pub fn finish(&mut self) {
+        self.trimmed_trailing_bytes += self.trailing.drain(..).map(|c| c.len()).sum::<usize>();
+        self.trimmed_leading_bytes += self.leading.drain(..).map(|c| c.len()).sum::<usize>();
+    }
+
+    /// 开头裁掉的时长（毫秒）
+    pub fn trimmed_leading_ms(&self) -> u64 {
+        (self.trimmed_leading_bytes / 2 / SAMPLES_PER_MS) as u64
+    }
+
+    /// 结尾裁掉的时长（毫秒）
+    pub fn trimmed_trailing_ms(&self) -> u64 {
+        (self.trimmed_trailing_bytes / 2 / SAMPLES_PER_MS) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100ms 的帧，所有采样都是 `level`
+    fn chunk(level: i16) -> Vec<u8> {
+        [level; 1600].iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_leading_silence_keeps_pre_speech_context() {
+        let mut trimmer = SilenceTrimmer::new(500.0, 1);
+        assert!(trimmer.push(chunk(10)).is_empty());
+        assert!(trimmer.push(chunk(20)).is_empty());
+        assert!(trimmer.push(chunk(30)).is_empty());
+
+        let out = trimmer.push(chunk(2000));
+        assert_eq!(out, vec![chunk(30), chunk(2000)]);
+        assert_eq!(trimmer.trimmed_leading_ms(), 200);
+    }
+
+    #[test]
+    fn test_trailing_silence_dropped_pauses_kept() {
+        let mut trimmer = SilenceTrimmer::new(500.0, 0);
+        assert_eq!(trimmer.push(chunk(2000)), vec![chunk(2000)]);
+        assert!(trimmer.push(chunk(0)).is_empty());
+        // 中途停顿在下一段语音前补发
+        assert_eq!(trimmer.push(chunk(1500)), vec![chunk(0), chunk(1500)]);
+        assert!(trimmer.push(chunk(0)).is_empty());
+        assert!(trimmer.push(chunk(0)).is_empty());
+
+        trimmer.finish();
+        assert_eq!(trimmer.trimmed_leading_ms(), 0);
+        assert_eq!(trimmer.trimmed_trailing_ms(), 200);
+    }
+
+    #[test]
+    fn test_long_pause_flushed() {
+        let mut trimmer = SilenceTrimmer::new(500.0, 0);
+        trimmer.push(chunk(2000));
+        let pause = MAX_TRAILING_MS / 100;
+        for _ in 0..pause {
+            assert!(trimmer.push(chunk(0)).is_empty());
+        }
+        // 超过上限时暂存的静音全部发送
+        assert_eq!(trimmer.push(chunk(0)).len(), pause + 1);
+        assert!(trimmer.push(chunk(0)).is_empty());
+
+        trimmer.finish();
+        assert_eq!(trimmer.trimmed_trailing_ms(), 100);
+    }
+
+    #[test]
+    fn test_accidental_tap() {
+        assert!(is_accidental_tap(200, 3000.0, 300, 200.0));
+        assert!(is_accidental_tap(1500, 80.0, 300, 200.0));
+        assert!(!is_accidental_tap(1500, 3000.0, 300, 200.0));
+        // 阈值为 0 时关闭对应检查
+        assert!(!is_accidental_tap(100, 0.0, 0, 0.0));
+    }
+
+    #[test]
+    fn test_utterance_ended() {
+        // 还没有说话：不结束
+        assert!(!utterance_ended(None, 5000, 800));
+        assert!(!utterance_ended(Some(1000), 1500, 800));
+        assert!(utterance_ended(Some(1000), 1800, 800));
+        assert!(utterance_ended(Some(1000), 3000, 800));
+    }
+
+    #[test]
+    fn test_no_speech_drops_everything() {
+        let mut trimmer = SilenceTrimmer::new(500.0, 1);
+        assert!(trimmer.push(chunk(0)).is_empty());
+        assert!(trimmer.push(chunk(0)).is_empty());
+        trimmer.finish();
+        assert_eq!(trimmer.trimmed_leading_ms(), 200);
+    }
+}
@@ -0,0 +1,377 @@
+//! 通过内存传输层按脚本模拟服务端，验证会话的停止、finish 和报错流程
+//!
+//! 使用暂停的 tokio 时钟：等待和超时不占用真实时间，耗时断言也不受机器快慢影响
+//!
+//! `tests/fixtures/asr/*.json` 是真实会话录下的服务端消息，按原顺序回放并核对最终结果。
+//! 录制方法：开启 debug 日志听写一句，把 `[DoubaoASR] Frame:` 后的消息依次放进 `frames`，
+//! 实际粘贴的文字填入 `final`
+
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use typefree_core::codec::AudioFormat;
+use typefree_core::doubao_asr::{self, AsrError, AsrResult, AsrTransport, SessionStats};
+
+/// 模拟服务端的一步
+enum Step {
+    /// 发送一条文本消息
+    Send(&'static str),
+    /// 回放一条录下的消息
+    Frame(String),
+    /// 等待一段时间（毫秒）
+    Wait(u64),
+    /// 等客户端发出 finish
+    AwaitFinish,
+    /// 关闭连接
+    Close,
+    /// 不再响应
+    Hang,
+}
+
+/// 记录客户端发送的消息
+struct FakeTx {
+    sent: Arc<Mutex<Vec<Message>>>,
+    finish: Arc<tokio::sync::Notify>,
+}
+
+impl futures_util::Sink<Message> for FakeTx {
+    type Error = std::convert::Infallible;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if is_finish(&item) {
+            self.finish.notify_one();
+        }
+        self.sent.lock().unwrap().push(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+type FakeRx = Pin<Box<dyn futures_util::Stream<Item = Result<Message, WsError>> + Send>>;
+
+/// 按脚本响应的内存传输层
+struct FakeTransport {
+    steps: Vec<Step>,
+    sent: Arc<Mutex<Vec<Message>>>,
+}
+
+impl AsrTransport for FakeTransport {
+    type Tx = FakeTx;
+    type SendError = std::convert::Infallible;
+    type Rx = FakeRx;
+
+    fn into_parts(self) -> (Self::Tx, Self::Rx) {
+        let finish = Arc::new(tokio::sync::Notify::new());
+        let tx = FakeTx {
+            sent: self.sent,
+            finish: finish.clone(),
+        };
+        let rx = futures_util::stream::unfold(
+            (self.steps.into_iter(), finish),
+            |(mut steps, finish)| async move {
+                loop {
+                    let message = match steps.next()? {
+                        Step::Send(text) => Message::Text(text.to_string()),
+                        Step::Frame(text) => Message::Text(text),
+                        Step::Close => Message::Close(None),
+                        Step::Wait(ms) => {
+                            tokio::time::sleep(Duration::from_millis(ms)).await;
+                            continue;
+                        }
+                        Step::AwaitFinish => {
+                            finish.notified().await;
+                            continue;
+                        }
+                        Step::Hang => std::future::pending().await,
+                    };
+                    return Some((Ok(message), (steps, finish)));
+                }
+            },
+        );
+        (tx, Box::pin(rx))
+    }
+}
+
+/// 脚本会话的结果
+struct ScriptedRun {
+    result: Result<SessionStats, AsrError>,
+    partials: Vec<String>,
+    finals: Vec<String>,
+    sent: Vec<Message>,
+    /// 松开按键到会话结束的时间（暂停时钟下的虚拟时间）
+    after_stop: Duration,
+}
+
+/// 第 i 帧录音的内容（3200 字节的 i）
+fn synthetic_frame(i: usize) -> Vec<u8> {
+    vec![i as u8; 3200]
+}
+
+fn is_finish(message: &Message) -> bool {
+    matches!(message, Message::Text(t) if t.contains("finish"))
+}
+
+fn finish_count(messages: &[Message]) -> usize {
+    messages.iter().filter(|m| is_finish(m)).count()
+}
+
+/// 录好 `frames` 帧并松开按键后开始会话，服务端按 `steps` 响应
+async fn run_scripted(steps: Vec<Step>, frames: usize) -> ScriptedRun {
+    // 录音已结束：帧都在 channel 中，发送端已释放
+    let (audio_tx, audio_rx) = std::sync::mpsc::channel::<Vec<u8>>();
+    for i in 0..frames {
+        audio_tx.send(synthetic_frame(i)).unwrap();
+    }
+    drop(audio_tx);
+    let stop_flag = Arc::new(AtomicBool::new(true));
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let partials = Arc::new(Mutex::new(Vec::new()));
+    let partials_cb = partials.clone();
+    let finals = Arc::new(Mutex::new(Vec::new()));
+    let finals_cb = finals.clone();
+    let transport = FakeTransport {
+        steps,
+        sent: sent.clone(),
+    };
+
+    let stopped_at = Instant::now();
+    let result = doubao_asr::run_on_transport(
+        transport,
+        audio_rx,
+        AudioFormat::Pcm,
+        stop_flag,
+        Arc::new(AtomicBool::new(false)),
+        move |text: &str| partials_cb.lock().unwrap().push(text.to_string()),
+        move |r: &AsrResult| finals_cb.lock().unwrap().push(r.text.clone()),
+    )
+    .await;
+    let after_stop = stopped_at.elapsed();
+
+    let partials = partials.lock().unwrap().clone();
+    let finals = finals.lock().unwrap().clone();
+    let sent = sent.lock().unwrap().clone();
+    ScriptedRun {
+        result,
+        partials,
+        finals,
+        sent,
+        after_stop,
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_session_normal_finish() {
+    let run = run_scripted(
+        vec![
+            Step::Send(r#"{"event":"result","result":{"Text":"今天"}}"#),
+            Step::AwaitFinish,
+            Step::Send(r#"{"event":"result","result":{"Text":"今天天气不错"}}"#),
+            Step::Send(r#"{"event":"finish"}"#),
+        ],
+        5,
+    )
+    .await;
+
+    assert_eq!(run.finals, vec!["今天天气不错"]);
+    assert_eq!(run.result.unwrap().messages_sent, 5);
+    // 全部音频在 finish 之前发出，finish 只发一次
+    assert_eq!(finish_count(&run.sent), 1);
+    assert!(run.sent.last().is_some_and(is_finish));
+    // 收到 finish 后只再等很短的时间，不会等满 1 秒超时
+    assert!(run.after_stop < Duration::from_secs(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_session_forwards_pcm_in_order() {
+    let run = run_scripted(
+        vec![
+            Step::Send(r#"{"event":"result","result":{"Text":"一"}}"#),
+            Step::Send(r#"{"event":"result","result":{"Text":"一二"}}"#),
+            Step::AwaitFinish,
+            Step::Send(r#"{"event":"result","result":{"Text":"一二三"}}"#),
+            Step::Send(r#"{"event":"finish"}"#),
+        ],
+        8,
+    )
+    .await;
+
+    // 每帧原样转发、顺序不变，之后才是 finish
+    let binary: Vec<&Vec<u8>> = run
+        .sent
+        .iter()
+        .filter_map(|m| match m {
+            Message::Binary(data) => Some(data),
+            _ => None,
+        })
+        .collect();
+    let expected: Vec<Vec<u8>> = (0..8).map(synthetic_frame).collect();
+    assert_eq!(binary, expected.iter().collect::<Vec<_>>());
+    let finish_at = run.sent.iter().position(is_finish);
+    assert_eq!(finish_at, Some(run.sent.len() - 1));
+
+    assert_eq!(run.partials, vec!["一", "一二", "一二三"]);
+    assert_eq!(run.finals, vec!["一二三"]);
+    let stats = run.result.unwrap();
+    assert_eq!(stats.messages_sent, 8);
+    assert_eq!(stats.partials, 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_session_finish_never_arrives() {
+    let run = run_scripted(
+        vec![
+            Step::Send(r#"{"event":"result","result":{"Text":"你好"}}"#),
+            Step::Hang,
+        ],
+        3,
+    )
+    .await;
+
+    // 停止后等 1 秒超时，用已有结果作为最终结果
+    assert_eq!(run.finals, vec!["你好"]);
+    assert!(run.result.is_ok());
+    assert!(run.after_stop >= Duration::from_secs(1));
+    assert!(run.after_stop < Duration::from_millis(1200));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_session_error_mid_session() {
+    let run = run_scripted(
+        vec![
+            Step::Send(r#"{"event":"result","result":{"Text":"说到一半"}}"#),
+            Step::Send(r#"{"code":710022002,"message":"service busy"}"#),
+        ],
+        5,
+    )
+    .await;
+
+    assert_eq!(run.finals, vec!["说到一半"]);
+    match run.result {
+        Err(AsrError::Server { error, stats }) => {
+            assert_eq!(error.code, 710022002);
+            assert!(stats.degraded);
+        }
+        other => panic!("expected server error, got {:?}", other.map(|s| s.partials)),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_session_close_after_partials() {
+    let run = run_scripted(
+        vec![
+            Step::Send(r#"{"event":"result","result":{"Text":"第一"}}"#),
+            Step::Send(r#"{"event":"result","result":{"Text":"第一句"}}"#),
+            Step::Close,
+        ],
+        3,
+    )
+    .await;
+
+    assert_eq!(run.finals, vec!["第一句"]);
+    assert_eq!(run.result.unwrap().partials, 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_session_slow_responses() {
+    let run = run_scripted(
+        vec![
+            Step::Wait(300),
+            Step::Send(r#"{"event":"result","result":{"Text":"慢"}}"#),
+            Step::AwaitFinish,
+            Step::Wait(400),
+            Step::Send(r#"{"event":"result","result":{"Text":"慢一点也没关系"}}"#),
+            Step::Send(r#"{"event":"finish"}"#),
+        ],
+        5,
+    )
+    .await;
+
+    // 停止后 1 秒内到达的结果仍然采用
+    assert_eq!(run.finals, vec!["慢一点也没关系"]);
+    assert!(run.result.is_ok());
+    assert!(run.after_stop < Duration::from_secs(1));
+}
+
+/// 录下的一次会话
+#[derive(serde::Deserialize)]
+struct Fixture {
+    /// 实际交付的最终文字
+    #[serde(rename = "final")]
+    final_text: String,
+    /// 服务端消息，按收到的顺序
+    frames: Vec<serde_json::Value>,
+}
+
+fn fixtures() -> Vec<(String, Fixture)> {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/asr");
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let json = std::fs::read_to_string(&path).unwrap();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let fixture = serde_json::from_str(&json)
+                .unwrap_or_else(|e| panic!("{}: invalid fixture: {}", name, e));
+            (name, fixture)
+        })
+        .collect()
+}
+
+/// 回放录下的消息：客户端发出 finish 后服务端才会回 finish
+fn replay_steps(frames: &[serde_json::Value]) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut awaiting = true;
+    for frame in frames {
+        if awaiting && frame["event"] == "finish" {
+            steps.push(Step::AwaitFinish);
+            awaiting = false;
+        }
+        steps.push(Step::Frame(frame.to_string()));
+    }
+    steps
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_captured_fixtures() {
+    for (name, fixture) in fixtures() {
+        // 每条 result 和 finish 消息都能按已知结构解析
+        for frame in &fixture.frames {
+            if matches!(frame["event"].as_str(), Some("result")) {
+                assert!(
+                    AsrResult::from_message(frame).is_some(),
+                    "{}: unparsed result frame {}",
+                    name,
+                    frame
+                );
+            }
+        }
+
+        let run = run_scripted(replay_steps(&fixture.frames), 3).await;
+        assert!(run.result.is_ok(), "{}: session failed", name);
+        assert_eq!(run.finals, vec![fixture.final_text.clone()], "{}", name);
+    }
+}
@@ -0,0 +1,91 @@
+//! 通过公开接口驱动模拟 CDP 服务：不需要豆包桌面端，验证外部调用方能拿到的结果
+
+#[allow(dead_code)]
+#[path = "../src/cdp_mock.rs"]
+mod cdp_mock;
+
+use cdp_mock::{MockCdp, MockScript};
+use typefree_core::doubao_cdp;
+
+/// CDP 地址是全局的，使用模拟服务的测试依次执行
+static MOCK_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// 启动模拟服务并把 CDP 地址指向它
+async fn start_mock(script: MockScript) -> (MockCdp, tokio::sync::MutexGuard<'static, ()>) {
+    let guard = MOCK_LOCK.lock().await;
+    let mock = MockCdp::start(script);
+    doubao_cdp::set_cdp_base_url(Some(mock.base_url()));
+    (mock, guard)
+}
+
+#[tokio::test]
+async fn test_fetch_asr_info_captures_url_template() {
+    let asr_url = "wss://ws-samantha.doubao.com/samantha/audio/asr?version_code=20800&format=pcm";
+    let (_mock, _guard) = start_mock(MockScript {
+        cookies: vec![
+            ("sessionid", "abc123", ".doubao.com"),
+            ("s_v_web_id", "verify_web", ".doubao.com"),
+        ],
+        evaluate: vec![
+            ("'clicked'", "clicked".into()),
+            ("'stopped'", "stopped".into()),
+        ],
+        websocket_created: Some(asr_url.to_string()),
+        ..Default::default()
+    })
+    .await;
+    doubao_cdp::clear_cached_url_params();
+
+    let (cookie, info) = doubao_cdp::fetch_asr_info_auto().await.unwrap();
+    assert!(cookie.contains("sessionid=abc123"));
+    // 捕获的参数模板被沿用，并缓存给下一次会话
+    assert!(info.url_template.starts_with("wss://"));
+    assert!(info.url_template.contains("version_code=20800"));
+    assert_eq!(info.origin, "https://www.doubao.com");
+    assert!(doubao_cdp::get_cached_url_params().is_some());
+    assert_eq!(
+        doubao_cdp::get_cached_cookies().as_deref(),
+        Some(cookie.as_str())
+    );
+
+    // 下一次会话使用缓存的模板：web_tab_id 重新生成，稳定参数不变
+    let (_, next) = doubao_cdp::fetch_asr_info_auto().await.unwrap();
+    let mut first = doubao_cdp::parse_asr_url_params(&doubao_cdp::build_session_url(&info));
+    let mut second = doubao_cdp::parse_asr_url_params(&doubao_cdp::build_session_url(&next));
+    assert_ne!(first.remove("web_tab_id"), second.remove("web_tab_id"));
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn test_login_and_availability() {
+    {
+        let (_mock, _guard) = start_mock(MockScript {
+            evaluate: vec![("const labels", "logged_out".into())],
+            ..Default::default()
+        })
+        .await;
+        assert!(doubao_cdp::is_doubao_debug_available().await);
+        assert_eq!(doubao_cdp::check_login_status().await, Ok(false));
+    }
+
+    // 调试端口属于其他浏览器
+    let (_mock, _guard) = start_mock(MockScript {
+        user_agent: "Mozilla/5.0 (Macintosh) Chrome/138.0.0.0 Safari/537.36".to_string(),
+        ..Default::default()
+    })
+    .await;
+    assert!(!doubao_cdp::is_doubao_debug_available().await);
+    assert!(doubao_cdp::verify_cdp_endpoint().await.is_err());
+}
+
+#[tokio::test]
+async fn test_no_doubao_page() {
+    let (_mock, _guard) = start_mock(MockScript {
+        pages: vec!["https://example.com/".to_string()],
+        ..Default::default()
+    })
+    .await;
+    assert!(doubao_cdp::fetch_cookies().await.is_err());
+    assert!(doubao_cdp::fetch_asr_info_auto().await.is_err());
+    assert!(doubao_cdp::list_doubao_pages().await.unwrap().is_empty());
+}